@@ -1,85 +1,121 @@
-use actix_web::{get, post, put, delete, web, HttpResponse};
-use shared::{ApiResponse, Agent, CreateAgentRequest, UpdateAgentRequest, PaginatedResponse};
+use actix_web::{get, post, put, delete, web, HttpRequest, HttpResponse};
+use shared::{ApiResponse, Agent, CallDockerError, CreateAgentRequest, UpdateAgentRequest};
+use shared::auth::Claims;
 use crate::services::agent_service::AgentService;
+use crate::middleware::auth::{get_claims, resolve_company, RequireRole};
 use validator::Validate;
 
-#[post("/agents")]
+/// Load an agent, rejecting callers from other companies.
+async fn authorized_agent(
+    agent_service: &AgentService,
+    claims: &Claims,
+    agent_id: uuid::Uuid,
+) -> Result<Agent, CallDockerError> {
+    let agent = agent_service.get_agent(agent_id).await?;
+
+    if !claims.role.is_super_admin() && claims.company_id != Some(agent.company_id) {
+        return Err(CallDockerError::Authorization("Access denied to this agent".to_string()));
+    }
+
+    Ok(agent)
+}
+
+#[post("/agents", wrap = "RequireRole::new(\"company_admin\")")]
 pub async fn create_agent(
     req: web::Json<CreateAgentRequest>,
+    http_req: HttpRequest,
     agent_service: web::Data<AgentService>,
-    // TODO: Add authentication middleware
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = req.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
+    req.validate()?;
 
-    match agent_service.create_agent(&req.into_inner()).await {
-        Ok(agent) => HttpResponse::Created().json(ApiResponse::success(agent)),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+    let company_id = resolve_company(&claims, req.company_id)?;
+
+    let agent = agent_service.create_agent(company_id, &req.into_inner()).await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(agent)))
 }
 
 #[get("/agents/{agent_id}")]
 pub async fn get_agent(
     path: web::Path<uuid::Uuid>,
+    http_req: HttpRequest,
     agent_service: web::Data<AgentService>,
-    // TODO: Add authentication middleware
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     let agent_id = path.into_inner();
 
-    match agent_service.get_agent(agent_id).await {
-        Ok(agent) => HttpResponse::Ok().json(ApiResponse::success(agent)),
-        Err(e) => HttpResponse::NotFound().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let agent = authorized_agent(&agent_service, &claims, agent_id).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(agent)))
 }
 
-#[put("/agents/{agent_id}")]
+#[put("/agents/{agent_id}", wrap = "RequireRole::new(\"company_admin\")")]
 pub async fn update_agent(
     path: web::Path<uuid::Uuid>,
     req: web::Json<UpdateAgentRequest>,
+    http_req: HttpRequest,
     agent_service: web::Data<AgentService>,
-    // TODO: Add authentication middleware
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = req.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
+    req.validate()?;
 
     let agent_id = path.into_inner();
 
-    match agent_service.update_agent(agent_id, &req.into_inner()).await {
-        Ok(agent) => HttpResponse::Ok().json(ApiResponse::success(agent)),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    authorized_agent(&agent_service, &claims, agent_id).await?;
+
+    let agent = agent_service.update_agent(agent_id, &req.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(agent)))
 }
 
 #[get("/agents")]
 pub async fn list_agents(
     query: web::Query<std::collections::HashMap<String, String>>,
+    http_req: HttpRequest,
     agent_service: web::Data<AgentService>,
-    // TODO: Add authentication middleware
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
     let page = query.get("page").and_then(|p| p.parse::<u32>().ok()).unwrap_or(1);
     let per_page = query.get("per_page").and_then(|p| p.parse::<u32>().ok()).unwrap_or(10);
-    let company_id = query.get("company_id").and_then(|id| uuid::Uuid::parse_str(id).ok());
+    let requested = query.get("company_id").and_then(|id| uuid::Uuid::parse_str(id).ok());
 
-    match agent_service.list_agents(company_id, page, per_page).await {
-        Ok(agents) => HttpResponse::Ok().json(ApiResponse::success(agents)),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    // Everyone but super admins is limited to their own company
+    let company_id = if claims.role.is_super_admin() {
+        requested
+    } else {
+        if requested.is_some() && requested != claims.company_id {
+            return Err(CallDockerError::Authorization("Access denied to this company".to_string()));
+        }
+        let company_id = claims.company_id.ok_or_else(|| {
+            CallDockerError::Authorization("User is not associated with a company".to_string())
+        })?;
+        Some(company_id)
+    };
+
+    let agents = agent_service.list_agents(company_id, page, per_page).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(agents)))
 }
 
-#[delete("/agents/{agent_id}")]
+#[delete("/agents/{agent_id}", wrap = "RequireRole::new(\"company_admin\")")]
 pub async fn delete_agent(
     path: web::Path<uuid::Uuid>,
+    http_req: HttpRequest,
     agent_service: web::Data<AgentService>,
-    // TODO: Add authentication middleware
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     let agent_id = path.into_inner();
 
-    match agent_service.delete_agent(agent_id).await {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::message("Agent deleted successfully".to_string())),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    authorized_agent(&agent_service, &claims, agent_id).await?;
+
+    agent_service.delete_agent(agent_id).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::<()>::message("Agent deleted successfully".to_string())))
 }
@@ -1,114 +1,89 @@
 use actix_web::{post, get, web, HttpResponse, HttpRequest};
-use shared::{ApiResponse, LoginRequest, RegisterRequest, AuthToken, User, UserRole};
+use shared::{ApiResponse, CallDockerError, LoginRequest, RegisterRequest, AuthToken, User, UserRole};
 use crate::services::auth_service::AuthService;
 use crate::config::Config;
 use validator::Validate;
 
+/// Extract the bearer token from the Authorization header
+fn bearer_token(req: &HttpRequest) -> Result<String, CallDockerError> {
+    let header = req
+        .headers()
+        .get("Authorization")
+        .ok_or_else(|| CallDockerError::Authentication("Missing authorization header".to_string()))?;
+
+    header
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.to_string())
+        .ok_or_else(|| CallDockerError::Authentication("Invalid authorization header".to_string()))
+}
+
 #[post("/auth/register")]
 pub async fn register(
     req: web::Json<RegisterRequest>,
     auth_service: web::Data<AuthService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = req.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
+    req.validate()?;
 
-    match auth_service.register_user(&req.into_inner()).await {
-        Ok(user) => HttpResponse::Created().json(ApiResponse::success(user)),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    let user = auth_service.register_user(&req.into_inner()).await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(user)))
 }
 
 #[post("/auth/login")]
 pub async fn login(
     req: web::Json<LoginRequest>,
     auth_service: web::Data<AuthService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = req.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
+    req.validate()?;
 
-    match auth_service.login_user(&req.into_inner()).await {
-        Ok(token) => HttpResponse::Ok().json(ApiResponse::success(token)),
-        Err(e) => HttpResponse::Unauthorized().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    let token = auth_service.login_user(&req.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(token)))
 }
 
 #[post("/auth/refresh")]
 pub async fn refresh_token(
     req: HttpRequest,
     auth_service: web::Data<AuthService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Extract refresh token from Authorization header
-    let auth_header = req.headers().get("Authorization");
-    let token = match auth_header {
-        Some(header) => {
-            let header_str = header.to_str().unwrap_or("");
-            if header_str.starts_with("Bearer ") {
-                header_str[7..].to_string()
-            } else {
-                return HttpResponse::BadRequest().json(ApiResponse::<()>::error("Invalid authorization header".to_string()));
-            }
-        }
-        None => return HttpResponse::BadRequest().json(ApiResponse::<()>::error("Missing authorization header".to_string())),
-    };
+    let token = bearer_token(&req)?;
 
-    match auth_service.refresh_token(&token).await {
-        Ok(new_token) => HttpResponse::Ok().json(ApiResponse::success(new_token)),
-        Err(e) => HttpResponse::Unauthorized().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    let new_token = auth_service.refresh_token(&token).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(new_token)))
 }
 
 #[post("/auth/logout")]
 pub async fn logout(
     req: HttpRequest,
     auth_service: web::Data<AuthService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Extract token from Authorization header
-    let auth_header = req.headers().get("Authorization");
-    let token = match auth_header {
-        Some(header) => {
-            let header_str = header.to_str().unwrap_or("");
-            if header_str.starts_with("Bearer ") {
-                header_str[7..].to_string()
-            } else {
-                return HttpResponse::BadRequest().json(ApiResponse::<()>::error("Invalid authorization header".to_string()));
-            }
-        }
-        None => return HttpResponse::BadRequest().json(ApiResponse::<()>::error("Missing authorization header".to_string())),
-    };
+    let token = bearer_token(&req)?;
 
-    match auth_service.logout_user(&token).await {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::message("Successfully logged out".to_string())),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    auth_service.logout_user(&token).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::<()>::message("Successfully logged out".to_string())))
 }
 
 #[post("/auth/forgot-password")]
 pub async fn forgot_password(
     req: web::Json<shared::PasswordResetRequest>,
     auth_service: web::Data<AuthService>,
-) -> HttpResponse {
-    match auth_service.forgot_password(&req.email).await {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::message("Password reset email sent".to_string())),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+) -> Result<HttpResponse, CallDockerError> {
+    auth_service.forgot_password(&req.email).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::<()>::message("Password reset email sent".to_string())))
 }
 
 #[post("/auth/reset-password")]
 pub async fn reset_password(
     req: web::Json<shared::PasswordResetConfirm>,
     auth_service: web::Data<AuthService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = req.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
+    req.validate()?;
 
-    match auth_service.reset_password(&req.token, &req.new_password).await {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::message("Password successfully reset".to_string())),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    auth_service.reset_password(&req.token, &req.new_password).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::<()>::message("Password successfully reset".to_string())))
 }
@@ -1,4 +1,4 @@
-use actix_web::{App, HttpServer, middleware::Logger};
+use actix_web::{App, HttpServer, middleware::Logger, web};
 use actix_cors::Cors;
 use actix_web_prom::PrometheusMetricsBuilder;
 use tracing::{info, error};
@@ -16,6 +16,8 @@ mod utils;
 
 #[cfg(test)]
 mod test_auth;
+#[cfg(test)]
+mod test_middleware;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -63,10 +65,13 @@ async fn main() -> std::io::Result<()> {
         .build()
         .unwrap();
 
+    let jwt_secret = config.jwt.secret.clone();
+
     // Start HTTP server
     HttpServer::new(move || {
         App::new()
-            .wrap(Logger::default())
+            .wrap(shared::request_id::RequestId)
+            .wrap(Logger::new(r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}o"#))
             .wrap(prometheus.clone())
             .wrap(
                 Cors::default()
@@ -81,31 +86,43 @@ async fn main() -> std::io::Result<()> {
             .app_data(actix_web::web::Data::new(agent_service.clone()))
             .app_data(actix_web::web::Data::new(db_pool.clone()))
             .app_data(actix_web::web::Data::new(redis_conn.clone()))
-            .service(handlers::health::health_check)
-            .service(handlers::health::detailed_health_check)
-            .service(handlers::health::ready_check)
-            .service(handlers::auth::register)
-            .service(handlers::auth::login)
-            .service(handlers::auth::refresh_token)
-            .service(handlers::auth::logout)
-            .service(handlers::auth::forgot_password)
-            .service(handlers::auth::reset_password)
-            .service(handlers::users::get_profile)
-            .service(handlers::users::update_profile)
-            .service(handlers::users::change_password)
-            .service(handlers::users::list_users)
-            .service(handlers::companies::create_company)
-            .service(handlers::companies::get_company)
-            .service(handlers::companies::update_company)
-            .service(handlers::companies::list_companies)
-            .service(handlers::companies::update_company_status)
-            .service(handlers::agents::create_agent)
-            .service(handlers::agents::get_agent)
-            .service(handlers::agents::update_agent)
-            .service(handlers::agents::list_agents)
-            .service(handlers::agents::delete_agent)
+            .configure(|cfg| configure_routes(cfg, &jwt_secret))
     })
     .bind(format!("0.0.0.0:{}", config.server.port))?
     .run()
     .await
 }
+
+/// Register every route: public ones first, then the JWT-protected scope.
+pub fn configure_routes(cfg: &mut web::ServiceConfig, jwt_secret: &str) {
+    cfg
+        // Public routes
+        .service(handlers::health::health_check)
+        .service(handlers::health::detailed_health_check)
+        .service(handlers::health::ready_check)
+        .service(handlers::auth::register)
+        .service(handlers::auth::login)
+        .service(handlers::auth::refresh_token)
+        .service(handlers::auth::forgot_password)
+        .service(handlers::auth::reset_password)
+        // Protected routes; must stay last since the empty prefix matches every path
+        .service(
+            web::scope("")
+                .wrap(middleware::auth::AuthMiddleware::new(jwt_secret.to_string()))
+                .service(handlers::auth::logout)
+                .service(handlers::users::get_profile)
+                .service(handlers::users::update_profile)
+                .service(handlers::users::change_password)
+                .service(handlers::users::list_users)
+                .service(handlers::companies::create_company)
+                .service(handlers::companies::get_company)
+                .service(handlers::companies::update_company)
+                .service(handlers::companies::list_companies)
+                .service(handlers::companies::update_company_status)
+                .service(handlers::agents::create_agent)
+                .service(handlers::agents::get_agent)
+                .service(handlers::agents::update_agent)
+                .service(handlers::agents::list_agents)
+                .service(handlers::agents::delete_agent)
+        );
+}
@@ -1,16 +1,14 @@
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    error::ErrorUnauthorized,
-    http::header::{self, HeaderValue},
+    http::header,
     Error, HttpMessage, HttpRequest,
 };
 use futures_util::future::{ready, LocalBoxFuture, Ready};
 use jsonwebtoken::{decode, DecodingKey, Validation};
-use shared::{Claims, CallDockerError};
-use std::future::Future;
-use std::pin::Pin;
+use shared::{auth::{Claims, UserRole}, CallDockerError};
 use std::rc::Rc;
 use std::task::{Context, Poll};
+use uuid::Uuid;
 
 pub struct AuthMiddleware {
     jwt_secret: String,
@@ -24,7 +22,7 @@ impl AuthMiddleware {
 
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddleware
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -49,7 +47,7 @@ pub struct AuthMiddlewareService<S> {
 
 impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -69,15 +67,13 @@ where
             // Extract token from Authorization header
             let auth_header = req.headers().get(header::AUTHORIZATION);
             let token = match auth_header {
-                Some(header) => {
-                    let header_str = header.to_str().unwrap_or("");
-                    if header_str.starts_with("Bearer ") {
-                        header_str[7..].to_string()
-                    } else {
-                        return Err(ErrorUnauthorized("Invalid authorization header"));
+                Some(header) => match header.to_str().unwrap_or("").strip_prefix("Bearer ") {
+                    Some(token) => token.to_string(),
+                    None => {
+                        return Err(CallDockerError::Authentication("Invalid authorization header".to_string()).into())
                     }
-                }
-                None => return Err(ErrorUnauthorized("Missing authorization header")),
+                },
+                None => return Err(CallDockerError::Authentication("Missing authorization header".to_string()).into()),
             };
 
             // Decode and validate token
@@ -86,7 +82,7 @@ where
                 &DecodingKey::from_secret(jwt_secret.as_ref()),
                 &Validation::default(),
             )
-            .map_err(|_| ErrorUnauthorized("Invalid token"))?;
+            .map_err(|_| CallDockerError::Authentication("Invalid token".to_string()))?;
 
             // Add claims to request extensions
             req.extensions_mut().insert(claims.claims);
@@ -98,6 +94,72 @@ where
     }
 }
 
+/// Rejects requests whose claims do not satisfy `has_role`. Must run inside
+/// `AuthMiddleware`, i.e. be registered with `.wrap` before it.
+pub struct RequireRole {
+    role: &'static str,
+}
+
+impl RequireRole {
+    pub fn new(role: &'static str) -> Self {
+        Self { role }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireRoleService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireRoleService {
+            service: Rc::new(service),
+            role: self.role,
+        }))
+    }
+}
+
+pub struct RequireRoleService<S> {
+    service: Rc<S>,
+    role: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireRoleService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let allowed = req
+            .extensions()
+            .get::<Claims>()
+            .map(|claims| has_role(claims, self.role));
+
+        Box::pin(async move {
+            match allowed {
+                Some(true) => svc.call(req).await,
+                Some(false) => Err(CallDockerError::Authorization("Insufficient permissions".to_string()).into()),
+                None => Err(CallDockerError::Authentication("Missing authentication".to_string()).into()),
+            }
+        })
+    }
+}
+
 // Helper function to extract claims from request
 pub fn get_claims(req: &HttpRequest) -> Result<Claims, CallDockerError> {
     req.extensions()
@@ -109,9 +171,25 @@ pub fn get_claims(req: &HttpRequest) -> Result<Claims, CallDockerError> {
 // Helper function to check if user has required role
 pub fn has_role(claims: &Claims, required_role: &str) -> bool {
     match required_role {
-        "super_admin" => matches!(claims.role, shared::UserRole::SuperAdmin),
-        "company_admin" => matches!(claims.role, shared::UserRole::SuperAdmin | shared::UserRole::CompanyAdmin),
+        "super_admin" => matches!(claims.role, UserRole::SuperAdmin),
+        "company_admin" => matches!(claims.role, UserRole::SuperAdmin | UserRole::CompanyAdmin),
         "agent" => true, // All roles can access agent-level endpoints
         _ => false,
     }
 }
+
+// Helper function to pick the company a request acts on: the user's own, or for
+// super admins the one they name
+pub fn resolve_company(claims: &Claims, requested: Option<Uuid>) -> Result<Uuid, CallDockerError> {
+    if claims.role.is_super_admin() {
+        return requested.ok_or_else(|| CallDockerError::Validation("company_id is required".to_string()));
+    }
+
+    let company_id = claims
+        .company_id
+        .ok_or_else(|| CallDockerError::Authorization("User is not associated with a company".to_string()))?;
+    if requested.is_some_and(|requested| requested != company_id) {
+        return Err(CallDockerError::Authorization("Access denied to this company".to_string()));
+    }
+    Ok(company_id)
+}
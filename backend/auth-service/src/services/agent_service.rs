@@ -12,7 +12,7 @@ impl AgentService {
         Self { db_pool }
     }
 
-    pub async fn create_agent(&self, req: &CreateAgentRequest) -> Result<Agent> {
+    pub async fn create_agent(&self, company_id: Uuid, req: &CreateAgentRequest) -> Result<Agent> {
         // Create user for the agent
         let user_id = sqlx::query!(
             r#"
@@ -22,7 +22,7 @@ impl AgentService {
             "#,
             req.email,
             "temp_password_hash", // TODO: Generate temporary password
-            Uuid::nil() // TODO: Get from context
+            company_id
         )
         .fetch_one(&self.db_pool)
         .await?
@@ -36,7 +36,7 @@ impl AgentService {
             RETURNING id, user_id, company_id, name, email, phone, avatar_url, status, skills, max_concurrent_calls, current_calls, total_calls_handled, average_call_duration, is_active, created_at, updated_at
             "#,
             user_id,
-            Uuid::nil(), // TODO: Get from context
+            company_id,
             req.name,
             req.email,
             req.phone,
@@ -242,6 +242,7 @@ impl AgentService {
                 per_page,
                 total: total as u64,
                 total_pages,
+                next_cursor: None,
             },
         })
     }
@@ -33,7 +33,7 @@ impl AuthService {
         .await?;
 
         if existing_user.is_some() {
-            return Err(shared::CallDockerError::Authentication("User already exists".to_string()));
+            return Err(shared::CallDockerError::Conflict("User already exists".to_string()));
         }
 
         // Check if company UUID is available
@@ -45,12 +45,12 @@ impl AuthService {
         .await?;
 
         if existing_company.is_some() {
-            return Err(shared::CallDockerError::Validation("Company UUID already taken".to_string()));
+            return Err(shared::CallDockerError::Conflict("Company UUID already taken".to_string()));
         }
 
         // Hash password
         let password_hash = hash(req.password.as_bytes(), DEFAULT_COST)
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Password hashing failed: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Password hashing failed: {}", e)))?;
 
         // Start transaction
         let mut tx = self.db_pool.begin().await?;
@@ -123,7 +123,7 @@ impl AuthService {
 
         // Verify password
         let valid = verify(req.password.as_bytes(), &user.password_hash)
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Password verification failed: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Password verification failed: {}", e)))?;
 
         if !valid {
             return Err(shared::CallDockerError::Authentication("Invalid credentials".to_string()));
@@ -134,7 +134,7 @@ impl AuthService {
             "super_admin" => UserRole::SuperAdmin,
             "company_admin" => UserRole::CompanyAdmin,
             "agent" => UserRole::Agent,
-            _ => return Err(shared::CallDockerError::Authentication("Invalid user role".to_string())),
+            _ => return Err(shared::CallDockerError::Database("Invalid user role".to_string())),
         };
 
         // Generate tokens
@@ -149,7 +149,7 @@ impl AuthService {
             .arg(&refresh_token)
             .query_async(&mut self.redis_conn.clone())
             .await
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Redis error: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Redis error: {}", e)))?;
 
         // Update last login
         sqlx::query!(
@@ -184,7 +184,7 @@ impl AuthService {
             .arg(&key)
             .query_async(&mut self.redis_conn.clone())
             .await
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Redis error: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Redis error: {}", e)))?;
 
         if stored_token.as_ref() != Some(&refresh_token.to_string()) {
             return Err(shared::CallDockerError::Authentication("Invalid refresh token".to_string()));
@@ -215,7 +215,7 @@ impl AuthService {
             "super_admin" => UserRole::SuperAdmin,
             "company_admin" => UserRole::CompanyAdmin,
             "agent" => UserRole::Agent,
-            _ => return Err(shared::CallDockerError::Authentication("Invalid user role".to_string())),
+            _ => return Err(shared::CallDockerError::Database("Invalid user role".to_string())),
         };
 
         // Generate new tokens
@@ -229,7 +229,7 @@ impl AuthService {
             .arg(&new_refresh_token)
             .query_async(&mut self.redis_conn.clone())
             .await
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Redis error: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Redis error: {}", e)))?;
 
         Ok(AuthToken {
             access_token,
@@ -256,7 +256,7 @@ impl AuthService {
             .arg(&key)
             .query_async(&mut self.redis_conn.clone())
             .await
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Redis error: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Redis error: {}", e)))?;
 
         Ok(())
     }
@@ -332,7 +332,7 @@ impl AuthService {
 
         // Hash new password
         let password_hash = hash(new_password.as_bytes(), DEFAULT_COST)
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Password hashing failed: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Password hashing failed: {}", e)))?;
 
         // Update password and mark token as used
         sqlx::query!(
@@ -375,7 +375,7 @@ impl AuthService {
             &claims,
             &EncodingKey::from_secret(self.config.jwt.secret.as_ref())
         )
-        .map_err(|e| shared::CallDockerError::Authentication(format!("Token encoding failed: {}", e)))
+        .map_err(|e| shared::CallDockerError::Internal(format!("Token encoding failed: {}", e)))
     }
 
     fn generate_refresh_token(&self, user_id: &Uuid) -> Result<String> {
@@ -396,6 +396,6 @@ impl AuthService {
             &claims,
             &EncodingKey::from_secret(self.config.jwt.secret.as_ref())
         )
-        .map_err(|e| shared::CallDockerError::Authentication(format!("Token encoding failed: {}", e)))
+        .map_err(|e| shared::CallDockerError::Internal(format!("Token encoding failed: {}", e)))
     }
 }
@@ -22,7 +22,7 @@ impl CompanyService {
         .await?;
 
         if existing_company.is_some() {
-            return Err(shared::CallDockerError::Validation("Company UUID already taken".to_string()));
+            return Err(shared::CallDockerError::Conflict("Company UUID already taken".to_string()));
         }
 
         // Create company
@@ -204,6 +204,7 @@ impl CompanyService {
                 per_page,
                 total: total as u64,
                 total_pages,
+                next_cursor: None,
             },
         })
     }
@@ -39,7 +39,7 @@ impl UserService {
             "super_admin" => shared::UserRole::SuperAdmin,
             "company_admin" => shared::UserRole::CompanyAdmin,
             "agent" => shared::UserRole::Agent,
-            _ => return Err(shared::CallDockerError::Authentication("Invalid user role".to_string())),
+            _ => return Err(shared::CallDockerError::Database("Invalid user role".to_string())),
         };
 
         Ok(UserProfile {
@@ -98,7 +98,7 @@ impl UserService {
 
         // Verify current password
         let valid = verify(current_password.as_bytes(), &user.password_hash)
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Password verification failed: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Password verification failed: {}", e)))?;
 
         if !valid {
             return Err(shared::CallDockerError::Authentication("Current password is incorrect".to_string()));
@@ -106,7 +106,7 @@ impl UserService {
 
         // Hash new password
         let new_password_hash = hash(new_password.as_bytes(), DEFAULT_COST)
-            .map_err(|e| shared::CallDockerError::Authentication(format!("Password hashing failed: {}", e)))?;
+            .map_err(|e| shared::CallDockerError::Internal(format!("Password hashing failed: {}", e)))?;
 
         // Update password
         sqlx::query!(
@@ -160,7 +160,7 @@ impl UserService {
                 "super_admin" => shared::UserRole::SuperAdmin,
                 "company_admin" => shared::UserRole::CompanyAdmin,
                 "agent" => shared::UserRole::Agent,
-                _ => return Err(shared::CallDockerError::Authentication("Invalid user role".to_string())),
+                _ => return Err(shared::CallDockerError::Database("Invalid user role".to_string())),
             };
 
             user_profiles.push(UserProfile {
@@ -6,7 +6,9 @@ pub struct Config {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub redis: RedisConfig,
+    pub jwt: JwtConfig,
     pub webrtc: WebRTCConfig,
+    pub routing: RoutingConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +27,12 @@ pub struct RedisConfig {
     pub url: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtConfig {
+    pub secret: String,
+    pub widget_token_expiry: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebRTCConfig {
     pub ice_servers: Vec<IceServerConfig>,
@@ -32,6 +40,16 @@ pub struct WebRTCConfig {
     pub connection_timeout: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    /// Seconds between queue supervisor runs
+    pub queue_supervisor_interval: u64,
+    /// Seconds an agent has to accept a call offered to them
+    pub ring_timeout: u64,
+    /// Offers an agent may let ring out in a row before being set away; 0 never does
+    pub max_missed_offers: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IceServerConfig {
     pub urls: Vec<String>,
@@ -60,6 +78,14 @@ impl Config {
                 url: env::var("REDIS_URL")
                     .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
             },
+            jwt: JwtConfig {
+                secret: env::var("JWT_SECRET")
+                    .expect("JWT_SECRET must be set"),
+                widget_token_expiry: env::var("WIDGET_TOKEN_EXPIRY")
+                    .unwrap_or_else(|_| "900".to_string())
+                    .parse()
+                    .unwrap_or(900),
+            },
             webrtc: WebRTCConfig {
                 ice_servers: vec![
                     // TURN-only configuration for guaranteed 2-way audio
@@ -81,6 +107,20 @@ impl Config {
                     .parse()
                     .unwrap_or(30000),
             },
+            routing: RoutingConfig {
+                queue_supervisor_interval: env::var("QUEUE_SUPERVISOR_INTERVAL")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5),
+                ring_timeout: env::var("RING_TIMEOUT")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20),
+                max_missed_offers: env::var("MAX_MISSED_OFFERS")
+                    .unwrap_or_else(|_| "3".to_string())
+                    .parse()
+                    .unwrap_or(3),
+            },
         };
 
         Ok(config)
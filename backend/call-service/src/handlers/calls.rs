@@ -1,5 +1,8 @@
-use actix_web::{post, get, put, web, HttpResponse};
-use shared::{ApiResponse, call::{CreateCallRequest, Call, CallStatus}};
+use actix_web::{post, get, put, web, HttpRequest, HttpResponse};
+use shared::{ApiResponse, CallDockerError, call::{CallListQuery, CreateCallRequest, CreateCallResponse, EndCallRequest, UpdateCallRequest}};
+use shared::ivr::IVRInputRequest;
+use shared::routing::RoutingScopeQuery;
+use crate::middleware::auth::{get_claims, get_token};
 use crate::services::call_service::CallService;
 use validator::Validate;
 use uuid::Uuid;
@@ -8,60 +11,156 @@ use uuid::Uuid;
 pub async fn create_call(
     request: web::Json<CreateCallRequest>,
     call_service: web::Data<CallService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = request.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
-
-    match call_service.create_call(&request.into_inner()).await {
-        Ok(call) => HttpResponse::Created().json(ApiResponse::success(call)),
-        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    request.validate()?;
+
+    let call = call_service.create_call(&request.into_inner()).await?;
+    let widget_token = call_service.issue_widget_token(&call)?;
+
+    Ok(HttpResponse::Created().json(ApiResponse::success(CreateCallResponse { call, widget_token })))
+}
+
+/// Keys the customer pressed in the call's IVR flow. Public, like the widget;
+/// the widget token is checked against the call.
+#[post("/calls/{call_id}/ivr/input")]
+pub async fn ivr_input(
+    path: web::Path<Uuid>,
+    request: web::Json<IVRInputRequest>,
+    http_req: HttpRequest,
+    call_service: web::Data<CallService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract access or widget token
+    let token = get_token(&http_req, None)
+        .ok_or_else(|| CallDockerError::Authentication("Missing token".to_string()))?;
+
+    let call = call_service
+        .ivr_input(path.into_inner(), &token, request.into_inner().digits)
+        .await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(call)))
+}
+
+#[get("/calls/queue/stats")]
+pub async fn get_queue_stats(
+    query: web::Query<RoutingScopeQuery>,
+    http_req: HttpRequest,
+    call_service: web::Data<CallService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let stats = call_service.get_queue_stats(&claims, query.company_id).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
 }
 
 #[get("/calls/{call_id}")]
 pub async fn get_call(
     path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    call_service: web::Data<CallService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let call = call_service.get_call_for(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(call)))
+}
+
+#[get("/calls/{call_id}/events")]
+pub async fn get_call_events(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
     call_service: web::Data<CallService>,
-) -> HttpResponse {
-    let call_id = path.into_inner();
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
 
-    match call_service.get_call(call_id).await {
-        Ok(call) => HttpResponse::Ok().json(ApiResponse::success(call)),
-        Err(e) => HttpResponse::NotFound().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    let events = call_service.get_call_events(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(events)))
 }
 
 #[get("/calls")]
 pub async fn list_calls(
+    query: web::Query<CallListQuery>,
+    http_req: HttpRequest,
     call_service: web::Data<CallService>,
-) -> HttpResponse {
-    // This would typically support pagination and filtering
-    // For now, return a simple message
-    HttpResponse::Ok().json(ApiResponse::message("List calls endpoint - implementation pending".to_string()))
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    query.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let calls = call_service.list_calls(&claims, query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(calls)))
 }
 
 #[put("/calls/{call_id}")]
 pub async fn update_call(
     path: web::Path<Uuid>,
+    request: web::Json<UpdateCallRequest>,
+    http_req: HttpRequest,
     call_service: web::Data<CallService>,
-) -> HttpResponse {
-    let call_id = path.into_inner();
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
 
-    // This would typically update call details
-    // For now, return a simple message
-    HttpResponse::Ok().json(ApiResponse::message(format!("Update call {} - implementation pending", call_id)))
+    let call = call_service.update_call(&claims, path.into_inner(), request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(call)))
 }
 
 #[post("/calls/{call_id}/end")]
 pub async fn end_call(
     path: web::Path<Uuid>,
+    body: web::Bytes,
+    http_req: HttpRequest,
+    call_service: web::Data<CallService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Wrap-up data is optional, but a body that is there must parse
+    let wrap_up: EndCallRequest = if body.iter().all(u8::is_ascii_whitespace) {
+        EndCallRequest::default()
+    } else {
+        serde_json::from_slice(&body)?
+    };
+
+    // Validate request
+    wrap_up.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let call = call_service.end_call(&claims, path.into_inner(), wrap_up).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(call)))
+}
+
+#[post("/calls/{call_id}/accept")]
+pub async fn accept_call(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    call_service: web::Data<CallService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let call = call_service.accept_offer(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(call)))
+}
+
+#[post("/calls/{call_id}/reject")]
+pub async fn reject_call(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
     call_service: web::Data<CallService>,
-) -> HttpResponse {
-    let call_id = path.into_inner();
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
 
-    // This would typically end the call and update status
-    // For now, return a simple message
-    HttpResponse::Ok().json(ApiResponse::message(format!("End call {} - implementation pending", call_id)))
+    let call = call_service.reject_offer(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(call)))
 }
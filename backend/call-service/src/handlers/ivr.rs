@@ -0,0 +1,219 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use shared::{
+    ApiResponse, CallDockerError,
+    ivr::{
+        CreateIVRFlowRequest, CreateIVRTemplateRequest, IVRFlowDiffQuery, InstantiateIVRTemplateRequest,
+        RollbackIVRFlowRequest, UpdateIVRFlowRequest,
+    },
+    routing::RoutingScopeQuery,
+};
+use crate::middleware::auth::get_claims;
+use crate::services::ivr_flow_service::IVRFlowService;
+use validator::Validate;
+use uuid::Uuid;
+
+#[get("/flows")]
+pub async fn list_flows(
+    query: web::Query<RoutingScopeQuery>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let flows = ivr_flow_service.list_flows(&claims, query.company_id).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(flows)))
+}
+
+#[post("/flows")]
+pub async fn create_flow(
+    query: web::Query<RoutingScopeQuery>,
+    request: web::Json<CreateIVRFlowRequest>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let flow = ivr_flow_service.create_flow(&claims, query.company_id, request.into_inner()).await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(flow)))
+}
+
+#[get("/flows/{flow_id}")]
+pub async fn get_flow(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let flow = ivr_flow_service.get_flow(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(flow)))
+}
+
+#[put("/flows/{flow_id}")]
+pub async fn update_flow(
+    path: web::Path<Uuid>,
+    request: web::Json<UpdateIVRFlowRequest>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let flow = ivr_flow_service.update_flow(&claims, path.into_inner(), request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(flow)))
+}
+
+#[delete("/flows/{flow_id}")]
+pub async fn delete_flow(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    ivr_flow_service.delete_flow(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/flows/{flow_id}/publish")]
+pub async fn publish_flow(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let version = ivr_flow_service.publish_flow(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(version)))
+}
+
+#[get("/flows/{flow_id}/versions")]
+pub async fn list_versions(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let versions = ivr_flow_service.list_versions(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(versions)))
+}
+
+#[get("/flows/{flow_id}/versions/{version}")]
+pub async fn get_version(
+    path: web::Path<(Uuid, u32)>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let (flow_id, version) = path.into_inner();
+    let version = ivr_flow_service.get_version(&claims, flow_id, version).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(version)))
+}
+
+#[post("/flows/{flow_id}/rollback")]
+pub async fn rollback_flow(
+    path: web::Path<Uuid>,
+    request: web::Json<RollbackIVRFlowRequest>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let flow = ivr_flow_service.rollback_flow(&claims, path.into_inner(), request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(flow)))
+}
+
+#[get("/flows/{flow_id}/diff")]
+pub async fn diff_flow(
+    path: web::Path<Uuid>,
+    query: web::Query<IVRFlowDiffQuery>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let diff = ivr_flow_service.diff_flow(&claims, path.into_inner(), query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(diff)))
+}
+
+#[get("/templates")]
+pub async fn list_templates(
+    query: web::Query<RoutingScopeQuery>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let templates = ivr_flow_service.list_templates(&claims, query.company_id).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(templates)))
+}
+
+#[post("/templates")]
+pub async fn create_template(
+    request: web::Json<CreateIVRTemplateRequest>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let template = ivr_flow_service.create_template(&claims, request.into_inner()).await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(template)))
+}
+
+#[delete("/templates/{template_id}")]
+pub async fn delete_template(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    ivr_flow_service.delete_template(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/templates/{template_id}/instantiate")]
+pub async fn instantiate_template(
+    path: web::Path<Uuid>,
+    query: web::Query<RoutingScopeQuery>,
+    request: web::Json<InstantiateIVRTemplateRequest>,
+    http_req: HttpRequest,
+    ivr_flow_service: web::Data<IVRFlowService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let flow = ivr_flow_service
+        .instantiate_template(&claims, query.company_id, path.into_inner(), request.into_inner())
+        .await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(flow)))
+}
@@ -1,4 +1,6 @@
 pub mod health;
 pub mod calls;
+pub mod routing;
+pub mod ivr;
 pub mod webrtc;
 pub mod websocket;
@@ -0,0 +1,236 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use shared::{
+    ApiResponse, CallDockerError,
+    routing::{
+        AgentStatsQuery, CreateRoutingQueueRequest, CreateRoutingRuleRequest, ReorderRoutingRulesRequest,
+        RoutingScopeQuery, UpdateRoutingQueueRequest, UpdateRoutingRuleRequest,
+    },
+    routing_policy::RoutingPolicy,
+    rule_engine::RuleDryRunRequest,
+};
+use crate::middleware::auth::get_claims;
+use crate::services::{call_service::CallService, routing_config_service::RoutingConfigService};
+use validator::Validate;
+use uuid::Uuid;
+
+#[get("/rules")]
+pub async fn list_rules(
+    query: web::Query<RoutingScopeQuery>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let rules = routing_service.list_rules(&claims, query.company_id).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(rules)))
+}
+
+#[post("/rules")]
+pub async fn create_rule(
+    query: web::Query<RoutingScopeQuery>,
+    request: web::Json<CreateRoutingRuleRequest>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let rule = routing_service.create_rule(&claims, query.company_id, request.into_inner()).await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(rule)))
+}
+
+#[put("/rules/order")]
+pub async fn reorder_rules(
+    query: web::Query<RoutingScopeQuery>,
+    request: web::Json<ReorderRoutingRulesRequest>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let rules = routing_service.reorder_rules(&claims, query.company_id, request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(rules)))
+}
+
+#[post("/rules/dry-run")]
+pub async fn dry_run_rules(
+    request: web::Json<RuleDryRunRequest>,
+    http_req: HttpRequest,
+    call_service: web::Data<CallService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let evaluation = call_service.dry_run_routing(&claims, request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(evaluation)))
+}
+
+#[get("/rules/{rule_id}")]
+pub async fn get_rule(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let rule = routing_service.get_rule(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(rule)))
+}
+
+#[put("/rules/{rule_id}")]
+pub async fn update_rule(
+    path: web::Path<Uuid>,
+    request: web::Json<UpdateRoutingRuleRequest>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let rule = routing_service.update_rule(&claims, path.into_inner(), request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(rule)))
+}
+
+#[delete("/rules/{rule_id}")]
+pub async fn delete_rule(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    routing_service.delete_rule(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/queues")]
+pub async fn list_queues(
+    query: web::Query<RoutingScopeQuery>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let queues = routing_service.list_queues(&claims, query.company_id).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(queues)))
+}
+
+#[post("/queues")]
+pub async fn create_queue(
+    query: web::Query<RoutingScopeQuery>,
+    request: web::Json<CreateRoutingQueueRequest>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let queue = routing_service.create_queue(&claims, query.company_id, request.into_inner()).await?;
+    Ok(HttpResponse::Created().json(ApiResponse::success(queue)))
+}
+
+#[get("/queues/{queue_id}")]
+pub async fn get_queue(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let queue = routing_service.get_queue(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(queue)))
+}
+
+#[put("/queues/{queue_id}")]
+pub async fn update_queue(
+    path: web::Path<Uuid>,
+    request: web::Json<UpdateRoutingQueueRequest>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let queue = routing_service.update_queue(&claims, path.into_inner(), request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(queue)))
+}
+
+#[delete("/queues/{queue_id}")]
+pub async fn delete_queue(
+    path: web::Path<Uuid>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    routing_service.delete_queue(&claims, path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/policy")]
+pub async fn get_policy(
+    query: web::Query<RoutingScopeQuery>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let policy = routing_service.get_policy(&claims, query.company_id).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(policy)))
+}
+
+#[put("/policy")]
+pub async fn update_policy(
+    query: web::Query<RoutingScopeQuery>,
+    request: web::Json<RoutingPolicy>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Validate request
+    request.validate()?;
+
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let policy = routing_service.update_policy(&claims, query.company_id, request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(policy)))
+}
+
+#[get("/stats/agents")]
+pub async fn agent_stats(
+    query: web::Query<AgentStatsQuery>,
+    http_req: HttpRequest,
+    routing_service: web::Data<RoutingConfigService>,
+) -> Result<HttpResponse, CallDockerError> {
+    // Extract user claims from JWT token
+    let claims = get_claims(&http_req)?;
+
+    let stats = routing_service.agent_stats(&claims, query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
+}
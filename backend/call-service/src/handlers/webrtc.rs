@@ -1,52 +1,57 @@
-use actix_web::{post, web, HttpResponse};
-use shared::{ApiResponse, WebRTCSignal, SignalType};
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use shared::{ApiResponse, CallDockerError, WebRTCSignal};
+use crate::middleware::auth::get_token;
 use crate::services::call_service::CallService;
-use validator::Validate;
 
+/// Signaling is public, like the widget; every signal's token is checked
+/// against its call.
 #[post("/webrtc/offer")]
 pub async fn offer(
     signal: web::Json<WebRTCSignal>,
+    http_req: HttpRequest,
     call_service: web::Data<CallService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = signal.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
-
-    match call_service.handle_webrtc_offer(&signal.into_inner()).await {
-        Ok(response) => HttpResponse::Ok().json(ApiResponse::success(response)),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    signal.signal()?;
+
+    // Extract access or widget token
+    let token = get_token(&http_req, None)
+        .ok_or_else(|| CallDockerError::Authentication("Missing token".to_string()))?;
+
+    let response = call_service.handle_webrtc_offer(&signal.into_inner(), &token).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
 }
 
 #[post("/webrtc/answer")]
 pub async fn answer(
     signal: web::Json<WebRTCSignal>,
+    http_req: HttpRequest,
     call_service: web::Data<CallService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = signal.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
-
-    match call_service.handle_webrtc_answer(&signal.into_inner()).await {
-        Ok(response) => HttpResponse::Ok().json(ApiResponse::success(response)),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    signal.signal()?;
+
+    // Extract access or widget token
+    let token = get_token(&http_req, None)
+        .ok_or_else(|| CallDockerError::Authentication("Missing token".to_string()))?;
+
+    let response = call_service.handle_webrtc_answer(&signal.into_inner(), &token).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
 }
 
 #[post("/webrtc/ice-candidate")]
 pub async fn ice_candidate(
     signal: web::Json<WebRTCSignal>,
+    http_req: HttpRequest,
     call_service: web::Data<CallService>,
-) -> HttpResponse {
+) -> Result<HttpResponse, CallDockerError> {
     // Validate request
-    if let Err(e) = signal.validate() {
-        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Validation error: {}", e)));
-    }
-
-    match call_service.handle_ice_candidate(&signal.into_inner()).await {
-        Ok(_) => HttpResponse::Ok().json(ApiResponse::message("ICE candidate processed".to_string())),
-        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string())),
-    }
+    signal.signal()?;
+
+    // Extract access or widget token
+    let token = get_token(&http_req, None)
+        .ok_or_else(|| CallDockerError::Authentication("Missing token".to_string()))?;
+
+    call_service.handle_ice_candidate(&signal.into_inner(), &token).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::<()>::message("ICE candidate processed".to_string())))
 }
@@ -1,17 +1,74 @@
+use actix::Addr;
 use actix_web::{get, web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
-use crate::websocket::CallWebSocket;
+use serde::Deserialize;
+use shared::{CallDockerError, signaling::negotiate_version};
+use uuid::Uuid;
+use crate::config::Config;
+use crate::middleware::auth::{authenticate_participant, decode_access_token, get_token};
+use crate::rooms::RoomRegistry;
+use crate::services::call_service::CallService;
+use crate::websocket::{AgentWebSocket, CallWebSocket};
+
+#[derive(Debug, Deserialize)]
+pub struct WsConnectQuery {
+    pub token: Option<String>,
+    pub version: Option<u16>,
+}
 
 #[get("/ws/{call_id}")]
 pub async fn ws_route(
     req: HttpRequest,
     stream: web::Payload,
-    path: web::Path<String>,
+    path: web::Path<Uuid>,
+    query: web::Query<WsConnectQuery>,
+    rooms: web::Data<Addr<RoomRegistry>>,
+    call_service: web::Data<CallService>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, Error> {
     let call_id = path.into_inner();
-    
+
+    // Negotiate protocol version
+    let version = negotiate_version(query.version).map_err(CallDockerError::from)?;
+
+    // Extract access or widget token
+    let token = get_token(&req, query.token.as_deref())
+        .ok_or_else(|| CallDockerError::Authentication("Missing token".to_string()))?;
+
+    // Validate call exists
+    let call = call_service.get_call(call_id).await?;
+
+    let (user_id, role) = authenticate_participant(&token, &config.jwt.secret, &call)?;
+
+    ws::start(
+        CallWebSocket::new(call_id, version, user_id, role, rooms.get_ref().clone()),
+        &req,
+        stream,
+    )
+}
+
+/// The agent's own socket, over which calls are offered to them.
+#[get("/ws/agent")]
+pub async fn agent_ws_route(
+    req: HttpRequest,
+    stream: web::Payload,
+    query: web::Query<WsConnectQuery>,
+    rooms: web::Data<Addr<RoomRegistry>>,
+    call_service: web::Data<CallService>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, Error> {
+    // Negotiate protocol version
+    let version = negotiate_version(query.version).map_err(CallDockerError::from)?;
+
+    // Extract access token; customers have no agent socket
+    let token = get_token(&req, query.token.as_deref())
+        .ok_or_else(|| CallDockerError::Authentication("Missing token".to_string()))?;
+    let claims = decode_access_token(&token, &config.jwt.secret)?;
+
+    let agent_id = call_service.current_agent(&claims).await?;
+
     ws::start(
-        CallWebSocket::new(call_id),
+        AgentWebSocket::new(agent_id, version, claims.sub, rooms.get_ref().clone()),
         &req,
         stream,
     )
@@ -1,3 +1,4 @@
+use actix::Actor;
 use actix_web::{App, HttpServer, middleware::Logger, web};
 use actix_cors::Cors;
 use actix_web_prom::PrometheusMetricsBuilder;
@@ -6,12 +7,22 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod config;
 mod handlers;
+mod middleware;
+mod repositories;
+mod rooms;
+mod queue_updates;
 mod services;
 mod websocket;
 mod webrtc;
 
+#[cfg(test)]
+mod test_middleware;
+#[cfg(test)]
+mod test_rooms;
 #[cfg(test)]
 mod test_webrtc;
+#[cfg(test)]
+mod test_ws_auth;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -46,6 +57,22 @@ async fn main() -> std::io::Result<()> {
         redis_conn.clone(),
         config.clone(),
     );
+    call_service
+        .restore_queues()
+        .await
+        .expect("Failed to restore the routing queue");
+    services::queue_supervisor::spawn(
+        call_service.clone(),
+        std::time::Duration::from_secs(config.routing.queue_supervisor_interval.max(1)),
+    );
+    let routing_config_service = services::routing_config_service::RoutingConfigService::new(db_pool.clone());
+    let ivr_flow_service = services::ivr_flow_service::IVRFlowService::new(db_pool.clone());
+
+    // Signaling rooms shared by every WebSocket connection
+    let rooms = rooms::RoomRegistry::default().start();
+    queue_updates::spawn(redis_client.clone(), rooms.clone());
+    let app_config = config.clone();
+    let jwt_secret = config.jwt.secret.clone();
 
     // Create Prometheus metrics
     let prometheus = PrometheusMetricsBuilder::new("call_service")
@@ -56,7 +83,8 @@ async fn main() -> std::io::Result<()> {
     // Start HTTP server
     HttpServer::new(move || {
         App::new()
-            .wrap(Logger::default())
+            .wrap(shared::request_id::RequestId)
+            .wrap(Logger::new(r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}o"#))
             .wrap(prometheus.clone())
             .wrap(
                 Cors::default()
@@ -66,18 +94,81 @@ async fn main() -> std::io::Result<()> {
                     .max_age(3600)
             )
             .app_data(web::Data::new(call_service.clone()))
-            .service(handlers::health::health_check)
-            .service(handlers::calls::create_call)
-            .service(handlers::calls::get_call)
-            .service(handlers::calls::list_calls)
-            .service(handlers::calls::update_call)
-            .service(handlers::calls::end_call)
-            .service(handlers::webrtc::offer)
-            .service(handlers::webrtc::answer)
-            .service(handlers::webrtc::ice_candidate)
-            .service(handlers::websocket::ws_route)
+            .app_data(web::Data::new(routing_config_service.clone()))
+            .app_data(web::Data::new(ivr_flow_service.clone()))
+            .app_data(web::Data::new(rooms.clone()))
+            .app_data(web::Data::new(app_config.clone()))
+            .configure(|cfg| configure_routes(cfg, &jwt_secret))
     })
     .bind(format!("0.0.0.0:{}", config.server.port))?
     .run()
     .await
 }
+
+/// Register every route: public ones first, then the JWT-protected scope. The
+/// widget-facing routes stay public; the WebSocket checks its own token.
+pub fn configure_routes(cfg: &mut web::ServiceConfig, jwt_secret: &str) {
+    cfg
+        // Public routes
+        .service(handlers::health::health_check)
+        .service(handlers::calls::create_call)
+        .service(handlers::calls::ivr_input)
+        .service(handlers::webrtc::offer)
+        .service(handlers::webrtc::answer)
+        .service(handlers::webrtc::ice_candidate)
+        // Before `/ws/{call_id}` so "agent" is not parsed as an id
+        .service(handlers::websocket::agent_ws_route)
+        .service(handlers::websocket::ws_route)
+        // Protected routes; must stay last since the empty prefix matches every path
+        .service(
+            web::scope("")
+                .wrap(middleware::auth::AuthMiddleware::new(jwt_secret.to_string()))
+                .service(
+                    web::scope("/routing")
+                        .wrap(middleware::auth::RequireRole::new("company_admin"))
+                        // Fixed paths before `{rule_id}` so they are not parsed as ids
+                        .service(handlers::routing::reorder_rules)
+                        .service(handlers::routing::dry_run_rules)
+                        .service(handlers::routing::list_rules)
+                        .service(handlers::routing::create_rule)
+                        .service(handlers::routing::get_rule)
+                        .service(handlers::routing::update_rule)
+                        .service(handlers::routing::delete_rule)
+                        .service(handlers::routing::list_queues)
+                        .service(handlers::routing::create_queue)
+                        .service(handlers::routing::get_queue)
+                        .service(handlers::routing::update_queue)
+                        .service(handlers::routing::delete_queue)
+                        .service(handlers::routing::get_policy)
+                        .service(handlers::routing::update_policy)
+                        .service(handlers::routing::agent_stats),
+                )
+                .service(
+                    web::scope("/ivr")
+                        .wrap(middleware::auth::RequireRole::new("company_admin"))
+                        .service(handlers::ivr::list_flows)
+                        .service(handlers::ivr::create_flow)
+                        .service(handlers::ivr::get_flow)
+                        .service(handlers::ivr::update_flow)
+                        .service(handlers::ivr::delete_flow)
+                        .service(handlers::ivr::publish_flow)
+                        .service(handlers::ivr::list_versions)
+                        .service(handlers::ivr::get_version)
+                        .service(handlers::ivr::rollback_flow)
+                        .service(handlers::ivr::diff_flow)
+                        .service(handlers::ivr::list_templates)
+                        .service(handlers::ivr::create_template)
+                        .service(handlers::ivr::delete_template)
+                        .service(handlers::ivr::instantiate_template),
+                )
+                // Before `/calls/{call_id}` so "queue" is not parsed as an id
+                .service(handlers::calls::get_queue_stats)
+                .service(handlers::calls::get_call)
+                .service(handlers::calls::get_call_events)
+                .service(handlers::calls::list_calls)
+                .service(handlers::calls::update_call)
+                .service(handlers::calls::end_call)
+                .service(handlers::calls::accept_call)
+                .service(handlers::calls::reject_call),
+        );
+}
@@ -0,0 +1,258 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header,
+    Error, HttpMessage, HttpRequest,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use std::rc::Rc;
+use shared::{
+    auth::{Claims, ParticipantRole, UserRole, WidgetClaims},
+    call::Call,
+    CallDockerError,
+};
+use uuid::Uuid;
+
+/// Decodes the bearer token and stores its `Claims` in the request extensions.
+pub struct AuthMiddleware {
+    jwt_secret: String,
+}
+
+impl AuthMiddleware {
+    pub fn new(jwt_secret: String) -> Self {
+        Self { jwt_secret }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AuthMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthMiddlewareService {
+            service: Rc::new(service),
+            jwt_secret: self.jwt_secret.clone(),
+        }))
+    }
+}
+
+pub struct AuthMiddlewareService<S> {
+    service: Rc<S>,
+    jwt_secret: String,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let jwt_secret = self.jwt_secret.clone();
+
+        Box::pin(async move {
+            // Extract token from Authorization header
+            let token = req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or_else(|| CallDockerError::Authentication("Missing or invalid authorization header".to_string()))?;
+
+            // Decode and validate token
+            let claims = decode::<Claims>(
+                token,
+                &DecodingKey::from_secret(jwt_secret.as_ref()),
+                &Validation::default(),
+            )
+            .map_err(|_| CallDockerError::Authentication("Invalid token".to_string()))?;
+
+            // Add claims to request extensions
+            req.extensions_mut().insert(claims.claims);
+
+            svc.call(req).await
+        })
+    }
+}
+
+/// Rejects requests whose claims do not satisfy `has_role`. Must run inside
+/// `AuthMiddleware`, i.e. be registered with `.wrap` before it.
+pub struct RequireRole {
+    role: &'static str,
+}
+
+impl RequireRole {
+    pub fn new(role: &'static str) -> Self {
+        Self { role }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireRoleService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireRoleService {
+            service: Rc::new(service),
+            role: self.role,
+        }))
+    }
+}
+
+pub struct RequireRoleService<S> {
+    service: Rc<S>,
+    role: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireRoleService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let allowed = req
+            .extensions()
+            .get::<Claims>()
+            .map(|claims| has_role(claims, self.role));
+
+        Box::pin(async move {
+            match allowed {
+                Some(true) => svc.call(req).await,
+                Some(false) => Err(CallDockerError::Authorization("Insufficient permissions".to_string()).into()),
+                None => Err(CallDockerError::Authentication("Missing authentication".to_string()).into()),
+            }
+        })
+    }
+}
+
+// Helper function to extract claims from request
+pub fn get_claims(req: &HttpRequest) -> Result<Claims, CallDockerError> {
+    req.extensions()
+        .get::<Claims>()
+        .cloned()
+        .ok_or_else(|| CallDockerError::Authentication("Claims not found in request".to_string()))
+}
+
+// Helper function to check if user has required role
+pub fn has_role(claims: &Claims, required_role: &str) -> bool {
+    match required_role {
+        "super_admin" => matches!(claims.role, UserRole::SuperAdmin),
+        "company_admin" => matches!(claims.role, UserRole::SuperAdmin | UserRole::CompanyAdmin),
+        "agent" => true, // All roles can access agent-level endpoints
+        _ => false,
+    }
+}
+
+// Helper function to check the user may act on data owned by `company_id`
+pub fn authorize_company(claims: &Claims, company_id: Uuid) -> Result<(), CallDockerError> {
+    if claims.role.is_super_admin() || claims.company_id == Some(company_id) {
+        Ok(())
+    } else {
+        Err(CallDockerError::Authorization("Access denied to this company".to_string()))
+    }
+}
+
+// Helper function to pick the company a request acts on: the user's own, or for
+// super admins the one they name
+pub fn resolve_company(claims: &Claims, requested: Option<Uuid>) -> Result<Uuid, CallDockerError> {
+    if claims.role.is_super_admin() {
+        return requested.ok_or_else(|| CallDockerError::Validation("company_id is required".to_string()));
+    }
+
+    let company_id = claims
+        .company_id
+        .ok_or_else(|| CallDockerError::Authorization("User is not associated with a company".to_string()))?;
+    if requested.is_some_and(|requested| requested != company_id) {
+        return Err(CallDockerError::Authorization("Access denied to this company".to_string()));
+    }
+    Ok(company_id)
+}
+
+// Helper function to extract a token from the Authorization header or `token` query parameter.
+// Browsers cannot set headers on WebSocket upgrades, so sockets pass it in the query string.
+pub fn get_token(req: &HttpRequest, query_token: Option<&str>) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query_token)
+        .map(str::to_string)
+}
+
+/// Issue a widget token that only admits its holder to `call`.
+pub fn issue_widget_token(call: &Call, secret: &str, expiry: i64) -> Result<String, CallDockerError> {
+    let now = chrono::Utc::now().timestamp();
+    let claims = WidgetClaims {
+        sub: call.customer_id.unwrap_or_else(Uuid::new_v4),
+        call_id: call.id,
+        company_id: call.company_id,
+        exp: now + expiry,
+        iat: now,
+    };
+
+    Ok(encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref()))?)
+}
+
+/// Decode a user's access token; widget tokens are not accepted.
+pub fn decode_access_token(token: &str, secret: &str) -> Result<Claims, CallDockerError> {
+    let key = DecodingKey::from_secret(secret.as_ref());
+    decode::<Claims>(token, &key, &Validation::default())
+        .map(|data| data.claims)
+        .map_err(|_| CallDockerError::Authentication("Invalid token".to_string()))
+}
+
+/// Resolve who is joining `call` from either an access token or a widget token.
+/// Returns the participant's user id and role, or an error if the token is invalid
+/// or does not grant access to this call.
+pub fn authenticate_participant(
+    token: &str,
+    secret: &str,
+    call: &Call,
+) -> Result<(Uuid, ParticipantRole), CallDockerError> {
+    let key = DecodingKey::from_secret(secret.as_ref());
+    let validation = Validation::default();
+
+    if let Ok(data) = decode::<Claims>(token, &key, &validation) {
+        let claims = data.claims;
+        authorize_company(&claims, call.company_id)?;
+        return Ok((claims.sub, ParticipantRole::from(&claims.role)));
+    }
+
+    let claims = decode::<WidgetClaims>(token, &key, &validation)?.claims;
+    if claims.call_id != call.id || claims.company_id != call.company_id {
+        return Err(CallDockerError::Authorization("Widget token is not valid for this call".to_string()));
+    }
+
+    Ok((claims.sub, ParticipantRole::Customer))
+}
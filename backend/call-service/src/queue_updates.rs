@@ -0,0 +1,97 @@
+use std::time::Duration;
+use actix::Addr;
+use futures_util::StreamExt;
+use shared::routing::QueuedCall;
+use shared::signaling::{AgentUpdate, CallerUpdate, ServerMessage};
+use crate::rooms::{Notify, RoomRegistry};
+
+/// Channel pattern matching every company's queue channel.
+const QUEUE_CHANNELS: &str = "calls:*:queue";
+
+/// Channel pattern matching every company's agent channel.
+const AGENT_CHANNELS: &str = "calls:*:agents";
+
+/// Channel pattern matching every company's caller channel.
+const CALLER_CHANNELS: &str = "calls:*:callers";
+
+/// How long to wait before subscribing again after losing Redis.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Forward queue positions, caller updates such as IVR prompts, and agent
+/// updates such as offered calls, published by any instance to the callers and
+/// agents connected to this one. Runs for as
+/// long as the service does, resubscribing whenever the Redis connection drops.
+pub fn spawn(redis_client: redis::Client, rooms: Addr<RoomRegistry>) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = forward(&redis_client, &rooms).await {
+                tracing::warn!("Queue update subscription failed: {}", e);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn forward(redis_client: &redis::Client, rooms: &Addr<RoomRegistry>) -> redis::RedisResult<()> {
+    let mut pubsub = redis_client.get_async_connection().await?.into_pubsub();
+    pubsub.psubscribe(QUEUE_CHANNELS).await?;
+    pubsub.psubscribe(AGENT_CHANNELS).await?;
+    pubsub.psubscribe(CALLER_CHANNELS).await?;
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        let channel = message.get_channel_name();
+        let forwarded = if channel.ends_with(":agents") {
+            forward_agent_update(rooms, &payload)
+        } else if channel.ends_with(":callers") {
+            forward_caller_update(rooms, &payload)
+        } else {
+            forward_positions(rooms, &payload)
+        };
+        if let Err(e) = forwarded {
+            tracing::warn!("Ignoring malformed update on {}: {}", message.get_channel_name(), e);
+        }
+    }
+
+    Ok(())
+}
+
+fn forward_positions(rooms: &Addr<RoomRegistry>, payload: &str) -> serde_json::Result<()> {
+    let calls: Vec<QueuedCall> = serde_json::from_str(payload)?;
+
+    let queue_length = calls.len() as u32;
+    for call in calls {
+        rooms.do_send(Notify {
+            call_id: call.call_id,
+            message: ServerMessage::QueuePosition {
+                call_id: call.call_id,
+                position: call.position,
+                queue_length,
+                estimated_wait_time: call.estimated_wait_time,
+            },
+        });
+    }
+    Ok(())
+}
+
+fn forward_agent_update(rooms: &Addr<RoomRegistry>, payload: &str) -> serde_json::Result<()> {
+    let update: AgentUpdate = serde_json::from_str(payload)?;
+
+    // Agents' sockets share a room keyed by the agent id
+    rooms.do_send(Notify {
+        call_id: update.agent_id,
+        message: update.message,
+    });
+    Ok(())
+}
+
+fn forward_caller_update(rooms: &Addr<RoomRegistry>, payload: &str) -> serde_json::Result<()> {
+    let update: CallerUpdate = serde_json::from_str(payload)?;
+
+    rooms.do_send(Notify {
+        call_id: update.call_id,
+        message: update.message,
+    });
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::{Result, CallDockerError};
+use shared::routing::{response_percentage, AgentAvailability, AgentRoutingStats, AgentStatus};
+
+#[derive(Debug, FromRow)]
+struct AgentAvailabilityRow {
+    id: Uuid,
+    status: Option<String>,
+    current_calls: Option<i32>,
+    max_concurrent_calls: Option<i32>,
+    skills: Option<Vec<String>>,
+    is_active: Option<bool>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<AgentAvailabilityRow> for AgentAvailability {
+    type Error = CallDockerError;
+
+    fn try_from(row: AgentAvailabilityRow) -> Result<Self> {
+        Ok(AgentAvailability {
+            agent_id: row.id,
+            status: row
+                .status
+                .as_deref()
+                .unwrap_or("offline")
+                .parse()
+                .map_err(CallDockerError::Database)?,
+            current_calls: row.current_calls.unwrap_or(0).max(0) as u32,
+            max_calls: row.max_concurrent_calls.unwrap_or(1).max(0) as u32,
+            skills: row.skills.unwrap_or_default(),
+            last_activity: row.updated_at.unwrap_or_else(Utc::now),
+            is_available: row.is_active.unwrap_or(true),
+        })
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct AgentStatsRow {
+    id: Uuid,
+    name: String,
+    offered: i64,
+    accepted: i64,
+    rejected: i64,
+    missed: i64,
+    average_answer_time: Option<f64>,
+    average_call_duration: Option<f64>,
+}
+
+impl From<AgentStatsRow> for AgentRoutingStats {
+    fn from(row: AgentStatsRow) -> Self {
+        let offered = row.offered.max(0) as u64;
+        let accepted = row.accepted.max(0) as u64;
+        let rejected = row.rejected.max(0) as u64;
+
+        AgentRoutingStats {
+            agent_id: row.id,
+            agent_name: row.name,
+            total_calls_routed: offered,
+            answered_calls: accepted,
+            rejected_calls: rejected,
+            missed_calls: row.missed.max(0) as u64,
+            average_answer_time: row.average_answer_time.unwrap_or(0.0).round() as u64,
+            average_call_duration: row.average_call_duration.unwrap_or(0.0).round() as u64,
+            availability_percentage: response_percentage(offered, accepted + rejected),
+        }
+    }
+}
+
+const AVAILABILITY_COLUMNS: &str =
+    "id, status, current_calls, max_concurrent_calls, skills, is_active, updated_at";
+
+#[derive(Clone)]
+pub struct AgentRepository {
+    pool: PgPool,
+}
+
+impl AgentRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_availability(&self, id: Uuid) -> Result<Option<AgentAvailability>> {
+        let row = sqlx::query_as::<_, AgentAvailabilityRow>(&format!(
+            "SELECT {} FROM agents WHERE id = $1",
+            AVAILABILITY_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(AgentAvailability::try_from).transpose()
+    }
+
+    /// Online, active agents of a company with at least one free call slot,
+    /// least busy first.
+    pub async fn find_available_by_company(&self, company_id: Uuid) -> Result<Vec<AgentAvailability>> {
+        let rows = sqlx::query_as::<_, AgentAvailabilityRow>(&format!(
+            r#"
+            SELECT {} FROM agents
+            WHERE company_id = $1 AND is_active = true AND status = 'online'
+                  AND current_calls < max_concurrent_calls
+            ORDER BY current_calls ASC, total_calls_handled ASC
+            "#,
+            AVAILABILITY_COLUMNS
+        ))
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(AgentAvailability::try_from).collect()
+    }
+
+    /// Which of `ids` are agents of the company.
+    pub async fn find_ids_in_company(&self, company_id: Uuid, ids: &[Uuid]) -> Result<Vec<Uuid>> {
+        let found: Vec<(Uuid,)> = sqlx::query_as("SELECT id FROM agents WHERE company_id = $1 AND id = ANY($2)")
+            .bind(company_id)
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(found.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// Take one of the agent's concurrent call slots. The check and the increment
+    /// happen in one statement, so two calls can never both get the last slot.
+    /// Returns false when the agent is no longer available.
+    pub async fn reserve_call_slot(&self, id: Uuid) -> Result<bool> {
+        let reserved = sqlx::query(
+            r#"
+            UPDATE agents
+            SET current_calls = current_calls + 1,
+                updated_at = NOW()
+            WHERE id = $1 AND is_active = true AND status = 'online'
+                  AND current_calls < max_concurrent_calls
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(reserved.rows_affected() == 1)
+    }
+
+    /// Free one of the agent's concurrent call slots.
+    pub async fn release_call_slot(&self, id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE agents
+            SET current_calls = GREATEST(current_calls - 1, 0),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The agent a user works as within a company.
+    pub async fn find_id_by_user(&self, company_id: Uuid, user_id: Uuid) -> Result<Option<Uuid>> {
+        let found: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM agents WHERE company_id = $1 AND user_id = $2")
+            .bind(company_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(found.map(|(id,)| id))
+    }
+
+    pub async fn set_status(&self, id: Uuid, status: AgentStatus) -> Result<()> {
+        sqlx::query("UPDATE agents SET status = $2, updated_at = NOW() WHERE id = $1")
+            .bind(id)
+            .bind(status.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// How every agent of a company handled the calls offered to them between
+    /// `from` and `to`, taken from the offer events of those calls.
+    pub async fn routing_stats(
+        &self,
+        company_id: Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<AgentRoutingStats>> {
+        let rows = sqlx::query_as::<_, AgentStatsRow>(
+            r#"
+            SELECT a.id, a.name,
+                   COUNT(e.id) FILTER (WHERE e.event_type = 'call_offered') AS offered,
+                   COUNT(e.id) FILTER (WHERE e.event_type = 'offer_accepted') AS accepted,
+                   COUNT(e.id) FILTER (WHERE e.event_type = 'offer_rejected') AS rejected,
+                   COUNT(e.id) FILTER (WHERE e.event_type = 'offer_timed_out') AS missed,
+                   AVG((e.data->>'ring_time')::DOUBLE PRECISION)
+                       FILTER (WHERE e.event_type = 'offer_accepted') AS average_answer_time,
+                   (SELECT AVG(c.duration)::DOUBLE PRECISION FROM calls c
+                    WHERE c.agent_id = a.id AND c.ended_at >= $2 AND c.ended_at < $3) AS average_call_duration
+            FROM agents a
+            LEFT JOIN call_events e
+                   ON e.data->>'agent_id' = a.id::TEXT AND e.timestamp >= $2 AND e.timestamp < $3
+            WHERE a.company_id = $1
+            GROUP BY a.id, a.name
+            ORDER BY a.name ASC
+            "#,
+        )
+        .bind(company_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(AgentRoutingStats::from).collect())
+    }
+}
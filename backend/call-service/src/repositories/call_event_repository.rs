@@ -0,0 +1,75 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::{Result, CallDockerError};
+use shared::call::CallEvent;
+
+#[derive(Debug, FromRow)]
+struct CallEventRow {
+    id: Uuid,
+    call_id: Uuid,
+    event_type: String,
+    data: Option<serde_json::Value>,
+    timestamp: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<CallEventRow> for CallEvent {
+    type Error = CallDockerError;
+
+    fn try_from(row: CallEventRow) -> Result<Self> {
+        Ok(CallEvent {
+            id: row.id,
+            call_id: row.call_id,
+            event_type: row.event_type.parse().map_err(CallDockerError::Database)?,
+            data: row.data.unwrap_or_else(|| serde_json::json!({})),
+            timestamp: row.timestamp.unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct CallEventRepository {
+    pool: PgPool,
+}
+
+impl CallEventRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, event: &CallEvent) -> Result<CallEvent> {
+        let row = sqlx::query_as::<_, CallEventRow>(
+            r#"
+            INSERT INTO call_events (id, call_id, event_type, data, timestamp)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, call_id, event_type, data, timestamp
+            "#,
+        )
+        .bind(event.id)
+        .bind(event.call_id)
+        .bind(event.event_type.to_string())
+        .bind(&event.data)
+        .bind(event.timestamp)
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.try_into()
+    }
+
+    /// Every event recorded for a call, oldest first.
+    pub async fn list_by_call(&self, call_id: Uuid) -> Result<Vec<CallEvent>> {
+        let rows = sqlx::query_as::<_, CallEventRow>(
+            r#"
+            SELECT id, call_id, event_type, data, timestamp
+            FROM call_events
+            WHERE call_id = $1
+            ORDER BY timestamp ASC, id ASC
+            "#,
+        )
+        .bind(call_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(CallEvent::try_from).collect()
+    }
+}
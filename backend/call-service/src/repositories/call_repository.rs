@@ -0,0 +1,439 @@
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::{Result, CallDockerError};
+use shared::call::{Call, CallListQuery, CallNote, CallSortField, SortOrder};
+
+#[derive(Debug, FromRow)]
+struct CallRow {
+    id: Uuid,
+    company_id: Uuid,
+    agent_id: Option<Uuid>,
+    customer_id: Option<Uuid>,
+    status: String,
+    direction: String,
+    caller_number: Option<String>,
+    called_number: Option<String>,
+    customer_name: Option<String>,
+    customer_email: Option<String>,
+    duration: Option<i32>,
+    recording_url: Option<String>,
+    notes: Option<String>,
+    tags: Option<Vec<String>>,
+    metadata: Option<serde_json::Value>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    answered_at: Option<DateTime<Utc>>,
+    ended_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<CallRow> for Call {
+    type Error = CallDockerError;
+
+    fn try_from(row: CallRow) -> Result<Self> {
+        Ok(Call {
+            id: row.id,
+            company_id: row.company_id,
+            agent_id: row.agent_id,
+            customer_id: row.customer_id,
+            status: row.status.parse().map_err(CallDockerError::Database)?,
+            direction: row.direction.parse().map_err(CallDockerError::Database)?,
+            caller_number: row.caller_number,
+            called_number: row.called_number,
+            customer_name: row.customer_name,
+            customer_email: row.customer_email,
+            duration: row.duration.map(|d| d as u64),
+            recording_url: row.recording_url,
+            notes: row.notes,
+            tags: row.tags.unwrap_or_default(),
+            metadata: row.metadata.unwrap_or_else(|| serde_json::json!({})),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            answered_at: row.answered_at,
+            ended_at: row.ended_at,
+        })
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct CallNoteRow {
+    id: Uuid,
+    call_id: Uuid,
+    agent_id: Uuid,
+    content: String,
+    is_private: Option<bool>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<CallNoteRow> for CallNote {
+    fn from(row: CallNoteRow) -> Self {
+        CallNote {
+            id: row.id,
+            call_id: row.call_id,
+            agent_id: row.agent_id,
+            content: row.content,
+            is_private: row.is_private.unwrap_or(false),
+            created_at: row.created_at,
+        }
+    }
+}
+
+const CALL_COLUMNS: &str = r#"
+    id, company_id, agent_id, customer_id, status, direction, caller_number,
+    called_number, customer_name, customer_email, duration, recording_url, notes,
+    tags, metadata, created_at, updated_at, answered_at, ended_at
+"#;
+
+/// One page of calls plus the keyset cursor for the page after it.
+#[derive(Debug, Clone)]
+pub struct CallPage {
+    pub calls: Vec<Call>,
+    pub total: u64,
+    pub next_cursor: Option<String>,
+}
+
+/// Keyset position encoded as `<sort value>_<call id>`.
+struct CallCursor {
+    value: i64,
+    id: Uuid,
+}
+
+impl CallCursor {
+    fn for_call(call: &Call, sort_by: CallSortField) -> Self {
+        let value = match sort_by {
+            CallSortField::CreatedAt => call.created_at.timestamp_micros(),
+            CallSortField::Duration => call.duration.unwrap_or(0) as i64,
+        };
+        Self { value, id: call.id }
+    }
+
+    fn encode(&self) -> String {
+        format!("{}_{}", self.value, self.id)
+    }
+
+    fn decode(cursor: &str) -> Result<Self> {
+        let invalid = || CallDockerError::Validation(format!("Invalid cursor: {}", cursor));
+        let (value, id) = cursor.split_once('_').ok_or_else(invalid)?;
+
+        Ok(Self {
+            value: value.parse().map_err(|_| invalid())?,
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct CallRepository {
+    pool: PgPool,
+}
+
+impl CallRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, call: &Call) -> Result<Call> {
+        let row = sqlx::query_as::<_, CallRow>(&format!(
+            r#"
+            INSERT INTO calls (id, company_id, agent_id, customer_id, status, direction,
+                               caller_number, called_number, customer_name, customer_email,
+                               duration, recording_url, notes, tags, metadata,
+                               created_at, updated_at, answered_at, ended_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
+            RETURNING {}
+            "#,
+            CALL_COLUMNS
+        ))
+        .bind(call.id)
+        .bind(call.company_id)
+        .bind(call.agent_id)
+        .bind(call.customer_id)
+        .bind(call.status.to_string())
+        .bind(call.direction.to_string())
+        .bind(&call.caller_number)
+        .bind(&call.called_number)
+        .bind(&call.customer_name)
+        .bind(&call.customer_email)
+        .bind(call.duration.map(|d| d as i32))
+        .bind(&call.recording_url)
+        .bind(&call.notes)
+        .bind(&call.tags)
+        .bind(&call.metadata)
+        .bind(call.created_at)
+        .bind(call.updated_at)
+        .bind(call.answered_at)
+        .bind(call.ended_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.try_into()
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Call>> {
+        let row = sqlx::query_as::<_, CallRow>(&format!(
+            "SELECT {} FROM calls WHERE id = $1",
+            CALL_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(Call::try_from).transpose()
+    }
+
+    /// Persist a call's tags, notes and metadata only, leaving its status and
+    /// agent to whoever else is moving the call on.
+    pub async fn update_details(&self, call: &Call) -> Result<Call> {
+        let row = sqlx::query_as::<_, CallRow>(&format!(
+            r#"
+            UPDATE calls
+            SET notes = $2,
+                tags = $3,
+                metadata = $4,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            CALL_COLUMNS
+        ))
+        .bind(call.id)
+        .bind(&call.notes)
+        .bind(&call.tags)
+        .bind(&call.metadata)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| CallDockerError::CallNotFound(call.id.to_string()))?;
+
+        row.try_into()
+    }
+
+    /// Persist every mutable column of a call read as `previous`. Should
+    /// another request have changed its status or agent since, nothing is
+    /// written and the change is rejected.
+    pub async fn update_from(&self, call: &Call, previous: &Call) -> Result<Call> {
+        let row = sqlx::query_as::<_, CallRow>(&format!(
+            r#"
+            UPDATE calls
+            SET agent_id = $2,
+                status = $3,
+                duration = $4,
+                recording_url = $5,
+                notes = $6,
+                tags = $7,
+                metadata = $8,
+                answered_at = $9,
+                ended_at = $10,
+                updated_at = NOW()
+            WHERE id = $1 AND status = $11 AND agent_id IS NOT DISTINCT FROM $12
+            RETURNING {}
+            "#,
+            CALL_COLUMNS
+        ))
+        .bind(call.id)
+        .bind(call.agent_id)
+        .bind(call.status.to_string())
+        .bind(call.duration.map(|d| d as i32))
+        .bind(&call.recording_url)
+        .bind(&call.notes)
+        .bind(&call.tags)
+        .bind(&call.metadata)
+        .bind(call.answered_at)
+        .bind(call.ended_at)
+        .bind(previous.status.to_string())
+        .bind(previous.agent_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| {
+            CallDockerError::InvalidTransition(format!(
+                "Call {} changed while moving from {} to {}",
+                call.id, previous.status, call.status
+            ))
+        })?;
+
+        row.try_into()
+    }
+
+    /// List calls matching every filter in `query`, using keyset pagination when a
+    /// cursor is supplied and page/offset pagination otherwise.
+    pub async fn list(&self, query: &CallListQuery) -> Result<CallPage> {
+        let mut count = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM calls WHERE 1 = 1");
+        Self::push_filters(&mut count, query);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let sort_key = match query.sort_by {
+            CallSortField::CreatedAt => "created_at",
+            CallSortField::Duration => "COALESCE(duration, 0)",
+        };
+        let (comparison, direction) = match query.sort_order {
+            SortOrder::Asc => (">", "ASC"),
+            SortOrder::Desc => ("<", "DESC"),
+        };
+
+        let mut select = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM calls WHERE 1 = 1",
+            CALL_COLUMNS
+        ));
+        Self::push_filters(&mut select, query);
+
+        let cursor = query.cursor.as_deref().map(CallCursor::decode).transpose()?;
+        if let Some(cursor) = &cursor {
+            select.push(format!(" AND ({}, id) {} (", sort_key, comparison));
+            match query.sort_by {
+                CallSortField::CreatedAt => {
+                    let created_at = DateTime::<Utc>::from_timestamp_micros(cursor.value)
+                        .ok_or_else(|| CallDockerError::Validation("Invalid cursor timestamp".to_string()))?;
+                    select.push_bind(created_at);
+                }
+                CallSortField::Duration => {
+                    select.push_bind(cursor.value);
+                }
+            }
+            select.push(", ").push_bind(cursor.id).push(")");
+        }
+
+        let per_page = query.per_page as i64;
+        select.push(format!(" ORDER BY {} {}, id {}", sort_key, direction, direction));
+        // Fetch one extra row to know whether another page exists
+        select.push(" LIMIT ").push_bind(per_page + 1);
+        if cursor.is_none() {
+            select
+                .push(" OFFSET ")
+                .push_bind((query.page.max(1) as i64 - 1) * per_page);
+        }
+
+        let rows = select.build_query_as::<CallRow>().fetch_all(&self.pool).await?;
+        let has_more = rows.len() as i64 > per_page;
+
+        let calls = rows
+            .into_iter()
+            .take(query.per_page as usize)
+            .map(Call::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        let next_cursor = if has_more {
+            calls
+                .last()
+                .map(|call| CallCursor::for_call(call, query.sort_by).encode())
+        } else {
+            None
+        };
+
+        Ok(CallPage {
+            calls,
+            total: total as u64,
+            next_cursor,
+        })
+    }
+
+    fn push_filters(builder: &mut QueryBuilder<'_, Postgres>, query: &CallListQuery) {
+        if let Some(company_id) = query.company_id {
+            builder.push(" AND company_id = ").push_bind(company_id);
+        }
+        if let Some(agent_id) = query.agent_id {
+            builder.push(" AND agent_id = ").push_bind(agent_id);
+        }
+        if let Some(status) = &query.status {
+            builder.push(" AND status = ").push_bind(status.to_string());
+        }
+        if let Some(direction) = &query.direction {
+            builder.push(" AND direction = ").push_bind(direction.to_string());
+        }
+        if let Some(tag) = &query.tag {
+            builder.push(" AND ").push_bind(tag.clone()).push(" = ANY(tags)");
+        }
+        if let Some(caller_number) = &query.caller_number {
+            builder
+                .push(" AND caller_number LIKE ")
+                .push_bind(format!("%{}%", escape_like(caller_number)))
+                .push(r" ESCAPE '\'");
+        }
+        if let Some(from) = query.from {
+            builder.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = query.to {
+            builder.push(" AND created_at < ").push_bind(to);
+        }
+    }
+
+    pub async fn create_note(
+        &self,
+        call_id: Uuid,
+        agent_id: Uuid,
+        content: &str,
+        is_private: bool,
+    ) -> Result<CallNote> {
+        let row = sqlx::query_as::<_, CallNoteRow>(
+            r#"
+            INSERT INTO call_notes (call_id, agent_id, content, is_private)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, call_id, agent_id, content, is_private, created_at
+            "#,
+        )
+        .bind(call_id)
+        .bind(agent_id)
+        .bind(content)
+        .bind(is_private)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.into())
+    }
+
+    /// Average talk time in seconds of the company's last `sample` calls that
+    /// an agent handled, or `None` before the first one
+    pub async fn recent_handle_time(&self, company_id: Uuid, sample: i64) -> Result<Option<f64>> {
+        let (average,): (Option<f64>,) = sqlx::query_as(
+            r#"
+            SELECT AVG(duration)::FLOAT8
+            FROM (
+                SELECT duration
+                FROM calls
+                WHERE company_id = $1 AND agent_id IS NOT NULL AND status = 'ended' AND duration IS NOT NULL
+                ORDER BY ended_at DESC NULLS LAST
+                LIMIT $2
+            ) recent
+            "#,
+        )
+        .bind(company_id)
+        .bind(sample)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(average)
+    }
+
+    /// Agent who answered the customer's most recent call since `since`, other
+    /// than `call`. The customer is recognised by id, email or phone number.
+    pub async fn last_agent_for_customer(&self, call: &Call, since: DateTime<Utc>) -> Result<Option<Uuid>> {
+        if call.customer_id.is_none() && call.customer_email.is_none() && call.caller_number.is_none() {
+            return Ok(None);
+        }
+
+        let agent_id: Option<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT agent_id
+            FROM calls
+            WHERE company_id = $1 AND id <> $2 AND agent_id IS NOT NULL AND answered_at IS NOT NULL
+                  AND created_at >= $6
+                  AND (customer_id = $3 OR LOWER(customer_email) = LOWER($4) OR caller_number = $5)
+            ORDER BY answered_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(call.company_id)
+        .bind(call.id)
+        .bind(call.customer_id)
+        .bind(call.customer_email.as_deref())
+        .bind(call.caller_number.as_deref())
+        .bind(since)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(agent_id.map(|(agent_id,)| agent_id))
+    }
+}
+
+/// `text` matched literally by `LIKE`, its wildcards escaped
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
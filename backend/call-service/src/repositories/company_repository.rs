@@ -0,0 +1,92 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use shared::{Result, CallDockerError};
+use shared::company::CompanyContact;
+use shared::routing_policy::RoutingPolicy;
+
+#[derive(Debug, FromRow)]
+struct CompanyContactRow {
+    name: String,
+    phone: Option<String>,
+    website: Option<String>,
+    address: Option<String>,
+}
+
+impl From<CompanyContactRow> for CompanyContact {
+    fn from(row: CompanyContactRow) -> Self {
+        CompanyContact {
+            name: row.name,
+            phone: row.phone,
+            website: row.website,
+            address: row.address,
+        }
+    }
+}
+
+/// The parts of a company's settings the call service owns.
+#[derive(Clone)]
+pub struct CompanyRepository {
+    pool: PgPool,
+}
+
+impl CompanyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// The company's name and how callers reach it
+    pub async fn find_contact(&self, company_id: Uuid) -> Result<CompanyContact> {
+        let row = sqlx::query_as::<_, CompanyContactRow>(
+            "SELECT name, phone, website, address FROM companies WHERE id = $1",
+        )
+        .bind(company_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(CompanyContact::from)
+            .ok_or_else(|| CallDockerError::CompanyNotFound(company_id.to_string()))
+    }
+
+    /// The company's routing policy, or the default one when it has none.
+    /// A stored policy that no longer parses is ignored rather than blocking
+    /// routing.
+    pub async fn find_routing_policy(&self, company_id: Uuid) -> Result<RoutingPolicy> {
+        let stored: Option<(Option<serde_json::Value>,)> =
+            sqlx::query_as("SELECT settings -> 'routing_policy' FROM companies WHERE id = $1")
+                .bind(company_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        let Some((policy,)) = stored else {
+            return Err(CallDockerError::CompanyNotFound(company_id.to_string()));
+        };
+        match policy.map(serde_json::from_value::<RoutingPolicy>) {
+            None => Ok(RoutingPolicy::default()),
+            Some(Ok(policy)) => Ok(policy),
+            Some(Err(e)) => {
+                tracing::warn!("Ignoring invalid routing policy of company {}: {}", company_id, e);
+                Ok(RoutingPolicy::default())
+            }
+        }
+    }
+
+    pub async fn save_routing_policy(&self, company_id: Uuid, policy: &RoutingPolicy) -> Result<RoutingPolicy> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE companies
+            SET settings = jsonb_set(settings, '{routing_policy}', $2),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(company_id)
+        .bind(serde_json::to_value(policy)?)
+        .execute(&self.pool)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(CallDockerError::CompanyNotFound(company_id.to_string()));
+        }
+        Ok(policy.clone())
+    }
+}
@@ -0,0 +1,293 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::{Result, CallDockerError};
+use shared::ivr::{IVRFlow, IVRFlowVersion};
+
+#[derive(Debug, FromRow)]
+struct IVRFlowRow {
+    id: Uuid,
+    company_id: Uuid,
+    name: String,
+    description: Option<String>,
+    is_active: Option<bool>,
+    welcome_message: Option<String>,
+    welcome_audio_url: Option<String>,
+    nodes: serde_json::Value,
+    published_version: Option<i32>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<IVRFlowRow> for IVRFlow {
+    type Error = CallDockerError;
+
+    fn try_from(row: IVRFlowRow) -> Result<Self> {
+        let created_at = row.created_at.unwrap_or_else(Utc::now);
+        Ok(IVRFlow {
+            id: row.id,
+            company_id: row.company_id,
+            name: row.name,
+            description: row.description,
+            is_active: row.is_active.unwrap_or(true),
+            welcome_message: row.welcome_message,
+            welcome_audio_url: row.welcome_audio_url,
+            nodes: serde_json::from_value(row.nodes)
+                .map_err(|e| CallDockerError::Database(format!("Invalid nodes on IVR flow {}: {}", row.id, e)))?,
+            published_version: row.published_version.map(|version| version.max(0) as u32),
+            created_at,
+            updated_at: row.updated_at.unwrap_or(created_at),
+        })
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct IVRFlowVersionRow {
+    id: Uuid,
+    flow_id: Uuid,
+    version: i32,
+    name: String,
+    description: Option<String>,
+    welcome_message: Option<String>,
+    welcome_audio_url: Option<String>,
+    nodes: serde_json::Value,
+    published_by: Option<Uuid>,
+    created_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<IVRFlowVersionRow> for IVRFlowVersion {
+    type Error = CallDockerError;
+
+    fn try_from(row: IVRFlowVersionRow) -> Result<Self> {
+        Ok(IVRFlowVersion {
+            id: row.id,
+            flow_id: row.flow_id,
+            version: row.version.max(0) as u32,
+            name: row.name,
+            description: row.description,
+            welcome_message: row.welcome_message,
+            welcome_audio_url: row.welcome_audio_url,
+            nodes: serde_json::from_value(row.nodes).map_err(|e| {
+                let message = format!("Invalid nodes on IVR flow {} version {}: {}", row.flow_id, row.version, e);
+                CallDockerError::Database(message)
+            })?,
+            published_by: row.published_by,
+            created_at: row.created_at.unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+const FLOW_COLUMNS: &str = "id, company_id, name, description, is_active, welcome_message, welcome_audio_url, nodes, \
+                            published_version, created_at, updated_at";
+
+const VERSION_COLUMNS: &str =
+    "id, flow_id, version, name, description, welcome_message, welcome_audio_url, nodes, published_by, created_at";
+
+/// IVR flows, whose own row is the draft, and their published versions.
+#[derive(Clone)]
+pub struct IVRFlowRepository {
+    pool: PgPool,
+}
+
+impl IVRFlowRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, flow: &IVRFlow) -> Result<IVRFlow> {
+        let row = sqlx::query_as::<_, IVRFlowRow>(&format!(
+            r#"
+            INSERT INTO ivr_flows (id, company_id, name, description, is_active, welcome_message, welcome_audio_url,
+                                   nodes, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING {}
+            "#,
+            FLOW_COLUMNS
+        ))
+        .bind(flow.id)
+        .bind(flow.company_id)
+        .bind(&flow.name)
+        .bind(&flow.description)
+        .bind(flow.is_active)
+        .bind(&flow.welcome_message)
+        .bind(&flow.welcome_audio_url)
+        .bind(serde_json::to_value(&flow.nodes)?)
+        .bind(flow.created_at)
+        .bind(flow.updated_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.try_into()
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<IVRFlow>> {
+        let row = sqlx::query_as::<_, IVRFlowRow>(&format!("SELECT {} FROM ivr_flows WHERE id = $1", FLOW_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(IVRFlow::try_from).transpose()
+    }
+
+    pub async fn list_by_company(&self, company_id: Uuid) -> Result<Vec<IVRFlow>> {
+        let rows = sqlx::query_as::<_, IVRFlowRow>(&format!(
+            r#"
+            SELECT {} FROM ivr_flows
+            WHERE company_id = $1
+            ORDER BY name ASC, created_at ASC
+            "#,
+            FLOW_COLUMNS
+        ))
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(IVRFlow::try_from).collect()
+    }
+
+    /// Save the draft and whether the flow is active. The published version is
+    /// left alone.
+    pub async fn update(&self, flow: &IVRFlow) -> Result<IVRFlow> {
+        let row = sqlx::query_as::<_, IVRFlowRow>(&format!(
+            r#"
+            UPDATE ivr_flows
+            SET name = $2,
+                description = $3,
+                is_active = $4,
+                welcome_message = $5,
+                welcome_audio_url = $6,
+                nodes = $7,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            FLOW_COLUMNS
+        ))
+        .bind(flow.id)
+        .bind(&flow.name)
+        .bind(&flow.description)
+        .bind(flow.is_active)
+        .bind(&flow.welcome_message)
+        .bind(&flow.welcome_audio_url)
+        .bind(serde_json::to_value(&flow.nodes)?)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(IVRFlow::try_from)
+            .transpose()?
+            .ok_or_else(|| CallDockerError::IVRFlowNotFound(flow.id.to_string()))
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        let deleted = sqlx::query("DELETE FROM ivr_flows WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(CallDockerError::IVRFlowNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Store `flow`'s draft as its next version and make that version live,
+    /// all or nothing. Concurrent publishes of a flow take turns.
+    pub async fn publish(&self, flow: &IVRFlow, published_by: Option<Uuid>) -> Result<IVRFlowVersion> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("SELECT id FROM ivr_flows WHERE id = $1 FOR UPDATE")
+            .bind(flow.id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| CallDockerError::IVRFlowNotFound(flow.id.to_string()))?;
+
+        // Read once the lock is held, so it sees the previous publish
+        let latest: Option<i32> = sqlx::query_scalar("SELECT MAX(version) FROM ivr_flow_versions WHERE flow_id = $1")
+            .bind(flow.id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let version = IVRFlowVersion::snapshot(flow, latest.unwrap_or(0).max(0) as u32 + 1, published_by, Utc::now());
+        let row = sqlx::query_as::<_, IVRFlowVersionRow>(&format!(
+            r#"
+            INSERT INTO ivr_flow_versions (id, flow_id, version, name, description, welcome_message,
+                                           welcome_audio_url, nodes, published_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING {}
+            "#,
+            VERSION_COLUMNS
+        ))
+        .bind(version.id)
+        .bind(version.flow_id)
+        .bind(version.version as i32)
+        .bind(&version.name)
+        .bind(&version.description)
+        .bind(&version.welcome_message)
+        .bind(&version.welcome_audio_url)
+        .bind(serde_json::to_value(&version.nodes)?)
+        .bind(version.published_by)
+        .bind(version.created_at)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE ivr_flows SET published_version = $2 WHERE id = $1")
+            .bind(flow.id)
+            .bind(version.version as i32)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        row.try_into()
+    }
+
+    /// Make an already published version the one new calls run
+    pub async fn set_published_version(&self, id: Uuid, version: u32) -> Result<IVRFlow> {
+        let row = sqlx::query_as::<_, IVRFlowRow>(&format!(
+            r#"
+            UPDATE ivr_flows
+            SET published_version = $2
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            FLOW_COLUMNS
+        ))
+        .bind(id)
+        .bind(version as i32)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(IVRFlow::try_from)
+            .transpose()?
+            .ok_or_else(|| CallDockerError::IVRFlowNotFound(id.to_string()))
+    }
+
+    pub async fn find_version(&self, flow_id: Uuid, version: u32) -> Result<Option<IVRFlowVersion>> {
+        let row = sqlx::query_as::<_, IVRFlowVersionRow>(&format!(
+            "SELECT {} FROM ivr_flow_versions WHERE flow_id = $1 AND version = $2",
+            VERSION_COLUMNS
+        ))
+        .bind(flow_id)
+        .bind(version as i32)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(IVRFlowVersion::try_from).transpose()
+    }
+
+    /// Every published version of a flow, newest first
+    pub async fn list_versions(&self, flow_id: Uuid) -> Result<Vec<IVRFlowVersion>> {
+        let rows = sqlx::query_as::<_, IVRFlowVersionRow>(&format!(
+            r#"
+            SELECT {} FROM ivr_flow_versions
+            WHERE flow_id = $1
+            ORDER BY version DESC
+            "#,
+            VERSION_COLUMNS
+        ))
+        .bind(flow_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(IVRFlowVersion::try_from).collect()
+    }
+}
@@ -0,0 +1,182 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::{Result, CallDockerError};
+use shared::ivr::{IVRInteraction, IVRSession};
+
+#[derive(Debug, FromRow)]
+struct IVRSessionRow {
+    id: Uuid,
+    call_id: Uuid,
+    flow_id: Uuid,
+    current_node_id: Uuid,
+    session_data: Option<serde_json::Value>,
+    attempts: Option<i32>,
+    is_active: Option<bool>,
+    flow_version: Option<i32>,
+    timeout_at: Option<DateTime<Utc>>,
+    outcome: Option<serde_json::Value>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<IVRSessionRow> for IVRSession {
+    type Error = CallDockerError;
+
+    fn try_from(row: IVRSessionRow) -> Result<Self> {
+        let created_at = row.created_at.unwrap_or_else(Utc::now);
+        Ok(IVRSession {
+            id: row.id,
+            call_id: row.call_id,
+            flow_id: row.flow_id,
+            current_node_id: row.current_node_id,
+            session_data: row.session_data.unwrap_or_else(|| serde_json::json!({})),
+            attempts: row.attempts.unwrap_or(0).max(0) as u32,
+            is_active: row.is_active.unwrap_or(false),
+            flow_version: row.flow_version.map(|version| version.max(0) as u32),
+            timeout_at: row.timeout_at,
+            outcome: row
+                .outcome
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|e| CallDockerError::Database(format!("Invalid outcome on IVR session {}: {}", row.id, e)))?,
+            created_at,
+            updated_at: row.updated_at.unwrap_or(created_at),
+        })
+    }
+}
+
+const SESSION_COLUMNS: &str = "id, call_id, flow_id, current_node_id, session_data, attempts, is_active, flow_version, \
+                               timeout_at, outcome, created_at, updated_at";
+
+/// IVR sessions and the caller input they handled.
+#[derive(Clone)]
+pub struct IVRSessionRepository {
+    pool: PgPool,
+}
+
+impl IVRSessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, session: &IVRSession) -> Result<IVRSession> {
+        let row = sqlx::query_as::<_, IVRSessionRow>(&format!(
+            r#"
+            INSERT INTO ivr_sessions (id, call_id, flow_id, current_node_id, session_data, attempts, is_active,
+                                      flow_version, timeout_at, outcome, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(session.id)
+        .bind(session.call_id)
+        .bind(session.flow_id)
+        .bind(session.current_node_id)
+        .bind(&session.session_data)
+        .bind(session.attempts as i32)
+        .bind(session.is_active)
+        .bind(session.flow_version.map(|version| version as i32))
+        .bind(session.timeout_at)
+        .bind(session.outcome.as_ref().map(serde_json::to_value).transpose()?)
+        .bind(session.created_at)
+        .bind(session.updated_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.try_into()
+    }
+
+    /// Save a session the engine moved on, unless it changed since it was read
+    /// at `read_at`. Returns `None` when another request or instance got there
+    /// first, so each input is handled exactly once.
+    pub async fn update(&self, session: &IVRSession, read_at: DateTime<Utc>) -> Result<Option<IVRSession>> {
+        let row = sqlx::query_as::<_, IVRSessionRow>(&format!(
+            r#"
+            UPDATE ivr_sessions
+            SET current_node_id = $3, session_data = $4, attempts = $5, is_active = $6, timeout_at = $7,
+                outcome = $8, updated_at = NOW()
+            WHERE id = $1 AND updated_at = $2
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(session.id)
+        .bind(read_at)
+        .bind(session.current_node_id)
+        .bind(&session.session_data)
+        .bind(session.attempts as i32)
+        .bind(session.is_active)
+        .bind(session.timeout_at)
+        .bind(session.outcome.as_ref().map(serde_json::to_value).transpose()?)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(IVRSession::try_from).transpose()
+    }
+
+    /// The session a call is in, if its IVR flow is still running.
+    pub async fn find_active_by_call(&self, call_id: Uuid) -> Result<Option<IVRSession>> {
+        let row = sqlx::query_as::<_, IVRSessionRow>(&format!(
+            r#"
+            SELECT {} FROM ivr_sessions
+            WHERE call_id = $1 AND is_active = true
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(call_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(IVRSession::try_from).transpose()
+    }
+
+    /// Running sessions whose caller did not answer by `now`.
+    pub async fn list_timed_out(&self, now: DateTime<Utc>) -> Result<Vec<IVRSession>> {
+        let rows = sqlx::query_as::<_, IVRSessionRow>(&format!(
+            r#"
+            SELECT {} FROM ivr_sessions
+            WHERE is_active = true AND timeout_at <= $1
+            ORDER BY timeout_at ASC
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(IVRSession::try_from).collect()
+    }
+
+    /// How many calls are still in a flow
+    pub async fn count_active_by_flow(&self, flow_id: Uuid) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ivr_sessions WHERE flow_id = $1 AND is_active = true")
+            .bind(flow_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    pub async fn create_interaction(&self, interaction: &IVRInteraction) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ivr_interactions (id, session_id, node_id, input, selected_option, timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(interaction.id)
+        .bind(interaction.session_id)
+        .bind(interaction.node_id)
+        .bind(&interaction.input)
+        .bind(&interaction.selected_option)
+        .bind(interaction.timestamp)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
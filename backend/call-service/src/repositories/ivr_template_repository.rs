@@ -0,0 +1,124 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::{Result, CallDockerError};
+use shared::ivr::IVRTemplate;
+
+#[derive(Debug, FromRow)]
+struct IVRTemplateRow {
+    id: Uuid,
+    company_id: Option<Uuid>,
+    name: String,
+    description: Option<String>,
+    category: String,
+    template_data: serde_json::Value,
+    is_public: Option<bool>,
+    created_by: Option<Uuid>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<IVRTemplateRow> for IVRTemplate {
+    type Error = CallDockerError;
+
+    fn try_from(row: IVRTemplateRow) -> Result<Self> {
+        let created_at = row.created_at.unwrap_or_else(Utc::now);
+        Ok(IVRTemplate {
+            id: row.id,
+            company_id: row.company_id,
+            name: row.name,
+            description: row.description,
+            category: row.category.parse().map_err(CallDockerError::Database)?,
+            template_data: serde_json::from_value(row.template_data).map_err(|e| {
+                CallDockerError::Database(format!("Invalid template data on IVR template {}: {}", row.id, e))
+            })?,
+            is_public: row.is_public.unwrap_or(false),
+            created_by: row.created_by,
+            created_at,
+            updated_at: row.updated_at.unwrap_or(created_at),
+        })
+    }
+}
+
+const TEMPLATE_COLUMNS: &str =
+    "id, company_id, name, description, category, template_data, is_public, created_by, created_at, updated_at";
+
+/// IVR templates saved by companies or offered to all of them. The built-in
+/// templates of `shared::ivr_template` are not stored.
+#[derive(Clone)]
+pub struct IVRTemplateRepository {
+    pool: PgPool,
+}
+
+impl IVRTemplateRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, template: &IVRTemplate) -> Result<IVRTemplate> {
+        let row = sqlx::query_as::<_, IVRTemplateRow>(&format!(
+            r#"
+            INSERT INTO ivr_templates (id, company_id, name, description, category, template_data, is_public,
+                                       created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING {}
+            "#,
+            TEMPLATE_COLUMNS
+        ))
+        .bind(template.id)
+        .bind(template.company_id)
+        .bind(&template.name)
+        .bind(&template.description)
+        .bind(template.category.to_string())
+        .bind(serde_json::to_value(&template.template_data)?)
+        .bind(template.is_public)
+        .bind(template.created_by)
+        .bind(template.created_at)
+        .bind(template.updated_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.try_into()
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<IVRTemplate>> {
+        let row = sqlx::query_as::<_, IVRTemplateRow>(&format!(
+            "SELECT {} FROM ivr_templates WHERE id = $1",
+            TEMPLATE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(IVRTemplate::try_from).transpose()
+    }
+
+    /// The company's own templates and the public ones, by name
+    pub async fn list_for_company(&self, company_id: Uuid) -> Result<Vec<IVRTemplate>> {
+        let rows = sqlx::query_as::<_, IVRTemplateRow>(&format!(
+            r#"
+            SELECT {} FROM ivr_templates
+            WHERE company_id = $1 OR is_public = true
+            ORDER BY name ASC, created_at ASC
+            "#,
+            TEMPLATE_COLUMNS
+        ))
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(IVRTemplate::try_from).collect()
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        let deleted = sqlx::query("DELETE FROM ivr_templates WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(CallDockerError::IVRTemplateNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+pub mod agent_repository;
+pub mod call_event_repository;
+pub mod call_repository;
+pub mod company_repository;
+pub mod ivr_flow_repository;
+pub mod ivr_session_repository;
+pub mod ivr_template_repository;
+pub mod queue_item_repository;
+pub mod routing_queue_repository;
+pub mod routing_rule_repository;
+
+pub use agent_repository::*;
+pub use call_event_repository::*;
+pub use call_repository::*;
+pub use company_repository::*;
+pub use ivr_flow_repository::*;
+pub use ivr_session_repository::*;
+pub use ivr_template_repository::*;
+pub use queue_item_repository::*;
+pub use routing_queue_repository::*;
+pub use routing_rule_repository::*;
@@ -0,0 +1,156 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::Result;
+use shared::call::CallQueue;
+use shared::routing::PRIORITY_WEIGHT_SECS;
+
+#[derive(Debug, FromRow)]
+struct QueueItemRow {
+    id: Uuid,
+    company_id: Uuid,
+    call_id: Uuid,
+    queue_id: Option<Uuid>,
+    priority: Option<i32>,
+    aging_seconds_per_point: Option<f64>,
+    skills_required: Option<Vec<String>>,
+    wait_start: Option<DateTime<Utc>>,
+    preferred_agent_id: Option<Uuid>,
+    preferred_until: Option<DateTime<Utc>>,
+}
+
+impl From<QueueItemRow> for CallQueue {
+    fn from(row: QueueItemRow) -> Self {
+        CallQueue {
+            id: row.id,
+            company_id: row.company_id,
+            call_id: row.call_id,
+            priority: row.priority.unwrap_or(0).max(0) as u32,
+            skills_required: row.skills_required.unwrap_or_default(),
+            queue_id: row.queue_id,
+            aging_seconds_per_point: row.aging_seconds_per_point.unwrap_or(PRIORITY_WEIGHT_SECS),
+            preferred_agent_id: row.preferred_agent_id,
+            preferred_until: row.preferred_until,
+            created_at: row.wait_start.unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+/// Durable copy of the Redis call queue, used to rebuild it after a restart.
+#[derive(Clone)]
+pub struct QueueItemRepository {
+    pool: PgPool,
+}
+
+impl QueueItemRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record a waiting call. Re-queueing a call replaces its previous entry.
+    pub async fn upsert(&self, entry: &CallQueue, position: u32) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO queue_items (id, company_id, call_id, queue_id, priority, aging_seconds_per_point,
+                                     skills_required, wait_start, position, preferred_agent_id, preferred_until)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (call_id) DO UPDATE
+            SET queue_id = EXCLUDED.queue_id,
+                priority = EXCLUDED.priority,
+                aging_seconds_per_point = EXCLUDED.aging_seconds_per_point,
+                skills_required = EXCLUDED.skills_required,
+                position = EXCLUDED.position,
+                preferred_agent_id = EXCLUDED.preferred_agent_id,
+                preferred_until = EXCLUDED.preferred_until
+            "#,
+        )
+        .bind(entry.id)
+        .bind(entry.company_id)
+        .bind(entry.call_id)
+        .bind(entry.queue_id)
+        .bind(entry.priority as i32)
+        .bind(entry.aging_seconds_per_point)
+        .bind(&entry.skills_required)
+        .bind(entry.created_at)
+        .bind(position as i32)
+        .bind(entry.preferred_agent_id)
+        .bind(entry.preferred_until)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_priority(&self, call_id: Uuid, priority: u32) -> Result<()> {
+        sqlx::query("UPDATE queue_items SET priority = $2 WHERE call_id = $1")
+            .bind(call_id)
+            .bind(priority as i32)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_queue(&self, call_id: Uuid, queue_id: Option<Uuid>) -> Result<()> {
+        sqlx::query("UPDATE queue_items SET queue_id = $2 WHERE call_id = $1")
+            .bind(call_id)
+            .bind(queue_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_preferred_agent(
+        &self,
+        call_id: Uuid,
+        preferred_agent_id: Option<Uuid>,
+        preferred_until: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query("UPDATE queue_items SET preferred_agent_id = $2, preferred_until = $3 WHERE call_id = $1")
+            .bind(call_id)
+            .bind(preferred_agent_id)
+            .bind(preferred_until)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_by_call(&self, call_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM queue_items WHERE call_id = $1")
+            .bind(call_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Calls still waiting for an agent, oldest first. Entries of calls that
+    /// were answered or ended in the meantime are dropped.
+    pub async fn list_waiting(&self) -> Result<Vec<CallQueue>> {
+        sqlx::query(
+            r#"
+            DELETE FROM queue_items
+            USING calls
+            WHERE calls.id = queue_items.call_id
+                  AND (calls.status <> 'ringing' OR calls.agent_id IS NOT NULL)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let rows = sqlx::query_as::<_, QueueItemRow>(
+            r#"
+            SELECT id, company_id, call_id, queue_id, priority, aging_seconds_per_point, skills_required, wait_start,
+                   preferred_agent_id, preferred_until
+            FROM queue_items
+            ORDER BY wait_start ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(CallQueue::from).collect())
+    }
+}
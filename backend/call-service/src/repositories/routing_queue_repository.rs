@@ -0,0 +1,174 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::{Result, CallDockerError};
+use shared::routing::RoutingQueue;
+
+#[derive(Debug, FromRow)]
+struct RoutingQueueRow {
+    id: Uuid,
+    company_id: Uuid,
+    name: String,
+    description: Option<String>,
+    strategy: String,
+    max_wait_time: Option<i32>,
+    max_queue_size: Option<i32>,
+    overflow_action: String,
+    overflow_target: Option<String>,
+    agents: Option<Vec<Uuid>>,
+    is_active: Option<bool>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<RoutingQueueRow> for RoutingQueue {
+    type Error = CallDockerError;
+
+    fn try_from(row: RoutingQueueRow) -> Result<Self> {
+        let created_at = row.created_at.unwrap_or_else(Utc::now);
+        Ok(RoutingQueue {
+            id: row.id,
+            company_id: row.company_id,
+            name: row.name,
+            description: row.description,
+            strategy: row.strategy.parse().map_err(CallDockerError::Database)?,
+            max_wait_time: row.max_wait_time.map(|t| t.max(0) as u32),
+            max_queue_size: row.max_queue_size.map(|s| s.max(0) as u32),
+            overflow_action: row.overflow_action.parse().map_err(CallDockerError::Database)?,
+            overflow_target: row.overflow_target,
+            agents: row.agents.unwrap_or_default(),
+            is_active: row.is_active.unwrap_or(true),
+            created_at,
+            updated_at: row.updated_at.unwrap_or(created_at),
+        })
+    }
+}
+
+const QUEUE_COLUMNS: &str = "id, company_id, name, description, strategy, max_wait_time, max_queue_size, \
+     overflow_action, overflow_target, agents, is_active, created_at, updated_at";
+
+#[derive(Clone)]
+pub struct RoutingQueueRepository {
+    pool: PgPool,
+}
+
+impl RoutingQueueRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<RoutingQueue>> {
+        let row = sqlx::query_as::<_, RoutingQueueRow>(&format!(
+            "SELECT {} FROM routing_queues WHERE id = $1",
+            QUEUE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(RoutingQueue::try_from).transpose()
+    }
+
+    pub async fn create(&self, queue: &RoutingQueue) -> Result<RoutingQueue> {
+        let row = sqlx::query_as::<_, RoutingQueueRow>(&format!(
+            r#"
+            INSERT INTO routing_queues (id, company_id, name, description, strategy, max_wait_time,
+                                        max_queue_size, overflow_action, overflow_target, agents,
+                                        is_active, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING {}
+            "#,
+            QUEUE_COLUMNS
+        ))
+        .bind(queue.id)
+        .bind(queue.company_id)
+        .bind(&queue.name)
+        .bind(&queue.description)
+        .bind(queue.strategy.to_string())
+        .bind(queue.max_wait_time.map(|t| t as i32))
+        .bind(queue.max_queue_size.map(|s| s as i32))
+        .bind(queue.overflow_action.to_string())
+        .bind(&queue.overflow_target)
+        .bind(&queue.agents)
+        .bind(queue.is_active)
+        .bind(queue.created_at)
+        .bind(queue.updated_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.try_into()
+    }
+
+    pub async fn list_by_company(&self, company_id: Uuid) -> Result<Vec<RoutingQueue>> {
+        let rows = sqlx::query_as::<_, RoutingQueueRow>(&format!(
+            "SELECT {} FROM routing_queues WHERE company_id = $1 ORDER BY name ASC",
+            QUEUE_COLUMNS
+        ))
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(RoutingQueue::try_from).collect()
+    }
+
+    /// Active queues of every company that limit how long calls may wait
+    pub async fn list_with_max_wait_time(&self) -> Result<Vec<RoutingQueue>> {
+        let rows = sqlx::query_as::<_, RoutingQueueRow>(&format!(
+            "SELECT {} FROM routing_queues WHERE max_wait_time IS NOT NULL AND is_active = true",
+            QUEUE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(RoutingQueue::try_from).collect()
+    }
+
+    pub async fn update(&self, queue: &RoutingQueue) -> Result<RoutingQueue> {
+        let row = sqlx::query_as::<_, RoutingQueueRow>(&format!(
+            r#"
+            UPDATE routing_queues
+            SET name = $2,
+                description = $3,
+                strategy = $4,
+                max_wait_time = $5,
+                max_queue_size = $6,
+                overflow_action = $7,
+                overflow_target = $8,
+                agents = $9,
+                is_active = $10,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            QUEUE_COLUMNS
+        ))
+        .bind(queue.id)
+        .bind(&queue.name)
+        .bind(&queue.description)
+        .bind(queue.strategy.to_string())
+        .bind(queue.max_wait_time.map(|t| t as i32))
+        .bind(queue.max_queue_size.map(|s| s as i32))
+        .bind(queue.overflow_action.to_string())
+        .bind(&queue.overflow_target)
+        .bind(&queue.agents)
+        .bind(queue.is_active)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(RoutingQueue::try_from)
+            .transpose()?
+            .ok_or_else(|| CallDockerError::QueueNotFound(queue.id.to_string()))
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        let deleted = sqlx::query("DELETE FROM routing_queues WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(CallDockerError::QueueNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use shared::{Result, CallDockerError};
+use shared::routing::RoutingRule;
+
+#[derive(Debug, FromRow)]
+struct RoutingRuleRow {
+    id: Uuid,
+    company_id: Uuid,
+    name: String,
+    description: Option<String>,
+    priority: Option<i32>,
+    is_active: Option<bool>,
+    conditions: serde_json::Value,
+    actions: serde_json::Value,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<RoutingRuleRow> for RoutingRule {
+    type Error = CallDockerError;
+
+    fn try_from(row: RoutingRuleRow) -> Result<Self> {
+        let created_at = row.created_at.unwrap_or_else(Utc::now);
+        Ok(RoutingRule {
+            id: row.id,
+            company_id: row.company_id,
+            name: row.name,
+            description: row.description,
+            priority: row.priority.unwrap_or(0).max(0) as u32,
+            is_active: row.is_active.unwrap_or(true),
+            conditions: serde_json::from_value(row.conditions)
+                .map_err(|e| CallDockerError::Database(format!("Invalid conditions on rule {}: {}", row.id, e)))?,
+            actions: serde_json::from_value(row.actions)
+                .map_err(|e| CallDockerError::Database(format!("Invalid actions on rule {}: {}", row.id, e)))?,
+            created_at,
+            updated_at: row.updated_at.unwrap_or(created_at),
+        })
+    }
+}
+
+const RULE_COLUMNS: &str =
+    "id, company_id, name, description, priority, is_active, conditions, actions, created_at, updated_at";
+
+#[derive(Clone)]
+pub struct RoutingRuleRepository {
+    pool: PgPool,
+}
+
+impl RoutingRuleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, rule: &RoutingRule) -> Result<RoutingRule> {
+        let row = sqlx::query_as::<_, RoutingRuleRow>(&format!(
+            r#"
+            INSERT INTO routing_rules (id, company_id, name, description, priority, is_active,
+                                       conditions, actions, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING {}
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(rule.id)
+        .bind(rule.company_id)
+        .bind(&rule.name)
+        .bind(&rule.description)
+        .bind(rule.priority as i32)
+        .bind(rule.is_active)
+        .bind(serde_json::to_value(&rule.conditions)?)
+        .bind(serde_json::to_value(&rule.actions)?)
+        .bind(rule.created_at)
+        .bind(rule.updated_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.try_into()
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<RoutingRule>> {
+        let row = sqlx::query_as::<_, RoutingRuleRow>(&format!(
+            "SELECT {} FROM routing_rules WHERE id = $1",
+            RULE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(RoutingRule::try_from).transpose()
+    }
+
+    /// Every rule of a company, active or not, highest priority first.
+    pub async fn list_by_company(&self, company_id: Uuid) -> Result<Vec<RoutingRule>> {
+        let rows = sqlx::query_as::<_, RoutingRuleRow>(&format!(
+            r#"
+            SELECT {} FROM routing_rules
+            WHERE company_id = $1
+            ORDER BY priority DESC, created_at ASC
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(RoutingRule::try_from).collect()
+    }
+
+    /// Active rules of a company, highest priority first.
+    pub async fn list_active_by_company(&self, company_id: Uuid) -> Result<Vec<RoutingRule>> {
+        let rows = sqlx::query_as::<_, RoutingRuleRow>(&format!(
+            r#"
+            SELECT {} FROM routing_rules
+            WHERE company_id = $1 AND is_active = true
+            ORDER BY priority DESC, created_at ASC
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(RoutingRule::try_from).collect()
+    }
+
+    pub async fn update(&self, rule: &RoutingRule) -> Result<RoutingRule> {
+        let row = sqlx::query_as::<_, RoutingRuleRow>(&format!(
+            r#"
+            UPDATE routing_rules
+            SET name = $2,
+                description = $3,
+                priority = $4,
+                is_active = $5,
+                conditions = $6,
+                actions = $7,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(rule.id)
+        .bind(&rule.name)
+        .bind(&rule.description)
+        .bind(rule.priority as i32)
+        .bind(rule.is_active)
+        .bind(serde_json::to_value(&rule.conditions)?)
+        .bind(serde_json::to_value(&rule.actions)?)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(RoutingRule::try_from)
+            .transpose()?
+            .ok_or_else(|| CallDockerError::RoutingRuleNotFound(rule.id.to_string()))
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        let deleted = sqlx::query("DELETE FROM routing_rules WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(CallDockerError::RoutingRuleNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Give the company's rules descending priorities in the order of
+    /// `rule_ids`, all or nothing.
+    pub async fn reorder(&self, company_id: Uuid, rule_ids: &[Uuid]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for (index, id) in rule_ids.iter().enumerate() {
+            sqlx::query(
+                r#"
+                UPDATE routing_rules
+                SET priority = $3,
+                    updated_at = NOW()
+                WHERE id = $1 AND company_id = $2
+                "#,
+            )
+            .bind(id)
+            .bind(company_id)
+            .bind((rule_ids.len() - index) as i32)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
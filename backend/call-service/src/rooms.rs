@@ -0,0 +1,158 @@
+use actix::{Actor, Context, Handler, Message, Recipient};
+use shared::signaling::{ParticipantInfo, ServerMessage};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A text frame delivered to one participant's socket.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "()")]
+pub struct RoomMessage(pub String);
+
+/// Add a participant to the room for `call_id`.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Join {
+    pub call_id: Uuid,
+    pub participant: ParticipantInfo,
+    pub addr: Recipient<RoomMessage>,
+}
+
+/// Remove a participant from the room for `call_id`.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Leave {
+    pub call_id: Uuid,
+    pub session_id: String,
+}
+
+/// Forward a message to every other participant in the room.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Relay {
+    pub call_id: Uuid,
+    pub session_id: String,
+    pub message: ServerMessage,
+}
+
+/// Send a server message to every participant in the room for `call_id`.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Notify {
+    pub call_id: Uuid,
+    pub message: ServerMessage,
+}
+
+struct Member {
+    participant: ParticipantInfo,
+    addr: Recipient<RoomMessage>,
+}
+
+/// Registry of signaling rooms keyed by call id, shared by every socket. An
+/// agent's own sockets share a room keyed by their agent id.
+#[derive(Default)]
+pub struct RoomRegistry {
+    rooms: HashMap<Uuid, HashMap<String, Member>>,
+}
+
+impl RoomRegistry {
+    fn members(&self, call_id: &Uuid) -> Vec<ParticipantInfo> {
+        let mut members: Vec<ParticipantInfo> = self
+            .rooms
+            .get(call_id)
+            .map(|room| room.values().map(|member| member.participant.clone()).collect())
+            .unwrap_or_default();
+        members.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        members
+    }
+
+    /// Send `message` to everyone in the room except `skip`.
+    fn broadcast(&self, call_id: &Uuid, message: &ServerMessage, skip: &str) {
+        if let Some(room) = self.rooms.get(call_id) {
+            let text = message.to_text();
+            for (session_id, member) in room.iter() {
+                if session_id != skip {
+                    member.addr.do_send(RoomMessage(text.clone()));
+                }
+            }
+        }
+    }
+}
+
+impl Actor for RoomRegistry {
+    type Context = Context<Self>;
+}
+
+impl Handler<Join> for RoomRegistry {
+    type Result = ();
+
+    fn handle(&mut self, msg: Join, _: &mut Self::Context) {
+        let session_id = msg.participant.session_id.clone();
+        tracing::info!("{} session {} joined call room {}", msg.participant.role, session_id, msg.call_id);
+
+        self.rooms.entry(msg.call_id).or_default().insert(
+            session_id.clone(),
+            Member {
+                participant: msg.participant.clone(),
+                addr: msg.addr.clone(),
+            },
+        );
+
+        // Notify the others, then tell the newcomer who is already here
+        let joined = ServerMessage::UserJoined {
+            participant: msg.participant,
+            room_id: msg.call_id,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        };
+        self.broadcast(&msg.call_id, &joined, &session_id);
+
+        let room_joined = ServerMessage::RoomJoined {
+            room_id: msg.call_id,
+            users: self.members(&msg.call_id),
+        };
+        msg.addr.do_send(RoomMessage(room_joined.to_text()));
+    }
+}
+
+impl Handler<Leave> for RoomRegistry {
+    type Result = ();
+
+    fn handle(&mut self, msg: Leave, _: &mut Self::Context) {
+        let Some(room) = self.rooms.get_mut(&msg.call_id) else {
+            return;
+        };
+        let Some(member) = room.remove(&msg.session_id) else {
+            return;
+        };
+
+        tracing::info!("Session {} left call room {}", msg.session_id, msg.call_id);
+
+        if room.is_empty() {
+            self.rooms.remove(&msg.call_id);
+            return;
+        }
+
+        let left = ServerMessage::UserLeft {
+            participant: member.participant,
+            room_id: msg.call_id,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        };
+        self.broadcast(&msg.call_id, &left, &msg.session_id);
+    }
+}
+
+impl Handler<Relay> for RoomRegistry {
+    type Result = ();
+
+    fn handle(&mut self, msg: Relay, _: &mut Self::Context) {
+        self.broadcast(&msg.call_id, &msg.message, &msg.session_id);
+    }
+}
+
+impl Handler<Notify> for RoomRegistry {
+    type Result = ();
+
+    fn handle(&mut self, msg: Notify, _: &mut Self::Context) {
+        // Session ids are never empty, so nobody is skipped
+        self.broadcast(&msg.call_id, &msg.message, "");
+    }
+}
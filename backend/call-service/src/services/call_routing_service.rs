@@ -1,177 +1,902 @@
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use uuid::Uuid;
-use shared::call::{Call, CallQueue, CallRouting, RoutingType};
+use chrono::{DateTime, Utc};
+use redis::aio::Connection;
+use serde::Serialize;
+use shared::call::{call_queue_channel, Call, CallQueue};
+use shared::ivr_condition::IVRContext;
+use shared::queue_strategy::{SelectionContext, StrategyRegistry};
+use shared::routing::{
+    eligible_agents, estimated_wait_time, AgentAvailability, OverflowAction, OverflowReason, QueuedCall, RoutingQueue,
+    RoutingResult, RoutingRule, RoutingTargetType, DEFAULT_HANDLE_TIME_SECS,
+};
+use shared::routing_policy::{RoutingPolicy, StickyRouting};
+use shared::rule_engine::{evaluate_rules, RoutingContext, RoutingDecision, RuleEvaluation};
+use shared::{CallDockerError, Result};
+use crate::repositories::{
+    AgentRepository, CallRepository, CompanyRepository, RoutingQueueRepository, RoutingRuleRepository,
+};
+use super::offer_store::OfferStore;
+use super::queue_store::QueueStore;
+
+/// How long a routing rule webhook may take before it is abandoned.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many recently handled calls the average handle time is taken over.
+const HANDLE_TIME_SAMPLE: i64 = 50;
+
+/// A call sent on by a routing queue's overflow action
+#[derive(Debug, Clone)]
+pub struct QueueOverflow {
+    pub queue_id: Uuid,
+    /// The action taken; voicemail when the queue's own action lacks a target
+    pub action: OverflowAction,
+    pub reason: OverflowReason,
+}
+
+/// Where routing sent a call
+#[derive(Debug, Clone, Default)]
+pub struct RoutingOutcome {
+    /// `None` while the call waits for an agent, or once it has been hung up
+    pub routed: Option<RoutingResult>,
+    /// Set when a routing queue could not hold the call
+    pub overflow: Option<QueueOverflow>,
+}
 
 #[derive(Clone)]
 pub struct CallRoutingService {
-    queues: Arc<RwLock<HashMap<Uuid, Vec<CallQueue>>>>, // company_id -> queues
-    routing_rules: Arc<RwLock<HashMap<Uuid, Vec<CallRouting>>>>, // company_id -> rules
+    queue_store: QueueStore,
+    offer_store: OfferStore,
+    call_repository: CallRepository,
+    company_repository: CompanyRepository,
+    agent_repository: AgentRepository,
+    rule_repository: RoutingRuleRepository,
+    queue_repository: RoutingQueueRepository,
+    redis_conn: Arc<RwLock<Connection>>,
+    strategies: StrategyRegistry,
+    http_client: reqwest::Client,
 }
 
 impl CallRoutingService {
-    pub fn new() -> Self {
+    pub fn new(
+        call_repository: CallRepository,
+        company_repository: CompanyRepository,
+        agent_repository: AgentRepository,
+        rule_repository: RoutingRuleRepository,
+        queue_repository: RoutingQueueRepository,
+        queue_store: QueueStore,
+        redis_conn: Arc<RwLock<Connection>>,
+    ) -> Self {
         Self {
-            queues: Arc::new(RwLock::new(HashMap::new())),
-            routing_rules: Arc::new(RwLock::new(HashMap::new())),
+            queue_store,
+            offer_store: OfferStore::new(redis_conn.clone()),
+            call_repository,
+            company_repository,
+            agent_repository,
+            rule_repository,
+            queue_repository,
+            redis_conn,
+            strategies: StrategyRegistry::new(),
+            http_client: reqwest::Client::new(),
         }
     }
 
-    /// Add call to routing queue
-    pub async fn add_to_queue(&self, call: &Call) -> Result<(), Box<dyn std::error::Error>> {
-        let mut queues = self.queues.write().await;
-        
-        let company_queues = queues.entry(call.company_id).or_insert_with(Vec::new);
-        
+    /// Add call to routing queue. It waits from when it came in, so a call put
+    /// back after an agent let it go keeps its place.
+    pub async fn add_to_queue(&self, call: &Call) -> Result<()> {
+        let policy = self.policy(call.company_id).await?;
         let queue_entry = CallQueue {
             id: Uuid::new_v4(),
             company_id: call.company_id,
             call_id: call.id,
-            priority: self.calculate_priority(call),
-            skills_required: self.extract_required_skills(call),
-            created_at: chrono::Utc::now(),
+            priority: policy.priority(call),
+            skills_required: policy.required_skills(call),
+            queue_id: None,
+            aging_seconds_per_point: policy.aging_seconds_per_point,
+            preferred_agent_id: None,
+            preferred_until: None,
+            created_at: call.created_at,
         };
 
-        company_queues.push(queue_entry);
-        
-        // Sort by priority (highest first)
-        company_queues.sort_by(|a, b| b.priority.cmp(&a.priority));
+        let position = self.queue_store.push(&queue_entry).await?;
 
-        tracing::info!("Added call {} to routing queue for company {}", call.id, call.company_id);
+        tracing::info!(
+            "Added call {} to routing queue for company {} at position {}",
+            call.id,
+            call.company_id,
+            position
+        );
+        self.publish_positions(call.company_id).await;
         Ok(())
     }
 
-    /// Route call to best available agent
-    pub async fn route_call(&self, call_id: Uuid, company_id: Uuid) -> Result<Option<Uuid>, Box<dyn std::error::Error>> {
-        // This would typically:
-        // 1. Check agent availability
-        // 2. Match skills requirements
-        // 3. Apply routing rules (round-robin, skills-based, etc.)
-        // 4. Return best agent ID or None if no agents available
-        
-        // For now, return None to indicate no routing decision made
-        tracing::info!("Routing call {} for company {}", call_id, company_id);
-        Ok(None)
+    /// Route a call. The company's routing rules run first and may tag the call,
+    /// change its priority or send it somewhere specific; otherwise it goes to the
+    /// least busy available agent with the required skills, reserving one of their
+    /// call slots. Tags and metadata set by rules are written onto `call`. With
+    /// sticky routing a returning customer goes to their last agent first, or is
+    /// held for them while they are busy, unless a rule picked somewhere other
+    /// than a routing queue. When nobody can take it the call stays queued,
+    /// unless the routing queue it was sent to is full and overflows it.
+    pub async fn route_call(&self, call: &mut Call) -> Result<RoutingOutcome> {
+        tracing::info!("Routing call {} for company {}", call.id, call.company_id);
+
+        let (priority, skills_required) = self.call_requirements(call).await?;
+        let agents = self.available_agents(call).await?;
+        let rules = self.rule_repository.list_active_by_company(call.company_id).await?;
+
+        let context = self.routing_context(call, priority, &agents).await?;
+        let evaluation = evaluate_rules(&rules, context);
+        // Rules may raise the priority, but not past the policy's cap
+        let policy = self.policy(call.company_id).await?;
+        let priority = policy.cap(evaluation.context.priority);
+        self.apply_rule_effects(call, &evaluation, priority).await?;
+
+        let mut preferred = None;
+        let mut held = None;
+        if matches!(evaluation.decision, None | Some(RoutingDecision::Queue(_))) {
+            if let Some(agent) = self.preferred_agent(call, &policy.sticky_routing).await? {
+                preferred = self.route_to_preferred(call, &agent, priority, &skills_required).await?;
+                if preferred.is_none() && agent.is_working() && agent.has_skills(&skills_required) {
+                    held = policy
+                        .sticky_routing
+                        .wait_until(call.created_at, Utc::now())
+                        .map(|until| (agent.agent_id, until));
+                }
+            }
+        }
+
+        let (routed, overflow) = match preferred {
+            Some(preferred) => (Some(preferred), None),
+            None => {
+                self.dispatch(call, evaluation.decision, priority, skills_required, &agents, held)
+                    .await?
+            }
+        };
+
+        if let (Some((agent_id, until)), None, None) = (held, &routed, &overflow) {
+            self.queue_store.set_preferred_agent(call.company_id, call.id, Some((agent_id, until))).await?;
+            tracing::info!("Call {} waits for preferred agent {} until {}", call.id, agent_id, until);
+        }
+
+        Ok(RoutingOutcome {
+            routed: routed.map(|mut routed| {
+                routed.priority = priority;
+                routed.applied_rules = evaluation.applied_rules;
+                routed
+            }),
+            overflow,
+        })
+    }
+
+    /// Route a call straight to where `decision` sends it, without running the
+    /// routing rules or sticky routing, e.g. once the caller picked a department
+    /// in an IVR flow. Without a decision it goes to the least busy agent.
+    pub async fn route_call_to(&self, call: &Call, decision: Option<RoutingDecision>) -> Result<RoutingOutcome> {
+        tracing::info!("Routing call {} for company {} to {:?}", call.id, call.company_id, decision);
+
+        let (priority, skills_required) = self.call_requirements(call).await?;
+        let agents = self.available_agents(call).await?;
+        let (routed, overflow) = self
+            .dispatch(call, decision, priority, skills_required, &agents, None)
+            .await?;
+
+        Ok(RoutingOutcome { routed, overflow })
     }
 
-    /// Get next call from queue for agent
-    pub async fn get_next_call(&self, agent_id: Uuid, company_id: Uuid) -> Result<Option<Uuid>, Box<dyn std::error::Error>> {
-        let mut queues = self.queues.write().await;
-        
-        if let Some(company_queues) = queues.get_mut(&company_id) {
-            // Find highest priority call that matches agent skills
-            if let Some(index) = company_queues.iter().position(|queue| {
-                // This would check if agent has required skills
-                // For now, just return the first available call
-                true
-            }) {
-                let call_id = company_queues[index].call_id;
-                company_queues.remove(index);
-                return Ok(Some(call_id));
+    /// Route a call where `decision` sends it, or to the least busy agent with
+    /// the skills it needs without one. A call `held` for its preferred agent
+    /// only takes its place in the queue.
+    async fn dispatch(
+        &self,
+        call: &Call,
+        decision: Option<RoutingDecision>,
+        priority: u32,
+        mut skills_required: Vec<String>,
+        agents: &[AgentAvailability],
+        held: Option<(Uuid, DateTime<Utc>)>,
+    ) -> Result<(Option<RoutingResult>, Option<QueueOverflow>)> {
+        let mut overflow = None;
+        let routed = match decision {
+            Some(RoutingDecision::Queue(queue_id)) => match self.queue_repository.find_by_id(queue_id).await? {
+                Some(queue) if queue.is_active && queue.company_id == call.company_id => {
+                    // A call held for its preferred agent only takes its place in the queue
+                    let routed = match held {
+                        Some(_) => None,
+                        None => self.route_call_to_queue(call, &queue).await?,
+                    };
+                    match routed {
+                        Some(routed) => Some(routed),
+                        None => {
+                            let outcome = self.hold_in_queue(call, &queue).await?;
+                            overflow = outcome.overflow;
+                            outcome.routed
+                        }
+                    }
+                }
+                _ => {
+                    tracing::warn!("Routing rule sent call {} to unknown or inactive queue {}", call.id, queue_id);
+                    match held {
+                        Some(_) => None,
+                        None => self.route_to_least_busy(call, priority, &skills_required, agents).await?,
+                    }
+                }
+            },
+            Some(RoutingDecision::Agent(agent_id)) => {
+                if agents.iter().any(|agent| agent.agent_id == agent_id)
+                    && self.agent_repository.reserve_call_slot(agent_id).await?
+                {
+                    self.remove_from_queue(call.company_id, call.id).await?;
+                    let reason = "Agent picked by routing rule";
+                    Some(routing_result(call, RoutingTargetType::Agent, agent_id, priority, reason))
+                } else {
+                    tracing::info!("Agent {} picked by routing rule is unavailable for call {}", agent_id, call.id);
+                    self.route_to_least_busy(call, priority, &skills_required, agents).await?
+                }
+            }
+            Some(RoutingDecision::Department(department)) => {
+                skills_required.push(department);
+                self.route_to_least_busy(call, priority, &skills_required, agents).await?
+            }
+            Some(RoutingDecision::IVR(flow_id)) => {
+                self.remove_from_queue(call.company_id, call.id).await?;
+                let reason = "IVR flow picked by routing rule";
+                Some(routing_result(call, RoutingTargetType::IVR, flow_id, priority, reason))
+            }
+            Some(RoutingDecision::Voicemail(mailbox_id)) => {
+                self.remove_from_queue(call.company_id, call.id).await?;
+                // Without a mailbox the call lands in the company's voicemail
+                let target_id = mailbox_id.unwrap_or(call.company_id);
+                let reason = "Voicemail picked by routing rule";
+                Some(routing_result(call, RoutingTargetType::Voicemail, target_id, priority, reason))
+            }
+            Some(RoutingDecision::External(number)) => {
+                self.remove_from_queue(call.company_id, call.id).await?;
+                let reason = format!("Forwarded to {} by routing rule", number);
+                Some(routing_result(call, RoutingTargetType::External, Uuid::nil(), priority, reason))
+            }
+            None if held.is_some() => None,
+            None => self.route_to_least_busy(call, priority, &skills_required, agents).await?,
+        };
+
+        Ok((routed, overflow))
+    }
+
+    /// Try a call held for its preferred agent again. Once its wait window is
+    /// over the call stops waiting for them and goes to its routing queue's
+    /// strategy, or the least busy agent, without running the rules again.
+    pub async fn route_held(&self, call: &Call) -> Result<RoutingOutcome> {
+        let Some(entry) = self.queue_store.get(call.company_id, call.id).await? else {
+            return Ok(RoutingOutcome::default());
+        };
+        let (Some(agent_id), Some(until)) = (entry.preferred_agent_id, entry.preferred_until) else {
+            // Queued again since, without a preference
+            self.queue_store.set_preferred_agent(call.company_id, call.id, None).await?;
+            return Ok(RoutingOutcome::default());
+        };
+
+        if let Some(agent) = self.undeclined_agent(call, agent_id).await? {
+            let routed = self
+                .route_to_preferred(call, &agent, entry.priority, &entry.skills_required)
+                .await?;
+            if routed.is_some() {
+                return Ok(RoutingOutcome { routed, overflow: None });
             }
         }
+        if Utc::now() < until {
+            return Ok(RoutingOutcome::default());
+        }
 
-        Ok(None)
+        tracing::info!("Call {} stops waiting for preferred agent {}", call.id, agent_id);
+        self.queue_store.set_preferred_agent(call.company_id, call.id, None).await?;
+        let queue = match entry.queue_id {
+            Some(queue_id) => self.queue_repository.find_by_id(queue_id).await?,
+            None => None,
+        };
+        let routed = match queue {
+            Some(queue) if queue.is_active && queue.company_id == call.company_id => {
+                self.route_call_to_queue(call, &queue).await?
+            }
+            _ => {
+                let agents = self.available_agents(call).await?;
+                self.route_to_least_busy(call, entry.priority, &entry.skills_required, &agents)
+                    .await?
+            }
+        };
+
+        Ok(RoutingOutcome { routed, overflow: None })
+    }
+
+    /// Waiting calls held for a preferred agent, as `(company_id, call_id)`
+    pub async fn held_calls(&self) -> Result<Vec<(Uuid, Uuid)>> {
+        self.queue_store.held().await
+    }
+
+    /// The agent who last answered the customer, when the company routes
+    /// returning customers to them and the agent has not let this call go.
+    async fn preferred_agent(&self, call: &Call, sticky: &StickyRouting) -> Result<Option<AgentAvailability>> {
+        if !sticky.enabled {
+            return Ok(None);
+        }
+        let since = sticky.history_since(Utc::now());
+        match self.call_repository.last_agent_for_customer(call, since).await? {
+            Some(agent_id) => self.undeclined_agent(call, agent_id).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn undeclined_agent(&self, call: &Call, agent_id: Uuid) -> Result<Option<AgentAvailability>> {
+        if self.offer_store.declined(call.id).await?.contains(&agent_id) {
+            return Ok(None);
+        }
+        self.agent_repository.find_availability(agent_id).await
+    }
+
+    /// Hand a call to its preferred agent when they have a free slot and the
+    /// skills it needs.
+    async fn route_to_preferred(
+        &self,
+        call: &Call,
+        agent: &AgentAvailability,
+        priority: u32,
+        skills_required: &[String],
+    ) -> Result<Option<RoutingResult>> {
+        if !agent.has_capacity()
+            || !agent.has_skills(skills_required)
+            || !self.agent_repository.reserve_call_slot(agent.agent_id).await?
+        {
+            return Ok(None);
+        }
+
+        self.remove_from_queue(call.company_id, call.id).await?;
+        let routing_reason = "Preferred agent: last spoke with the customer";
+        tracing::info!("Routed call {} to agent {}: {}", call.id, agent.agent_id, routing_reason);
+
+        Ok(Some(routing_result(call, RoutingTargetType::Agent, agent.agent_id, priority, routing_reason)))
     }
 
-    /// Calculate call priority based on various factors
-    fn calculate_priority(&self, call: &Call) -> u32 {
-        let mut priority = 100; // Base priority
+    /// Keep a call nobody can take yet waiting in a routing queue, or overflow it
+    /// when the queue is full.
+    async fn hold_in_queue(&self, call: &Call, queue: &RoutingQueue) -> Result<RoutingOutcome> {
+        let waiting = self
+            .queue_store
+            .entries(call.company_id)
+            .await?
+            .iter()
+            .filter(|entry| entry.queue_id == Some(queue.id))
+            .count();
 
-        // VIP customers get higher priority
-        if let Some(email) = &call.customer_email {
-            if email.contains("vip") || email.contains("premium") {
-                priority += 50;
+        if queue.is_full(waiting) {
+            tracing::info!("Queue {} is full ({} waiting), overflowing call {}", queue.id, waiting, call.id);
+            let outcome = self.overflow(call, queue, OverflowReason::QueueFull).await?;
+            return Ok(outcome.unwrap_or_default());
+        }
+
+        self.queue_store.assign_queue(call.company_id, call.id, Some(queue.id)).await?;
+        tracing::info!("Call {} waits in queue {} behind {} calls", call.id, queue.id, waiting);
+        Ok(RoutingOutcome::default())
+    }
+
+    /// Take a waiting call out of a routing queue and carry out the queue's
+    /// overflow action. Returns `None` when the call is no longer queued, e.g.
+    /// because an agent took it in the meantime.
+    pub async fn overflow(
+        &self,
+        call: &Call,
+        queue: &RoutingQueue,
+        reason: OverflowReason,
+    ) -> Result<Option<RoutingOutcome>> {
+        let Some(mut entry) = self.queue_store.get(call.company_id, call.id).await? else {
+            return Ok(None);
+        };
+        // Whoever removes the call owns it
+        if !self.queue_store.remove(call.company_id, call.id).await? {
+            return Ok(None);
+        }
+
+        let priority = entry.priority;
+        let routing_reason = format!("Overflow of queue {} ({})", queue.name, reason);
+        let target = queue.overflow_target.as_deref();
+
+        // IVR and external overflows need a target; without one the call goes to voicemail
+        let action = match queue.overflow_action.check_target(target) {
+            Ok(()) => queue.overflow_action.clone(),
+            Err(e) => {
+                tracing::warn!("Queue {} cannot overflow call {}: {}; using voicemail", queue.id, call.id, e);
+                OverflowAction::RouteToVoicemail
+            }
+        };
+
+        let routed = match action {
+            OverflowAction::RouteToFallback => {
+                let agents = self.available_agents(call).await?;
+                let routed = self
+                    .route_to_least_busy(call, priority, &entry.skills_required, &agents)
+                    .await?;
+                if routed.is_none() {
+                    // Keep its place, now waiting for any agent of the company
+                    entry.queue_id = None;
+                    self.queue_store.push(&entry).await?;
+                }
+                routed
+            }
+            // Without a mailbox the call lands in the company's voicemail
+            OverflowAction::RouteToVoicemail => Some(routing_result(
+                call,
+                RoutingTargetType::Voicemail,
+                call.company_id,
+                priority,
+                routing_reason,
+            )),
+            OverflowAction::RouteToIVR => target
+                .and_then(|target| Uuid::parse_str(target).ok())
+                .map(|flow_id| routing_result(call, RoutingTargetType::IVR, flow_id, priority, routing_reason)),
+            OverflowAction::RouteToExternal => target.map(|number| {
+                let reason = format!("{}: forwarded to {}", routing_reason, number);
+                routing_result(call, RoutingTargetType::External, Uuid::nil(), priority, reason)
+            }),
+            OverflowAction::Hangup => None,
+        };
+
+        tracing::info!("Call {} overflowed queue {} ({}): {}", call.id, queue.id, reason, action);
+        self.publish_positions(call.company_id).await;
+        Ok(Some(RoutingOutcome {
+            routed,
+            overflow: Some(QueueOverflow {
+                queue_id: queue.id,
+                action,
+                reason,
+            }),
+        }))
+    }
+
+    /// Waiting calls held in a routing queue for longer than its `max_wait_time`
+    pub async fn overdue_calls(&self) -> Result<Vec<(CallQueue, RoutingQueue)>> {
+        let mut companies: HashMap<Uuid, Vec<RoutingQueue>> = HashMap::new();
+        for queue in self.queue_repository.list_with_max_wait_time().await? {
+            companies.entry(queue.company_id).or_default().push(queue);
+        }
+
+        let now = chrono::Utc::now();
+        let mut overdue = Vec::new();
+        for (company_id, queues) in companies {
+            for entry in self.queue_store.entries(company_id).await? {
+                let queue = queues
+                    .iter()
+                    .find(|queue| entry.queue_id == Some(queue.id) && queue.wait_exceeded(entry.created_at, now));
+                if let Some(queue) = queue {
+                    overdue.push((entry, queue.clone()));
+                }
             }
         }
 
-        // Inbound calls typically get higher priority than outbound
-        match call.direction {
-            shared::call::CallDirection::Inbound => priority += 25,
-            shared::call::CallDirection::Outbound => priority += 0,
+        Ok(overdue)
+    }
+
+    /// Explain how the company's routing rules, or `rules` when given, treat a
+    /// call, without routing it or sending any webhooks.
+    pub async fn explain_routing(&self, call: &Call, rules: Option<Vec<RoutingRule>>) -> Result<RuleEvaluation> {
+        let rules = match rules {
+            Some(rules) => rules,
+            None => self.rule_repository.list_active_by_company(call.company_id).await?,
+        };
+        let agents = self.agent_repository.find_available_by_company(call.company_id).await?;
+        let priority = self.policy(call.company_id).await?.priority(call);
+        let context = self.routing_context(call, priority, &agents).await?;
+
+        Ok(evaluate_rules(&rules, context))
+    }
+
+    async fn route_to_least_busy(
+        &self,
+        call: &Call,
+        priority: u32,
+        skills_required: &[String],
+        agents: &[AgentAvailability],
+    ) -> Result<Option<RoutingResult>> {
+        for agent in eligible_agents(agents, skills_required) {
+            // Another call may have taken the last slot since the agents were loaded
+            if !self.agent_repository.reserve_call_slot(agent.agent_id).await? {
+                continue;
+            }
+
+            self.remove_from_queue(call.company_id, call.id).await?;
+
+            let routing_reason = if skills_required.is_empty() {
+                format!("Least busy available agent ({}/{} calls)", agent.current_calls, agent.max_calls)
+            } else {
+                format!(
+                    "Least busy available agent with skills [{}] ({}/{} calls)",
+                    skills_required.join(", "),
+                    agent.current_calls,
+                    agent.max_calls
+                )
+            };
+            tracing::info!("Routed call {} to agent {}: {}", call.id, agent.agent_id, routing_reason);
+
+            let routed = routing_result(call, RoutingTargetType::Agent, agent.agent_id, priority, routing_reason);
+            return Ok(Some(routed));
         }
 
-        // Calls with specific tags get priority
-        for tag in &call.tags {
-            match tag.as_str() {
-                "urgent" => priority += 75,
-                "high_priority" => priority += 50,
-                "escalation" => priority += 100,
-                _ => {}
+        tracing::info!(
+            "No available agent for call {} (skills required: {:?})",
+            call.id,
+            skills_required
+        );
+        Ok(None)
+    }
+
+    /// What routing rules see of a call: its details plus the skills on offer
+    /// and how busy the company's queue is.
+    async fn routing_context(
+        &self,
+        call: &Call,
+        priority: u32,
+        agents: &[AgentAvailability],
+    ) -> Result<RoutingContext> {
+        let mut context = RoutingContext::from_call(call, priority);
+
+        for skill in agents.iter().filter(|agent| agent.has_capacity()).flat_map(|agent| &agent.skills) {
+            if !context.agent_skills.iter().any(|own| own.eq_ignore_ascii_case(skill)) {
+                context.agent_skills.push(skill.clone());
             }
         }
+        if let Some(agent_id) = call.agent_id {
+            context.agent_status = self
+                .agent_repository
+                .find_availability(agent_id)
+                .await?
+                .map(|agent| agent.status);
+        }
+        context.queue_length = self.queue_store.len(call.company_id).await?;
 
-        priority
+        Ok(context)
     }
 
-    /// Extract required skills from call metadata
-    fn extract_required_skills(&self, call: &Call) -> Vec<String> {
-        let mut skills = Vec::new();
+    /// Carry what the matching rules changed over to the call and its queue
+    /// entry, and send the webhooks they asked for.
+    async fn apply_rule_effects(&self, call: &mut Call, evaluation: &RuleEvaluation, priority: u32) -> Result<()> {
+        call.tags = evaluation.context.tags.clone();
+        call.metadata = evaluation.context.metadata.clone();
+        let moved = self.queue_store.set_priority(call.company_id, call.id, priority).await?;
+        if moved {
+            self.publish_positions(call.company_id).await;
+        }
 
-        // Extract skills from metadata
-        if let Some(metadata) = call.metadata.as_object() {
-            if let Some(skills) = metadata.get("required_skills") {
-                if let Some(skills_array) = skills.as_array() {
-                    for skill in skills_array {
-                        if let Some(skill_str) = skill.as_str() {
-                            skills.push(skill_str.to_string());
-                        }
-                    }
+        for webhook in &evaluation.webhooks {
+            let client = self.http_client.clone();
+            let webhook = webhook.clone();
+            // Webhooks are fire-and-forget; a slow receiver must not hold up routing
+            tokio::spawn(async move {
+                let sent = client
+                    .post(&webhook.url)
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .json(&webhook.payload)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = sent {
+                    tracing::warn!("Routing rule {} webhook to {} failed: {}", webhook.rule_id, webhook.url, e);
                 }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Route call through a routing queue, letting the queue's strategy pick among
+    /// its agents. The agent picked is remembered per queue for round-robin.
+    pub async fn route_call_to_queue(&self, call: &Call, queue: &RoutingQueue) -> Result<Option<RoutingResult>> {
+        let strategy = self.strategies.resolve(&queue.strategy)?;
+        let (priority, skills_required) = self.call_requirements(call).await?;
+
+        let mut agents = self.available_agents(call).await?;
+        if !queue.agents.is_empty() {
+            agents.retain(|agent| queue.agents.contains(&agent.agent_id));
+        }
+        let cursor = self.load_cursor(queue.id).await;
+
+        loop {
+            let context = SelectionContext {
+                queue,
+                required_skills: &skills_required,
+                cursor,
+            };
+            let Some(agent_id) = strategy.select(&agents, &context) else {
+                tracing::info!("Queue {} has no agent for call {}", queue.id, call.id);
+                return Ok(None);
+            };
+
+            // Lost a race for the agent's last slot; let the strategy pick again
+            if !self.agent_repository.reserve_call_slot(agent_id).await? {
+                agents.retain(|agent| agent.agent_id != agent_id);
+                continue;
             }
+
+            self.save_cursor(queue.id, agent_id).await;
+            self.remove_from_queue(call.company_id, call.id).await?;
+
+            let routing_reason = format!("Selected by {:?} strategy of queue {}", queue.strategy, queue.name);
+            tracing::info!("Routed call {} to agent {}: {}", call.id, agent_id, routing_reason);
+
+            return Ok(Some(routing_result(call, RoutingTargetType::Agent, agent_id, priority, routing_reason)));
         }
+    }
 
-        // Add skills based on customer info
-        if let Some(email) = &call.customer_email {
-            if email.contains("technical") {
-                skills.push("technical_support".to_string());
+    /// Hand the first waiting call an agent can take to them, reserving one of
+    /// their call slots. Calls needing skills they lack, waiting in a routing
+    /// queue they are not in, held for another agent or that they already let
+    /// go are passed over. `None` when the agent has no free slot or nothing
+    /// waiting suits them.
+    pub async fn get_next_call(&self, agent_id: Uuid, company_id: Uuid) -> Result<Option<RoutingResult>> {
+        let entries = self.queue_store.entries(company_id).await?;
+        if entries.is_empty() {
+            return Ok(None);
+        }
+        let agent = self
+            .agent_repository
+            .find_availability(agent_id)
+            .await?
+            .ok_or_else(|| CallDockerError::AgentNotFound(agent_id.to_string()))?;
+        if !agent.has_capacity() {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        let mut queues: HashMap<Uuid, Option<RoutingQueue>> = HashMap::new();
+        let mut takeable = HashSet::new();
+        for entry in entries {
+            if !agent.has_skills(&entry.skills_required) {
+                continue;
+            }
+            let held_for_other = entry.preferred_agent_id.is_some_and(|preferred| preferred != agent_id)
+                && entry.preferred_until.is_some_and(|until| now < until);
+            if held_for_other {
+                continue;
+            }
+            if let Some(queue_id) = entry.queue_id {
+                let queue = match queues.entry(queue_id) {
+                    Entry::Occupied(queue) => queue.into_mut(),
+                    Entry::Vacant(slot) => slot.insert(self.queue_repository.find_by_id(queue_id).await?),
+                };
+                let outside_queue = queue.as_ref().is_some_and(|queue| {
+                    queue.is_active && !queue.agents.is_empty() && !queue.agents.contains(&agent_id)
+                });
+                if outside_queue {
+                    continue;
+                }
             }
-            if email.contains("billing") {
-                skills.push("billing_support".to_string());
+            if self.offer_store.declined(entry.call_id).await?.contains(&agent_id) {
+                continue;
             }
+            takeable.insert(entry.call_id);
         }
+        if takeable.is_empty() || !self.agent_repository.reserve_call_slot(agent_id).await? {
+            return Ok(None);
+        }
+
+        // First call in queue order; another instance may have taken the others
+        let claimed = self
+            .queue_store
+            .claim_next(company_id, |entry| takeable.contains(&entry.call_id))
+            .await?;
+        let Some(entry) = claimed else {
+            self.agent_repository.release_call_slot(agent_id).await?;
+            return Ok(None);
+        };
+        self.publish_positions(company_id).await;
 
-        skills
+        let routing_reason = "Next waiting call for a freed call slot".to_string();
+        tracing::info!("Routed call {} to agent {}: {}", entry.call_id, agent_id, routing_reason);
+        Ok(Some(RoutingResult {
+            call_id: entry.call_id,
+            target_type: RoutingTargetType::Agent,
+            target_id: agent_id,
+            priority: entry.priority,
+            estimated_wait_time: None,
+            routing_reason,
+            applied_rules: Vec::new(),
+        }))
     }
 
-    /// Get queue statistics for company
-    pub async fn get_queue_stats(&self, company_id: Uuid) -> Result<QueueStats, Box<dyn std::error::Error>> {
-        let queues = self.queues.read().await;
-        
-        if let Some(company_queues) = queues.get(&company_id) {
-            let total_calls = company_queues.len() as u64;
-            let high_priority_calls = company_queues.iter()
-                .filter(|q| q.priority >= 150)
-                .count() as u64;
-            let average_wait_time = company_queues.iter()
-                .map(|q| chrono::Utc::now().signed_duration_since(q.created_at).num_seconds() as u64)
-                .sum::<u64>() / total_calls.max(1);
+    /// Companies with calls waiting for an agent
+    pub async fn waiting_companies(&self) -> Result<Vec<Uuid>> {
+        self.queue_store.companies().await
+    }
 
-            Ok(QueueStats {
-                company_id,
-                total_calls,
-                high_priority_calls,
-                average_wait_time,
-                timestamp: chrono::Utc::now(),
-            })
-        } else {
-            Ok(QueueStats {
-                company_id,
-                total_calls: 0,
-                high_priority_calls: 0,
-                average_wait_time: 0,
-                timestamp: chrono::Utc::now(),
+    /// Agents free to take a call, least busy first, leaving out those who
+    /// already rejected it or let it ring out.
+    async fn available_agents(&self, call: &Call) -> Result<Vec<AgentAvailability>> {
+        let mut agents = self.agent_repository.find_available_by_company(call.company_id).await?;
+        let declined = self.offer_store.declined(call.id).await?;
+        agents.retain(|agent| !declined.contains(&agent.agent_id));
+        Ok(agents)
+    }
+
+    /// Priority and required skills, taken from the queue entry when there is one
+    /// so routing and queueing agree.
+    async fn call_requirements(&self, call: &Call) -> Result<(u32, Vec<String>)> {
+        if let Some(entry) = self.queue_store.get(call.company_id, call.id).await? {
+            return Ok((entry.priority, entry.skills_required));
+        }
+        let policy = self.policy(call.company_id).await?;
+        Ok((policy.priority(call), policy.required_skills(call)))
+    }
+
+    /// Last agent a queue handed a call to. Routing carries on without it if
+    /// Redis is unavailable.
+    async fn load_cursor(&self, queue_id: Uuid) -> Option<Uuid> {
+        let mut conn = self.redis_conn.write().await;
+        let cursor: redis::RedisResult<Option<String>> = redis::cmd("GET")
+            .arg(queue_cursor_key(queue_id))
+            .query_async(&mut *conn)
+            .await;
+
+        match cursor {
+            Ok(cursor) => cursor.and_then(|id| Uuid::parse_str(&id).ok()),
+            Err(e) => {
+                tracing::warn!("Failed to load round-robin cursor for queue {}: {}", queue_id, e);
+                None
+            }
+        }
+    }
+
+    async fn save_cursor(&self, queue_id: Uuid, agent_id: Uuid) {
+        let mut conn = self.redis_conn.write().await;
+        let saved: redis::RedisResult<()> = redis::cmd("SET")
+            .arg(queue_cursor_key(queue_id))
+            .arg(agent_id.to_string())
+            .query_async(&mut *conn)
+            .await;
+
+        if let Err(e) = saved {
+            tracing::warn!("Failed to save round-robin cursor for queue {}: {}", queue_id, e);
+        }
+    }
+
+    /// Take a call out of the routing queue, e.g. once it has been routed or has
+    /// ended
+    pub async fn remove_from_queue(&self, company_id: Uuid, call_id: Uuid) -> Result<()> {
+        if self.queue_store.remove(company_id, call_id).await? {
+            self.publish_positions(company_id).await;
+        }
+        Ok(())
+    }
+
+    /// Rebuild the Redis queue from `queue_items` after a restart
+    pub async fn restore_queues(&self) -> Result<()> {
+        let restored = self.queue_store.restore().await?;
+        if restored > 0 {
+            tracing::info!("Restored {} waiting calls to the routing queue", restored);
+        }
+        Ok(())
+    }
+
+    /// What an IVR flow's Condition nodes see of a call right now
+    pub async fn ivr_context(&self, call: &Call) -> Result<IVRContext> {
+        let policy = self.policy(call.company_id).await?;
+        let queue_length = self.queue_store.len(call.company_id).await?;
+        Ok(IVRContext::from_call(call, &policy, queue_length, Utc::now()))
+    }
+
+    /// The company's routing policy
+    async fn policy(&self, company_id: Uuid) -> Result<RoutingPolicy> {
+        self.company_repository.find_routing_policy(company_id).await
+    }
+
+    /// Queue statistics for a company: every waiting call with its position and
+    /// estimated wait, based on recent handle times and the call slots of the
+    /// agents online.
+    pub async fn get_queue_stats(&self, company_id: Uuid) -> Result<QueueStats> {
+        let entries = self.queue_store.entries(company_id).await?;
+        let agents = self.agent_repository.find_available_by_company(company_id).await?;
+        let average_handle_time = self
+            .call_repository
+            .recent_handle_time(company_id, HANDLE_TIME_SAMPLE)
+            .await?
+            .unwrap_or(DEFAULT_HANDLE_TIME_SECS);
+        let staffed_slots: u32 = agents.iter().map(|agent| agent.max_calls).sum();
+
+        let now = chrono::Utc::now();
+        let calls: Vec<QueuedCall> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let position = i as u32 + 1;
+                QueuedCall {
+                    call_id: entry.call_id,
+                    queue_id: entry.queue_id,
+                    priority: entry.priority,
+                    position,
+                    waited: now.signed_duration_since(entry.created_at).num_seconds().max(0) as u64,
+                    estimated_wait_time: estimated_wait_time(position, average_handle_time, staffed_slots),
+                }
             })
+            .collect();
+
+        let total_calls = calls.len() as u64;
+        let high_priority_calls = calls.iter().filter(|call| call.priority >= 150).count() as u64;
+        let average_wait_time = calls.iter().map(|call| call.waited).sum::<u64>() / total_calls.max(1);
+
+        Ok(QueueStats {
+            company_id,
+            total_calls,
+            high_priority_calls,
+            average_wait_time,
+            average_handle_time: average_handle_time.round() as u32,
+            staffed_agents: agents.len() as u32,
+            staffed_slots,
+            estimated_wait_time: estimated_wait_time(calls.len() as u32 + 1, average_handle_time, staffed_slots),
+            calls,
+            timestamp: now,
+        })
+    }
+
+    /// Tell the company's waiting callers their new place in the queue. The
+    /// positions go out on the company's queue channel so every instance can
+    /// pass them to the sockets it holds; a failure only delays the next update.
+    async fn publish_positions(&self, company_id: Uuid) {
+        let published = async {
+            let stats = self.get_queue_stats(company_id).await?;
+            let payload = serde_json::to_string(&stats.calls)?;
+
+            let mut conn = self.redis_conn.write().await;
+            redis::cmd("PUBLISH")
+                .arg(call_queue_channel(company_id))
+                .arg(payload)
+                .query_async::<_, i64>(&mut *conn)
+                .await
+                .map_err(|e| CallDockerError::External(format!("Redis publish failed: {}", e)))
+        };
+
+        if let Err(e) = published.await {
+            tracing::warn!("Failed to publish queue positions for company {}: {}", company_id, e);
         }
     }
 }
 
-#[derive(Debug, Clone)]
+fn routing_result(
+    call: &Call,
+    target_type: RoutingTargetType,
+    target_id: Uuid,
+    priority: u32,
+    routing_reason: impl Into<String>,
+) -> RoutingResult {
+    RoutingResult {
+        call_id: call.id,
+        target_type,
+        target_id,
+        priority,
+        estimated_wait_time: None,
+        routing_reason: routing_reason.into(),
+        applied_rules: Vec::new(),
+    }
+}
+
+fn queue_cursor_key(queue_id: Uuid) -> String {
+    format!("routing:queues:{}:cursor", queue_id)
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct QueueStats {
     pub company_id: Uuid,
     pub total_calls: u64,
     pub high_priority_calls: u64,
+    /// Seconds, averaged over the calls waiting now
     pub average_wait_time: u64,
+    /// Seconds, averaged over recently handled calls
+    pub average_handle_time: u32,
+    /// Agents online to take calls
+    pub staffed_agents: u32,
+    /// Calls those agents can take at once
+    pub staffed_slots: u32,
+    /// Expected wait of a call joining the queue now
+    pub estimated_wait_time: Option<u32>,
+    /// Waiting calls, next to be served first
+    pub calls: Vec<QueuedCall>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
@@ -4,21 +4,45 @@ use uuid::Uuid;
 use sqlx::PgPool;
 use redis::aio::Connection;
 use shared::{
-    call::{Call, CallStatus, CallDirection, CreateCallRequest, CallEvent, CallEventType},
-    types::{WebRTCSignal, SignalType},
-    ApiResponse,
+    auth::{Claims, UserRole},
+    call::{
+        agent_updates_channel, call_events_channel, caller_updates_channel, Call, CallListQuery, CallStatus,
+        CreateCallRequest, EndCallRequest, UpdateCallRequest, CallEvent, CallEventType,
+    },
+    ivr::{IVROutcome, IVRSession},
+    ivr_engine::{IVRInput, IVRStep},
+    routing::{
+        should_set_away, AgentStatus, CallOffer, OfferOutcome, OverflowAction, OverflowReason, RoutingQueue,
+        RoutingResult, RoutingRule, RoutingTargetType,
+    },
+    rule_engine::{RoutingDecision, RuleDryRunRequest, RuleEvaluation},
+    signaling::{AgentUpdate, CallerUpdate, ServerMessage},
+    types::WebRTCSignal,
+    CallDockerError, PaginatedResponse, Pagination, Result,
 };
 use crate::config::Config;
+use crate::middleware::auth;
+use crate::repositories::{
+    AgentRepository, CallEventRepository, CallRepository, CompanyRepository, QueueItemRepository,
+    RoutingQueueRepository, RoutingRuleRepository,
+};
 use super::webrtc_service::WebRTCService;
-use super::call_routing_service::CallRoutingService;
+use super::call_routing_service::{CallRoutingService, QueueOverflow, QueueStats, RoutingOutcome};
+use super::ivr_service::IVRService;
+use super::offer_store::OfferStore;
+use super::queue_store::QueueStore;
 
 #[derive(Clone)]
 pub struct CallService {
-    db_pool: PgPool,
     redis_conn: Arc<RwLock<Connection>>,
     config: Config,
     webrtc_service: WebRTCService,
     routing_service: CallRoutingService,
+    ivr_service: IVRService,
+    offer_store: OfferStore,
+    call_repository: CallRepository,
+    agent_repository: AgentRepository,
+    event_repository: CallEventRepository,
 }
 
 impl CallService {
@@ -28,42 +52,36 @@ impl CallService {
         config: Config,
     ) -> Self {
         let redis_conn = Arc::new(RwLock::new(redis_conn));
+        let agent_repository = AgentRepository::new(db_pool.clone());
+        let queue_store = QueueStore::new(redis_conn.clone(), QueueItemRepository::new(db_pool.clone()));
         
         Self {
-            db_pool,
+            call_repository: CallRepository::new(db_pool.clone()),
+            event_repository: CallEventRepository::new(db_pool.clone()),
+            routing_service: CallRoutingService::new(
+                CallRepository::new(db_pool.clone()),
+                CompanyRepository::new(db_pool.clone()),
+                agent_repository.clone(),
+                RoutingRuleRepository::new(db_pool.clone()),
+                RoutingQueueRepository::new(db_pool.clone()),
+                queue_store,
+                redis_conn.clone(),
+            ),
+            ivr_service: IVRService::new(db_pool),
+            offer_store: OfferStore::new(redis_conn.clone()),
+            agent_repository,
             redis_conn,
             config,
             webrtc_service: WebRTCService::new(),
-            routing_service: CallRoutingService::new(),
         }
     }
 
     /// Create a new call
-    pub async fn create_call(&self, request: &CreateCallRequest) -> Result<Call, Box<dyn std::error::Error>> {
-        let call = Call {
-            id: Uuid::new_v4(),
-            company_id: request.company_id,
-            agent_id: None,
-            customer_id: None,
-            status: CallStatus::Ringing,
-            direction: request.direction.clone(),
-            caller_number: request.customer_phone.clone(),
-            called_number: None,
-            customer_name: request.customer_name.clone(),
-            customer_email: request.customer_email.clone(),
-            duration: None,
-            recording_url: None,
-            notes: None,
-            tags: vec![],
-            metadata: request.metadata.clone().unwrap_or_default(),
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-            answered_at: None,
-            ended_at: None,
-        };
+    pub async fn create_call(&self, request: &CreateCallRequest) -> Result<Call> {
+        let call = new_call(request);
 
         // Store call in database
-        self.store_call(&call).await?;
+        let mut call = self.store_call(&call).await?;
         
         // Add to routing queue
         self.routing_service.add_to_queue(&call).await?;
@@ -71,106 +89,859 @@ impl CallService {
         // Emit call event
         self.emit_call_event(&call, CallEventType::CallInitiated).await?;
 
+        // Apply routing rules and hand the call to an agent straight away when one is free
+        let outcome = self.routing_service.route_call(&mut call).await?;
+        self.apply_routing(call, outcome).await
+    }
+
+    /// Send calls that waited in a routing queue for longer than it allows on to
+    /// the queue's overflow action. Returns how many calls were moved on.
+    pub async fn enforce_queue_limits(&self) -> Result<usize> {
+        let mut moved = 0;
+        for (entry, queue) in self.routing_service.overdue_calls().await? {
+            // One bad call must not hold up the rest of the queue
+            match self.overflow_call(entry.call_id, &queue).await {
+                Ok(true) => moved += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to overflow call {} from queue {}: {}", entry.call_id, queue.id, e),
+            }
+        }
+        Ok(moved)
+    }
+
+    /// Give calls held for their preferred agent to that agent once they are
+    /// free, or to anyone once the wait window is over. Returns how many calls
+    /// were routed.
+    pub async fn route_held_calls(&self) -> Result<usize> {
+        let mut routed = 0;
+        for (company_id, call_id) in self.routing_service.held_calls().await? {
+            // One bad call must not hold up the rest
+            match self.route_held_call(call_id).await {
+                Ok(true) => routed += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to route held call {} of company {}: {}", call_id, company_id, e),
+            }
+        }
+        Ok(routed)
+    }
+
+    /// Offer waiting calls to the agents with free call slots, one call per
+    /// agent in turn, until the slots are full or nothing waiting suits them.
+    /// Returns how many calls were offered.
+    pub async fn dispatch_waiting_calls(&self) -> Result<usize> {
+        let mut offered = 0;
+        for company_id in self.routing_service.waiting_companies().await? {
+            let agents = self.agent_repository.find_available_by_company(company_id).await?;
+            let mut free: Vec<Uuid> = agents
+                .iter()
+                .filter(|agent| agent.has_capacity())
+                .map(|agent| agent.agent_id)
+                .collect();
+
+            while !free.is_empty() {
+                let mut still_free = Vec::new();
+                for agent_id in free {
+                    // One bad agent must not hold up the others
+                    match self.offer_next_call(company_id, agent_id).await {
+                        Ok(true) => {
+                            offered += 1;
+                            still_free.push(agent_id);
+                        }
+                        Ok(false) => {}
+                        Err(e) => tracing::warn!("Failed to offer a waiting call to agent {}: {}", agent_id, e),
+                    }
+                }
+                free = still_free;
+            }
+        }
+        Ok(offered)
+    }
+
+    /// Offer the next waiting call an agent can take to them. Returns false
+    /// when they have no free call slot or nothing waiting suits them.
+    async fn offer_next_call(&self, company_id: Uuid, agent_id: Uuid) -> Result<bool> {
+        loop {
+            let Some(routing) = self.routing_service.get_next_call(agent_id, company_id).await? else {
+                return Ok(false);
+            };
+            match self.get_call(routing.call_id).await {
+                Ok(call) if matches!(call.status, CallStatus::Ringing) && call.agent_id.is_none() => {
+                    let outcome = RoutingOutcome { routed: Some(routing), overflow: None };
+                    // Ending a call routes again, to fill its agent's slot
+                    Box::pin(self.apply_routing(call, outcome)).await?;
+                    return Ok(true);
+                }
+                // Left in the queue by a call that stopped waiting; try the next one
+                Ok(_) => self.agent_repository.release_call_slot(agent_id).await?,
+                Err(e) => {
+                    self.agent_repository.release_call_slot(agent_id).await?;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Offer the agent whose call slot was just freed the next waiting call. A
+    /// failure leaves the call waiting for the queue supervisor.
+    async fn fill_freed_slot(&self, company_id: Uuid, agent_id: Uuid) {
+        if let Err(e) = self.offer_next_call(company_id, agent_id).await {
+            tracing::warn!("Failed to offer a waiting call to freed agent {}: {}", agent_id, e);
+        }
+    }
+
+    async fn route_held_call(&self, call_id: Uuid) -> Result<bool> {
+        let call = self.get_call(call_id).await?;
+        if !matches!(call.status, CallStatus::Ringing) {
+            self.routing_service.remove_from_queue(call.company_id, call.id).await?;
+            return Ok(false);
+        }
+
+        let outcome = self.routing_service.route_held(&call).await?;
+        let routed = outcome.routed.is_some();
+        self.apply_routing(call, outcome).await?;
+        Ok(routed)
+    }
+
+    async fn overflow_call(&self, call_id: Uuid, queue: &RoutingQueue) -> Result<bool> {
+        let call = self.get_call(call_id).await?;
+        if !matches!(call.status, CallStatus::Ringing) {
+            self.routing_service.remove_from_queue(call.company_id, call.id).await?;
+            return Ok(false);
+        }
+
+        match self.routing_service.overflow(&call, queue, OverflowReason::WaitTimeExceeded).await? {
+            Some(outcome) => {
+                self.apply_routing(call, outcome).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Carry where routing sent a call over to the call, offering it to the agent
+    /// it was routed to, escalating it when a routing queue overflowed it and
+    /// starting the IVR flow it was sent to
+    async fn apply_routing(&self, mut call: Call, outcome: RoutingOutcome) -> Result<Call> {
+        let offered_to = outcome
+            .routed
+            .as_ref()
+            .filter(|routing| matches!(routing.target_type, RoutingTargetType::Agent))
+            .map(|routing| (routing.target_id, routing.priority));
+        let previous = call.clone();
+        if let Some((agent_id, _)) = offered_to {
+            call.agent_id = Some(agent_id);
+        }
+        let call = match self.call_repository.update_from(&call, &previous).await {
+            Ok(call) => call,
+            Err(e) => {
+                // The call moved on while it was routed, e.g. the caller hung up
+                if let Some((agent_id, _)) = offered_to {
+                    self.agent_repository.release_call_slot(agent_id).await?;
+                }
+                return match e {
+                    CallDockerError::InvalidTransition(_) => self.get_call(call.id).await,
+                    e => Err(e),
+                };
+            }
+        };
+
+        if let Some((agent_id, priority)) = offered_to {
+            self.offer_call(&call, agent_id, priority).await?;
+        }
+
+        let call = match &outcome.overflow {
+            Some(overflow) => self.escalate(call, overflow, outcome.routed.as_ref()).await?,
+            None => call,
+        };
+
+        match outcome.routed {
+            Some(routing) if matches!(routing.target_type, RoutingTargetType::IVR) => {
+                self.start_ivr(call, routing.target_id).await
+            }
+            _ => Ok(call),
+        }
+    }
+
+    /// Put a call through an IVR flow. A flow that cannot run sends the call to
+    /// the least busy agent instead of leaving it stuck.
+    async fn start_ivr(&self, call: Call, flow_id: Uuid) -> Result<Call> {
+        let context = self.routing_service.ivr_context(&call).await?;
+        match self.ivr_service.start(&call, flow_id, &context).await {
+            Ok(step) => {
+                let data = serde_json::json!({ "flow_id": flow_id });
+                self.emit_call_event_with(&call, CallEventType::IvrStarted, data).await?;
+                self.continue_ivr(call, step).await
+            }
+            Err(e) => self.fail_ivr(call, e).await,
+        }
+    }
+
+    /// Handle keys the call's customer pressed in its IVR flow
+    pub async fn ivr_input(&self, call_id: Uuid, token: &str, digits: String) -> Result<Call> {
+        let call = self.get_call(call_id).await?;
+        auth::authenticate_participant(token, &self.config.jwt.secret, &call)?;
+        if !matches!(call.status, CallStatus::Ringing) {
+            return Err(CallDockerError::IVRSessionNotFound(format!("Call {} is not in an IVR flow", call.id)));
+        }
+
+        let context = self.routing_service.ivr_context(&call).await?;
+        match self.ivr_service.input(call.id, IVRInput::Digits(digits), &context).await {
+            Ok(Some(step)) => self.continue_ivr(call, step).await,
+            Ok(None) => Ok(call),
+            Err(e @ CallDockerError::IVR(_)) => self.fail_ivr(call, e).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Move on IVR sessions whose caller did not answer in time, asking again or
+    /// going where the flow leads. Returns how many sessions timed out.
+    pub async fn expire_ivr_prompts(&self) -> Result<usize> {
+        let now = chrono::Utc::now();
+        let mut expired = 0;
+        for session in self.ivr_service.timed_out(now).await? {
+            let call_id = session.call_id;
+            // One bad call must not hold up the other sessions
+            match self.expire_ivr_prompt(session, now).await {
+                Ok(true) => expired += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to time out IVR session of call {}: {}", call_id, e),
+            }
+        }
+        Ok(expired)
+    }
+
+    async fn expire_ivr_prompt(&self, session: IVRSession, now: chrono::DateTime<chrono::Utc>) -> Result<bool> {
+        let call = self.get_call(session.call_id).await?;
+        let context = self.routing_service.ivr_context(&call).await?;
+        match self.ivr_service.advance(session, IVRInput::Timeout, now, &context).await {
+            Ok(Some(step)) => self.continue_ivr(call, step).await?,
+            // Answered just in time, or timed out by another instance
+            Ok(None) => return Ok(false),
+            Err(e @ CallDockerError::IVR(_)) => self.fail_ivr(call, e).await?,
+            Err(e) => return Err(e),
+        };
+        Ok(true)
+    }
+
+    /// Play what an IVR step has for the caller and, once the flow is over,
+    /// send the call where it led
+    async fn continue_ivr(&self, call: Call, step: IVRStep) -> Result<Call> {
+        for prompt in step.prompts {
+            let message = ServerMessage::IvrPrompt { call_id: call.id, prompt };
+            self.notify_caller(&call, message).await;
+        }
+
+        match step.outcome {
+            Some(outcome) => self.leave_ivr(call, outcome).await,
+            None => Ok(call),
+        }
+    }
+
+    async fn leave_ivr(&self, call: Call, outcome: IVROutcome) -> Result<Call> {
+        tracing::info!("Call {} left its IVR flow: {}", call.id, outcome);
+        let data = serde_json::json!({ "outcome": outcome });
+        self.emit_call_event_with(&call, CallEventType::IvrCompleted, data).await?;
+        let message = ServerMessage::IvrEnded {
+            call_id: call.id,
+            outcome: outcome.clone(),
+        };
+        self.notify_caller(&call, message).await;
+
+        match outcome.routing_decision() {
+            Some(decision) => self.route_after_ivr(call, Some(decision)).await,
+            None => self.transition_call(call.id, CallStatus::Ended).await,
+        }
+    }
+
+    /// A flow that broke mid-call hands the call to the least busy agent
+    async fn fail_ivr(&self, call: Call, error: CallDockerError) -> Result<Call> {
+        tracing::warn!("IVR flow of call {} failed, routing it to an agent: {}", call.id, error);
+        self.ivr_service.abandon(call.id).await?;
+        self.route_after_ivr(call, None).await
+    }
+
+    /// Queue a call that came out of its IVR flow and route it where the flow
+    /// sent it, without running the routing rules again
+    async fn route_after_ivr(&self, call: Call, decision: Option<RoutingDecision>) -> Result<Call> {
+        self.routing_service.add_to_queue(&call).await?;
+        let outcome = self.routing_service.route_call_to(&call, decision).await?;
+        // Routing may send the call through another IVR flow, e.g. overflowing a queue
+        Box::pin(self.apply_routing(call, outcome)).await
+    }
+
+    /// Record that a call overflowed its routing queue, hanging it up when that
+    /// is the queue's overflow action
+    async fn escalate(&self, call: Call, overflow: &QueueOverflow, routed: Option<&RoutingResult>) -> Result<Call> {
+        let data = serde_json::json!({
+            "queue_id": overflow.queue_id,
+            "reason": overflow.reason,
+            "overflow_action": overflow.action.to_string(),
+            "target_type": routed.map(|routed| &routed.target_type),
+            "target_id": routed.map(|routed| routed.target_id),
+            "routing_reason": routed.map(|routed| &routed.routing_reason),
+        });
+        self.emit_call_event_with(&call, CallEventType::CallEscalated, data).await?;
+
+        if matches!(overflow.action, OverflowAction::Hangup) {
+            tracing::info!("Hanging up call {} after overflowing queue {}", call.id, overflow.queue_id);
+            return self.transition_call(call.id, CallStatus::Missed).await;
+        }
+        Ok(call)
+    }
+
+    /// Ring the agent a call was routed to. The call slot routing reserved for
+    /// them is given back should they reject or miss the offer.
+    async fn offer_call(&self, call: &Call, agent_id: Uuid, priority: u32) -> Result<()> {
+        let ring_timeout = self.config.routing.ring_timeout;
+        let offer = CallOffer::new(call.id, call.company_id, agent_id, chrono::Utc::now(), ring_timeout);
+        self.offer_store.put(&offer).await?;
+
+        tracing::info!("Offering call {} to agent {} for {}s", call.id, agent_id, ring_timeout);
+        let message = ServerMessage::CallOffered {
+            call_id: call.id,
+            customer_name: call.customer_name.clone(),
+            priority,
+            expires_at: offer.expires_at,
+        };
+        self.notify_agent(call.company_id, agent_id, message).await;
+
+        let data = serde_json::json!({ "agent_id": agent_id, "expires_at": offer.expires_at });
+        self.emit_call_event_with(call, CallEventType::CallOffered, data).await
+    }
+
+    /// Accept a call offered to the caller. The call stays ringing until the
+    /// agent answers it.
+    pub async fn accept_offer(&self, claims: &Claims, call_id: Uuid) -> Result<Call> {
+        let call = self.get_call_for(claims, call_id).await?;
+        let offer = self.claim_offer(claims, &call).await?;
+
+        self.accept(&call, &offer).await?;
+        Ok(call)
+    }
+
+    /// Turn down a call offered to the caller; it goes to the next agent.
+    pub async fn reject_offer(&self, claims: &Claims, call_id: Uuid) -> Result<Call> {
+        let call = self.get_call_for(claims, call_id).await?;
+        let offer = self.claim_offer(claims, &call).await?;
+
+        tracing::info!("Agent {} rejected call {}", offer.agent_id, call.id);
+        // Rejecting is still a response, so it does not count as a missed offer
+        self.offer_store.reset_misses(offer.agent_id).await?;
+        self.emit_call_event_with(&call, CallEventType::OfferRejected, offer_data(&offer)).await?;
+
+        self.reroute(call, &offer).await
+    }
+
+    /// Move calls whose agent did not respond in time on to the next agent,
+    /// setting agents away once they have missed too many offers in a row.
+    /// Returns how many offers timed out.
+    pub async fn expire_offers(&self) -> Result<usize> {
+        let mut expired = 0;
+        for offer in self.offer_store.expired(chrono::Utc::now()).await? {
+            // Whoever takes the offer handles it; the agent may have just accepted
+            let Some(offer) = self.offer_store.take(offer.call_id).await? else {
+                continue;
+            };
+            expired += 1;
+            // One bad call must not hold up the other offers
+            if let Err(e) = self.miss_offer(&offer).await {
+                tracing::warn!("Failed to move on call {} offered to agent {}: {}", offer.call_id, offer.agent_id, e);
+            }
+        }
+        Ok(expired)
+    }
+
+    async fn miss_offer(&self, offer: &CallOffer) -> Result<()> {
+        let call = self.get_call(offer.call_id).await?;
+        tracing::info!("Offer of call {} to agent {} rang out", call.id, offer.agent_id);
+
+        let message = ServerMessage::OfferWithdrawn {
+            call_id: call.id,
+            reason: OfferOutcome::TimedOut,
+        };
+        self.notify_agent(call.company_id, offer.agent_id, message).await;
+        self.emit_call_event_with(&call, CallEventType::OfferTimedOut, offer_data(offer)).await?;
+
+        let missed = self.offer_store.record_miss(offer.agent_id).await?;
+        let max_missed = self.config.routing.max_missed_offers;
+        if should_set_away(missed, max_missed) {
+            tracing::info!("Setting agent {} away after {} missed offers", offer.agent_id, missed);
+            self.agent_repository.set_status(offer.agent_id, AgentStatus::Away).await?;
+            self.offer_store.reset_misses(offer.agent_id).await?;
+            let message = ServerMessage::AgentStatusChanged {
+                agent_id: offer.agent_id,
+                status: AgentStatus::Away,
+                reason: format!("{} offered calls in a row rang out", missed),
+            };
+            self.notify_agent(call.company_id, offer.agent_id, message).await;
+        }
+
+        self.reroute(call, offer).await?;
+        Ok(())
+    }
+
+    /// Take the offer of `call` made to the caller's agent
+    async fn claim_offer(&self, claims: &Claims, call: &Call) -> Result<CallOffer> {
+        let agent_id = self.agent_for(claims, call.company_id).await?;
+        let not_offered = || CallDockerError::OfferNotFound(format!("Call {} is not offered to you", call.id));
+
+        match self.offer_store.get(call.id).await? {
+            Some(offer) if offer.agent_id == agent_id => {}
+            _ => return Err(not_offered()),
+        }
+        let offer = self.offer_store.take(call.id).await?.ok_or_else(not_offered)?;
+        if offer.agent_id != agent_id {
+            // Expired and offered to someone else in the meantime; leave theirs be
+            self.offer_store.put(&offer).await?;
+            return Err(not_offered());
+        }
+        Ok(offer)
+    }
+
+    async fn accept(&self, call: &Call, offer: &CallOffer) -> Result<()> {
+        tracing::info!("Agent {} accepted call {}", offer.agent_id, call.id);
+        self.offer_store.reset_misses(offer.agent_id).await?;
+        self.offer_store.forget_declined(call.id).await?;
+
+        self.emit_call_event_with(call, CallEventType::OfferAccepted, offer_data(offer)).await
+    }
+
+    /// Free the agent who let a call go and route it again, to anyone but the
+    /// agents who already let it go
+    async fn reroute(&self, call: Call, offer: &CallOffer) -> Result<Call> {
+        self.offer_store.decline(call.id, offer.agent_id).await?;
+        // Hung up while ringing, which freed the agent
+        if !matches!(call.status, CallStatus::Ringing) {
+            return Ok(call);
+        }
+
+        let previous = call.clone();
+        let call = Call { agent_id: None, ..call };
+        let mut call = match self.call_repository.update_from(&call, &previous).await {
+            Ok(call) => call,
+            // Hung up meanwhile, which freed the agent
+            Err(CallDockerError::InvalidTransition(_)) => return self.get_call(call.id).await,
+            Err(e) => return Err(e),
+        };
+        self.agent_repository.release_call_slot(offer.agent_id).await?;
+
+        self.routing_service.add_to_queue(&call).await?;
+        let outcome = self.routing_service.route_call(&mut call).await?;
+        let call = self.apply_routing(call, outcome).await?;
+
+        self.fill_freed_slot(call.company_id, offer.agent_id).await;
         Ok(call)
     }
 
+    /// Settle the offer of a call that stopped ringing: answering it accepts
+    /// the offer, anything else withdraws it
+    async fn close_offer(&self, call: &Call) -> Result<()> {
+        if let Some(offer) = self.offer_store.take(call.id).await? {
+            if matches!(call.status, CallStatus::Connected) {
+                return self.accept(call, &offer).await;
+            }
+            let message = ServerMessage::OfferWithdrawn {
+                call_id: call.id,
+                reason: OfferOutcome::Withdrawn,
+            };
+            self.notify_agent(call.company_id, offer.agent_id, message).await;
+        }
+        self.offer_store.forget_declined(call.id).await
+    }
+
+    /// The agent the caller works as in `company_id`
+    async fn agent_for(&self, claims: &Claims, company_id: Uuid) -> Result<Uuid> {
+        self.agent_repository
+            .find_id_by_user(company_id, claims.sub)
+            .await?
+            .ok_or_else(|| CallDockerError::AgentNotFound(format!("No agent for user {}", claims.sub)))
+    }
+
+    /// The caller's own agent, whose socket receives their offers
+    pub async fn current_agent(&self, claims: &Claims) -> Result<Uuid> {
+        let company_id = claims
+            .company_id
+            .ok_or_else(|| CallDockerError::Authorization("User is not associated with a company".to_string()))?;
+        self.agent_for(claims, company_id).await
+    }
+
+    /// Send a message to an agent's sockets on whichever instance holds them. A
+    /// failure is only logged; the offer stands and expires as usual.
+    async fn notify_agent(&self, company_id: Uuid, agent_id: Uuid, message: ServerMessage) {
+        let published = async {
+            let payload = serde_json::to_string(&AgentUpdate { agent_id, message })?;
+            let mut conn = self.redis_conn.write().await;
+            redis::cmd("PUBLISH")
+                .arg(agent_updates_channel(company_id))
+                .arg(payload)
+                .query_async::<_, i64>(&mut *conn)
+                .await
+                .map_err(|e| CallDockerError::External(format!("Redis publish failed: {}", e)))
+        };
+
+        if let Err(e) = published.await {
+            tracing::warn!("Failed to notify agent {}: {}", agent_id, e);
+        }
+    }
+
+    /// Send a message to the sockets of a call's room on whichever instance
+    /// holds them. A failure is only logged; the caller can still key in input.
+    async fn notify_caller(&self, call: &Call, message: ServerMessage) {
+        let published = async {
+            let payload = serde_json::to_string(&CallerUpdate { call_id: call.id, message })?;
+            let mut conn = self.redis_conn.write().await;
+            redis::cmd("PUBLISH")
+                .arg(caller_updates_channel(call.company_id))
+                .arg(payload)
+                .query_async::<_, i64>(&mut *conn)
+                .await
+                .map_err(|e| CallDockerError::External(format!("Redis publish failed: {}", e)))
+        };
+
+        if let Err(e) = published.await {
+            tracing::warn!("Failed to notify caller of call {}: {}", call.id, e);
+        }
+    }
+
+    /// Explain which routing rules apply to a sample call and where it would go
+    pub async fn dry_run_routing(&self, claims: &Claims, request: RuleDryRunRequest) -> Result<RuleEvaluation> {
+        auth::authorize_company(claims, request.call.company_id)?;
+
+        let mut call = new_call(&request.call);
+        call.tags = request.tags;
+        if let Some(call_time) = request.call_time {
+            call.created_at = call_time;
+        }
+
+        // Unsaved rules keep the order they were given in when priorities tie
+        let now = chrono::Utc::now();
+        let rules = request.rules.map(|rules| {
+            rules
+                .into_iter()
+                .enumerate()
+                .map(|(i, rule)| RoutingRule {
+                    id: Uuid::new_v4(),
+                    company_id: call.company_id,
+                    name: rule.name,
+                    description: rule.description,
+                    priority: rule.priority,
+                    is_active: true,
+                    conditions: rule.conditions,
+                    actions: rule.actions,
+                    created_at: now + chrono::Duration::milliseconds(i as i64),
+                    updated_at: now,
+                })
+                .collect()
+        });
+
+        self.routing_service.explain_routing(&call, rules).await
+    }
+
+    /// Waiting calls of the caller's company, with positions and estimated waits
+    pub async fn get_queue_stats(&self, claims: &Claims, company_id: Option<Uuid>) -> Result<QueueStats> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        self.routing_service.get_queue_stats(company_id).await
+    }
+
+    /// Put calls that were waiting before a restart back into the routing queue
+    pub async fn restore_queues(&self) -> Result<()> {
+        self.routing_service.restore_queues().await
+    }
+
+    /// Issue a short-lived token admitting the call's customer to its WebSocket room
+    pub fn issue_widget_token(&self, call: &Call) -> Result<String> {
+        auth::issue_widget_token(call, &self.config.jwt.secret, self.config.jwt.widget_token_expiry)
+    }
+
     /// Handle WebRTC offer signal
-    pub async fn handle_webrtc_offer(&self, signal: &WebRTCSignal) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-        // Validate call exists
-        let call = self.get_call(signal.call_id).await?;
-        
+    pub async fn handle_webrtc_offer(&self, signal: &WebRTCSignal, token: &str) -> Result<serde_json::Value> {
+        // Validate call exists and may (still) ring
+        let call = self.signaled_call(signal, token).await?;
+        call.ensure_can_transition(&CallStatus::Ringing)?;
+
         // Process offer through WebRTC service
         let response = self.webrtc_service.handle_offer(signal).await?;
-        
-        // Update call status
-        self.update_call_status(signal.call_id, CallStatus::Ringing).await?;
-        
-        // Emit call event
-        self.emit_call_event(&call, CallEventType::CallRinging).await?;
+
+        // Update call status and emit call event
+        self.transition_call(signal.call_id, CallStatus::Ringing).await?;
 
         Ok(response)
     }
 
     /// Handle WebRTC answer signal
-    pub async fn handle_webrtc_answer(&self, signal: &WebRTCSignal) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-        // Validate call exists
-        let call = self.get_call(signal.call_id).await?;
-        
+    pub async fn handle_webrtc_answer(&self, signal: &WebRTCSignal, token: &str) -> Result<serde_json::Value> {
+        // Validate call exists and can be answered
+        let call = self.signaled_call(signal, token).await?;
+        call.ensure_can_transition(&CallStatus::Connected)?;
+
         // Process answer through WebRTC service
         let response = self.webrtc_service.handle_answer(signal).await?;
-        
-        // Update call status
-        self.update_call_status(signal.call_id, CallStatus::Connected).await?;
-        
-        // Emit call event
-        self.emit_call_event(&call, CallEventType::CallAnswered).await?;
+
+        // Update call status and emit call event
+        self.transition_call(signal.call_id, CallStatus::Connected).await?;
 
         Ok(response)
     }
 
     /// Handle ICE candidate signal
-    pub async fn handle_ice_candidate(&self, signal: &WebRTCSignal) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn handle_ice_candidate(&self, signal: &WebRTCSignal, token: &str) -> Result<()> {
+        // Validate call exists
+        self.signaled_call(signal, token).await?;
+
         // Process ICE candidate through WebRTC service
         self.webrtc_service.handle_ice_candidate(signal).await?;
         Ok(())
     }
 
+    /// The call a signal is for, once the token is known to admit its sender
+    /// to it, like the WebSocket does
+    async fn signaled_call(&self, signal: &WebRTCSignal, token: &str) -> Result<Call> {
+        let call = self.get_call(signal.call_id).await?;
+        auth::authenticate_participant(token, &self.config.jwt.secret, &call)?;
+        Ok(call)
+    }
+
     /// Get call by ID
-    pub async fn get_call(&self, call_id: Uuid) -> Result<Call, Box<dyn std::error::Error>> {
-        // This would typically query the database
-        // For now, return a mock call
-        Ok(Call {
-            id: call_id,
-            company_id: Uuid::new_v4(),
-            agent_id: None,
-            customer_id: None,
-            status: CallStatus::Ringing,
-            direction: CallDirection::Inbound,
-            caller_number: None,
-            called_number: None,
-            customer_name: None,
-            customer_email: None,
-            duration: None,
-            recording_url: None,
-            notes: None,
-            tags: vec![],
-            metadata: serde_json::Value::Null,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-            answered_at: None,
-            ended_at: None,
+    pub async fn get_call(&self, call_id: Uuid) -> Result<Call> {
+        self.call_repository
+            .find_by_id(call_id)
+            .await?
+            .ok_or_else(|| CallDockerError::CallNotFound(call_id.to_string()))
+    }
+
+    /// Get call by ID, rejecting callers outside the call's company
+    pub async fn get_call_for(&self, claims: &Claims, call_id: Uuid) -> Result<Call> {
+        let call = self.get_call(call_id).await?;
+        auth::authorize_company(claims, call.company_id)?;
+        Ok(call)
+    }
+
+    /// Get the ordered event timeline for a call
+    pub async fn get_call_events(&self, claims: &Claims, call_id: Uuid) -> Result<Vec<CallEvent>> {
+        // Validate call exists and is visible to the caller
+        self.get_call_for(claims, call_id).await?;
+
+        self.event_repository.list_by_call(call_id).await
+    }
+
+    /// List calls visible to the caller, scoped to their company unless they are a super admin
+    pub async fn list_calls(&self, claims: &Claims, mut query: CallListQuery) -> Result<PaginatedResponse<Call>> {
+        if !matches!(claims.role, UserRole::SuperAdmin) {
+            let company_id = claims.company_id.ok_or_else(|| {
+                CallDockerError::Authorization("User is not associated with a company".to_string())
+            })?;
+
+            if query.company_id.is_some_and(|requested| requested != company_id) {
+                return Err(CallDockerError::Authorization("Access denied to this company".to_string()));
+            }
+            query.company_id = Some(company_id);
+        }
+
+        let page = self.call_repository.list(&query).await?;
+        let total_pages = (page.total as f64 / query.per_page as f64).ceil() as u32;
+
+        Ok(PaginatedResponse {
+            data: page.calls,
+            pagination: Pagination {
+                page: query.page,
+                per_page: query.per_page,
+                total: page.total,
+                total_pages,
+                next_cursor: page.next_cursor,
+            },
         })
     }
 
-    /// Update call status
-    async fn update_call_status(&self, call_id: Uuid, status: CallStatus) -> Result<(), Box<dyn std::error::Error>> {
-        // This would typically update the database
-        // For now, just log the update
-        tracing::info!("Updating call {} status to {:?}", call_id, status);
-        Ok(())
+    /// End a call, recording the agent's wrap-up and releasing their call slot
+    pub async fn end_call(&self, claims: &Claims, call_id: Uuid, wrap_up: EndCallRequest) -> Result<Call> {
+        let mut call = self.get_call_for(claims, call_id).await?;
+        let previous = call.clone();
+        let event_type = call.transition_to(CallStatus::Ended, chrono::Utc::now())?;
+
+        // Apply wrap-up data
+        if let Some(notes) = &wrap_up.notes {
+            call.notes = Some(notes.clone());
+        }
+        merge_tags(&mut call.tags, wrap_up.tags);
+        if let Some(disposition_code) = wrap_up.disposition_code {
+            if !call.metadata.is_object() {
+                call.metadata = serde_json::json!({});
+            }
+            call.metadata["disposition_code"] = serde_json::Value::String(disposition_code);
+        }
+
+        tracing::info!("Ending call {} after {}s", call_id, call.duration.unwrap_or(0));
+        let call = self.save_transition(&previous, call, event_type).await?;
+
+        if let (Some(agent_id), Some(notes)) = (call.agent_id, &wrap_up.notes) {
+            self.call_repository.create_note(call.id, agent_id, notes, false).await?;
+        }
+
+        Ok(call)
+    }
+
+    /// Update a call's tags, notes and metadata
+    pub async fn update_call(&self, claims: &Claims, call_id: Uuid, request: UpdateCallRequest) -> Result<Call> {
+        let mut call = self.get_call_for(claims, call_id).await?;
+
+        if let Some(tags) = request.tags {
+            call.tags = Vec::new();
+            merge_tags(&mut call.tags, tags);
+        }
+        if let Some(notes) = request.notes {
+            call.notes = Some(notes);
+        }
+        if let Some(metadata) = request.metadata {
+            call.metadata = metadata;
+        }
+
+        self.call_repository.update_details(&call).await
+    }
+
+    /// Move a call through the status state machine, persist it and emit the
+    /// matching event. Illegal transitions, and calls another request moved on
+    /// in the meantime, are rejected before anything is written.
+    async fn transition_call(&self, call_id: Uuid, status: CallStatus) -> Result<Call> {
+        let mut call = self.get_call(call_id).await?;
+        let previous = call.clone();
+        let event_type = call.transition_to(status, chrono::Utc::now())?;
+
+        tracing::info!("Updating call {} status to {}", call_id, call.status);
+        self.save_transition(&previous, call, event_type).await
+    }
+
+    /// Persist a call that moved on from `previous` and clear up after it: a
+    /// call no longer ringing leaves the queue, its offer and its IVR flow, and
+    /// one that is over tears down its media and frees its agent's call slot
+    /// for the next waiting call.
+    async fn save_transition(&self, previous: &Call, call: Call, event_type: CallEventType) -> Result<Call> {
+        let call = self.call_repository.update_from(&call, previous).await?;
+
+        // Only ringing calls wait for an agent
+        if !matches!(call.status, CallStatus::Ringing) {
+            self.routing_service.remove_from_queue(call.company_id, call.id).await?;
+            self.close_offer(&call).await?;
+            self.ivr_service.abandon(call.id).await?;
+        }
+        let freed = call.agent_id.filter(|_| call.status.is_terminal());
+        if call.status.is_terminal() {
+            self.webrtc_service.close_connection(call.id).await?;
+        }
+        if let Some(agent_id) = freed {
+            self.agent_repository.release_call_slot(agent_id).await?;
+        }
+
+        self.emit_call_event(&call, event_type).await?;
+
+        if let Some(agent_id) = freed {
+            self.fill_freed_slot(call.company_id, agent_id).await;
+        }
+        Ok(call)
     }
 
     /// Store call in database
-    async fn store_call(&self, call: &Call) -> Result<(), Box<dyn std::error::Error>> {
-        // This would typically insert into the database
-        // For now, just log the storage
+    async fn store_call(&self, call: &Call) -> Result<Call> {
         tracing::info!("Storing call {} in database", call.id);
-        Ok(())
+        self.call_repository.create(call).await
     }
 
-    /// Emit call event
-    async fn emit_call_event(&self, call: &Call, event_type: CallEventType) -> Result<(), Box<dyn std::error::Error>> {
+    /// Record a call event and publish it on the company's Redis channel
+    async fn emit_call_event(&self, call: &Call, event_type: CallEventType) -> Result<()> {
+        self.emit_call_event_with(call, event_type, serde_json::json!({})).await
+    }
+
+    /// Like `emit_call_event`, adding the fields of `extra` to the event data
+    async fn emit_call_event_with(
+        &self,
+        call: &Call,
+        event_type: CallEventType,
+        extra: serde_json::Value,
+    ) -> Result<()> {
+        let mut data = serde_json::json!({
+            "company_id": call.company_id,
+            "agent_id": call.agent_id,
+            "status": call.status,
+            "duration": call.duration,
+        });
+        if let (Some(data), Some(extra)) = (data.as_object_mut(), extra.as_object()) {
+            data.extend(extra.clone());
+        }
+
         let event = CallEvent {
             id: Uuid::new_v4(),
             call_id: call.id,
             event_type,
-            data: serde_json::Value::Null,
+            data,
             timestamp: chrono::Utc::now(),
         };
 
-        // This would typically publish to Redis or a message queue
-        // For now, just log the event
-        tracing::info!("Emitting call event: {:?} for call {}", event_type, call.id);
+        tracing::info!("Emitting call event: {} for call {}", event.event_type, call.id);
+        let event = self.event_repository.create(&event).await?;
+
+        // The event is already durable, so a failed publish only costs real-time subscribers
+        if let Err(e) = self.publish_call_event(call.company_id, &event).await {
+            tracing::warn!("Failed to publish call event {}: {}", event.id, e);
+        }
+
         Ok(())
     }
+
+    /// Publish a call event to subscribers of the company's channel
+    async fn publish_call_event(&self, company_id: Uuid, event: &CallEvent) -> Result<()> {
+        let payload = serde_json::to_string(event)?;
+        let mut conn = self.redis_conn.write().await;
+
+        let _: i64 = redis::cmd("PUBLISH")
+            .arg(call_events_channel(company_id))
+            .arg(payload)
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| CallDockerError::External(format!("Redis publish failed: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// A ringing call for `request`, not yet stored.
+fn new_call(request: &CreateCallRequest) -> Call {
+    Call {
+        id: Uuid::new_v4(),
+        company_id: request.company_id,
+        agent_id: None,
+        customer_id: None,
+        status: CallStatus::Ringing,
+        direction: request.direction.clone(),
+        caller_number: request.customer_phone.clone(),
+        called_number: None,
+        customer_name: request.customer_name.clone(),
+        customer_email: request.customer_email.clone(),
+        duration: None,
+        recording_url: None,
+        notes: None,
+        tags: vec![],
+        metadata: request.metadata.clone().unwrap_or_else(|| serde_json::json!({})),
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+        answered_at: None,
+        ended_at: None,
+    }
+}
+
+/// Event data of an offer's outcome: who it was offered to and how long it rang.
+/// Agent routing statistics are built from these.
+fn offer_data(offer: &CallOffer) -> serde_json::Value {
+    serde_json::json!({
+        "agent_id": offer.agent_id,
+        "ring_time": offer.ring_time(chrono::Utc::now()),
+    })
+}
+
+/// Append trimmed tags that are not already present.
+fn merge_tags(tags: &mut Vec<String>, new_tags: Vec<String>) {
+    for tag in new_tags {
+        let tag = tag.trim().to_string();
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
 }
@@ -0,0 +1,289 @@
+use uuid::Uuid;
+use sqlx::PgPool;
+use shared::{
+    auth::Claims,
+    ivr::{
+        CreateIVRFlowRequest, CreateIVRTemplateRequest, IVRFlow, IVRFlowDiffQuery, IVRFlowDraft, IVRFlowVersion,
+        IVRTemplate, IVRTemplateData, InstantiateIVRTemplateRequest, RollbackIVRFlowRequest, UpdateIVRFlowRequest,
+    },
+    ivr_diff::{diff_flows, IVRFlowDiff},
+    ivr_template::{builtin_template, builtin_templates, company_placeholders, instantiate_template},
+    ivr_validation::validate_flow,
+    CallDockerError, Result,
+};
+use crate::middleware::auth;
+use crate::repositories::{CompanyRepository, IVRFlowRepository, IVRSessionRepository, IVRTemplateRepository};
+
+/// Company admins' management of IVR flows. Admins edit a flow's draft and
+/// publish it as a new version; calls only ever run published versions, and
+/// keep the version they started on. Every operation is limited to the
+/// caller's company. New flows may also start from a template.
+#[derive(Clone)]
+pub struct IVRFlowService {
+    flow_repository: IVRFlowRepository,
+    session_repository: IVRSessionRepository,
+    template_repository: IVRTemplateRepository,
+    company_repository: CompanyRepository,
+}
+
+impl IVRFlowService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            flow_repository: IVRFlowRepository::new(db_pool.clone()),
+            session_repository: IVRSessionRepository::new(db_pool.clone()),
+            template_repository: IVRTemplateRepository::new(db_pool.clone()),
+            company_repository: CompanyRepository::new(db_pool),
+        }
+    }
+
+    /// List a company's flows by name
+    pub async fn list_flows(&self, claims: &Claims, company_id: Option<Uuid>) -> Result<Vec<IVRFlow>> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        self.flow_repository.list_by_company(company_id).await
+    }
+
+    /// Get a flow of the caller's company, with what is wrong with its draft
+    pub async fn get_flow(&self, claims: &Claims, flow_id: Uuid) -> Result<IVRFlowDraft> {
+        let flow = self.flow(claims, flow_id).await?;
+        Ok(draft(flow))
+    }
+
+    /// Create a flow, active but unpublished. The draft is saved even when it
+    /// has errors.
+    pub async fn create_flow(
+        &self,
+        claims: &Claims,
+        company_id: Option<Uuid>,
+        request: CreateIVRFlowRequest,
+    ) -> Result<IVRFlowDraft> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        let now = chrono::Utc::now();
+
+        let flow = IVRFlow {
+            id: Uuid::new_v4(),
+            company_id,
+            name: request.name,
+            description: request.description,
+            is_active: true,
+            welcome_message: request.welcome_message,
+            welcome_audio_url: request.welcome_audio_url,
+            nodes: request.nodes.into_iter().map(|node| node.into_node()).collect(),
+            published_version: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        tracing::info!("Creating IVR flow {} for company {}", flow.id, company_id);
+        Ok(draft(self.flow_repository.create(&flow).await?))
+    }
+
+    /// Update the given fields of a flow's draft. Calls keep running the
+    /// published version until the draft is published.
+    pub async fn update_flow(
+        &self,
+        claims: &Claims,
+        flow_id: Uuid,
+        request: UpdateIVRFlowRequest,
+    ) -> Result<IVRFlowDraft> {
+        let mut flow = self.flow(claims, flow_id).await?;
+
+        if let Some(name) = request.name {
+            flow.name = name;
+        }
+        if let Some(description) = request.description {
+            flow.description = Some(description);
+        }
+        if let Some(welcome_message) = request.welcome_message {
+            flow.welcome_message = Some(welcome_message);
+        }
+        if let Some(welcome_audio_url) = request.welcome_audio_url {
+            flow.welcome_audio_url = Some(welcome_audio_url);
+        }
+        if let Some(nodes) = request.nodes {
+            flow.nodes = nodes.into_iter().map(|node| node.into_node()).collect();
+        }
+        if let Some(is_active) = request.is_active {
+            flow.is_active = is_active;
+        }
+
+        Ok(draft(self.flow_repository.update(&flow).await?))
+    }
+
+    /// Delete a flow and its versions, unless calls are still in it
+    pub async fn delete_flow(&self, claims: &Claims, flow_id: Uuid) -> Result<()> {
+        let flow = self.flow(claims, flow_id).await?;
+
+        let active = self.session_repository.count_active_by_flow(flow.id).await?;
+        if active > 0 {
+            return Err(CallDockerError::Validation(format!(
+                "IVR flow {} still has {} call(s) in it",
+                flow.id, active
+            )));
+        }
+
+        tracing::info!("Deleting IVR flow {} of company {}", flow.id, flow.company_id);
+        self.flow_repository.delete(flow.id).await
+    }
+
+    /// Publish a flow's draft as its next version, which new calls run from
+    /// then on. Only a draft without errors can be published.
+    pub async fn publish_flow(&self, claims: &Claims, flow_id: Uuid) -> Result<IVRFlowVersion> {
+        let flow = self.flow(claims, flow_id).await?;
+        validate_flow(&flow.nodes).into_result()?;
+
+        let version = self.flow_repository.publish(&flow, Some(claims.sub)).await?;
+        tracing::info!("Published IVR flow {} as version {}", flow.id, version.version);
+        Ok(version)
+    }
+
+    /// List a flow's published versions, newest first
+    pub async fn list_versions(&self, claims: &Claims, flow_id: Uuid) -> Result<Vec<IVRFlowVersion>> {
+        let flow = self.flow(claims, flow_id).await?;
+        self.flow_repository.list_versions(flow.id).await
+    }
+
+    /// Get one published version of a flow
+    pub async fn get_version(&self, claims: &Claims, flow_id: Uuid, version: u32) -> Result<IVRFlowVersion> {
+        let flow = self.flow(claims, flow_id).await?;
+        self.version(&flow, version).await
+    }
+
+    /// Make an earlier version the one new calls run. The draft is left as is.
+    pub async fn rollback_flow(
+        &self,
+        claims: &Claims,
+        flow_id: Uuid,
+        request: RollbackIVRFlowRequest,
+    ) -> Result<IVRFlow> {
+        let flow = self.flow(claims, flow_id).await?;
+        let version = self.version(&flow, request.version).await?;
+
+        tracing::info!(
+            "Rolling IVR flow {} back from version {:?} to {}",
+            flow.id,
+            flow.published_version,
+            version.version
+        );
+        self.flow_repository.set_published_version(flow.id, version.version).await
+    }
+
+    /// What changed from one version to another, or to the draft when `to`
+    /// is not given
+    pub async fn diff_flow(&self, claims: &Claims, flow_id: Uuid, query: IVRFlowDiffQuery) -> Result<IVRFlowDiff> {
+        let flow = self.flow(claims, flow_id).await?;
+        let from = self.version(&flow, query.from).await?.apply_to(&flow);
+        let to = match query.to {
+            Some(to) => self.version(&flow, to).await?.apply_to(&flow),
+            None => flow,
+        };
+
+        Ok(diff_flows(&from, &to))
+    }
+
+    /// The built-in templates, then the public ones and the company's own by
+    /// name
+    pub async fn list_templates(&self, claims: &Claims, company_id: Option<Uuid>) -> Result<Vec<IVRTemplate>> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        let mut templates = builtin_templates();
+        templates.extend(self.template_repository.list_for_company(company_id).await?);
+        Ok(templates)
+    }
+
+    /// Save a flow's draft as a template only the flow's company is offered
+    pub async fn create_template(&self, claims: &Claims, request: CreateIVRTemplateRequest) -> Result<IVRTemplate> {
+        let flow = self.flow(claims, request.flow_id).await?;
+        let now = chrono::Utc::now();
+
+        let template = IVRTemplate {
+            id: Uuid::new_v4(),
+            company_id: Some(flow.company_id),
+            name: request.name,
+            description: request.description,
+            category: request.category,
+            template_data: IVRTemplateData {
+                welcome_message: flow.welcome_message,
+                welcome_audio_url: flow.welcome_audio_url,
+                nodes: flow.nodes,
+            },
+            is_public: false,
+            created_by: Some(claims.sub),
+            created_at: now,
+            updated_at: now,
+        };
+
+        tracing::info!("Saving IVR flow {} as template {}", flow.id, template.id);
+        self.template_repository.create(&template).await
+    }
+
+    /// Delete one of the company's templates. Built-in and public templates
+    /// cannot be deleted through here.
+    pub async fn delete_template(&self, claims: &Claims, template_id: Uuid) -> Result<()> {
+        let company_id = self
+            .template_repository
+            .find_by_id(template_id)
+            .await?
+            .and_then(|template| template.company_id)
+            .ok_or_else(|| CallDockerError::IVRTemplateNotFound(template_id.to_string()))?;
+
+        auth::authorize_company(claims, company_id)?;
+        self.template_repository.delete(template_id).await
+    }
+
+    /// Create an unpublished flow from a template, its placeholders filled from
+    /// the company's details and the request
+    pub async fn instantiate_template(
+        &self,
+        claims: &Claims,
+        company_id: Option<Uuid>,
+        template_id: Uuid,
+        request: InstantiateIVRTemplateRequest,
+    ) -> Result<IVRFlowDraft> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        let template = self.template(company_id, template_id).await?;
+
+        let contact = self.company_repository.find_contact(company_id).await?;
+        let policy = self.company_repository.find_routing_policy(company_id).await?;
+        let mut placeholders = company_placeholders(&contact, &policy.business_hours);
+        placeholders.extend(request.placeholders);
+
+        let flow = instantiate_template(&template, company_id, request.name, &placeholders, chrono::Utc::now())?;
+        tracing::info!("Creating IVR flow {} for company {} from template {}", flow.id, company_id, template.id);
+        Ok(draft(self.flow_repository.create(&flow).await?))
+    }
+
+    async fn flow(&self, claims: &Claims, flow_id: Uuid) -> Result<IVRFlow> {
+        let flow = self
+            .flow_repository
+            .find_by_id(flow_id)
+            .await?
+            .ok_or_else(|| CallDockerError::IVRFlowNotFound(flow_id.to_string()))?;
+
+        auth::authorize_company(claims, flow.company_id)?;
+        Ok(flow)
+    }
+
+    async fn version(&self, flow: &IVRFlow, version: u32) -> Result<IVRFlowVersion> {
+        self.flow_repository
+            .find_version(flow.id, version)
+            .await?
+            .ok_or_else(|| CallDockerError::IVRFlowNotFound(format!("{} version {}", flow.id, version)))
+    }
+
+    /// A template the company is offered; others are reported as not found
+    async fn template(&self, company_id: Uuid, template_id: Uuid) -> Result<IVRTemplate> {
+        if let Some(template) = builtin_template(template_id) {
+            return Ok(template);
+        }
+
+        self.template_repository
+            .find_by_id(template_id)
+            .await?
+            .filter(|template| template.is_public || template.company_id == Some(company_id))
+            .ok_or_else(|| CallDockerError::IVRTemplateNotFound(template_id.to_string()))
+    }
+}
+
+fn draft(flow: IVRFlow) -> IVRFlowDraft {
+    let report = validate_flow(&flow.nodes);
+    IVRFlowDraft { flow, report }
+}
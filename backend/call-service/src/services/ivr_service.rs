@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use sqlx::PgPool;
+use shared::call::Call;
+use shared::ivr::{IVRFlow, IVROutcome, IVRSession};
+use shared::ivr_condition::IVRContext;
+use shared::ivr_engine::{IVREngine, IVRInput, IVRStep};
+use shared::{CallDockerError, Result};
+use crate::repositories::{IVRFlowRepository, IVRSessionRepository};
+
+/// Runs calls through IVR flows. Sessions are stored after every step, so any
+/// instance can handle the caller's next input; saving a step only succeeds
+/// when the session has not moved on since it was read, which decides the
+/// instance that handles it.
+#[derive(Clone)]
+pub struct IVRService {
+    flow_repository: IVRFlowRepository,
+    session_repository: IVRSessionRepository,
+}
+
+impl IVRService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            flow_repository: IVRFlowRepository::new(db_pool.clone()),
+            session_repository: IVRSessionRepository::new(db_pool),
+        }
+    }
+
+    /// Start a call's session of the published version of an active flow of
+    /// its company. The session keeps running that version.
+    pub async fn start(&self, call: &Call, flow_id: Uuid, context: &IVRContext) -> Result<IVRStep> {
+        let flow = self.flow(flow_id).await?;
+        if flow.company_id != call.company_id || !flow.is_active {
+            return Err(CallDockerError::IVR(format!("IVR flow {} is not active for call {}", flow_id, call.id)));
+        }
+        let Some(version) = flow.published_version else {
+            return Err(CallDockerError::IVR(format!("IVR flow {} has not been published", flow_id)));
+        };
+        let flow = self.version(&flow, version).await?;
+        // A call sent through IVR again leaves its previous session
+        self.abandon(call.id).await?;
+
+        let (session, step) = IVREngine::new(&flow, context).start(call.id, Utc::now())?;
+        self.session_repository.create(&session).await?;
+
+        tracing::info!("Call {} entered IVR flow {} at node {}", call.id, flow.id, session.current_node_id);
+        Ok(step)
+    }
+
+    /// Handle what the caller entered at the node their session waits at.
+    /// Returns `None` when their session moved on in the meantime.
+    pub async fn input(&self, call_id: Uuid, input: IVRInput, context: &IVRContext) -> Result<Option<IVRStep>> {
+        let session = self
+            .session_repository
+            .find_active_by_call(call_id)
+            .await?
+            .ok_or_else(|| CallDockerError::IVRSessionNotFound(format!("Call {} is not in an IVR flow", call_id)))?;
+
+        self.advance(session, input, Utc::now(), context).await
+    }
+
+    /// Running sessions whose caller did not answer in time
+    pub async fn timed_out(&self, now: DateTime<Utc>) -> Result<Vec<IVRSession>> {
+        self.session_repository.list_timed_out(now).await
+    }
+
+    /// Move a session on and store it, along with the input it handled.
+    /// Returns `None` when nothing changed or the session moved on in the
+    /// meantime.
+    pub async fn advance(
+        &self,
+        mut session: IVRSession,
+        input: IVRInput,
+        now: DateTime<Utc>,
+        context: &IVRContext,
+    ) -> Result<Option<IVRStep>> {
+        let flow = self.flow(session.flow_id).await?;
+        let flow = match session.flow_version {
+            Some(version) => self.version(&flow, version).await?,
+            None => flow,
+        };
+        let read_at = session.updated_at;
+        let step = IVREngine::new(&flow, context).advance(&mut session, input, now)?;
+        if step.interaction.is_none() && step.outcome.is_none() {
+            return Ok(None);
+        }
+
+        if self.session_repository.update(&session, read_at).await?.is_none() {
+            tracing::info!("IVR session {} of call {} moved on elsewhere", session.id, session.call_id);
+            return Ok(None);
+        }
+        if let Some(interaction) = &step.interaction {
+            self.session_repository.create_interaction(interaction).await?;
+        }
+        Ok(Some(step))
+    }
+
+    /// End a call's running session, e.g. because the caller hung up
+    pub async fn abandon(&self, call_id: Uuid) -> Result<()> {
+        let Some(mut session) = self.session_repository.find_active_by_call(call_id).await? else {
+            return Ok(());
+        };
+
+        let read_at = session.updated_at;
+        session.is_active = false;
+        session.timeout_at = None;
+        session.outcome = Some(IVROutcome::Abandoned);
+        // Having moved on elsewhere, the session ends there
+        if self.session_repository.update(&session, read_at).await?.is_some() {
+            tracing::info!("Call {} left IVR session {}", call_id, session.id);
+        }
+        Ok(())
+    }
+
+    async fn flow(&self, flow_id: Uuid) -> Result<IVRFlow> {
+        self.flow_repository
+            .find_by_id(flow_id)
+            .await?
+            .ok_or_else(|| CallDockerError::IVR(format!("IVR flow {} not found", flow_id)))
+    }
+
+    /// `flow` as it was published as `version`
+    async fn version(&self, flow: &IVRFlow, version: u32) -> Result<IVRFlow> {
+        self.flow_repository
+            .find_version(flow.id, version)
+            .await?
+            .map(|published| published.apply_to(flow))
+            .ok_or_else(|| CallDockerError::IVR(format!("IVR flow {} has no version {}", flow.id, version)))
+    }
+}
@@ -1,3 +1,9 @@
 pub mod call_service;
 pub mod webrtc_service;
 pub mod call_routing_service;
+pub mod offer_store;
+pub mod queue_store;
+pub mod queue_supervisor;
+pub mod routing_config_service;
+pub mod ivr_service;
+pub mod ivr_flow_service;
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use redis::aio::Connection;
+use shared::routing::CallOffer;
+use shared::{CallDockerError, Result};
+
+/// How long the agents who let a call go are remembered, should the call never
+/// end cleanly.
+const DECLINED_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Calls reserved for an agent and waiting for them to respond. Offers live in
+/// Redis so every instance sees them: a sorted set of call ids scored by when
+/// the offer expires, next to a hash holding each offer. Like the call queue,
+/// removing a call from the sorted set claims its offer, so accepting,
+/// rejecting and timing out cannot all happen to the same offer.
+#[derive(Clone)]
+pub struct OfferStore {
+    redis_conn: Arc<RwLock<Connection>>,
+}
+
+impl OfferStore {
+    pub fn new(redis_conn: Arc<RwLock<Connection>>) -> Self {
+        Self { redis_conn }
+    }
+
+    pub async fn put(&self, offer: &CallOffer) -> Result<()> {
+        let call_id = offer.call_id.to_string();
+        let payload = serde_json::to_string(offer)?;
+
+        let mut conn = self.redis_conn.write().await;
+        redis::pipe()
+            .atomic()
+            .cmd("HSET")
+            .arg(OFFER_ENTRIES_KEY)
+            .arg(&call_id)
+            .arg(payload)
+            .ignore()
+            .cmd("ZADD")
+            .arg(OFFERS_KEY)
+            .arg(offer.expires_at.timestamp_millis())
+            .arg(&call_id)
+            .ignore()
+            .query_async::<_, ()>(&mut *conn)
+            .await
+            .map_err(redis_error)
+    }
+
+    pub async fn get(&self, call_id: Uuid) -> Result<Option<CallOffer>> {
+        let mut conn = self.redis_conn.write().await;
+        let payload: Option<String> = redis::cmd("HGET")
+            .arg(OFFER_ENTRIES_KEY)
+            .arg(call_id.to_string())
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+
+        payload.map(|payload| parse_offer(&payload)).transpose()
+    }
+
+    /// Claim the offer for a call. Returns `None` when there is none, or when
+    /// another instance claimed it first.
+    pub async fn take(&self, call_id: Uuid) -> Result<Option<CallOffer>> {
+        let mut conn = self.redis_conn.write().await;
+        let (payload, removed): (Option<String>, u32) = redis::pipe()
+            .atomic()
+            .cmd("HGET")
+            .arg(OFFER_ENTRIES_KEY)
+            .arg(call_id.to_string())
+            .cmd("ZREM")
+            .arg(OFFERS_KEY)
+            .arg(call_id.to_string())
+            .cmd("HDEL")
+            .arg(OFFER_ENTRIES_KEY)
+            .arg(call_id.to_string())
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+
+        match payload {
+            Some(payload) if removed > 0 => parse_offer(&payload).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Offers nobody responded to before `now`, soonest expired first.
+    pub async fn expired(&self, now: DateTime<Utc>) -> Result<Vec<CallOffer>> {
+        let mut conn = self.redis_conn.write().await;
+        let call_ids: Vec<String> = redis::cmd("ZRANGEBYSCORE")
+            .arg(OFFERS_KEY)
+            .arg("-inf")
+            .arg(now.timestamp_millis())
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+        if call_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let payloads: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(OFFER_ENTRIES_KEY)
+            .arg(&call_ids)
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+
+        // An offer claimed between the two reads has no entry any more; skip it
+        payloads.into_iter().flatten().map(|payload| parse_offer(&payload)).collect()
+    }
+
+    /// Remember that an agent let a call go, so it is not offered to them again.
+    pub async fn decline(&self, call_id: Uuid, agent_id: Uuid) -> Result<()> {
+        let mut conn = self.redis_conn.write().await;
+        redis::pipe()
+            .atomic()
+            .cmd("SADD")
+            .arg(declined_key(call_id))
+            .arg(agent_id.to_string())
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(declined_key(call_id))
+            .arg(DECLINED_TTL_SECS)
+            .ignore()
+            .query_async::<_, ()>(&mut *conn)
+            .await
+            .map_err(redis_error)
+    }
+
+    /// Agents who rejected the call or let it ring out.
+    pub async fn declined(&self, call_id: Uuid) -> Result<Vec<Uuid>> {
+        let mut conn = self.redis_conn.write().await;
+        let agent_ids: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(declined_key(call_id))
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+
+        Ok(agent_ids.iter().filter_map(|id| Uuid::parse_str(id).ok()).collect())
+    }
+
+    pub async fn forget_declined(&self, call_id: Uuid) -> Result<()> {
+        let mut conn = self.redis_conn.write().await;
+        redis::cmd("DEL")
+            .arg(declined_key(call_id))
+            .query_async::<_, ()>(&mut *conn)
+            .await
+            .map_err(redis_error)
+    }
+
+    /// Count one more offer the agent let ring out. Returns how many they have
+    /// missed in a row.
+    pub async fn record_miss(&self, agent_id: Uuid) -> Result<u32> {
+        let mut conn = self.redis_conn.write().await;
+        redis::cmd("INCR")
+            .arg(missed_key(agent_id))
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)
+    }
+
+    pub async fn reset_misses(&self, agent_id: Uuid) -> Result<()> {
+        let mut conn = self.redis_conn.write().await;
+        redis::cmd("DEL")
+            .arg(missed_key(agent_id))
+            .query_async::<_, ()>(&mut *conn)
+            .await
+            .map_err(redis_error)
+    }
+}
+
+const OFFERS_KEY: &str = "routing:offers";
+const OFFER_ENTRIES_KEY: &str = "routing:offers:entries";
+
+fn parse_offer(payload: &str) -> Result<CallOffer> {
+    serde_json::from_str(payload).map_err(|e| CallDockerError::Internal(format!("Corrupt call offer: {}", e)))
+}
+
+fn redis_error(e: redis::RedisError) -> CallDockerError {
+    CallDockerError::External(format!("Redis offer operation failed: {}", e))
+}
+
+fn declined_key(call_id: Uuid) -> String {
+    format!("routing:calls:{}:declined", call_id)
+}
+
+fn missed_key(agent_id: Uuid) -> String {
+    format!("routing:agents:{}:missed", agent_id)
+}
@@ -0,0 +1,404 @@
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use redis::aio::Connection;
+use shared::call::CallQueue;
+use shared::routing::queue_score;
+use shared::{CallDockerError, Result};
+use crate::repositories::QueueItemRepository;
+
+/// Calls waiting for an agent, per company. The live queue is kept in Redis so
+/// every call-service instance works on the same one: a sorted set of call ids
+/// scored by `queue_score`, next to a hash holding each call's entry.
+/// `queue_items` mirrors it so the queue can be rebuilt should Redis lose it.
+#[derive(Clone)]
+pub struct QueueStore {
+    redis_conn: Arc<RwLock<Connection>>,
+    queue_items: QueueItemRepository,
+}
+
+impl QueueStore {
+    pub fn new(redis_conn: Arc<RwLock<Connection>>, queue_items: QueueItemRepository) -> Self {
+        Self { redis_conn, queue_items }
+    }
+
+    /// Queue a call, or replace its entry when it is already waiting. Returns its
+    /// 1-based position.
+    pub async fn push(&self, entry: &CallQueue) -> Result<u32> {
+        let call_id = entry.call_id.to_string();
+        let payload = serde_json::to_string(entry)?;
+
+        let mut conn = self.redis_conn.write().await;
+        let (rank,): (Option<u32>,) = redis::pipe()
+            .atomic()
+            .cmd("HSET")
+            .arg(entries_key(entry.company_id))
+            .arg(&call_id)
+            .arg(payload)
+            .ignore()
+            .cmd("ZADD")
+            .arg(queue_key(entry.company_id))
+            .arg(queue_score(entry.priority, entry.created_at, entry.aging_seconds_per_point))
+            .arg(&call_id)
+            .ignore()
+            .cmd("ZRANK")
+            .arg(queue_key(entry.company_id))
+            .arg(&call_id)
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+        drop(conn);
+
+        let position = rank.map_or(1, |rank| rank + 1);
+        if let Err(e) = self.queue_items.upsert(entry, position).await {
+            tracing::warn!("Failed to persist queue entry for call {}: {}", entry.call_id, e);
+        }
+        Ok(position)
+    }
+
+    pub async fn get(&self, company_id: Uuid, call_id: Uuid) -> Result<Option<CallQueue>> {
+        let mut conn = self.redis_conn.write().await;
+        let payload: Option<String> = redis::cmd("HGET")
+            .arg(entries_key(company_id))
+            .arg(call_id.to_string())
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+
+        payload.map(|payload| parse_entry(&payload)).transpose()
+    }
+
+    /// Take a call out of the queue. Returns false when it was not queued, which
+    /// is also what another instance sees after losing a race for the call.
+    pub async fn remove(&self, company_id: Uuid, call_id: Uuid) -> Result<bool> {
+        let mut conn = self.redis_conn.write().await;
+        let (removed,): (u32,) = redis::pipe()
+            .atomic()
+            .cmd("ZREM")
+            .arg(queue_key(company_id))
+            .arg(call_id.to_string())
+            .cmd("HDEL")
+            .arg(entries_key(company_id))
+            .arg(call_id.to_string())
+            .ignore()
+            .cmd("ZREM")
+            .arg(PREFERRED_KEY)
+            .arg(preferred_member(company_id, call_id))
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+        drop(conn);
+
+        if removed > 0 {
+            if let Err(e) = self.queue_items.delete_by_call(call_id).await {
+                tracing::warn!("Failed to delete persisted queue entry for call {}: {}", call_id, e);
+            }
+        }
+        Ok(removed > 0)
+    }
+
+    /// Take the first waiting call, in queue order, that `accept` agrees to. The
+    /// removal is the claim, so when instances race for a call exactly one gets
+    /// it and the others carry on down the queue.
+    pub async fn claim_next(
+        &self,
+        company_id: Uuid,
+        accept: impl Fn(&CallQueue) -> bool,
+    ) -> Result<Option<CallQueue>> {
+        for entry in self.entries(company_id).await? {
+            if accept(&entry) && self.remove(company_id, entry.call_id).await? {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Change a waiting call's priority, moving it within the queue. Returns
+    /// false when the call is not queued or already has that priority.
+    pub async fn set_priority(&self, company_id: Uuid, call_id: Uuid, priority: u32) -> Result<bool> {
+        let Some(mut entry) = self.get(company_id, call_id).await? else {
+            return Ok(false);
+        };
+        if entry.priority == priority {
+            return Ok(false);
+        }
+        entry.priority = priority;
+        if !self.update_entry(&entry, EntryUpdate::Priority).await? {
+            return Ok(false);
+        }
+
+        if let Err(e) = self.queue_items.update_priority(call_id, priority).await {
+            tracing::warn!("Failed to persist priority of queued call {}: {}", call_id, e);
+        }
+        Ok(true)
+    }
+
+    /// Write a waiting call's changed entry, along with what else `update`
+    /// changes. A call claimed or removed since it was read must not come back,
+    /// so nothing is written once it left the queue; returns whether the entry
+    /// was written.
+    async fn update_entry(&self, entry: &CallQueue, update: EntryUpdate) -> Result<bool> {
+        let mut score = String::new();
+        let mut preferred = (String::new(), String::new());
+        match update {
+            EntryUpdate::Fields => {}
+            EntryUpdate::Priority => {
+                score = queue_score(entry.priority, entry.created_at, entry.aging_seconds_per_point).to_string();
+            }
+            EntryUpdate::PreferredAgent => {
+                let until = entry.preferred_until.map(|until| until.timestamp_millis().to_string());
+                preferred = (preferred_member(entry.company_id, entry.call_id), until.unwrap_or_default());
+            }
+        }
+
+        let mut conn = self.redis_conn.write().await;
+        let written: u32 = redis::Script::new(UPDATE_ENTRY_SCRIPT)
+            .key(queue_key(entry.company_id))
+            .key(entries_key(entry.company_id))
+            .key(PREFERRED_KEY)
+            .arg(entry.call_id.to_string())
+            .arg(serde_json::to_string(entry)?)
+            .arg(score)
+            .arg(preferred.0)
+            .arg(preferred.1)
+            .invoke_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+
+        Ok(written > 0)
+    }
+
+    /// Record the routing queue a waiting call is held in.
+    pub async fn assign_queue(&self, company_id: Uuid, call_id: Uuid, queue_id: Option<Uuid>) -> Result<()> {
+        let Some(mut entry) = self.get(company_id, call_id).await? else {
+            return Ok(());
+        };
+        entry.queue_id = queue_id;
+        if !self.update_entry(&entry, EntryUpdate::Fields).await? {
+            return Ok(());
+        }
+
+        if let Err(e) = self.queue_items.update_queue(call_id, queue_id).await {
+            tracing::warn!("Failed to persist routing queue of queued call {}: {}", call_id, e);
+        }
+        Ok(())
+    }
+
+    /// Hold a waiting call for its preferred agent until the given time, or stop
+    /// holding it with `None`.
+    pub async fn set_preferred_agent(
+        &self,
+        company_id: Uuid,
+        call_id: Uuid,
+        preferred: Option<(Uuid, DateTime<Utc>)>,
+    ) -> Result<()> {
+        let Some(mut entry) = self.get(company_id, call_id).await? else {
+            return Ok(());
+        };
+        entry.preferred_agent_id = preferred.map(|(agent_id, _)| agent_id);
+        entry.preferred_until = preferred.map(|(_, until)| until);
+        // A call claimed meanwhile must not be held, and offered, again
+        if !self.update_entry(&entry, EntryUpdate::PreferredAgent).await? {
+            return Ok(());
+        }
+
+        let persisted = self
+            .queue_items
+            .update_preferred_agent(call_id, entry.preferred_agent_id, entry.preferred_until)
+            .await;
+        if let Err(e) = persisted {
+            tracing::warn!("Failed to persist preferred agent of queued call {}: {}", call_id, e);
+        }
+        Ok(())
+    }
+
+    /// Waiting calls held for a preferred agent, across companies, as
+    /// `(company_id, call_id)`.
+    pub async fn held(&self) -> Result<Vec<(Uuid, Uuid)>> {
+        let mut conn = self.redis_conn.write().await;
+        let members: Vec<String> = redis::cmd("ZRANGE")
+            .arg(PREFERRED_KEY)
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+
+        Ok(members.iter().filter_map(|member| parse_preferred_member(member)).collect())
+    }
+
+    /// Companies with calls waiting. Redis drops a queue once it is empty, so
+    /// every queue key left belongs to one.
+    pub async fn companies(&self) -> Result<Vec<Uuid>> {
+        let mut conn = self.redis_conn.write().await;
+        let mut companies = Vec::new();
+        let mut cursor = 0u64;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(queue_key("*"))
+                .arg("COUNT")
+                .arg(100)
+                .query_async(&mut *conn)
+                .await
+                .map_err(redis_error)?;
+            companies.extend(keys.iter().filter_map(|key| parse_queue_key(key)));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+
+        // SCAN may return a key more than once
+        companies.sort();
+        companies.dedup();
+        Ok(companies)
+    }
+
+    /// Number of calls waiting for the company.
+    pub async fn len(&self, company_id: Uuid) -> Result<u32> {
+        let mut conn = self.redis_conn.write().await;
+        redis::cmd("ZCARD")
+            .arg(queue_key(company_id))
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)
+    }
+
+    /// The company's waiting calls, next to be served first.
+    pub async fn entries(&self, company_id: Uuid) -> Result<Vec<CallQueue>> {
+        let mut conn = self.redis_conn.write().await;
+        let call_ids: Vec<String> = redis::cmd("ZRANGE")
+            .arg(queue_key(company_id))
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+        if call_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let payloads: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(entries_key(company_id))
+            .arg(&call_ids)
+            .query_async(&mut *conn)
+            .await
+            .map_err(redis_error)?;
+
+        // A call removed between the two reads has no entry any more; skip it
+        payloads.into_iter().flatten().map(|payload| parse_entry(&payload)).collect()
+    }
+
+    /// Put calls recorded in `queue_items` that are still waiting back into
+    /// Redis. Calls Redis already has are left alone, so every instance can run
+    /// this on startup.
+    pub async fn restore(&self) -> Result<usize> {
+        let entries = self.queue_items.list_waiting().await?;
+
+        let mut restored = 0;
+        let mut conn = self.redis_conn.write().await;
+        for entry in &entries {
+            let call_id = entry.call_id.to_string();
+            let (added,): (u32,) = redis::pipe()
+                .atomic()
+                .cmd("HSETNX")
+                .arg(entries_key(entry.company_id))
+                .arg(&call_id)
+                .arg(serde_json::to_string(entry)?)
+                .ignore()
+                .cmd("ZADD")
+                .arg(queue_key(entry.company_id))
+                .arg("NX")
+                .arg(queue_score(entry.priority, entry.created_at, entry.aging_seconds_per_point))
+                .arg(&call_id)
+                .query_async(&mut *conn)
+                .await
+                .map_err(redis_error)?;
+            restored += added as usize;
+
+            if let Some(until) = entry.preferred_until {
+                redis::cmd("ZADD")
+                    .arg(PREFERRED_KEY)
+                    .arg("NX")
+                    .arg(until.timestamp_millis())
+                    .arg(preferred_member(entry.company_id, entry.call_id))
+                    .query_async::<_, ()>(&mut *conn)
+                    .await
+                    .map_err(redis_error)?;
+            }
+        }
+
+        Ok(restored)
+    }
+}
+
+/// Calls held for a preferred agent, scored by when they stop waiting for them
+const PREFERRED_KEY: &str = "routing:preferred";
+
+/// Replace a call's entry only while the call is queued, giving it a new
+/// score unless that is empty, and holding it for its preferred agent until
+/// the given time, or no longer when that is empty, unless the member is.
+/// KEYS: queue, entries, preferred. ARGV: call id, entry, score, preferred
+/// member, until.
+const UPDATE_ENTRY_SCRIPT: &str = r"
+if not redis.call('ZSCORE', KEYS[1], ARGV[1]) then
+    return 0
+end
+redis.call('HSET', KEYS[2], ARGV[1], ARGV[2])
+if ARGV[3] ~= '' then
+    redis.call('ZADD', KEYS[1], 'XX', ARGV[3], ARGV[1])
+end
+if ARGV[4] ~= '' then
+    if ARGV[5] ~= '' then
+        redis.call('ZADD', KEYS[3], ARGV[5], ARGV[4])
+    else
+        redis.call('ZREM', KEYS[3], ARGV[4])
+    end
+end
+return 1
+";
+
+/// What changes with a waiting call's entry besides its fields
+enum EntryUpdate {
+    /// Nothing else
+    Fields,
+    /// Its place in the queue
+    Priority,
+    /// Whether it is held for its preferred agent
+    PreferredAgent,
+}
+
+fn parse_entry(payload: &str) -> Result<CallQueue> {
+    serde_json::from_str(payload)
+        .map_err(|e| CallDockerError::Internal(format!("Corrupt call queue entry: {}", e)))
+}
+
+fn redis_error(e: redis::RedisError) -> CallDockerError {
+    CallDockerError::External(format!("Redis queue operation failed: {}", e))
+}
+
+fn queue_key(company_id: impl std::fmt::Display) -> String {
+    format!("routing:companies:{}:queue", company_id)
+}
+
+fn parse_queue_key(key: &str) -> Option<Uuid> {
+    let company_id = key.strip_prefix("routing:companies:")?.strip_suffix(":queue")?;
+    Uuid::parse_str(company_id).ok()
+}
+
+fn entries_key(company_id: Uuid) -> String {
+    format!("routing:companies:{}:queue:entries", company_id)
+}
+
+fn preferred_member(company_id: Uuid, call_id: Uuid) -> String {
+    format!("{}:{}", company_id, call_id)
+}
+
+fn parse_preferred_member(member: &str) -> Option<(Uuid, Uuid)> {
+    let (company_id, call_id) = member.split_once(':')?;
+    Some((Uuid::parse_str(company_id).ok()?, Uuid::parse_str(call_id).ok()?))
+}
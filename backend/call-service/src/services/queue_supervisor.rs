@@ -0,0 +1,49 @@
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use super::call_service::CallService;
+
+/// Start the background task enforcing routing queues' `max_wait_time` and the
+/// ring timeout of offers: every `period` calls that waited too long are sent on
+/// to their queue's overflow action, calls agents did not accept in time go to
+/// the next agent, calls held for a preferred agent are given to them once
+/// free, or to anyone once their wait window is over, calls still waiting are
+/// offered to agents with free call slots, and IVR prompts the caller did not
+/// answer in time are moved on. Each instance runs one; taking a call out of the
+/// queue, its offer, or saving its IVR session decides which instance handles
+/// it.
+pub fn spawn(call_service: CallService, period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticks.tick().await;
+            match call_service.enforce_queue_limits().await {
+                Ok(0) => {}
+                Ok(moved) => tracing::info!("Queue supervisor overflowed {} calls", moved),
+                Err(e) => tracing::error!("Queue supervisor failed: {}", e),
+            }
+            match call_service.expire_offers().await {
+                Ok(0) => {}
+                Ok(expired) => tracing::info!("Queue supervisor moved on {} unanswered offers", expired),
+                Err(e) => tracing::error!("Queue supervisor failed to expire offers: {}", e),
+            }
+            match call_service.route_held_calls().await {
+                Ok(0) => {}
+                Ok(routed) => tracing::info!("Queue supervisor routed {} calls held for preferred agents", routed),
+                Err(e) => tracing::error!("Queue supervisor failed to route held calls: {}", e),
+            }
+            match call_service.dispatch_waiting_calls().await {
+                Ok(0) => {}
+                Ok(offered) => tracing::info!("Queue supervisor offered {} waiting calls to free agents", offered),
+                Err(e) => tracing::error!("Queue supervisor failed to offer waiting calls: {}", e),
+            }
+            match call_service.expire_ivr_prompts().await {
+                Ok(0) => {}
+                Ok(expired) => tracing::info!("Queue supervisor timed out {} IVR prompts", expired),
+                Err(e) => tracing::error!("Queue supervisor failed to time out IVR prompts: {}", e),
+            }
+        }
+    })
+}
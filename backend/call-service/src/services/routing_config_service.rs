@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use sqlx::PgPool;
+use shared::{
+    auth::Claims,
+    routing::{
+        AgentRoutingStats, AgentStatsQuery, CreateRoutingQueueRequest, CreateRoutingRuleRequest,
+        ReorderRoutingRulesRequest, RoutingQueue, RoutingRule, UpdateRoutingQueueRequest, UpdateRoutingRuleRequest,
+    },
+    routing_policy::RoutingPolicy,
+    CallDockerError, Result,
+};
+use crate::middleware::auth;
+use crate::repositories::{AgentRepository, CompanyRepository, RoutingQueueRepository, RoutingRuleRepository};
+
+/// Company admins' management of routing rules, routing queues and the routing
+/// policy. Every operation is limited to the caller's company.
+#[derive(Clone)]
+pub struct RoutingConfigService {
+    rule_repository: RoutingRuleRepository,
+    queue_repository: RoutingQueueRepository,
+    agent_repository: AgentRepository,
+    company_repository: CompanyRepository,
+}
+
+impl RoutingConfigService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            rule_repository: RoutingRuleRepository::new(db_pool.clone()),
+            queue_repository: RoutingQueueRepository::new(db_pool.clone()),
+            agent_repository: AgentRepository::new(db_pool.clone()),
+            company_repository: CompanyRepository::new(db_pool),
+        }
+    }
+
+    /// List a company's rules, highest priority first
+    pub async fn list_rules(&self, claims: &Claims, company_id: Option<Uuid>) -> Result<Vec<RoutingRule>> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        self.rule_repository.list_by_company(company_id).await
+    }
+
+    /// Get a rule of the caller's company
+    pub async fn get_rule(&self, claims: &Claims, rule_id: Uuid) -> Result<RoutingRule> {
+        let rule = self
+            .rule_repository
+            .find_by_id(rule_id)
+            .await?
+            .ok_or_else(|| CallDockerError::RoutingRuleNotFound(rule_id.to_string()))?;
+
+        auth::authorize_company(claims, rule.company_id)?;
+        Ok(rule)
+    }
+
+    /// Create a rule, active straight away
+    pub async fn create_rule(
+        &self,
+        claims: &Claims,
+        company_id: Option<Uuid>,
+        request: CreateRoutingRuleRequest,
+    ) -> Result<RoutingRule> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        let now = chrono::Utc::now();
+
+        let rule = RoutingRule {
+            id: Uuid::new_v4(),
+            company_id,
+            name: request.name,
+            description: request.description,
+            priority: request.priority,
+            is_active: true,
+            conditions: request.conditions,
+            actions: request.actions,
+            created_at: now,
+            updated_at: now,
+        };
+
+        tracing::info!("Creating routing rule {} for company {}", rule.id, company_id);
+        self.rule_repository.create(&rule).await
+    }
+
+    /// Update the given fields of a rule
+    pub async fn update_rule(
+        &self,
+        claims: &Claims,
+        rule_id: Uuid,
+        request: UpdateRoutingRuleRequest,
+    ) -> Result<RoutingRule> {
+        let mut rule = self.get_rule(claims, rule_id).await?;
+
+        if let Some(name) = request.name {
+            rule.name = name;
+        }
+        if let Some(description) = request.description {
+            rule.description = Some(description);
+        }
+        if let Some(priority) = request.priority {
+            rule.priority = priority;
+        }
+        if let Some(is_active) = request.is_active {
+            rule.is_active = is_active;
+        }
+        if let Some(conditions) = request.conditions {
+            rule.conditions = conditions;
+        }
+        if let Some(actions) = request.actions {
+            rule.actions = actions;
+        }
+
+        self.rule_repository.update(&rule).await
+    }
+
+    /// Delete a rule
+    pub async fn delete_rule(&self, claims: &Claims, rule_id: Uuid) -> Result<()> {
+        let rule = self.get_rule(claims, rule_id).await?;
+
+        tracing::info!("Deleting routing rule {} of company {}", rule.id, rule.company_id);
+        self.rule_repository.delete(rule.id).await
+    }
+
+    /// Reassign priorities so rules are evaluated in the given order. The order
+    /// must list every rule of the company exactly once.
+    pub async fn reorder_rules(
+        &self,
+        claims: &Claims,
+        company_id: Option<Uuid>,
+        request: ReorderRoutingRulesRequest,
+    ) -> Result<Vec<RoutingRule>> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        let existing: HashSet<Uuid> = self
+            .rule_repository
+            .list_by_company(company_id)
+            .await?
+            .into_iter()
+            .map(|rule| rule.id)
+            .collect();
+        let requested: HashSet<Uuid> = request.rule_ids.iter().copied().collect();
+
+        if requested != existing || requested.len() != request.rule_ids.len() {
+            return Err(CallDockerError::Validation(
+                "rule_ids must list every routing rule of the company exactly once".to_string(),
+            ));
+        }
+
+        self.rule_repository.reorder(company_id, &request.rule_ids).await?;
+        self.rule_repository.list_by_company(company_id).await
+    }
+
+    /// List a company's queues
+    pub async fn list_queues(&self, claims: &Claims, company_id: Option<Uuid>) -> Result<Vec<RoutingQueue>> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        self.queue_repository.list_by_company(company_id).await
+    }
+
+    /// Get a queue of the caller's company
+    pub async fn get_queue(&self, claims: &Claims, queue_id: Uuid) -> Result<RoutingQueue> {
+        let queue = self
+            .queue_repository
+            .find_by_id(queue_id)
+            .await?
+            .ok_or_else(|| CallDockerError::QueueNotFound(queue_id.to_string()))?;
+
+        auth::authorize_company(claims, queue.company_id)?;
+        Ok(queue)
+    }
+
+    /// Create a queue, active straight away
+    pub async fn create_queue(
+        &self,
+        claims: &Claims,
+        company_id: Option<Uuid>,
+        request: CreateRoutingQueueRequest,
+    ) -> Result<RoutingQueue> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        request
+            .overflow_action
+            .check_target(request.overflow_target.as_deref())
+            .map_err(CallDockerError::Validation)?;
+        self.ensure_company_agents(company_id, &request.agents).await?;
+        let now = chrono::Utc::now();
+
+        let queue = RoutingQueue {
+            id: Uuid::new_v4(),
+            company_id,
+            name: request.name,
+            description: request.description,
+            strategy: request.strategy,
+            max_wait_time: request.max_wait_time,
+            max_queue_size: request.max_queue_size,
+            overflow_action: request.overflow_action,
+            overflow_target: request.overflow_target,
+            agents: request.agents,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        };
+
+        tracing::info!("Creating routing queue {} for company {}", queue.id, company_id);
+        self.queue_repository.create(&queue).await
+    }
+
+    /// Update the given fields of a queue
+    pub async fn update_queue(
+        &self,
+        claims: &Claims,
+        queue_id: Uuid,
+        request: UpdateRoutingQueueRequest,
+    ) -> Result<RoutingQueue> {
+        let mut queue = self.get_queue(claims, queue_id).await?;
+
+        if let Some(name) = request.name {
+            queue.name = name;
+        }
+        if let Some(description) = request.description {
+            queue.description = Some(description);
+        }
+        if let Some(strategy) = request.strategy {
+            queue.strategy = strategy;
+        }
+        if let Some(max_wait_time) = request.max_wait_time {
+            queue.max_wait_time = Some(max_wait_time);
+        }
+        if let Some(max_queue_size) = request.max_queue_size {
+            queue.max_queue_size = Some(max_queue_size);
+        }
+        if let Some(overflow_action) = request.overflow_action {
+            queue.overflow_action = overflow_action;
+        }
+        if let Some(overflow_target) = request.overflow_target {
+            queue.overflow_target = Some(overflow_target);
+        }
+        queue
+            .overflow_action
+            .check_target(queue.overflow_target.as_deref())
+            .map_err(CallDockerError::Validation)?;
+        if let Some(agents) = request.agents {
+            self.ensure_company_agents(queue.company_id, &agents).await?;
+            queue.agents = agents;
+        }
+        if let Some(is_active) = request.is_active {
+            queue.is_active = is_active;
+        }
+
+        self.queue_repository.update(&queue).await
+    }
+
+    /// Delete a queue
+    pub async fn delete_queue(&self, claims: &Claims, queue_id: Uuid) -> Result<()> {
+        let queue = self.get_queue(claims, queue_id).await?;
+
+        tracing::info!("Deleting routing queue {} of company {}", queue.id, queue.company_id);
+        self.queue_repository.delete(queue.id).await
+    }
+
+    /// The company's routing policy; the default one until it is changed
+    pub async fn get_policy(&self, claims: &Claims, company_id: Option<Uuid>) -> Result<RoutingPolicy> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+        self.company_repository.find_routing_policy(company_id).await
+    }
+
+    /// Replace the company's routing policy. Calls already waiting keep the
+    /// priority they were queued with.
+    pub async fn update_policy(
+        &self,
+        claims: &Claims,
+        company_id: Option<Uuid>,
+        policy: RoutingPolicy,
+    ) -> Result<RoutingPolicy> {
+        let company_id = auth::resolve_company(claims, company_id)?;
+
+        tracing::info!("Updating routing policy of company {}", company_id);
+        self.company_repository.save_routing_policy(company_id, &policy).await
+    }
+
+    /// How the company's agents handled the calls offered to them, over the
+    /// last 24 hours unless the query names a period
+    pub async fn agent_stats(&self, claims: &Claims, query: AgentStatsQuery) -> Result<Vec<AgentRoutingStats>> {
+        let company_id = auth::resolve_company(claims, query.company_id)?;
+        let to = query.to.unwrap_or_else(Utc::now);
+        let from = query.from.unwrap_or(to - Duration::hours(24));
+        if from >= to {
+            return Err(CallDockerError::Validation("from must be before to".to_string()));
+        }
+
+        self.agent_repository.routing_stats(company_id, from, to).await
+    }
+
+    /// Fail unless every agent belongs to the company
+    async fn ensure_company_agents(&self, company_id: Uuid, agents: &[Uuid]) -> Result<()> {
+        if agents.is_empty() {
+            return Ok(());
+        }
+
+        let found = self.agent_repository.find_ids_in_company(company_id, agents).await?;
+        let unknown: Vec<String> = agents
+            .iter()
+            .filter(|id| !found.contains(id))
+            .map(|id| id.to_string())
+            .collect();
+
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(CallDockerError::Validation(format!(
+                "Agents do not belong to this company: {}",
+                unknown.join(", ")
+            )))
+        }
+    }
+}
@@ -3,8 +3,10 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use shared::{
-    types::{WebRTCSignal, SignalType},
+    signaling::Signal,
+    types::WebRTCSignal,
     call::{WebRTCConnection, ConnectionState, IceServer},
+    CallDockerError, Result,
 };
 
 #[derive(Clone)]
@@ -20,7 +22,11 @@ impl WebRTCService {
     }
 
     /// Handle WebRTC offer signal
-    pub async fn handle_offer(&self, signal: &WebRTCSignal) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    pub async fn handle_offer(&self, signal: &WebRTCSignal) -> Result<serde_json::Value> {
+        let sdp = match signal.signal()? {
+            Signal::Offer { sdp } => sdp,
+            other => return Err(CallDockerError::WebRTC(format!("Expected offer, got {}", other.signal_type()))),
+        };
         let mut connections = self.connections.write().await;
         
         // Create or update connection
@@ -49,7 +55,7 @@ impl WebRTCService {
 
         // Update connection state
         connection.connection_state = ConnectionState::Connecting;
-        connection.remote_sdp = Some(signal.data["sdp"].as_str().unwrap_or("").to_string());
+        connection.remote_sdp = Some(sdp);
         connection.updated_at = chrono::Utc::now();
 
         // Generate response with ICE servers and connection info
@@ -64,12 +70,16 @@ impl WebRTCService {
     }
 
     /// Handle WebRTC answer signal
-    pub async fn handle_answer(&self, signal: &WebRTCSignal) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    pub async fn handle_answer(&self, signal: &WebRTCSignal) -> Result<serde_json::Value> {
+        let sdp = match signal.signal()? {
+            Signal::Answer { sdp } => sdp,
+            other => return Err(CallDockerError::WebRTC(format!("Expected answer, got {}", other.signal_type()))),
+        };
         let mut connections = self.connections.write().await;
         
         if let Some(connection) = connections.get_mut(&signal.call_id) {
             // Update connection with answer SDP
-            connection.remote_sdp = Some(signal.data["sdp"].as_str().unwrap_or("").to_string());
+            connection.remote_sdp = Some(sdp);
             connection.connection_state = ConnectionState::Connected;
             connection.updated_at = chrono::Utc::now();
 
@@ -83,20 +93,22 @@ impl WebRTCService {
 
             Ok(response)
         } else {
-            Err("Connection not found".into())
+            Err(CallDockerError::WebRTC(format!("Connection not found for call {}", signal.call_id)))
         }
     }
 
     /// Handle ICE candidate signal
-    pub async fn handle_ice_candidate(&self, signal: &WebRTCSignal) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn handle_ice_candidate(&self, signal: &WebRTCSignal) -> Result<()> {
+        let candidate = match signal.signal()? {
+            Signal::IceCandidate { candidate, .. } => candidate,
+            other => return Err(CallDockerError::WebRTC(format!("Expected ice_candidate, got {}", other.signal_type()))),
+        };
         let mut connections = self.connections.write().await;
         
         if let Some(connection) = connections.get_mut(&signal.call_id) {
             // Add ICE candidate
-            if let Some(candidate) = signal.data["candidate"].as_str() {
-                connection.ice_candidates.push(candidate.to_string());
-                connection.updated_at = chrono::Utc::now();
-            }
+            connection.ice_candidates.push(candidate);
+            connection.updated_at = chrono::Utc::now();
         }
 
         Ok(())
@@ -109,7 +121,7 @@ impl WebRTCService {
     }
 
     /// Update connection state
-    pub async fn update_connection_state(&self, call_id: Uuid, state: ConnectionState) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn update_connection_state(&self, call_id: Uuid, state: ConnectionState) -> Result<()> {
         let mut connections = self.connections.write().await;
         
         if let Some(connection) = connections.get_mut(&call_id) {
@@ -121,7 +133,7 @@ impl WebRTCService {
     }
 
     /// Close connection
-    pub async fn close_connection(&self, call_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn close_connection(&self, call_id: Uuid) -> Result<()> {
         let mut connections = self.connections.write().await;
         
         if let Some(connection) = connections.get_mut(&call_id) {
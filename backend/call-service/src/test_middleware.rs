@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use crate::middleware::auth::{authorize_company, resolve_company, AuthMiddleware, RequireRole};
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::{header, StatusCode},
+        test::{init_service, TestRequest},
+        web, App, Error, HttpResponse,
+    };
+    use chrono::Utc;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use shared::auth::{Claims, UserRole};
+    use shared::CallDockerError;
+    use uuid::Uuid;
+
+    const SECRET: &str = "test-secret";
+
+    fn claims(role: UserRole, company_id: Option<Uuid>) -> Claims {
+        let now = Utc::now().timestamp();
+        Claims {
+            sub: Uuid::new_v4(),
+            email: "user@example.com".to_string(),
+            role,
+            company_id,
+            exp: now + 3600,
+            iat: now,
+        }
+    }
+
+    fn bearer(claims: &Claims) -> (header::HeaderName, String) {
+        let token = encode(&Header::default(), claims, &EncodingKey::from_secret(SECRET.as_ref())).unwrap();
+        (header::AUTHORIZATION, format!("Bearer {}", token))
+    }
+
+    /// Middleware rejections surface as service errors; map them to the status the
+    /// client would see.
+    fn status<B>(result: Result<ServiceResponse<B>, Error>) -> StatusCode {
+        match result {
+            Ok(response) => response.status(),
+            Err(e) => e.as_response_error().status_code(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_public_routes_need_no_token() {
+        let app = init_service(App::new().configure(|cfg| crate::configure_routes(cfg, SECRET))).await;
+
+        let req = TestRequest::get().uri("/health").to_request();
+        assert_eq!(status(app.call(req).await), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_protected_routes_reject_missing_or_invalid_tokens() {
+        let app = init_service(App::new().configure(|cfg| crate::configure_routes(cfg, SECRET))).await;
+        let call_id = Uuid::new_v4();
+
+        for uri in ["/calls".to_string(), format!("/calls/{}", call_id), format!("/calls/{}/events", call_id)] {
+            let req = TestRequest::get().uri(&uri).to_request();
+            assert_eq!(status(app.call(req).await), StatusCode::UNAUTHORIZED, "GET {}", uri);
+        }
+
+        let req = TestRequest::post()
+            .uri(&format!("/calls/{}/end", call_id))
+            .insert_header((header::AUTHORIZATION, "Bearer not-a-token"))
+            .to_request();
+        assert_eq!(status(app.call(req).await), StatusCode::UNAUTHORIZED);
+
+        // A token signed with another secret is rejected too
+        let forged = encode(
+            &Header::default(),
+            &claims(UserRole::SuperAdmin, None),
+            &EncodingKey::from_secret(b"other-secret"),
+        )
+        .unwrap();
+        let req = TestRequest::get()
+            .uri("/calls")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", forged)))
+            .to_request();
+        assert_eq!(status(app.call(req).await), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_routing_routes_require_company_admin() {
+        let app = init_service(App::new().configure(|cfg| crate::configure_routes(cfg, SECRET))).await;
+
+        let req = TestRequest::post().uri("/routing/rules/dry-run").to_request();
+        assert_eq!(status(app.call(req).await), StatusCode::UNAUTHORIZED);
+
+        let agent = claims(UserRole::Agent, Some(Uuid::new_v4()));
+        for uri in ["/routing/rules".to_string(), format!("/routing/queues/{}", Uuid::new_v4())] {
+            let req = TestRequest::get().uri(&uri).insert_header(bearer(&agent)).to_request();
+            assert_eq!(status(app.call(req).await), StatusCode::FORBIDDEN, "GET {}", uri);
+        }
+
+        let req = TestRequest::post()
+            .uri("/routing/rules/dry-run")
+            .insert_header(bearer(&agent))
+            .set_json(serde_json::json!({}))
+            .to_request();
+        assert_eq!(status(app.call(req).await), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_require_role_guards_scope() {
+        let app = init_service(
+            App::new().service(
+                web::scope("/admin")
+                    .wrap(RequireRole::new("company_admin"))
+                    .wrap(AuthMiddleware::new(SECRET.to_string()))
+                    .route("", web::get().to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+        let company_id = Some(Uuid::new_v4());
+
+        let req = TestRequest::get().uri("/admin").to_request();
+        assert_eq!(status(app.call(req).await), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::get()
+            .uri("/admin")
+            .insert_header(bearer(&claims(UserRole::Agent, company_id)))
+            .to_request();
+        assert_eq!(status(app.call(req).await), StatusCode::FORBIDDEN);
+
+        for role in [UserRole::CompanyAdmin, UserRole::SuperAdmin] {
+            let req = TestRequest::get()
+                .uri("/admin")
+                .insert_header(bearer(&claims(role, company_id)))
+                .to_request();
+            assert_eq!(status(app.call(req).await), StatusCode::OK);
+        }
+    }
+
+    #[test]
+    fn test_authorize_company_rejects_other_tenants() {
+        let company_id = Uuid::new_v4();
+
+        assert!(authorize_company(&claims(UserRole::Agent, Some(company_id)), company_id).is_ok());
+        assert!(authorize_company(&claims(UserRole::SuperAdmin, None), company_id).is_ok());
+
+        for claims in [
+            claims(UserRole::CompanyAdmin, Some(Uuid::new_v4())),
+            claims(UserRole::Agent, None),
+        ] {
+            assert!(matches!(
+                authorize_company(&claims, company_id),
+                Err(CallDockerError::Authorization(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_resolve_company_limits_admins_to_their_own() {
+        let own = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let admin = claims(UserRole::CompanyAdmin, Some(own));
+
+        assert_eq!(resolve_company(&admin, None).unwrap(), own);
+        assert_eq!(resolve_company(&admin, Some(own)).unwrap(), own);
+        assert!(matches!(resolve_company(&admin, Some(other)), Err(CallDockerError::Authorization(_))));
+
+        let super_admin = claims(UserRole::SuperAdmin, None);
+        assert_eq!(resolve_company(&super_admin, Some(other)).unwrap(), other);
+        assert!(matches!(resolve_company(&super_admin, None), Err(CallDockerError::Validation(_))));
+    }
+}
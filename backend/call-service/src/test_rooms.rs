@@ -0,0 +1,219 @@
+#[cfg(test)]
+mod tests {
+    use crate::rooms::{Join, Leave, Notify, Relay, RoomMessage, RoomRegistry};
+    use actix::{Actor, Addr, Context, Handler, Message};
+    use shared::auth::ParticipantRole;
+    use shared::signaling::{ParticipantInfo, RelayedSignal, ServerMessage, Signal};
+    use uuid::Uuid;
+
+    /// Stands in for a participant's socket, recording every frame it receives.
+    #[derive(Default)]
+    struct Peer {
+        received: Vec<ServerMessage>,
+    }
+
+    impl Actor for Peer {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<RoomMessage> for Peer {
+        type Result = ();
+
+        fn handle(&mut self, msg: RoomMessage, _: &mut Self::Context) {
+            self.received.push(serde_json::from_str(&msg.0).unwrap());
+        }
+    }
+
+    #[derive(Message)]
+    #[rtype(result = "Vec<ServerMessage>")]
+    struct TakeReceived;
+
+    impl Handler<TakeReceived> for Peer {
+        type Result = Vec<ServerMessage>;
+
+        fn handle(&mut self, _: TakeReceived, _: &mut Self::Context) -> Self::Result {
+            std::mem::take(&mut self.received)
+        }
+    }
+
+    fn participant(session_id: &str, role: ParticipantRole) -> ParticipantInfo {
+        ParticipantInfo {
+            session_id: session_id.to_string(),
+            user_id: Uuid::new_v4(),
+            role,
+        }
+    }
+
+    async fn join(rooms: &Addr<RoomRegistry>, call_id: Uuid, participant: &ParticipantInfo) -> Addr<Peer> {
+        let peer = Peer::default().start();
+        rooms
+            .send(Join {
+                call_id,
+                participant: participant.clone(),
+                addr: peer.clone().recipient(),
+            })
+            .await
+            .unwrap();
+        peer
+    }
+
+    async fn relay(rooms: &Addr<RoomRegistry>, call_id: Uuid, from: &ParticipantInfo, signal: Signal) -> ServerMessage {
+        let message = ServerMessage::Signal(RelayedSignal {
+            from: from.session_id.clone(),
+            signal,
+        });
+        rooms
+            .send(Relay {
+                call_id,
+                session_id: from.session_id.clone(),
+                message: message.clone(),
+            })
+            .await
+            .unwrap();
+        message
+    }
+
+    async fn leave(rooms: &Addr<RoomRegistry>, call_id: Uuid, participant: &ParticipantInfo) {
+        rooms
+            .send(Leave {
+                call_id,
+                session_id: participant.session_id.clone(),
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn received(peer: &Addr<Peer>) -> Vec<ServerMessage> {
+        peer.send(TakeReceived).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_join_notifies_existing_participants() {
+        let rooms = RoomRegistry::default().start();
+        let call_id = Uuid::new_v4();
+        let agent = participant("a", ParticipantRole::Agent);
+        let customer = participant("b", ParticipantRole::Customer);
+
+        let agent_peer = join(&rooms, call_id, &agent).await;
+        assert_eq!(
+            received(&agent_peer).await,
+            vec![ServerMessage::RoomJoined { room_id: call_id, users: vec![agent.clone()] }]
+        );
+
+        let customer_peer = join(&rooms, call_id, &customer).await;
+
+        let agent_frames = received(&agent_peer).await;
+        assert_eq!(agent_frames.len(), 1);
+        match &agent_frames[0] {
+            ServerMessage::UserJoined { participant, room_id, .. } => {
+                assert_eq!(participant, &customer);
+                assert_eq!(room_id, &call_id);
+            }
+            other => panic!("expected user_joined, got {:?}", other),
+        }
+
+        assert_eq!(
+            received(&customer_peer).await,
+            vec![ServerMessage::RoomJoined { room_id: call_id, users: vec![agent, customer] }]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_participants_exchange_signaling_messages() {
+        let rooms = RoomRegistry::default().start();
+        let call_id = Uuid::new_v4();
+        let agent = participant("agent", ParticipantRole::Agent);
+        let customer = participant("customer", ParticipantRole::Customer);
+        let agent_peer = join(&rooms, call_id, &agent).await;
+        let customer_peer = join(&rooms, call_id, &customer).await;
+        received(&agent_peer).await;
+        received(&customer_peer).await;
+
+        let offer = relay(&rooms, call_id, &customer, Signal::Offer { sdp: "v=0 offer".to_string() }).await;
+        let answer = relay(&rooms, call_id, &agent, Signal::Answer { sdp: "v=0 answer".to_string() }).await;
+        let candidate = relay(
+            &rooms,
+            call_id,
+            &customer,
+            Signal::IceCandidate {
+                candidate: "candidate:1 1 UDP".to_string(),
+                sdp_mid: Some("0".to_string()),
+                sdp_m_line_index: Some(0),
+            },
+        )
+        .await;
+
+        assert_eq!(received(&agent_peer).await, vec![offer, candidate]);
+        assert_eq!(received(&customer_peer).await, vec![answer]);
+    }
+
+    #[actix_rt::test]
+    async fn test_rooms_are_isolated_per_call() {
+        let rooms = RoomRegistry::default().start();
+        let first = participant("a", ParticipantRole::Agent);
+        let second = participant("b", ParticipantRole::Agent);
+        let first_call = Uuid::new_v4();
+        let first_peer = join(&rooms, first_call, &first).await;
+        let second_peer = join(&rooms, Uuid::new_v4(), &second).await;
+        received(&first_peer).await;
+        received(&second_peer).await;
+
+        relay(&rooms, first_call, &first, Signal::Offer { sdp: "x".to_string() }).await;
+
+        assert!(received(&first_peer).await.is_empty());
+        assert!(received(&second_peer).await.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_notify_reaches_everyone_in_the_room() {
+        let rooms = RoomRegistry::default().start();
+        let call_id = Uuid::new_v4();
+        let customer = participant("customer", ParticipantRole::Customer);
+        let supervisor = participant("supervisor", ParticipantRole::Supervisor);
+        let customer_peer = join(&rooms, call_id, &customer).await;
+        let supervisor_peer = join(&rooms, call_id, &supervisor).await;
+        let other_peer = join(&rooms, Uuid::new_v4(), &participant("other", ParticipantRole::Customer)).await;
+        received(&customer_peer).await;
+        received(&supervisor_peer).await;
+        received(&other_peer).await;
+
+        let message = ServerMessage::QueuePosition {
+            call_id,
+            position: 3,
+            queue_length: 4,
+            estimated_wait_time: Some(240),
+        };
+        rooms.send(Notify { call_id, message: message.clone() }).await.unwrap();
+
+        assert_eq!(received(&customer_peer).await, vec![message.clone()]);
+        assert_eq!(received(&supervisor_peer).await, vec![message]);
+        assert!(received(&other_peer).await.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_leave_notifies_and_cleans_up() {
+        let rooms = RoomRegistry::default().start();
+        let call_id = Uuid::new_v4();
+        let agent = participant("agent", ParticipantRole::Agent);
+        let customer = participant("customer", ParticipantRole::Customer);
+        let agent_peer = join(&rooms, call_id, &agent).await;
+        let _customer_peer = join(&rooms, call_id, &customer).await;
+        received(&agent_peer).await;
+
+        leave(&rooms, call_id, &customer).await;
+
+        let frames = received(&agent_peer).await;
+        assert_eq!(frames.len(), 1);
+        assert!(matches!(&frames[0], ServerMessage::UserLeft { participant, .. } if participant == &customer));
+
+        leave(&rooms, call_id, &agent).await;
+
+        // An emptied room is dropped, so a later joiner starts alone
+        let late = participant("late", ParticipantRole::Supervisor);
+        let late_peer = join(&rooms, call_id, &late).await;
+        assert_eq!(
+            received(&late_peer).await,
+            vec![ServerMessage::RoomJoined { room_id: call_id, users: vec![late] }]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use crate::middleware::auth::{authenticate_participant, issue_widget_token};
+    use chrono::Utc;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use shared::auth::{Claims, ParticipantRole, UserRole};
+    use shared::call::Call;
+    use shared::CallDockerError;
+    use uuid::Uuid;
+
+    const SECRET: &str = "test-secret";
+
+    fn test_call(company_id: Uuid) -> Call {
+        Call {
+            company_id,
+            customer_id: Some(Uuid::new_v4()),
+            ..shared::test_support::test_call()
+        }
+    }
+
+    fn access_token(role: UserRole, company_id: Option<Uuid>) -> (Uuid, String) {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            sub: Uuid::new_v4(),
+            email: "agent@example.com".to_string(),
+            role,
+            company_id,
+            exp: now + 3600,
+            iat: now,
+        };
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET.as_ref())).unwrap();
+        (claims.sub, token)
+    }
+
+    #[test]
+    fn test_agent_joins_call_of_own_company() {
+        let call = test_call(Uuid::new_v4());
+        let (user_id, token) = access_token(UserRole::Agent, Some(call.company_id));
+
+        let participant = authenticate_participant(&token, SECRET, &call).unwrap();
+
+        assert_eq!(participant, (user_id, ParticipantRole::Agent));
+    }
+
+    #[test]
+    fn test_admins_join_as_supervisors() {
+        let call = test_call(Uuid::new_v4());
+
+        let (_, admin) = access_token(UserRole::CompanyAdmin, Some(call.company_id));
+        let (_, super_admin) = access_token(UserRole::SuperAdmin, None);
+
+        assert_eq!(authenticate_participant(&admin, SECRET, &call).unwrap().1, ParticipantRole::Supervisor);
+        assert_eq!(authenticate_participant(&super_admin, SECRET, &call).unwrap().1, ParticipantRole::Supervisor);
+    }
+
+    #[test]
+    fn test_agent_of_other_company_is_rejected() {
+        let call = test_call(Uuid::new_v4());
+        let (_, token) = access_token(UserRole::Agent, Some(Uuid::new_v4()));
+
+        let result = authenticate_participant(&token, SECRET, &call);
+
+        assert!(matches!(result, Err(CallDockerError::Authorization(_))));
+    }
+
+    #[test]
+    fn test_widget_token_admits_customer_to_its_call_only() {
+        let call = test_call(Uuid::new_v4());
+        let token = issue_widget_token(&call, SECRET, 900).unwrap();
+
+        let participant = authenticate_participant(&token, SECRET, &call).unwrap();
+        assert_eq!(participant, (call.customer_id.unwrap(), ParticipantRole::Customer));
+
+        let other_call = test_call(call.company_id);
+        let result = authenticate_participant(&token, SECRET, &other_call);
+        assert!(matches!(result, Err(CallDockerError::Authorization(_))));
+    }
+
+    #[test]
+    fn test_invalid_and_expired_tokens_are_rejected() {
+        let call = test_call(Uuid::new_v4());
+
+        let forged = issue_widget_token(&call, "other-secret", 900).unwrap();
+        let expired = issue_widget_token(&call, SECRET, -3600).unwrap();
+
+        assert!(matches!(
+            authenticate_participant(&forged, SECRET, &call),
+            Err(CallDockerError::Authentication(_))
+        ));
+        assert!(matches!(
+            authenticate_participant(&expired, SECRET, &call),
+            Err(CallDockerError::Authentication(_))
+        ));
+        assert!(matches!(
+            authenticate_participant("not-a-jwt", SECRET, &call),
+            Err(CallDockerError::Authentication(_))
+        ));
+    }
+}
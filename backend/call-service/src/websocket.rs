@@ -1,50 +1,69 @@
-use actix::{Actor, StreamHandler, Handler, Message};
+use actix::{Actor, ActorContext, Addr, AsyncContext, StreamHandler, Handler};
 use actix_web_actors::ws;
-use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
 use uuid::Uuid;
+use shared::auth::ParticipantRole;
+use shared::signaling::{ClientMessage, ErrorCode, ParticipantInfo, RelayedSignal, ServerMessage};
+use crate::rooms::{Join, Leave, Relay, RoomMessage, RoomRegistry};
 
 pub struct CallWebSocket {
-    pub call_id: String,
-    pub connections: Arc<Mutex<HashMap<String, actix::Addr<CallWebSocket>>>>,
+    pub call_id: Uuid,
+    pub version: u16,
+    pub participant: ParticipantInfo,
+    rooms: Addr<RoomRegistry>,
 }
 
 impl CallWebSocket {
-    pub fn new(call_id: String) -> Self {
+    pub fn new(
+        call_id: Uuid,
+        version: u16,
+        user_id: Uuid,
+        role: ParticipantRole,
+        rooms: Addr<RoomRegistry>,
+    ) -> Self {
         Self {
             call_id,
-            connections: Arc::new(Mutex::new(HashMap::new())),
+            version,
+            participant: ParticipantInfo {
+                session_id: Uuid::new_v4().to_string(),
+                user_id,
+                role,
+            },
+            rooms,
         }
     }
+
+    fn send(&self, ctx: &mut ws::WebsocketContext<Self>, message: ServerMessage) {
+        ctx.text(message.to_text());
+    }
 }
 
 impl Actor for CallWebSocket {
     type Context = ws::WebsocketContext<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        let session_id = Uuid::new_v4().to_string();
-        
-        // Store this connection
-        if let Ok(mut connections) = self.connections.lock() {
-            connections.insert(session_id.clone(), ctx.address());
-        }
+        // Send welcome message with the negotiated protocol version
+        self.send(ctx, ServerMessage::Connected {
+            version: self.version,
+            session_id: self.participant.session_id.clone(),
+            call_id: self.call_id,
+            user_id: self.participant.user_id,
+            role: self.participant.role,
+        });
 
-        // Send welcome message
-        let welcome_msg = serde_json::json!({
-            "type": "connected",
-            "session_id": session_id,
-            "call_id": self.call_id
+        // Join the shared room for this call
+        self.rooms.do_send(Join {
+            call_id: self.call_id,
+            participant: self.participant.clone(),
+            addr: ctx.address().recipient(),
         });
-        
-        ctx.text(serde_json::to_string(&welcome_msg).unwrap());
     }
 
     fn stopped(&mut self, _: &mut Self::Context) {
-        // Clean up connection when stopped
-        if let Ok(mut connections) = self.connections.lock() {
-            connections.retain(|_, addr| addr.connected());
-        }
+        // Leave the room so the other participants are notified
+        self.rooms.do_send(Leave {
+            call_id: self.call_id,
+            session_id: self.participant.session_id.clone(),
+        });
     }
 }
 
@@ -56,32 +75,26 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for CallWebSocket {
     ) {
         match msg {
             Ok(ws::Message::Text(text)) => {
-                // Parse the incoming message
-                if let Ok(data) = serde_json::from_str::<Value>(&text) {
-                    // Handle different message types
-                    if let Some(msg_type) = data.get("type").and_then(|v| v.as_str()) {
-                        match msg_type {
-                            "offer" | "answer" | "ice-candidate" => {
-                                // Broadcast to other participants in the same call
-                                self.broadcast_to_call(&text, ctx);
-                            }
-                            "ping" => {
-                                // Respond with pong
-                                let pong = serde_json::json!({
-                                    "type": "pong",
-                                    "timestamp": chrono::Utc::now().timestamp()
-                                });
-                                ctx.text(serde_json::to_string(&pong).unwrap());
-                            }
-                            _ => {
-                                // Unknown message type
-                                let error = serde_json::json!({
-                                    "type": "error",
-                                    "message": "Unknown message type"
-                                });
-                                ctx.text(serde_json::to_string(&error).unwrap());
-                            }
-                        }
+                match ClientMessage::parse(&text) {
+                    Ok(ClientMessage::Signal(signal)) => {
+                        // Relay to the other participants in the same call
+                        self.rooms.do_send(Relay {
+                            call_id: self.call_id,
+                            session_id: self.participant.session_id.clone(),
+                            message: ServerMessage::Signal(RelayedSignal {
+                                from: self.participant.session_id.clone(),
+                                signal,
+                            }),
+                        });
+                    }
+                    Ok(ClientMessage::Ping) => {
+                        self.send(ctx, ServerMessage::Pong {
+                            timestamp: chrono::Utc::now().timestamp(),
+                        });
+                    }
+                    Err(e) => {
+                        // Reject messages outside the protocol schema
+                        self.send(ctx, e.into());
                     }
                 }
             }
@@ -97,37 +110,110 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for CallWebSocket {
             }
             Ok(ws::Message::Close(reason)) => {
                 ctx.close(reason);
+                ctx.stop();
             }
             _ => {}
         }
     }
 }
 
-impl CallWebSocket {
-    fn broadcast_to_call(&self, message: &str, ctx: &mut ws::WebsocketContext<Self>) {
-        if let Ok(connections) = self.connections.lock() {
-            for (session_id, addr) in connections.iter() {
-                // Don't send to self
-                if addr != &ctx.address() {
-                    let _ = addr.do_send(BroadcastMessage {
-                        message: message.to_string(),
-                    });
-                }
-            }
+impl Handler<RoomMessage> for CallWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, msg: RoomMessage, ctx: &mut Self::Context) {
+        ctx.text(msg.0);
+    }
+}
+
+/// An agent's own socket. It joins the room keyed by the agent's id, where the
+/// calls offered to them and changes made to their status arrive. Signals
+/// belong on the call's socket, so only pings are answered here.
+pub struct AgentWebSocket {
+    pub agent_id: Uuid,
+    pub version: u16,
+    pub participant: ParticipantInfo,
+    rooms: Addr<RoomRegistry>,
+}
+
+impl AgentWebSocket {
+    pub fn new(agent_id: Uuid, version: u16, user_id: Uuid, rooms: Addr<RoomRegistry>) -> Self {
+        Self {
+            agent_id,
+            version,
+            participant: ParticipantInfo {
+                session_id: Uuid::new_v4().to_string(),
+                user_id,
+                role: ParticipantRole::Agent,
+            },
+            rooms,
         }
     }
 }
 
-#[derive(Message)]
-#[rtype(result = "()")]
-struct BroadcastMessage {
-    message: String,
+impl Actor for AgentWebSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.text(
+            ServerMessage::AgentConnected {
+                version: self.version,
+                session_id: self.participant.session_id.clone(),
+                agent_id: self.agent_id,
+            }
+            .to_text(),
+        );
+
+        self.rooms.do_send(Join {
+            call_id: self.agent_id,
+            participant: self.participant.clone(),
+            addr: ctx.address().recipient(),
+        });
+    }
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.rooms.do_send(Leave {
+            call_id: self.agent_id,
+            session_id: self.participant.session_id.clone(),
+        });
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for AgentWebSocket {
+    fn handle(
+        &mut self,
+        msg: Result<ws::Message, ws::ProtocolError>,
+        ctx: &mut Self::Context,
+    ) {
+        match msg {
+            Ok(ws::Message::Text(text)) => {
+                let reply = match ClientMessage::parse(&text) {
+                    Ok(ClientMessage::Ping) => ServerMessage::Pong {
+                        timestamp: chrono::Utc::now().timestamp(),
+                    },
+                    Ok(ClientMessage::Signal(_)) => ServerMessage::Error {
+                        code: ErrorCode::InvalidMessage,
+                        message: "Signals must be sent on the call's socket".to_string(),
+                    },
+                    Err(e) => e.into(),
+                };
+                ctx.text(reply.to_text());
+            }
+            Ok(ws::Message::Ping(msg)) => {
+                ctx.pong(&msg);
+            }
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            _ => {}
+        }
+    }
 }
 
-impl Handler<BroadcastMessage> for CallWebSocket {
+impl Handler<RoomMessage> for AgentWebSocket {
     type Result = ();
 
-    fn handle(&mut self, msg: BroadcastMessage, ctx: &mut Self::Context) {
-        ctx.text(msg.message);
+    fn handle(&mut self, msg: RoomMessage, ctx: &mut Self::Context) {
+        ctx.text(msg.0);
     }
 }
@@ -0,0 +1,24 @@
+-- Migration: Persistent Call Queue
+-- Date: 2026-10-17
+-- Description: Mirror the Redis call queue to queue_items so waiting calls survive restarts
+
+-- ========================================
+-- QUEUE ITEMS
+-- ========================================
+
+-- Calls wait in their company's queue until routed; a routing queue is optional
+ALTER TABLE queue_items ALTER COLUMN queue_id DROP NOT NULL;
+ALTER TABLE queue_items ADD COLUMN company_id UUID REFERENCES companies(id) ON DELETE CASCADE;
+ALTER TABLE queue_items ADD COLUMN skills_required TEXT[] DEFAULT '{}';
+
+UPDATE queue_items
+SET company_id = routing_queues.company_id
+FROM routing_queues
+WHERE routing_queues.id = queue_items.queue_id;
+
+ALTER TABLE queue_items ALTER COLUMN company_id SET NOT NULL;
+
+-- A call waits in one queue at a time
+ALTER TABLE queue_items ADD CONSTRAINT queue_items_call_id_key UNIQUE (call_id);
+
+CREATE INDEX idx_queue_items_company_id ON queue_items(company_id);
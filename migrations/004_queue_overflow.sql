@@ -0,0 +1,22 @@
+-- Migration: Queue Overflow
+-- Date: 2026-10-17
+-- Description: Where routing queues send calls they cannot hold
+
+-- ========================================
+-- ROUTING QUEUES
+-- ========================================
+
+-- IVR flow id for the 'ivr' overflow action, phone number for 'external'
+ALTER TABLE routing_queues ADD COLUMN overflow_target VARCHAR(255);
+
+-- The supervisor only looks at queues that limit waiting
+CREATE INDEX idx_routing_queues_max_wait_time ON routing_queues(max_wait_time) WHERE max_wait_time IS NOT NULL;
+
+-- ========================================
+-- QUEUE ITEMS
+-- ========================================
+
+-- Deleting a routing queue leaves its calls waiting in the company queue
+ALTER TABLE queue_items DROP CONSTRAINT queue_items_queue_id_fkey;
+ALTER TABLE queue_items ADD CONSTRAINT queue_items_queue_id_fkey
+    FOREIGN KEY (queue_id) REFERENCES routing_queues(id) ON DELETE SET NULL;
@@ -0,0 +1,11 @@
+-- Migration: Routing Policy
+-- Date: 2026-10-17
+-- Description: Per-company priority aging for waiting calls
+
+-- ========================================
+-- QUEUE ITEMS
+-- ========================================
+
+-- Seconds of waiting one point of priority is worth, from the company's routing
+-- policy (companies.settings -> 'routing_policy') when the call was queued
+ALTER TABLE queue_items ADD COLUMN aging_seconds_per_point DOUBLE PRECISION NOT NULL DEFAULT 60;
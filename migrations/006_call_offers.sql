@@ -0,0 +1,11 @@
+-- Migration: Call Offers
+-- Date: 2026-10-17
+-- Description: Look up offer events by agent for agent routing statistics
+
+-- ========================================
+-- CALL EVENTS
+-- ========================================
+
+-- Offer events (call_offered, offer_accepted, offer_rejected, offer_timed_out)
+-- carry the agent the call was offered to in data->>'agent_id'
+CREATE INDEX idx_call_events_agent_id ON call_events ((data->>'agent_id'));
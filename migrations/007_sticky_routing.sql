@@ -0,0 +1,19 @@
+-- Migration: Sticky Routing
+-- Date: 2026-10-17
+-- Description: Hold waiting calls for the agent the customer last spoke with
+
+-- ========================================
+-- QUEUE ITEMS
+-- ========================================
+
+-- Agent a waiting call is held for, and until when, under sticky routing
+ALTER TABLE queue_items ADD COLUMN preferred_agent_id UUID REFERENCES agents(id) ON DELETE SET NULL;
+ALTER TABLE queue_items ADD COLUMN preferred_until TIMESTAMP WITH TIME ZONE;
+
+-- ========================================
+-- CALLS
+-- ========================================
+
+-- Finding a returning customer's last answered call
+CREATE INDEX idx_calls_company_customer_email ON calls(company_id, LOWER(customer_email));
+CREATE INDEX idx_calls_company_caller_number ON calls(company_id, caller_number);
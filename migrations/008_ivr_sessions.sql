@@ -0,0 +1,16 @@
+-- Migration: IVR Sessions
+-- Date: 2026-10-17
+-- Description: Track the caller's time to answer and where each IVR session sent the call
+
+-- ========================================
+-- IVR SESSIONS
+-- ========================================
+
+-- When the caller's time to answer the current node runs out
+ALTER TABLE ivr_sessions ADD COLUMN timeout_at TIMESTAMP WITH TIME ZONE;
+
+-- Where the flow sent the call once the session ended
+ALTER TABLE ivr_sessions ADD COLUMN outcome JSONB;
+
+-- Finding running sessions whose caller did not answer in time
+CREATE INDEX idx_ivr_sessions_timeout_at ON ivr_sessions(timeout_at) WHERE is_active = true;
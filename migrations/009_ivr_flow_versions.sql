@@ -0,0 +1,44 @@
+-- Migration: IVR Flow Versions
+-- Date: 2026-10-17
+-- Description: Publish IVR flows as immutable versions and pin sessions to the version they started on
+
+-- ========================================
+-- IVR FLOW VERSIONS
+-- ========================================
+
+CREATE TABLE ivr_flow_versions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    flow_id UUID NOT NULL REFERENCES ivr_flows(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    welcome_message TEXT,
+    welcome_audio_url VARCHAR(500),
+    nodes JSONB NOT NULL DEFAULT '[]',
+    published_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (flow_id, version)
+);
+
+-- ========================================
+-- IVR FLOWS
+-- ========================================
+
+-- Version new calls run; the flow's own columns hold the draft
+ALTER TABLE ivr_flows ADD COLUMN published_version INTEGER;
+
+-- Existing flows keep running as they are, as their first version
+INSERT INTO ivr_flow_versions (flow_id, version, name, description, welcome_message, welcome_audio_url, nodes, created_at)
+SELECT id, 1, name, description, welcome_message, welcome_audio_url, nodes, updated_at FROM ivr_flows;
+UPDATE ivr_flows SET published_version = 1;
+
+-- ========================================
+-- IVR SESSIONS
+-- ========================================
+
+-- Version of the flow a session runs
+ALTER TABLE ivr_sessions ADD COLUMN flow_version INTEGER;
+UPDATE ivr_sessions SET flow_version = 1;
+
+-- Finding the callers still in a flow
+CREATE INDEX idx_ivr_sessions_flow_id ON ivr_sessions(flow_id) WHERE is_active = true;
@@ -0,0 +1,25 @@
+-- Migration: IVR Templates
+-- Date: 2026-10-17
+-- Description: Store IVR templates companies create flows from, besides the built-in ones
+
+-- ========================================
+-- IVR TEMPLATES
+-- ========================================
+
+CREATE TABLE ivr_templates (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    -- NULL for templates offered to every company
+    company_id UUID REFERENCES companies(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    category VARCHAR(50) NOT NULL DEFAULT 'custom',
+    -- Welcome message, welcome audio and nodes of the flows made from it
+    template_data JSONB NOT NULL DEFAULT '{}',
+    is_public BOOLEAN DEFAULT false,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_ivr_templates_company_id ON ivr_templates(company_id);
+CREATE INDEX idx_ivr_templates_is_public ON ivr_templates(is_public) WHERE is_public = true;
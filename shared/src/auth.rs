@@ -20,6 +20,16 @@ impl std::fmt::Display for UserRole {
     }
 }
 
+impl UserRole {
+    pub fn is_super_admin(&self) -> bool {
+        matches!(self, UserRole::SuperAdmin)
+    }
+
+    pub fn is_company_admin(&self) -> bool {
+        matches!(self, UserRole::CompanyAdmin)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: Uuid,
@@ -69,6 +79,44 @@ pub struct Claims {
     pub iat: i64,
 }
 
+/// Claims of a short-lived token letting a website visitor join exactly one call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetClaims {
+    pub sub: Uuid,
+    pub call_id: Uuid,
+    pub company_id: Uuid,
+    pub exp: i64,
+    pub iat: i64,
+}
+
+/// The part a WebSocket session plays in a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticipantRole {
+    Agent,
+    Customer,
+    Supervisor,
+}
+
+impl From<&UserRole> for ParticipantRole {
+    fn from(role: &UserRole) -> Self {
+        match role {
+            UserRole::Agent => ParticipantRole::Agent,
+            UserRole::SuperAdmin | UserRole::CompanyAdmin => ParticipantRole::Supervisor,
+        }
+    }
+}
+
+impl std::fmt::Display for ParticipantRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParticipantRole::Agent => write!(f, "agent"),
+            ParticipantRole::Customer => write!(f, "customer"),
+            ParticipantRole::Supervisor => write!(f, "supervisor"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PasswordResetRequest {
     pub email: String,
@@ -1,9 +1,10 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use validator::Validate;
+use crate::error::{CallDockerError, Result};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CallStatus {
     Ringing,
     Connected,
@@ -26,12 +27,76 @@ impl std::fmt::Display for CallStatus {
     }
 }
 
+impl CallStatus {
+    /// Terminal statuses accept no further transitions.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, CallStatus::Ended | CallStatus::Missed | CallStatus::Busy | CallStatus::Failed)
+    }
+
+    /// Whether a call may move from `self` to `next`. A ringing call may be
+    /// re-offered (ringing again); every other edge moves strictly forward.
+    pub fn can_transition_to(&self, next: &CallStatus) -> bool {
+        matches!(
+            (self, next),
+            (CallStatus::Ringing, _) | (CallStatus::Connected, CallStatus::Ended | CallStatus::Failed)
+        )
+    }
+
+    /// The event emitted when a call enters this status.
+    pub fn event_type(&self) -> CallEventType {
+        match self {
+            CallStatus::Ringing => CallEventType::CallRinging,
+            CallStatus::Connected => CallEventType::CallAnswered,
+            CallStatus::Ended | CallStatus::Missed | CallStatus::Busy | CallStatus::Failed => {
+                CallEventType::CallEnded
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for CallStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "ringing" => Ok(CallStatus::Ringing),
+            "connected" => Ok(CallStatus::Connected),
+            "ended" => Ok(CallStatus::Ended),
+            "missed" => Ok(CallStatus::Missed),
+            "busy" => Ok(CallStatus::Busy),
+            "failed" => Ok(CallStatus::Failed),
+            _ => Err(format!("Unknown call status: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CallDirection {
     Inbound,
     Outbound,
 }
 
+impl std::fmt::Display for CallDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallDirection::Inbound => write!(f, "inbound"),
+            CallDirection::Outbound => write!(f, "outbound"),
+        }
+    }
+}
+
+impl std::str::FromStr for CallDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "inbound" => Ok(CallDirection::Inbound),
+            "outbound" => Ok(CallDirection::Outbound),
+            _ => Err(format!("Unknown call direction: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Call {
     pub id: Uuid,
@@ -55,6 +120,48 @@ pub struct Call {
     pub ended_at: Option<DateTime<Utc>>,
 }
 
+impl Call {
+    /// Fail with `InvalidTransition` unless the call may move to `next`.
+    pub fn ensure_can_transition(&self, next: &CallStatus) -> Result<()> {
+        if self.status.can_transition_to(next) {
+            Ok(())
+        } else {
+            Err(CallDockerError::InvalidTransition(format!(
+                "call {} cannot move from {} to {}",
+                self.id, self.status, next
+            )))
+        }
+    }
+
+    /// Move the call to `next`, stamping `answered_at`/`ended_at` and computing
+    /// `duration` (talk time in seconds, zero if never answered). Returns the
+    /// event to emit, or `InvalidTransition` if the edge is not allowed.
+    pub fn transition_to(&mut self, next: CallStatus, at: DateTime<Utc>) -> Result<CallEventType> {
+        self.ensure_can_transition(&next)?;
+
+        match next {
+            CallStatus::Connected => {
+                self.answered_at = Some(at);
+            }
+            CallStatus::Ended | CallStatus::Missed | CallStatus::Busy | CallStatus::Failed => {
+                self.ended_at = Some(at);
+                self.duration = Some(
+                    self.answered_at
+                        .map(|answered_at| (at - answered_at).num_seconds().max(0) as u64)
+                        .unwrap_or(0),
+                );
+            }
+            CallStatus::Ringing => {}
+        }
+
+        let event_type = next.event_type();
+        self.status = next;
+        self.updated_at = at;
+
+        Ok(event_type)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct CreateCallRequest {
     pub company_id: Uuid,
@@ -65,6 +172,136 @@ pub struct CreateCallRequest {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// A newly created call plus the widget token its customer uses to join the call socket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCallResponse {
+    #[serde(flatten)]
+    pub call: Call,
+    pub widget_token: String,
+}
+
+/// Wrap-up captured by the agent when hanging up.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct EndCallRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub disposition_code: Option<String>,
+    #[validate(length(max = 5000))]
+    pub notes: Option<String>,
+    #[serde(default)]
+    #[validate(custom = "validate_tags")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateCallRequest {
+    #[validate(custom = "validate_tags")]
+    pub tags: Option<Vec<String>>,
+    #[validate(length(max = 5000))]
+    pub notes: Option<String>,
+    #[validate(custom = "validate_metadata")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+fn validate_tags(tags: &[String]) -> std::result::Result<(), validator::ValidationError> {
+    if tags.len() > 20 {
+        return Err(validator::ValidationError::new("too_many_tags"));
+    }
+    if tags.iter().any(|tag| tag.trim().is_empty() || tag.len() > 50) {
+        return Err(validator::ValidationError::new("invalid_tag"));
+    }
+    Ok(())
+}
+
+fn validate_metadata(metadata: &serde_json::Value) -> std::result::Result<(), validator::ValidationError> {
+    if metadata.is_object() {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("metadata_must_be_object"))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CallListQuery {
+    pub company_id: Option<Uuid>,
+    pub agent_id: Option<Uuid>,
+    /// Lowercase as stored, e.g. `ringing`
+    #[serde(default, deserialize_with = "parse_lowercase")]
+    pub status: Option<CallStatus>,
+    /// Lowercase as stored, e.g. `inbound`
+    #[serde(default, deserialize_with = "parse_lowercase")]
+    pub direction: Option<CallDirection>,
+    pub tag: Option<String>,
+    pub caller_number: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub sort_by: CallSortField,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+    #[serde(default = "default_page")]
+    #[validate(range(min = 1))]
+    pub page: u32,
+    #[serde(default = "default_per_page")]
+    #[validate(range(min = 1, max = 100))]
+    pub per_page: u32,
+    /// Opaque keyset cursor returned as `next_cursor`; takes precedence over `page`.
+    pub cursor: Option<String>,
+}
+
+/// Parse a query value through `FromStr`, ignoring case so the variant names
+/// the API used to take keep working
+fn parse_lowercase<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr<Err = String>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| value.to_lowercase().parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+fn default_per_page() -> u32 {
+    20
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallSortField {
+    #[default]
+    CreatedAt,
+    Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Redis pub/sub channel carrying the positions of a company's waiting calls
+/// each time its queue moves.
+pub fn call_queue_channel(company_id: Uuid) -> String {
+    format!("calls:{}:queue", company_id)
+}
+
+/// Redis pub/sub channel carrying messages for the sockets of a company's agents,
+/// such as the calls offered to them.
+pub fn agent_updates_channel(company_id: Uuid) -> String {
+    format!("calls:{}:agents", company_id)
+}
+
+/// Redis pub/sub channel carrying messages for the sockets of a company's calls,
+/// such as what their IVR flow plays.
+pub fn caller_updates_channel(company_id: Uuid) -> String {
+    format!("calls:{}:callers", company_id)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallQueue {
     pub id: Uuid,
@@ -72,9 +309,26 @@ pub struct CallQueue {
     pub call_id: Uuid,
     pub priority: u32,
     pub skills_required: Vec<String>,
+    /// Routing queue the call waits in, if a routing rule sent it to one
+    #[serde(default)]
+    pub queue_id: Option<Uuid>,
+    /// Seconds of waiting one point of priority is worth, from the company's
+    /// routing policy
+    #[serde(default = "default_aging_seconds_per_point")]
+    pub aging_seconds_per_point: f64,
+    /// Agent the call is held for, the customer's last agent under sticky routing
+    #[serde(default)]
+    pub preferred_agent_id: Option<Uuid>,
+    /// When the call stops waiting for its preferred agent
+    #[serde(default)]
+    pub preferred_until: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
+fn default_aging_seconds_per_point() -> f64 {
+    crate::routing::PRIORITY_WEIGHT_SECS
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallRouting {
     pub call_id: Uuid,
@@ -162,6 +416,70 @@ pub enum CallEventType {
     AgentLeft,
     CustomerJoined,
     CustomerLeft,
+    CallOffered,
+    OfferAccepted,
+    OfferRejected,
+    OfferTimedOut,
+    IvrStarted,
+    IvrCompleted,
+}
+
+impl std::fmt::Display for CallEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallEventType::CallInitiated => write!(f, "call_initiated"),
+            CallEventType::CallRinging => write!(f, "call_ringing"),
+            CallEventType::CallAnswered => write!(f, "call_answered"),
+            CallEventType::CallEnded => write!(f, "call_ended"),
+            CallEventType::CallTransferred => write!(f, "call_transferred"),
+            CallEventType::CallEscalated => write!(f, "call_escalated"),
+            CallEventType::CallRecordingStarted => write!(f, "call_recording_started"),
+            CallEventType::CallRecordingStopped => write!(f, "call_recording_stopped"),
+            CallEventType::AgentJoined => write!(f, "agent_joined"),
+            CallEventType::AgentLeft => write!(f, "agent_left"),
+            CallEventType::CustomerJoined => write!(f, "customer_joined"),
+            CallEventType::CustomerLeft => write!(f, "customer_left"),
+            CallEventType::CallOffered => write!(f, "call_offered"),
+            CallEventType::OfferAccepted => write!(f, "offer_accepted"),
+            CallEventType::OfferRejected => write!(f, "offer_rejected"),
+            CallEventType::OfferTimedOut => write!(f, "offer_timed_out"),
+            CallEventType::IvrStarted => write!(f, "ivr_started"),
+            CallEventType::IvrCompleted => write!(f, "ivr_completed"),
+        }
+    }
+}
+
+impl std::str::FromStr for CallEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "call_initiated" => Ok(CallEventType::CallInitiated),
+            "call_ringing" => Ok(CallEventType::CallRinging),
+            "call_answered" => Ok(CallEventType::CallAnswered),
+            "call_ended" => Ok(CallEventType::CallEnded),
+            "call_transferred" => Ok(CallEventType::CallTransferred),
+            "call_escalated" => Ok(CallEventType::CallEscalated),
+            "call_recording_started" => Ok(CallEventType::CallRecordingStarted),
+            "call_recording_stopped" => Ok(CallEventType::CallRecordingStopped),
+            "agent_joined" => Ok(CallEventType::AgentJoined),
+            "agent_left" => Ok(CallEventType::AgentLeft),
+            "customer_joined" => Ok(CallEventType::CustomerJoined),
+            "customer_left" => Ok(CallEventType::CustomerLeft),
+            "call_offered" => Ok(CallEventType::CallOffered),
+            "offer_accepted" => Ok(CallEventType::OfferAccepted),
+            "offer_rejected" => Ok(CallEventType::OfferRejected),
+            "offer_timed_out" => Ok(CallEventType::OfferTimedOut),
+            "ivr_started" => Ok(CallEventType::IvrStarted),
+            "ivr_completed" => Ok(CallEventType::IvrCompleted),
+            _ => Err(format!("Unknown call event type: {}", s)),
+        }
+    }
+}
+
+/// Redis pub/sub channel carrying every call event for a company.
+pub fn call_events_channel(company_id: Uuid) -> String {
+    format!("calls:{}:events", company_id)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
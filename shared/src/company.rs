@@ -37,6 +37,15 @@ pub struct Company {
     pub updated_at: DateTime<Utc>,
 }
 
+/// How callers reach a company, as IVR templates tell them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompanyContact {
+    pub name: String,
+    pub phone: Option<String>,
+    pub website: Option<String>,
+    pub address: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompanySettings {
     pub max_agents: u32,
@@ -135,6 +144,9 @@ pub struct Agent {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct CreateAgentRequest {
+    /// Company the agent joins; only super admins may name one other than their own
+    #[serde(default)]
+    pub company_id: Option<Uuid>,
     #[validate(length(min = 2, max = 100))]
     pub name: String,
     #[validate(email)]
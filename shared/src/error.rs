@@ -1,4 +1,8 @@
+use crate::request_id::{current_request_id, REQUEST_ID_HEADER};
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use uuid::Uuid;
 
 #[derive(Error, Debug)]
 pub enum CallDockerError {
@@ -29,6 +33,30 @@ pub enum CallDockerError {
     #[error("Call not found: {0}")]
     CallNotFound(String),
 
+    #[error("Routing rule not found: {0}")]
+    RoutingRuleNotFound(String),
+
+    #[error("Routing queue not found: {0}")]
+    QueueNotFound(String),
+
+    #[error("Call offer not found: {0}")]
+    OfferNotFound(String),
+
+    #[error("IVR flow not found: {0}")]
+    IVRFlowNotFound(String),
+
+    #[error("IVR template not found: {0}")]
+    IVRTemplateNotFound(String),
+
+    #[error("IVR session not found: {0}")]
+    IVRSessionNotFound(String),
+
+    #[error("Invalid call state transition: {0}")]
+    InvalidTransition(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Invalid UUID: {0}")]
     InvalidUUID(String),
 
@@ -45,8 +73,114 @@ pub enum CallDockerError {
     External(String),
 }
 
+impl CallDockerError {
+    /// Stable, machine-readable code sent to clients alongside the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CallDockerError::Authentication(_) => "unauthenticated",
+            CallDockerError::Authorization(_) => "forbidden",
+            CallDockerError::Database(_) => "database_error",
+            CallDockerError::WebRTC(_) => "webrtc_error",
+            CallDockerError::CallRouting(_) => "call_routing_error",
+            CallDockerError::IVR(_) => "ivr_error",
+            CallDockerError::CompanyNotFound(_) => "company_not_found",
+            CallDockerError::AgentNotFound(_) => "agent_not_found",
+            CallDockerError::CallNotFound(_) => "call_not_found",
+            CallDockerError::RoutingRuleNotFound(_) => "routing_rule_not_found",
+            CallDockerError::QueueNotFound(_) => "queue_not_found",
+            CallDockerError::OfferNotFound(_) => "offer_not_found",
+            CallDockerError::IVRFlowNotFound(_) => "ivr_flow_not_found",
+            CallDockerError::IVRTemplateNotFound(_) => "ivr_template_not_found",
+            CallDockerError::IVRSessionNotFound(_) => "ivr_session_not_found",
+            CallDockerError::InvalidTransition(_) => "invalid_transition",
+            CallDockerError::Conflict(_) => "conflict",
+            CallDockerError::InvalidUUID(_) => "invalid_uuid",
+            CallDockerError::Validation(_) => "validation_error",
+            CallDockerError::Configuration(_) => "configuration_error",
+            CallDockerError::Internal(_) => "internal_error",
+            CallDockerError::External(_) => "external_service_error",
+        }
+    }
+
+    /// Message safe to show to clients. Server-side failures are replaced with a
+    /// generic message so database and driver details never leave the service.
+    pub fn public_message(&self) -> String {
+        if self.status_code().is_server_error() {
+            "Internal server error".to_string()
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+/// JSON body of every error response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub success: bool,
+    pub error: String,
+    pub code: String,
+    pub request_id: Uuid,
+}
+
+impl ResponseError for CallDockerError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            CallDockerError::Authentication(_) => StatusCode::UNAUTHORIZED,
+            CallDockerError::Authorization(_) => StatusCode::FORBIDDEN,
+            CallDockerError::CompanyNotFound(_)
+            | CallDockerError::AgentNotFound(_)
+            | CallDockerError::CallNotFound(_)
+            | CallDockerError::RoutingRuleNotFound(_)
+            | CallDockerError::QueueNotFound(_)
+            | CallDockerError::OfferNotFound(_)
+            | CallDockerError::IVRFlowNotFound(_)
+            | CallDockerError::IVRTemplateNotFound(_)
+            | CallDockerError::IVRSessionNotFound(_) => StatusCode::NOT_FOUND,
+            CallDockerError::InvalidTransition(_) | CallDockerError::Conflict(_) => StatusCode::CONFLICT,
+            CallDockerError::InvalidUUID(_) | CallDockerError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            CallDockerError::Database(_)
+            | CallDockerError::WebRTC(_)
+            | CallDockerError::CallRouting(_)
+            | CallDockerError::IVR(_)
+            | CallDockerError::Configuration(_)
+            | CallDockerError::Internal(_)
+            | CallDockerError::External(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        // Logged with the full error so the id in the response can be traced back
+        let request_id = current_request_id();
+        if status.is_server_error() {
+            tracing::error!(%request_id, code = self.code(), "{}", self);
+        } else {
+            tracing::debug!(%request_id, code = self.code(), "{}", self);
+        }
+
+        HttpResponse::build(status)
+            .insert_header((REQUEST_ID_HEADER, request_id.to_string()))
+            .json(ErrorResponse {
+                success: false,
+                error: self.public_message(),
+                code: self.code().to_string(),
+                request_id,
+            })
+    }
+}
+
+/// SQLSTATE of a unique constraint violation
+const UNIQUE_VIOLATION: &str = "23505";
+
 impl From<sqlx::Error> for CallDockerError {
     fn from(err: sqlx::Error) -> Self {
+        // Duplicates are the client's to resolve; the constraint stays internal
+        if let Some(db_err) = err.as_database_error() {
+            if db_err.code().as_deref() == Some(UNIQUE_VIOLATION) {
+                tracing::debug!("{}", db_err);
+                return CallDockerError::Conflict("Resource already exists".to_string());
+            }
+        }
         CallDockerError::Database(err.to_string())
     }
 }
@@ -57,6 +191,12 @@ impl From<serde_json::Error> for CallDockerError {
     }
 }
 
+impl From<validator::ValidationErrors> for CallDockerError {
+    fn from(err: validator::ValidationErrors) -> Self {
+        CallDockerError::Validation(err.to_string())
+    }
+}
+
 impl From<uuid::Error> for CallDockerError {
     fn from(err: uuid::Error) -> Self {
         CallDockerError::InvalidUUID(err.to_string())
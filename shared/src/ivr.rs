@@ -1,7 +1,9 @@
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use validator::Validate;
+use crate::ivr_validation::IVRFlowReport;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IVRFlow {
@@ -12,11 +14,63 @@ pub struct IVRFlow {
     pub is_active: bool,
     pub welcome_message: Option<String>,
     pub welcome_audio_url: Option<String>,
+    /// The draft; calls run the published version
     pub nodes: Vec<IVRNode>,
+    /// Version new calls run; `None` until the flow is first published
+    #[serde(default)]
+    pub published_version: Option<u32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// A flow as it was published. Versions never change, so a call keeps running
+/// the version it started on whatever happens to the draft.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IVRFlowVersion {
+    pub id: Uuid,
+    pub flow_id: Uuid,
+    /// Counts up from 1 per flow
+    pub version: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub welcome_message: Option<String>,
+    pub welcome_audio_url: Option<String>,
+    pub nodes: Vec<IVRNode>,
+    /// User who published it
+    pub published_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl IVRFlowVersion {
+    /// Snapshot of `flow`'s draft as `version`
+    pub fn snapshot(flow: &IVRFlow, version: u32, published_by: Option<Uuid>, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            flow_id: flow.id,
+            version,
+            name: flow.name.clone(),
+            description: flow.description.clone(),
+            welcome_message: flow.welcome_message.clone(),
+            welcome_audio_url: flow.welcome_audio_url.clone(),
+            nodes: flow.nodes.clone(),
+            published_by,
+            created_at: now,
+        }
+    }
+
+    /// `flow` with this version's content in place of its draft
+    pub fn apply_to(&self, flow: &IVRFlow) -> IVRFlow {
+        IVRFlow {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            welcome_message: self.welcome_message.clone(),
+            welcome_audio_url: self.welcome_audio_url.clone(),
+            nodes: self.nodes.clone(),
+            ..flow.clone()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IVRNode {
     pub id: Uuid,
@@ -30,6 +84,27 @@ pub struct IVRNode {
     pub max_attempts: Option<u32>,
     pub next_node_id: Option<Uuid>,
     pub position: IVRPosition,
+    /// Where a Transfer node sends the call
+    #[serde(default)]
+    pub transfer: Option<IVRTransfer>,
+    /// Session data key an Input node stores the caller's digits under, or a
+    /// Menu node the key they pressed; the node id when unset
+    #[serde(default)]
+    pub variable: Option<String>,
+    /// Expression a Condition node tests, see `ivr_condition::parse_condition`.
+    /// The session goes on to `next_node_id` when it holds.
+    #[serde(default)]
+    pub condition: Option<String>,
+    /// Where a Condition node goes when its condition does not hold
+    #[serde(default)]
+    pub else_node_id: Option<Uuid>,
+}
+
+impl IVRNode {
+    /// Session data key the caller's input at this node is stored under
+    pub fn variable_name(&self) -> String {
+        self.variable.clone().unwrap_or_else(|| self.id.to_string())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +118,17 @@ pub enum IVRNodeType {
     Condition,
 }
 
+/// Target of a Transfer node, handed to call routing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "target", rename_all = "snake_case")]
+pub enum IVRTransfer {
+    Agent(Uuid),
+    /// Least busy agent with the department's skill
+    Department(String),
+    Queue(Uuid),
+    Phone(String),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IVROption {
     pub key: String,
@@ -75,21 +161,84 @@ pub struct CreateIVRFlowRequest {
     pub description: Option<String>,
     pub welcome_message: Option<String>,
     pub welcome_audio_url: Option<String>,
+    /// The first node is where calls start
+    #[validate]
     pub nodes: Vec<CreateIVRNodeRequest>,
 }
 
+/// Changes to a flow's draft; only the fields given are changed. `nodes`
+/// replaces every node.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct UpdateIVRFlowRequest {
+    #[validate(length(min = 2, max = 100))]
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub welcome_message: Option<String>,
+    pub welcome_audio_url: Option<String>,
+    #[validate]
+    pub nodes: Option<Vec<CreateIVRNodeRequest>>,
+    pub is_active: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct CreateIVRNodeRequest {
+    /// Lets other nodes and options point at this one; a fresh id when unset
+    #[serde(default)]
+    pub id: Option<Uuid>,
     pub node_type: IVRNodeType,
     #[validate(length(min = 2, max = 100))]
     pub name: String,
     pub description: Option<String>,
     pub audio_url: Option<String>,
     pub text_to_speech: Option<String>,
+    #[validate]
     pub options: Vec<CreateIVROptionRequest>,
     pub timeout_seconds: Option<u32>,
     pub max_attempts: Option<u32>,
+    #[serde(default)]
+    pub next_node_id: Option<Uuid>,
     pub position: IVRPosition,
+    #[serde(default)]
+    pub transfer: Option<IVRTransfer>,
+    #[serde(default)]
+    #[validate(length(min = 1, max = 100))]
+    pub variable: Option<String>,
+    #[serde(default)]
+    #[validate(length(min = 1, max = 500))]
+    pub condition: Option<String>,
+    #[serde(default)]
+    pub else_node_id: Option<Uuid>,
+}
+
+impl CreateIVRNodeRequest {
+    pub fn into_node(self) -> IVRNode {
+        IVRNode {
+            id: self.id.unwrap_or_else(Uuid::new_v4),
+            node_type: self.node_type,
+            name: self.name,
+            description: self.description,
+            audio_url: self.audio_url,
+            text_to_speech: self.text_to_speech,
+            options: self
+                .options
+                .into_iter()
+                .map(|option| IVROption {
+                    key: option.key,
+                    label: option.label,
+                    next_node_id: option.next_node_id,
+                    action: option.action,
+                })
+                .collect(),
+            timeout_seconds: self.timeout_seconds,
+            max_attempts: self.max_attempts,
+            next_node_id: self.next_node_id,
+            position: self.position,
+            transfer: self.transfer,
+            variable: self.variable,
+            condition: self.condition,
+            else_node_id: self.else_node_id,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -111,10 +260,42 @@ pub struct IVRSession {
     pub session_data: serde_json::Value,
     pub attempts: u32,
     pub is_active: bool,
+    /// Published version of the flow the session runs, whatever was published
+    /// since; `None` for sessions started before flows had versions
+    #[serde(default)]
+    pub flow_version: Option<u32>,
+    /// When the caller's time to answer the current node runs out
+    #[serde(default)]
+    pub timeout_at: Option<DateTime<Utc>>,
+    /// Where the flow sent the call, once the session is over
+    #[serde(default)]
+    pub outcome: Option<IVROutcome>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// How an IVR session ended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IVROutcome {
+    Transfer { transfer: IVRTransfer },
+    Voicemail,
+    Hangup,
+    /// The call ended before the flow did
+    Abandoned,
+}
+
+impl std::fmt::Display for IVROutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IVROutcome::Transfer { .. } => write!(f, "transfer"),
+            IVROutcome::Voicemail => write!(f, "voicemail"),
+            IVROutcome::Hangup => write!(f, "hangup"),
+            IVROutcome::Abandoned => write!(f, "abandoned"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IVRInteraction {
     pub id: Uuid,
@@ -125,29 +306,127 @@ pub struct IVRInteraction {
     pub timestamp: DateTime<Utc>,
 }
 
+/// Version to make live again
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RollbackIVRFlowRequest {
+    #[validate(range(min = 1))]
+    pub version: u32,
+}
+
+/// Versions to compare; without `to`, `from` is compared with the draft.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IVRFlowDiffQuery {
+    pub from: u32,
+    pub to: Option<u32>,
+}
+
+/// A flow's draft with what `validate_flow` found in it. Drafts may be saved
+/// with errors; only publishing needs a valid flow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IVRFlowDraft {
+    #[serde(flatten)]
+    pub flow: IVRFlow,
+    pub report: IVRFlowReport,
+}
+
+/// Keys the caller pressed at the node their IVR session waits at
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct IVRInputRequest {
+    /// Fits `ivr_interactions.input`; empty when the caller entered nothing
+    #[validate(length(max = 255))]
+    pub digits: String,
+}
+
+/// A flow companies can start from, see `ivr_template::instantiate_template`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IVRTemplate {
     pub id: Uuid,
+    /// Company whose admins saved it; `None` for templates every company is offered
+    #[serde(default)]
+    pub company_id: Option<Uuid>,
     pub name: String,
     pub description: Option<String>,
     pub category: IVRCategory,
-    pub template_data: serde_json::Value,
+    pub template_data: IVRTemplateData,
     pub is_public: bool,
-    pub created_by: Uuid,
+    /// `None` for built-in templates
+    pub created_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// The flow a template creates. Its text may hold placeholders such as
+/// `{{company_name}}`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IVRTemplateData {
+    pub welcome_message: Option<String>,
+    pub welcome_audio_url: Option<String>,
+    pub nodes: Vec<IVRNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum IVRCategory {
     CustomerService,
     Sales,
     Support,
     Appointment,
     Information,
+    AfterHours,
     Custom,
 }
 
+impl std::fmt::Display for IVRCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IVRCategory::CustomerService => write!(f, "customer_service"),
+            IVRCategory::Sales => write!(f, "sales"),
+            IVRCategory::Support => write!(f, "support"),
+            IVRCategory::Appointment => write!(f, "appointment"),
+            IVRCategory::Information => write!(f, "information"),
+            IVRCategory::AfterHours => write!(f, "after_hours"),
+            IVRCategory::Custom => write!(f, "custom"),
+        }
+    }
+}
+
+impl std::str::FromStr for IVRCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "customer_service" => Ok(IVRCategory::CustomerService),
+            "sales" => Ok(IVRCategory::Sales),
+            "support" => Ok(IVRCategory::Support),
+            "appointment" => Ok(IVRCategory::Appointment),
+            "information" => Ok(IVRCategory::Information),
+            "after_hours" => Ok(IVRCategory::AfterHours),
+            "custom" => Ok(IVRCategory::Custom),
+            _ => Err(format!("Unknown IVR category: {}", s)),
+        }
+    }
+}
+
+/// Save a flow's draft as a template of the flow's company
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateIVRTemplateRequest {
+    pub flow_id: Uuid,
+    #[validate(length(min = 2, max = 100))]
+    pub name: String,
+    pub description: Option<String>,
+    pub category: IVRCategory,
+}
+
+/// Flow to create from a template. `placeholders` adds to, or overrides, the
+/// values taken from the company's details, e.g. `{"company_phone": "..."}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct InstantiateIVRTemplateRequest {
+    /// The template's name when unset
+    #[validate(length(min = 2, max = 100))]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub placeholders: BTreeMap<String, String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IVRAudio {
     pub id: Uuid,
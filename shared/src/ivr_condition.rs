@@ -0,0 +1,554 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+use crate::call::Call;
+use crate::routing::RoutingOperator;
+use crate::routing_policy::RoutingPolicy;
+use crate::rule_engine::compare;
+
+/// Longest condition accepted, in characters.
+pub const MAX_CONDITION_LENGTH: usize = 500;
+
+/// Deepest nesting of parentheses and `!` accepted.
+pub const MAX_CONDITION_DEPTH: usize = 32;
+
+/// What Condition nodes can test besides the session's own data.
+#[derive(Debug, Clone, Default)]
+pub struct IVRContext {
+    /// `caller.*`: the call's metadata, with `name`, `email`, `phone` and
+    /// `tier` taking precedence
+    pub caller: Map<String, Value>,
+    /// Calls of the company waiting for an agent
+    pub queue_length: u32,
+    pub business_hours_open: bool,
+}
+
+impl IVRContext {
+    /// Context for a call as of `now`. `caller.tier` is read from the metadata
+    /// key the company's routing policy keeps tiers under.
+    pub fn from_call(call: &Call, policy: &RoutingPolicy, queue_length: u32, now: DateTime<Utc>) -> Self {
+        let mut caller = call.metadata.as_object().cloned().unwrap_or_default();
+        caller.insert("name".to_string(), json!(call.customer_name));
+        caller.insert("email".to_string(), json!(call.customer_email));
+        caller.insert("phone".to_string(), json!(call.caller_number));
+        caller.insert(
+            "tier".to_string(),
+            call.metadata.get(&policy.tier_key).cloned().unwrap_or(Value::Null),
+        );
+
+        Self {
+            caller,
+            queue_length,
+            business_hours_open: policy.business_hours.is_open(now),
+        }
+    }
+}
+
+/// A condition that did not parse or type check, with the 1-based column of
+/// the offending part.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{message} at column {column}")]
+pub struct ConditionError {
+    pub column: usize,
+    pub message: String,
+}
+
+/// Something a condition can read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Variable {
+    /// `session.<key>`: what the caller entered at the node storing under `key`
+    Session(String),
+    /// `caller.<key>`
+    Caller(String),
+    /// `queue.length`
+    QueueLength,
+    /// `business_hours.open`
+    BusinessHoursOpen,
+}
+
+impl Variable {
+    fn resolve(path: &str) -> Option<Self> {
+        let segments: Vec<&str> = path.split('.').collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return None;
+        }
+        match segments.as_slice() {
+            ["session", key] => Some(Variable::Session(key.to_string())),
+            ["caller", key] => Some(Variable::Caller(key.to_string())),
+            ["queue", "length"] => Some(Variable::QueueLength),
+            ["business_hours", "open"] => Some(Variable::BusinessHoursOpen),
+            _ => None,
+        }
+    }
+
+    /// What the variable holds when set; session data and caller metadata can
+    /// hold anything.
+    fn value_type(&self) -> ValueType {
+        match self {
+            Variable::Caller(key) if matches!(key.as_str(), "name" | "email" | "phone" | "tier") => ValueType::String,
+            Variable::Session(_) | Variable::Caller(_) => ValueType::Any,
+            Variable::QueueLength => ValueType::Number,
+            Variable::BusinessHoursOpen => ValueType::Bool,
+        }
+    }
+
+    fn value(&self, session_data: &Value, context: &IVRContext) -> Value {
+        match self {
+            Variable::Session(key) => session_data.get(key).cloned().unwrap_or(Value::Null),
+            Variable::Caller(key) => context.caller.get(key).cloned().unwrap_or(Value::Null),
+            Variable::QueueLength => json!(context.queue_length),
+            Variable::BusinessHoursOpen => json!(context.business_hours_open),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn operator(self) -> RoutingOperator {
+        match self {
+            Comparison::Equal => RoutingOperator::Equals,
+            Comparison::NotEqual => RoutingOperator::NotEquals,
+            Comparison::Less => RoutingOperator::LessThan,
+            Comparison::LessOrEqual => RoutingOperator::LessThanOrEqual,
+            Comparison::Greater => RoutingOperator::GreaterThan,
+            Comparison::GreaterOrEqual => RoutingOperator::GreaterThanOrEqual,
+        }
+    }
+
+    fn is_equality(self) -> bool {
+        matches!(self, Comparison::Equal | Comparison::NotEqual)
+    }
+}
+
+/// A parsed and type checked condition. Evaluating one only reads the session
+/// and its `IVRContext`, and always finishes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Literal(Value),
+    Variable(Variable),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Compare(Box<Expression>, Comparison, Box<Expression>),
+}
+
+impl Expression {
+    /// Whether the condition holds for a session. A value that is not a
+    /// boolean, such as a missing session key, counts as false.
+    pub fn holds(&self, session_data: &Value, context: &IVRContext) -> bool {
+        self.evaluate(session_data, context) == Value::Bool(true)
+    }
+
+    fn evaluate(&self, session_data: &Value, context: &IVRContext) -> Value {
+        match self {
+            Expression::Literal(value) => value.clone(),
+            Expression::Variable(variable) => variable.value(session_data, context),
+            Expression::Not(inner) => json!(!inner.holds(session_data, context)),
+            Expression::And(left, right) => {
+                json!(left.holds(session_data, context) && right.holds(session_data, context))
+            }
+            Expression::Or(left, right) => {
+                json!(left.holds(session_data, context) || right.holds(session_data, context))
+            }
+            Expression::Compare(left, comparison, right) => {
+                let (left, right) = coerce(left.evaluate(session_data, context), right.evaluate(session_data, context));
+                json!(compare(&left, &comparison.operator(), &right))
+            }
+        }
+    }
+}
+
+/// Digits the caller entered are stored as strings; compared with a number
+/// they count as that number.
+fn coerce(left: Value, right: Value) -> (Value, Value) {
+    let as_number = |value: &Value| {
+        value
+            .as_str()
+            .and_then(|s| s.trim().parse::<f64>().ok())
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+    };
+    match (left.is_number(), right.is_number()) {
+        (true, false) => {
+            let right = as_number(&right).unwrap_or(right);
+            (left, right)
+        }
+        (false, true) => {
+            let left = as_number(&left).unwrap_or(left);
+            (left, right)
+        }
+        _ => (left, right),
+    }
+}
+
+/// Parse a Condition node's expression, e.g.
+/// `queue.length > 5 && caller.tier == "gold"`, and check that its parts fit
+/// together. Conditions combine comparisons (`==`, `!=`, `<`, `<=`, `>`, `>=`)
+/// of variables and literals (numbers, "strings", `true`, `false`, `null`)
+/// with `&&`, `||`, `!` and parentheses. Strings compare ignoring case.
+pub fn parse_condition(source: &str) -> Result<Expression, ConditionError> {
+    if source.chars().count() > MAX_CONDITION_LENGTH {
+        return Err(error(
+            MAX_CONDITION_LENGTH + 1,
+            format!("Condition is longer than {} characters", MAX_CONDITION_LENGTH),
+        ));
+    }
+
+    let tokens = tokenize(source)?;
+    if tokens.is_empty() {
+        return Err(error(1, "Condition is empty".to_string()));
+    }
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        depth: 0,
+        end: source.chars().count() + 1,
+    };
+
+    let typed = parser.or()?;
+    if let Some((token, column)) = parser.tokens.get(parser.position) {
+        return Err(error(*column, format!("Unexpected {}", token.describe())));
+    }
+    expect_bool(&typed)?;
+    Ok(typed.expression)
+}
+
+/// What a part of a condition evaluates to, as far as is known before it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueType {
+    Bool,
+    Number,
+    String,
+    Null,
+    /// Only known once the condition runs
+    Any,
+}
+
+impl ValueType {
+    fn describe(self) -> &'static str {
+        match self {
+            ValueType::Bool => "a boolean",
+            ValueType::Number => "a number",
+            ValueType::String => "a string",
+            ValueType::Null => "null",
+            ValueType::Any => "any value",
+        }
+    }
+}
+
+struct Typed {
+    expression: Expression,
+    value_type: ValueType,
+    column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Path(String),
+    Number(f64),
+    Str(String),
+    True,
+    False,
+    Null,
+    And,
+    Or,
+    Not,
+    Compare(Comparison),
+    LeftParen,
+    RightParen,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Path(path) => format!("`{}`", path),
+            Token::Number(number) => format!("number {}", number),
+            Token::Str(s) => format!("string \"{}\"", s),
+            Token::True => "`true`".to_string(),
+            Token::False => "`false`".to_string(),
+            Token::Null => "`null`".to_string(),
+            Token::And => "`&&`".to_string(),
+            Token::Or => "`||`".to_string(),
+            Token::Not => "`!`".to_string(),
+            Token::Compare(_) => "comparison".to_string(),
+            Token::LeftParen => "`(`".to_string(),
+            Token::RightParen => "`)`".to_string(),
+        }
+    }
+}
+
+fn error(column: usize, message: String) -> ConditionError {
+    ConditionError { column, message }
+}
+
+/// Split a condition into tokens, each with its 1-based column
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ConditionError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let (token, width) = match (c, next) {
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Compare(Comparison::Equal), 2),
+            ('!', Some('=')) => (Token::Compare(Comparison::NotEqual), 2),
+            ('<', Some('=')) => (Token::Compare(Comparison::LessOrEqual), 2),
+            ('>', Some('=')) => (Token::Compare(Comparison::GreaterOrEqual), 2),
+            ('<', _) => (Token::Compare(Comparison::Less), 1),
+            ('>', _) => (Token::Compare(Comparison::Greater), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::LeftParen, 1),
+            (')', _) => (Token::RightParen, 1),
+            ('&' | '|' | '=', _) => {
+                return Err(error(column, format!("Unexpected `{}`, did you mean `{}{}`?", c, c, c)));
+            }
+            ('"' | '\'', _) => {
+                let mut value = String::new();
+                let mut end = i + 1;
+                loop {
+                    match chars.get(end) {
+                        None => return Err(error(column, "Unterminated string".to_string())),
+                        Some(&quote) if quote == c => break,
+                        Some('\\') => {
+                            value.extend(chars.get(end + 1));
+                            end += 2;
+                        }
+                        Some(&other) => {
+                            value.push(other);
+                            end += 1;
+                        }
+                    }
+                }
+                (Token::Str(value), end + 1 - i)
+            }
+            _ if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let mut end = i + 1;
+                while chars.get(end).is_some_and(|d| d.is_ascii_digit() || *d == '.') {
+                    end += 1;
+                }
+                let text: String = chars[i..end].iter().collect();
+                let number = text
+                    .parse::<f64>()
+                    .map_err(|_| error(column, format!("Invalid number {}", text)))?;
+                (Token::Number(number), end - i)
+            }
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = i + 1;
+                while chars.get(end).is_some_and(|d| d.is_ascii_alphanumeric() || *d == '_' || *d == '.') {
+                    end += 1;
+                }
+                let word: String = chars[i..end].iter().collect();
+                let token = match word.as_str() {
+                    "true" => Token::True,
+                    "false" => Token::False,
+                    "null" => Token::Null,
+                    _ => Token::Path(word),
+                };
+                (token, end - i)
+            }
+            _ => return Err(error(column, format!("Unexpected character `{}`", c))),
+        };
+
+        tokens.push((token, column));
+        i += width;
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens, lowest precedence first: `||`, `&&`,
+/// `!`, then a single comparison. Types are checked as parts are built.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    depth: usize,
+    /// Column just past the end, for errors about a missing part
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn nest(&mut self, column: usize) -> Result<(), ConditionError> {
+        self.depth += 1;
+        if self.depth > MAX_CONDITION_DEPTH {
+            return Err(error(column, format!("Condition nests deeper than {} levels", MAX_CONDITION_DEPTH)));
+        }
+        Ok(())
+    }
+
+    fn or(&mut self) -> Result<Typed, ConditionError> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            let right = self.and()?;
+            expect_bool(&left)?;
+            expect_bool(&right)?;
+            left = Typed {
+                expression: Expression::Or(Box::new(left.expression), Box::new(right.expression)),
+                value_type: ValueType::Bool,
+                column: left.column,
+            };
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Typed, ConditionError> {
+        let mut left = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            let right = self.not()?;
+            expect_bool(&left)?;
+            expect_bool(&right)?;
+            left = Typed {
+                expression: Expression::And(Box::new(left.expression), Box::new(right.expression)),
+                value_type: ValueType::Bool,
+                column: left.column,
+            };
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Typed, ConditionError> {
+        if self.peek() != Some(&Token::Not) {
+            return self.comparison();
+        }
+
+        let column = self.tokens[self.position].1;
+        self.position += 1;
+        self.nest(column)?;
+        let inner = self.not()?;
+        self.depth -= 1;
+        expect_bool(&inner)?;
+        Ok(Typed {
+            expression: Expression::Not(Box::new(inner.expression)),
+            value_type: ValueType::Bool,
+            column,
+        })
+    }
+
+    fn comparison(&mut self) -> Result<Typed, ConditionError> {
+        let left = self.operand()?;
+        let Some(Token::Compare(comparison)) = self.peek().cloned() else {
+            return Ok(left);
+        };
+        self.next();
+        let right = self.operand()?;
+        check_comparable(&left, comparison, &right)?;
+
+        if let Some((Token::Compare(_), column)) = self.tokens.get(self.position) {
+            return Err(error(*column, "Comparisons cannot be chained, combine them with && or ||".to_string()));
+        }
+        Ok(Typed {
+            expression: Expression::Compare(Box::new(left.expression), comparison, Box::new(right.expression)),
+            value_type: ValueType::Bool,
+            column: left.column,
+        })
+    }
+
+    fn operand(&mut self) -> Result<Typed, ConditionError> {
+        let Some((token, column)) = self.next() else {
+            return Err(error(self.end, "Condition ends too early".to_string()));
+        };
+
+        let (expression, value_type) = match token {
+            Token::Number(number) => {
+                let number = serde_json::Number::from_f64(number)
+                    .ok_or_else(|| error(column, format!("Invalid number {}", number)))?;
+                (Expression::Literal(Value::Number(number)), ValueType::Number)
+            }
+            Token::Str(s) => (Expression::Literal(Value::String(s)), ValueType::String),
+            Token::True => (Expression::Literal(Value::Bool(true)), ValueType::Bool),
+            Token::False => (Expression::Literal(Value::Bool(false)), ValueType::Bool),
+            Token::Null => (Expression::Literal(Value::Null), ValueType::Null),
+            Token::Path(path) => {
+                let variable = Variable::resolve(&path).ok_or_else(|| {
+                    let expected = "session.<key>, caller.<key>, queue.length or business_hours.open";
+                    error(column, format!("Unknown variable `{}`, expected {}", path, expected))
+                })?;
+                let value_type = variable.value_type();
+                (Expression::Variable(variable), value_type)
+            }
+            Token::LeftParen => {
+                self.nest(column)?;
+                let inner = self.or()?;
+                self.depth -= 1;
+                match self.next() {
+                    Some((Token::RightParen, _)) => {}
+                    Some((token, at)) => return Err(error(at, format!("Expected `)` but found {}", token.describe()))),
+                    None => return Err(error(self.end, "Missing `)`".to_string())),
+                }
+                return Ok(Typed { column, ..inner });
+            }
+            token => return Err(error(column, format!("Unexpected {}", token.describe()))),
+        };
+
+        Ok(Typed {
+            expression,
+            value_type,
+            column,
+        })
+    }
+}
+
+fn expect_bool(typed: &Typed) -> Result<(), ConditionError> {
+    match typed.value_type {
+        ValueType::Bool | ValueType::Any => Ok(()),
+        other => Err(error(
+            typed.column,
+            format!("Expected a true or false condition but found {}", other.describe()),
+        )),
+    }
+}
+
+/// Equality needs operands of the same type, or `null`; ordering needs two
+/// numbers or two strings. Operands only known at run time fit anything.
+fn check_comparable(left: &Typed, comparison: Comparison, right: &Typed) -> Result<(), ConditionError> {
+    let (l, r) = (left.value_type, right.value_type);
+    if l == ValueType::Any || r == ValueType::Any {
+        return Ok(());
+    }
+
+    let fits = if comparison.is_equality() {
+        l == r || l == ValueType::Null || r == ValueType::Null
+    } else {
+        l == r && matches!(l, ValueType::Number | ValueType::String)
+    };
+    if fits {
+        return Ok(());
+    }
+
+    let message = if !comparison.is_equality() && l == r {
+        format!("Only numbers and strings can be ordered, not {}", l.describe())
+    } else {
+        format!("Cannot compare {} with {}", l.describe(), r.describe())
+    };
+    Err(error(left.column, message))
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use crate::ivr::{IVRFlow, IVRNode};
+
+/// What changed between two versions of a flow, nodes matched by id.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IVRFlowDiff {
+    /// Flow fields that changed: `name`, `description`, `welcome_message`,
+    /// `welcome_audio_url`, and `first_node` when calls start somewhere else
+    pub changed_fields: Vec<String>,
+    pub added_nodes: Vec<IVRNode>,
+    pub removed_nodes: Vec<IVRNode>,
+    pub changed_nodes: Vec<IVRNodeChange>,
+}
+
+impl IVRFlowDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changed_fields.is_empty()
+            && self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IVRNodeChange {
+    pub node_id: Uuid,
+    /// Node fields that changed, as named in the node's JSON
+    pub fields: Vec<String>,
+    pub before: IVRNode,
+    pub after: IVRNode,
+}
+
+/// Compare the content of two flows, in `to`'s node order. Moving a node to
+/// another place in the list only counts when it changes the first node.
+pub fn diff_flows(from: &IVRFlow, to: &IVRFlow) -> IVRFlowDiff {
+    let mut diff = IVRFlowDiff::default();
+
+    let fields = [
+        ("name", from.name != to.name),
+        ("description", from.description != to.description),
+        ("welcome_message", from.welcome_message != to.welcome_message),
+        ("welcome_audio_url", from.welcome_audio_url != to.welcome_audio_url),
+        ("first_node", from.nodes.first().map(|n| n.id) != to.nodes.first().map(|n| n.id)),
+    ];
+    diff.changed_fields = fields
+        .iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(field, _)| field.to_string())
+        .collect();
+
+    for after in &to.nodes {
+        match from.nodes.iter().find(|node| node.id == after.id) {
+            Some(before) => {
+                let fields = changed_fields(before, after);
+                if !fields.is_empty() {
+                    diff.changed_nodes.push(IVRNodeChange {
+                        node_id: after.id,
+                        fields,
+                        before: before.clone(),
+                        after: after.clone(),
+                    });
+                }
+            }
+            None => diff.added_nodes.push(after.clone()),
+        }
+    }
+    diff.removed_nodes = from
+        .nodes
+        .iter()
+        .filter(|before| !to.nodes.iter().any(|node| node.id == before.id))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+/// Fields of a node that differ, compared as JSON
+fn changed_fields(before: &IVRNode, after: &IVRNode) -> Vec<String> {
+    let (Value::Object(before), Value::Object(after)) = (json(before), json(after)) else {
+        return Vec::new();
+    };
+
+    let mut fields: Vec<String> = after
+        .iter()
+        .filter(|(field, value)| before.get(field.as_str()) != Some(value))
+        .map(|(field, _)| field.clone())
+        .collect();
+    fields.sort();
+    fields
+}
+
+fn json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
@@ -0,0 +1,364 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+use crate::error::CallDockerError;
+use crate::ivr_condition::{parse_condition, IVRContext};
+use crate::ivr::{IVRAction, IVRFlow, IVRInteraction, IVRNode, IVRNodeType, IVROutcome, IVRSession, IVRTransfer};
+use crate::rule_engine::RoutingDecision;
+
+/// Seconds a caller has to answer a Menu or Input node that sets no timeout.
+pub const DEFAULT_TIMEOUT_SECS: u32 = 10;
+
+/// Tries a caller gets at a Menu or Input node that sets no limit.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Nodes a session may pass through without waiting for the caller. A flow
+/// looping through Playback or Condition nodes is cut off here.
+pub const MAX_STEPS: usize = 50;
+
+/// What the caller did at the node waiting for them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IVRInput {
+    /// DTMF digits or the key picked in the widget; a trailing `#` ends the entry
+    Digits(String),
+    /// Nothing arrived before the session's `timeout_at`
+    Timeout,
+}
+
+/// Something to play to the caller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IVRPrompt {
+    /// `None` for the flow's welcome message
+    pub node_id: Option<Uuid>,
+    pub text: Option<String>,
+    pub audio_url: Option<String>,
+    /// Keys a Menu node accepts
+    pub options: Vec<IVRPromptOption>,
+    /// Seconds the caller has to answer; `None` when nothing is expected
+    pub timeout_seconds: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IVRPromptOption {
+    pub key: String,
+    pub label: String,
+}
+
+/// What a session did in response to starting or to the caller's input.
+#[derive(Debug, Clone, Default)]
+pub struct IVRStep {
+    /// Prompts to play, in order
+    pub prompts: Vec<IVRPrompt>,
+    /// Set once the session is over
+    pub outcome: Option<IVROutcome>,
+    /// The input handled, to be recorded in `ivr_interactions`
+    pub interaction: Option<IVRInteraction>,
+}
+
+/// Interpreter for one IVR flow. It keeps no state of its own: everything
+/// about a caller's progress is in their `IVRSession`, which the engine moves
+/// from node to node. Sessions start at the flow's first node. Condition nodes
+/// are tested against the session and `context`.
+pub struct IVREngine<'a> {
+    flow: &'a IVRFlow,
+    context: &'a IVRContext,
+}
+
+impl<'a> IVREngine<'a> {
+    pub fn new(flow: &'a IVRFlow, context: &'a IVRContext) -> Self {
+        Self { flow, context }
+    }
+
+    /// Start a session for a call: play the welcome message and run the flow
+    /// up to the first node that waits for the caller, or to its end. The
+    /// session is pinned to the flow's published version.
+    pub fn start(&self, call_id: Uuid, now: DateTime<Utc>) -> Result<(IVRSession, IVRStep), CallDockerError> {
+        let first = self
+            .flow
+            .nodes
+            .first()
+            .ok_or_else(|| CallDockerError::IVR(format!("IVR flow {} has no nodes", self.flow.id)))?;
+
+        let mut session = IVRSession {
+            id: Uuid::new_v4(),
+            call_id,
+            flow_id: self.flow.id,
+            current_node_id: first.id,
+            session_data: json!({}),
+            attempts: 0,
+            is_active: true,
+            flow_version: self.flow.published_version,
+            timeout_at: None,
+            outcome: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let mut step = IVRStep::default();
+        if self.flow.welcome_message.is_some() || self.flow.welcome_audio_url.is_some() {
+            step.prompts.push(IVRPrompt {
+                node_id: None,
+                text: self.flow.welcome_message.clone(),
+                audio_url: self.flow.welcome_audio_url.clone(),
+                options: Vec::new(),
+                timeout_seconds: None,
+            });
+        }
+        self.enter(&mut session, first.id, now, &mut step)?;
+
+        Ok((session, step))
+    }
+
+    /// Handle the caller's input at the node the session waits at. A timeout
+    /// before the session's `timeout_at` changes nothing.
+    pub fn advance(
+        &self,
+        session: &mut IVRSession,
+        input: IVRInput,
+        now: DateTime<Utc>,
+    ) -> Result<IVRStep, CallDockerError> {
+        if !session.is_active {
+            return Err(CallDockerError::IVR(format!("IVR session {} has ended", session.id)));
+        }
+        let mut step = IVRStep::default();
+        if input == IVRInput::Timeout && session.timeout_at.is_some_and(|timeout_at| timeout_at > now) {
+            return Ok(step);
+        }
+
+        let node = self.node(session.current_node_id)?;
+        let entry = match &input {
+            IVRInput::Digits(digits) => Some(digits.trim().trim_end_matches('#').to_string()),
+            IVRInput::Timeout => None,
+        };
+        let mut interaction = IVRInteraction {
+            id: Uuid::new_v4(),
+            session_id: session.id,
+            node_id: node.id,
+            input: entry.clone(),
+            selected_option: None,
+            timestamp: now,
+        };
+
+        match (&node.node_type, entry) {
+            (IVRNodeType::Menu, Some(key)) => {
+                if let Some(option) = node.options.iter().find(|option| option.key == key) {
+                    interaction.selected_option = Some(option.key.clone());
+                    step.interaction = Some(interaction);
+                    store(session, node, &option.key);
+                    if matches!(option.action, IVRAction::Hangup) {
+                        finish(session, IVROutcome::Hangup, now, &mut step);
+                    } else {
+                        self.enter(session, option.next_node_id, now, &mut step)?;
+                    }
+                    return Ok(step);
+                }
+            }
+            (IVRNodeType::Input, Some(digits)) if !digits.is_empty() => {
+                step.interaction = Some(interaction);
+                store(session, node, &digits);
+                self.follow(session, node.next_node_id, now, &mut step)?;
+                return Ok(step);
+            }
+            (IVRNodeType::Menu | IVRNodeType::Input, _) => {}
+            (node_type, _) => {
+                return Err(CallDockerError::IVR(format!(
+                    "IVR node {} ({:?}) does not take input",
+                    node.id, node_type
+                )))
+            }
+        }
+
+        // Wrong key, nothing entered or no answer in time
+        step.interaction = Some(interaction);
+        self.retry(session, node, now, &mut step)?;
+        Ok(step)
+    }
+
+    fn node(&self, node_id: Uuid) -> Result<&'a IVRNode, CallDockerError> {
+        self.flow
+            .nodes
+            .iter()
+            .find(|node| node.id == node_id)
+            .ok_or_else(|| CallDockerError::IVR(format!("IVR flow {} has no node {}", self.flow.id, node_id)))
+    }
+
+    /// Move to `node_id` and run nodes until one waits for the caller or the
+    /// flow ends.
+    fn enter(
+        &self,
+        session: &mut IVRSession,
+        node_id: Uuid,
+        now: DateTime<Utc>,
+        step: &mut IVRStep,
+    ) -> Result<(), CallDockerError> {
+        let mut node_id = node_id;
+        for _ in 0..MAX_STEPS {
+            let node = self.node(node_id)?;
+            session.current_node_id = node.id;
+            session.attempts = 0;
+            session.updated_at = now;
+
+            let next = match &node.node_type {
+                IVRNodeType::Menu | IVRNodeType::Input => {
+                    wait(session, node, now, step);
+                    return Ok(());
+                }
+                IVRNodeType::Playback => {
+                    step.prompts.extend(prompt(node, None));
+                    node.next_node_id
+                }
+                IVRNodeType::Condition => match &node.condition {
+                    Some(condition) => {
+                        let expression = parse_condition(condition).map_err(|e| {
+                            CallDockerError::IVR(format!("IVR condition node {} is invalid: {}", node.id, e))
+                        })?;
+                        if expression.holds(&session.session_data, self.context) {
+                            node.next_node_id
+                        } else {
+                            node.else_node_id
+                        }
+                    }
+                    // Without an expression a condition always holds
+                    None => node.next_node_id,
+                },
+                IVRNodeType::Transfer => {
+                    let transfer = node.transfer.clone().ok_or_else(|| {
+                        CallDockerError::IVR(format!("IVR transfer node {} has no target", node.id))
+                    })?;
+                    step.prompts.extend(prompt(node, None));
+                    finish(session, IVROutcome::Transfer { transfer }, now, step);
+                    return Ok(());
+                }
+                IVRNodeType::Voicemail => {
+                    step.prompts.extend(prompt(node, None));
+                    finish(session, IVROutcome::Voicemail, now, step);
+                    return Ok(());
+                }
+                IVRNodeType::Hangup => {
+                    step.prompts.extend(prompt(node, None));
+                    finish(session, IVROutcome::Hangup, now, step);
+                    return Ok(());
+                }
+            };
+
+            match next {
+                Some(next) => node_id = next,
+                // Running off the end of the flow hangs up
+                None => {
+                    finish(session, IVROutcome::Hangup, now, step);
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(CallDockerError::IVR(format!(
+            "IVR flow {} passed {} nodes without waiting for the caller",
+            self.flow.id, MAX_STEPS
+        )))
+    }
+
+    fn follow(
+        &self,
+        session: &mut IVRSession,
+        next_node_id: Option<Uuid>,
+        now: DateTime<Utc>,
+        step: &mut IVRStep,
+    ) -> Result<(), CallDockerError> {
+        match next_node_id {
+            Some(next) => self.enter(session, next, now, step),
+            None => {
+                finish(session, IVROutcome::Hangup, now, step);
+                Ok(())
+            }
+        }
+    }
+
+    /// Ask again, until the node's attempts run out. A Menu then continues at
+    /// its `next_node_id`; without one, or at an Input node, the call is hung up.
+    fn retry(
+        &self,
+        session: &mut IVRSession,
+        node: &IVRNode,
+        now: DateTime<Utc>,
+        step: &mut IVRStep,
+    ) -> Result<(), CallDockerError> {
+        session.attempts += 1;
+        session.updated_at = now;
+        if session.attempts < node.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS) {
+            wait(session, node, now, step);
+            return Ok(());
+        }
+
+        match (&node.node_type, node.next_node_id) {
+            (IVRNodeType::Menu, Some(fallback)) => self.enter(session, fallback, now, step),
+            _ => {
+                finish(session, IVROutcome::Hangup, now, step);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl IVROutcome {
+    /// Where call routing should send the call next; `None` when it is over.
+    pub fn routing_decision(&self) -> Option<RoutingDecision> {
+        match self {
+            IVROutcome::Transfer { transfer } => Some(match transfer {
+                IVRTransfer::Agent(agent_id) => RoutingDecision::Agent(*agent_id),
+                IVRTransfer::Department(department) => RoutingDecision::Department(department.clone()),
+                IVRTransfer::Queue(queue_id) => RoutingDecision::Queue(*queue_id),
+                IVRTransfer::Phone(number) => RoutingDecision::External(number.clone()),
+            }),
+            IVROutcome::Voicemail => Some(RoutingDecision::Voicemail(None)),
+            IVROutcome::Hangup | IVROutcome::Abandoned => None,
+        }
+    }
+}
+
+/// The node's prompt, if it has anything to play
+fn prompt(node: &IVRNode, timeout_seconds: Option<u32>) -> Option<IVRPrompt> {
+    let options: Vec<IVRPromptOption> = node
+        .options
+        .iter()
+        .map(|option| IVRPromptOption {
+            key: option.key.clone(),
+            label: option.label.clone(),
+        })
+        .collect();
+    if node.text_to_speech.is_none() && node.audio_url.is_none() && options.is_empty() && timeout_seconds.is_none() {
+        return None;
+    }
+
+    Some(IVRPrompt {
+        node_id: Some(node.id),
+        text: node.text_to_speech.clone(),
+        audio_url: node.audio_url.clone(),
+        options,
+        timeout_seconds,
+    })
+}
+
+/// Prompt the caller at a Menu or Input node and start their time to answer
+fn wait(session: &mut IVRSession, node: &IVRNode, now: DateTime<Utc>, step: &mut IVRStep) {
+    let timeout_seconds = node.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECS);
+    session.timeout_at = Some(now + Duration::seconds(timeout_seconds as i64));
+    step.prompts.extend(prompt(node, Some(timeout_seconds)));
+}
+
+fn finish(session: &mut IVRSession, outcome: IVROutcome, now: DateTime<Utc>, step: &mut IVRStep) {
+    session.is_active = false;
+    session.timeout_at = None;
+    session.outcome = Some(outcome.clone());
+    session.updated_at = now;
+    step.outcome = Some(outcome);
+}
+
+/// Keep what the caller entered at `node` in the session data
+fn store(session: &mut IVRSession, node: &IVRNode, value: &str) {
+    if !session.session_data.is_object() {
+        session.session_data = json!({});
+    }
+    session.session_data[node.variable_name()] = Value::String(value.to_string());
+}
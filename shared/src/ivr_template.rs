@@ -0,0 +1,392 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+use uuid::Uuid;
+use crate::company::CompanyContact;
+use crate::error::CallDockerError;
+use crate::ivr::{
+    IVRAction, IVRCategory, IVRFlow, IVRNode, IVRNodeType, IVROption, IVRPosition, IVRTemplate, IVRTemplateData,
+    IVRTransfer,
+};
+use crate::routing_policy::BusinessHours;
+
+pub const CUSTOMER_SERVICE_TEMPLATE_ID: Uuid = Uuid::from_u128(0x6c1d_0e2a_1f00_4c6b_9a01_0000_0000_0001);
+pub const SALES_TEMPLATE_ID: Uuid = Uuid::from_u128(0x6c1d_0e2a_1f00_4c6b_9a01_0000_0000_0002);
+pub const APPOINTMENT_TEMPLATE_ID: Uuid = Uuid::from_u128(0x6c1d_0e2a_1f00_4c6b_9a01_0000_0000_0003);
+pub const AFTER_HOURS_TEMPLATE_ID: Uuid = Uuid::from_u128(0x6c1d_0e2a_1f00_4c6b_9a01_0000_0000_0004);
+
+/// Placeholders `company_placeholders` fills from a company's details
+pub const COMPANY_PLACEHOLDERS: [&str; 5] =
+    ["company_name", "company_phone", "company_website", "company_address", "business_hours"];
+
+/// Templates every company is offered, whatever the database holds
+pub fn builtin_templates() -> Vec<IVRTemplate> {
+    vec![customer_service(), sales(), appointment(), after_hours()]
+}
+
+pub fn builtin_template(id: Uuid) -> Option<IVRTemplate> {
+    builtin_templates().into_iter().find(|template| template.id == id)
+}
+
+/// Values for the placeholders of `COMPANY_PLACEHOLDERS` the company has
+/// details for. Blank details are left out.
+pub fn company_placeholders(contact: &CompanyContact, hours: &BusinessHours) -> BTreeMap<String, String> {
+    let details = [
+        ("company_name", Some(&contact.name)),
+        ("company_phone", contact.phone.as_ref()),
+        ("company_website", contact.website.as_ref()),
+        ("company_address", contact.address.as_ref()),
+    ];
+
+    let mut values: BTreeMap<String, String> = details
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let value = value.map(|value| value.trim()).filter(|value| !value.is_empty())?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect();
+    values.insert("business_hours".to_string(), hours.describe());
+    values
+}
+
+/// A new, unpublished flow of `company_id` made from `template`: every
+/// `{{placeholder}}` in its text filled from `placeholders`, and every node
+/// given a fresh id with the nodes and options pointing at it following.
+/// Placeholders without a value are a validation error naming them.
+pub fn instantiate_template(
+    template: &IVRTemplate,
+    company_id: Uuid,
+    name: Option<String>,
+    placeholders: &BTreeMap<String, String>,
+    now: DateTime<Utc>,
+) -> Result<IVRFlow, CallDockerError> {
+    let mut missing = BTreeSet::new();
+    let name = fill(name.as_deref().unwrap_or(&template.name), placeholders, &mut missing);
+    let description = template
+        .description
+        .as_deref()
+        .map(|description| fill(description, placeholders, &mut missing));
+    let mut data = serde_json::to_value(&template.template_data)?;
+    fill_value(&mut data, placeholders, &mut missing);
+
+    if !missing.is_empty() {
+        let missing: Vec<String> = missing.into_iter().collect();
+        return Err(CallDockerError::Validation(format!(
+            "Template {} needs values for placeholders: {}",
+            template.id,
+            missing.join(", ")
+        )));
+    }
+    let data: IVRTemplateData = serde_json::from_value(data)?;
+
+    Ok(IVRFlow {
+        id: Uuid::new_v4(),
+        company_id,
+        name,
+        description,
+        is_active: true,
+        welcome_message: data.welcome_message,
+        welcome_audio_url: data.welcome_audio_url,
+        nodes: renumber(data.nodes),
+        published_version: None,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+/// Give every node a new id. References to nodes the template lacks are kept,
+/// for `validate_flow` to report.
+fn renumber(mut nodes: Vec<IVRNode>) -> Vec<IVRNode> {
+    let ids: HashMap<Uuid, Uuid> = nodes.iter().map(|node| (node.id, Uuid::new_v4())).collect();
+    let new_id = |id: Uuid| ids.get(&id).copied().unwrap_or(id);
+
+    for node in &mut nodes {
+        node.id = new_id(node.id);
+        node.next_node_id = node.next_node_id.map(new_id);
+        node.else_node_id = node.else_node_id.map(new_id);
+        for option in &mut node.options {
+            option.next_node_id = new_id(option.next_node_id);
+        }
+    }
+    nodes
+}
+
+fn fill_value(value: &mut Value, placeholders: &BTreeMap<String, String>, missing: &mut BTreeSet<String>) {
+    match value {
+        Value::String(text) => *text = fill(text, placeholders, missing),
+        Value::Array(items) => items.iter_mut().for_each(|item| fill_value(item, placeholders, missing)),
+        Value::Object(fields) => fields.values_mut().for_each(|field| fill_value(field, placeholders, missing)),
+        _ => {}
+    }
+}
+
+/// `text` with each `{{name}}` replaced by its value. Names without one are
+/// left in place and added to `missing`; values are not searched again.
+fn fill(text: &str, placeholders: &BTreeMap<String, String>, missing: &mut BTreeSet<String>) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else {
+            break;
+        };
+        let end = start + length + 2;
+        let name = rest[start + 2..end - 2].trim();
+
+        filled.push_str(&rest[..start]);
+        match placeholders.get(name) {
+            Some(value) => filled.push_str(value),
+            None => {
+                missing.insert(name.to_string());
+                filled.push_str(&rest[start..end]);
+            }
+        }
+        rest = &rest[end..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+fn customer_service() -> IVRTemplate {
+    let nodes = vec![
+        IVRNode {
+            options: vec![
+                option("1", "Sales", 2, IVRAction::TransferToDepartment),
+                option("2", "Support", 3, IVRAction::TransferToDepartment),
+                option("3", "Billing", 4, IVRAction::TransferToDepartment),
+                option("4", "Leave a message", 5, IVRAction::RecordVoicemail),
+            ],
+            // Callers who do not choose get support
+            next_node_id: Some(id(3)),
+            ..node(
+                1,
+                IVRNodeType::Menu,
+                "Main menu",
+                "For sales, press 1. For support, press 2. For billing, press 3. To leave a message, press 4.",
+                (0, 0),
+            )
+        },
+        transfer(2, "Sales", "Connecting you to our sales team.", "sales", (1, 0)),
+        transfer(3, "Support", "Connecting you to our support team.", "support", (1, 1)),
+        transfer(4, "Billing", "Connecting you to our billing team.", "billing", (1, 2)),
+        node(5, IVRNodeType::Voicemail, "Voicemail", "Please leave your message after the tone.", (1, 3)),
+    ];
+
+    template(
+        CUSTOMER_SERVICE_TEMPLATE_ID,
+        "Customer service",
+        "Main menu sending callers to sales, support, billing or voicemail",
+        IVRCategory::CustomerService,
+        "Thank you for calling {{company_name}}.",
+        nodes,
+    )
+}
+
+fn sales() -> IVRTemplate {
+    let nodes = vec![
+        IVRNode {
+            condition: Some("business_hours.open".to_string()),
+            next_node_id: Some(id(2)),
+            else_node_id: Some(id(5)),
+            ..node(1, IVRNodeType::Condition, "Open now", "", (0, 0))
+        },
+        IVRNode {
+            options: vec![
+                option("1", "New customers", 3, IVRAction::TransferToDepartment),
+                option("2", "Existing customers", 4, IVRAction::TransferToDepartment),
+            ],
+            next_node_id: Some(id(3)),
+            ..node(
+                2,
+                IVRNodeType::Menu,
+                "Sales menu",
+                "If you are new to {{company_name}}, press 1. If you are already a customer, press 2.",
+                (1, 0),
+            )
+        },
+        transfer(3, "New customers", "Connecting you to our sales team.", "sales", (2, 0)),
+        transfer(4, "Existing customers", "Connecting you to your account team.", "account_management", (2, 1)),
+        IVRNode {
+            next_node_id: Some(id(6)),
+            ..node(
+                5,
+                IVRNodeType::Playback,
+                "Closed",
+                "Our sales team is available {{business_hours}}. You can also find us at {{company_website}}.",
+                (1, 2),
+            )
+        },
+        node(6, IVRNodeType::Voicemail, "Voicemail", "Please leave your name and number after the tone.", (2, 2)),
+    ];
+
+    template(
+        SALES_TEMPLATE_ID,
+        "Sales",
+        "Sends new and existing customers to sales during business hours, voicemail otherwise",
+        IVRCategory::Sales,
+        "Thank you for your interest in {{company_name}}.",
+        nodes,
+    )
+}
+
+fn appointment() -> IVRTemplate {
+    let nodes = vec![
+        IVRNode {
+            options: vec![
+                option("1", "Book an appointment", 2, IVRAction::TransferToDepartment),
+                option("2", "Change or cancel", 3, IVRAction::GoToNode),
+                option("3", "Our address", 5, IVRAction::PlayMessage),
+            ],
+            next_node_id: Some(id(2)),
+            ..node(
+                1,
+                IVRNodeType::Menu,
+                "Appointments",
+                "To book an appointment, press 1. To change or cancel one, press 2. For our address, press 3.",
+                (0, 0),
+            )
+        },
+        transfer(2, "Book", "Connecting you to our scheduling team.", "scheduling", (1, 0)),
+        IVRNode {
+            variable: Some("booking_reference".to_string()),
+            timeout_seconds: Some(15),
+            next_node_id: Some(id(4)),
+            ..node(
+                3,
+                IVRNodeType::Input,
+                "Booking reference",
+                "Please enter your booking reference, followed by the hash key.",
+                (1, 1),
+            )
+        },
+        transfer(4, "Change or cancel", "Connecting you to our scheduling team.", "scheduling", (2, 1)),
+        IVRNode {
+            // Back to the menu
+            next_node_id: Some(id(1)),
+            ..node(
+                5,
+                IVRNodeType::Playback,
+                "Address",
+                "You can find us at {{company_address}}. We are open {{business_hours}}.",
+                (1, 2),
+            )
+        },
+    ];
+
+    template(
+        APPOINTMENT_TEMPLATE_ID,
+        "Appointments",
+        "Booking, changing and cancelling appointments, and where to find the company",
+        IVRCategory::Appointment,
+        "Thank you for calling {{company_name}}.",
+        nodes,
+    )
+}
+
+fn after_hours() -> IVRTemplate {
+    let nodes = vec![
+        IVRNode {
+            next_node_id: Some(id(2)),
+            ..node(1, IVRNodeType::Playback, "Closed", "We are closed. We are open {{business_hours}}.", (0, 0))
+        },
+        IVRNode {
+            options: vec![
+                option("1", "Leave a message", 3, IVRAction::RecordVoicemail),
+                option("2", "Urgent matters", 4, IVRAction::TransferToDepartment),
+                option("3", "End the call", 5, IVRAction::GoToNode),
+            ],
+            next_node_id: Some(id(3)),
+            ..node(
+                2,
+                IVRNodeType::Menu,
+                "After hours menu",
+                "To leave a message, press 1. For urgent matters, press 2. To end the call, press 3.",
+                (1, 0),
+            )
+        },
+        node(3, IVRNodeType::Voicemail, "Voicemail", "Please leave your message after the tone.", (2, 0)),
+        transfer(4, "On call", "Connecting you to the person on call.", "on_call", (2, 1)),
+        node(5, IVRNodeType::Hangup, "Goodbye", "Thank you for calling. Goodbye.", (2, 2)),
+    ];
+
+    template(
+        AFTER_HOURS_TEMPLATE_ID,
+        "After hours",
+        "Tells callers when the company opens and takes messages or urgent calls",
+        IVRCategory::AfterHours,
+        "Thank you for calling {{company_name}}.",
+        nodes,
+    )
+}
+
+fn template(
+    id: Uuid,
+    name: &str,
+    description: &str,
+    category: IVRCategory,
+    welcome_message: &str,
+    nodes: Vec<IVRNode>,
+) -> IVRTemplate {
+    let created_at = Utc.with_ymd_and_hms(2026, 10, 17, 0, 0, 0).single().unwrap_or_default();
+    IVRTemplate {
+        id,
+        company_id: None,
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        category,
+        template_data: IVRTemplateData {
+            welcome_message: Some(welcome_message.to_string()),
+            welcome_audio_url: None,
+            nodes,
+        },
+        is_public: true,
+        created_by: None,
+        created_at,
+        updated_at: created_at,
+    }
+}
+
+/// Template node ids only need to be unique within the template
+fn id(node: u128) -> Uuid {
+    Uuid::from_u128(node)
+}
+
+/// A node saying `prompt`, laid out by column and row
+fn node(node: u128, node_type: IVRNodeType, name: &str, prompt: &str, (column, row): (u8, u8)) -> IVRNode {
+    IVRNode {
+        id: id(node),
+        node_type,
+        name: name.to_string(),
+        description: None,
+        audio_url: None,
+        text_to_speech: (!prompt.is_empty()).then(|| prompt.to_string()),
+        options: Vec::new(),
+        timeout_seconds: None,
+        max_attempts: None,
+        next_node_id: None,
+        position: IVRPosition {
+            x: column as f32 * 250.0,
+            y: row as f32 * 150.0,
+        },
+        transfer: None,
+        variable: None,
+        condition: None,
+        else_node_id: None,
+    }
+}
+
+fn transfer(node_id: u128, name: &str, prompt: &str, department: &str, at: (u8, u8)) -> IVRNode {
+    IVRNode {
+        transfer: Some(IVRTransfer::Department(department.to_string())),
+        ..node(node_id, IVRNodeType::Transfer, name, prompt, at)
+    }
+}
+
+fn option(key: &str, label: &str, next: u128, action: IVRAction) -> IVROption {
+    IVROption {
+        key: key.to_string(),
+        label: label.to_string(),
+        next_node_id: id(next),
+        action,
+    }
+}
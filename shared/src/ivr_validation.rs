@@ -0,0 +1,257 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::error::CallDockerError;
+use crate::ivr_condition::parse_condition;
+use crate::ivr::{IVRAction, IVRNode, IVRNodeType};
+
+/// What is wrong with an IVR flow, or worth a second look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticCode {
+    /// The flow has no nodes to start from
+    EmptyFlow,
+    /// Two nodes share an id
+    DuplicateNodeId,
+    /// A `next_node_id` or option points at a node the flow does not have
+    DanglingReference,
+    /// No path from the first node leads here
+    UnreachableNode,
+    /// Every path from here loops back without ever leaving the flow
+    NoExit,
+    /// Two options of a Menu node share a key
+    DuplicateOptionKey,
+    MenuWithoutOptions,
+    TransferWithoutTarget,
+    /// A Condition node's expression does not parse or type check
+    InvalidCondition,
+}
+
+/// Errors keep a flow from running; warnings only point out dead weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IVRDiagnostic {
+    /// The node concerned; `None` for the flow as a whole
+    pub node_id: Option<Uuid>,
+    pub code: DiagnosticCode,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// Everything `validate_flow` found, in node order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IVRFlowReport {
+    pub diagnostics: Vec<IVRDiagnostic>,
+}
+
+impl IVRFlowReport {
+    /// Whether the flow can run: it may still have warnings.
+    pub fn is_valid(&self) -> bool {
+        !self.diagnostics.iter().any(|d| d.severity == DiagnosticSeverity::Error)
+    }
+
+    /// Diagnostics about one node
+    pub fn for_node(&self, node_id: Uuid) -> impl Iterator<Item = &IVRDiagnostic> {
+        self.diagnostics.iter().filter(move |d| d.node_id == Some(node_id))
+    }
+
+    /// Fail with the flow's errors, for callers that cannot use a broken flow
+    pub fn into_result(self) -> Result<Self, CallDockerError> {
+        if self.is_valid() {
+            return Ok(self);
+        }
+        let errors: Vec<String> = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Error)
+            .map(|d| match d.node_id {
+                Some(node_id) => format!("node {}: {}", node_id, d.message),
+                None => d.message.clone(),
+            })
+            .collect();
+        Err(CallDockerError::Validation(format!("Invalid IVR flow: {}", errors.join("; "))))
+    }
+
+    fn push(&mut self, node_id: Option<Uuid>, code: DiagnosticCode, message: String) {
+        let severity = match code {
+            DiagnosticCode::UnreachableNode => DiagnosticSeverity::Warning,
+            _ => DiagnosticSeverity::Error,
+        };
+        self.diagnostics.push(IVRDiagnostic {
+            node_id,
+            code,
+            severity,
+            message,
+        });
+    }
+}
+
+/// Check a flow's nodes the way `IVREngine` will run them, starting at the
+/// first node: every reference must lead to a node, every node should be
+/// reachable, a caller must be able to leave the flow from every node without
+/// failing a prompt, Menu nodes need distinct option keys, Transfer nodes a
+/// target and Condition nodes an expression that type checks.
+pub fn validate_flow(nodes: &[IVRNode]) -> IVRFlowReport {
+    let mut report = IVRFlowReport::default();
+    let Some(first) = nodes.first() else {
+        report.push(None, DiagnosticCode::EmptyFlow, "Flow has no nodes".to_string());
+        return report;
+    };
+
+    let mut by_id: HashMap<Uuid, &IVRNode> = HashMap::new();
+    for node in nodes {
+        if by_id.insert(node.id, node).is_some() {
+            let message = format!("Another node already has id {}", node.id);
+            report.push(Some(node.id), DiagnosticCode::DuplicateNodeId, message);
+        }
+    }
+
+    for node in nodes {
+        for (next, option_key) in successors(node) {
+            if !by_id.contains_key(&next) {
+                let message = match option_key {
+                    Some(key) => format!("Option {} leads to missing node {}", key, next),
+                    None => format!("Next node {} does not exist", next),
+                };
+                report.push(Some(node.id), DiagnosticCode::DanglingReference, message);
+            }
+        }
+
+        match node.node_type {
+            IVRNodeType::Menu if node.options.is_empty() => {
+                let message = "Menu has no options to pick from".to_string();
+                report.push(Some(node.id), DiagnosticCode::MenuWithoutOptions, message);
+            }
+            IVRNodeType::Menu => {
+                let mut keys = HashSet::new();
+                for option in &node.options {
+                    if !keys.insert(option.key.as_str()) {
+                        let message = format!("Option key {} is used more than once", option.key);
+                        report.push(Some(node.id), DiagnosticCode::DuplicateOptionKey, message);
+                    }
+                }
+            }
+            IVRNodeType::Transfer if node.transfer.is_none() => {
+                let message = "Transfer has no target".to_string();
+                report.push(Some(node.id), DiagnosticCode::TransferWithoutTarget, message);
+            }
+            IVRNodeType::Condition => {
+                if let Some(Err(e)) = node.condition.as_deref().map(parse_condition) {
+                    let message = format!("Condition is invalid: {}", e);
+                    report.push(Some(node.id), DiagnosticCode::InvalidCondition, message);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Nodes reachable from the first one
+    let mut reachable = HashSet::from([first.id]);
+    let mut pending = vec![first.id];
+    while let Some(node_id) = pending.pop() {
+        let Some(node) = by_id.get(&node_id) else {
+            continue;
+        };
+        for (next, _) in successors(node) {
+            if by_id.contains_key(&next) && reachable.insert(next) {
+                pending.push(next);
+            }
+        }
+    }
+
+    // Nodes a caller answering every prompt can leave the flow from, growing
+    // backwards from the exits. Missing nodes are already reported, so they
+    // count as a way out rather than as a loop.
+    let mut can_exit: HashSet<Uuid> = HashSet::new();
+    loop {
+        let before = can_exit.len();
+        for node in by_id.values() {
+            if !can_exit.contains(&node.id)
+                && (exits(node) || answered(node).any(|next| can_exit.contains(&next) || !by_id.contains_key(&next)))
+            {
+                can_exit.insert(node.id);
+            }
+        }
+        if can_exit.len() == before {
+            break;
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for node in nodes {
+        // Reported once, should ids repeat
+        if !seen.insert(node.id) {
+            continue;
+        }
+        if !reachable.contains(&node.id) {
+            let message = "No path from the first node leads here".to_string();
+            report.push(Some(node.id), DiagnosticCode::UnreachableNode, message);
+        } else if !can_exit.contains(&node.id) {
+            let message = "Every path from here loops without leaving the flow".to_string();
+            report.push(Some(node.id), DiagnosticCode::NoExit, message);
+        }
+    }
+
+    report
+}
+
+/// Nodes a session can move on to from `node`, with the Menu option leading
+/// there. A Hangup option ends the call, so where it points is never followed.
+fn successors(node: &IVRNode) -> Vec<(Uuid, Option<&str>)> {
+    let mut next: Vec<(Uuid, Option<&str>)> = Vec::new();
+    match node.node_type {
+        IVRNodeType::Menu => {
+            next.extend(
+                node.options
+                    .iter()
+                    .filter(|option| !matches!(option.action, IVRAction::Hangup))
+                    .map(|option| (option.next_node_id, Some(option.key.as_str()))),
+            );
+            // Where the menu goes after its attempts run out
+            next.extend(node.next_node_id.map(|id| (id, None)));
+        }
+        IVRNodeType::Input | IVRNodeType::Playback => {
+            next.extend(node.next_node_id.map(|id| (id, None)));
+        }
+        IVRNodeType::Condition => {
+            next.extend(node.next_node_id.map(|id| (id, None)));
+            // Without an expression the condition always holds
+            if node.condition.is_some() {
+                next.extend(node.else_node_id.map(|id| (id, None)));
+            }
+        }
+        IVRNodeType::Transfer | IVRNodeType::Voicemail | IVRNodeType::Hangup => {}
+    }
+    next
+}
+
+/// Nodes a caller who answers `node` can move on to. Running out of attempts
+/// is not a way out of a loop, so a Menu's fallback is left out, unless the
+/// Menu has no options and is reported for that already.
+fn answered(node: &IVRNode) -> impl Iterator<Item = Uuid> + '_ {
+    let skip_fallback = matches!(node.node_type, IVRNodeType::Menu) && !node.options.is_empty();
+    successors(node)
+        .into_iter()
+        .filter(move |(_, option_key)| !skip_fallback || option_key.is_some())
+        .map(|(next, _)| next)
+}
+
+/// Whether a caller answering every prompt can leave the flow at `node`: it
+/// hands the call on or hangs up, offers a Hangup option, or has no next node
+/// on some branch.
+fn exits(node: &IVRNode) -> bool {
+    match node.node_type {
+        IVRNodeType::Transfer | IVRNodeType::Voicemail | IVRNodeType::Hangup => true,
+        IVRNodeType::Menu => node.options.iter().any(|option| matches!(option.action, IVRAction::Hangup)),
+        IVRNodeType::Input | IVRNodeType::Playback => node.next_node_id.is_none(),
+        IVRNodeType::Condition => {
+            node.next_node_id.is_none() || (node.condition.is_some() && node.else_node_id.is_none())
+        }
+    }
+}
@@ -3,8 +3,48 @@ pub mod call;
 pub mod company;
 pub mod error;
 pub mod ivr;
+pub mod ivr_condition;
+pub mod ivr_diff;
+pub mod ivr_engine;
+pub mod ivr_template;
+pub mod ivr_validation;
+pub mod queue_strategy;
+pub mod request_id;
 pub mod routing;
+pub mod routing_policy;
+pub mod rule_engine;
+pub mod signaling;
 pub mod types;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_support;
+
+#[cfg(test)]
+mod test_call;
+#[cfg(test)]
+mod test_error;
+#[cfg(test)]
+mod test_ivr_condition;
+#[cfg(test)]
+mod test_ivr_diff;
+#[cfg(test)]
+mod test_ivr_engine;
+#[cfg(test)]
+mod test_ivr_template;
+#[cfg(test)]
+mod test_ivr_validation;
+#[cfg(test)]
+mod test_queue_strategy;
+#[cfg(test)]
+mod test_request_id;
+#[cfg(test)]
+mod test_routing;
+#[cfg(test)]
+mod test_routing_policy;
+#[cfg(test)]
+mod test_rule_engine;
+#[cfg(test)]
+mod test_signaling;
+
 pub use error::*;
 pub use types::*;
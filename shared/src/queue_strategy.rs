@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+use crate::error::{CallDockerError, Result};
+use crate::routing::{AgentAvailability, QueueStrategy, RoutingQueue};
+
+/// What a strategy knows about the call being placed.
+#[derive(Debug, Clone)]
+pub struct SelectionContext<'a> {
+    pub queue: &'a RoutingQueue,
+    pub required_skills: &'a [String],
+    /// Agent this queue handed its previous call to, for round-robin.
+    pub cursor: Option<Uuid>,
+}
+
+/// Picks one agent for a queued call. `agents` only holds agents with a free
+/// call slot; strategies apply the skill requirement themselves.
+pub trait AgentSelectionStrategy: Send + Sync {
+    fn select(&self, agents: &[AgentAvailability], context: &SelectionContext<'_>) -> Option<Uuid>;
+}
+
+/// Agents with capacity and every required skill, in a stable order.
+fn qualified<'a>(agents: &'a [AgentAvailability], context: &SelectionContext<'_>) -> Vec<&'a AgentAvailability> {
+    let mut qualified: Vec<_> = agents
+        .iter()
+        .filter(|agent| agent.has_capacity() && agent.has_skills(context.required_skills))
+        .collect();
+    qualified.sort_by_key(|agent| agent.agent_id);
+    qualified
+}
+
+/// Cycles through qualified agents in id order, starting after the cursor.
+#[derive(Debug, Default)]
+pub struct RoundRobin;
+
+impl AgentSelectionStrategy for RoundRobin {
+    fn select(&self, agents: &[AgentAvailability], context: &SelectionContext<'_>) -> Option<Uuid> {
+        let qualified = qualified(agents, context);
+        let next = match context.cursor {
+            Some(cursor) => qualified.iter().find(|agent| agent.agent_id > cursor),
+            None => None,
+        };
+        next.or_else(|| qualified.first()).map(|agent| agent.agent_id)
+    }
+}
+
+/// Fewest current calls first, then whoever has been idle the longest.
+#[derive(Debug, Default)]
+pub struct LeastBusy;
+
+impl AgentSelectionStrategy for LeastBusy {
+    fn select(&self, agents: &[AgentAvailability], context: &SelectionContext<'_>) -> Option<Uuid> {
+        qualified(agents, context)
+            .into_iter()
+            .min_by_key(|agent| (agent.current_calls, agent.last_activity))
+            .map(|agent| agent.agent_id)
+    }
+}
+
+/// Highest skill match score. Unlike the other strategies a partial match is
+/// accepted, so a call still reaches the closest expert when nobody has every
+/// skill.
+#[derive(Debug, Default)]
+pub struct MostSkilled;
+
+impl MostSkilled {
+    /// Required skills the agent has, then the breadth of their skill set.
+    pub fn score(agent: &AgentAvailability, required_skills: &[String]) -> (usize, usize) {
+        let matched = required_skills
+            .iter()
+            .filter(|skill| agent.skills.iter().any(|own| own.eq_ignore_ascii_case(skill)))
+            .count();
+        (matched, agent.skills.len())
+    }
+}
+
+impl AgentSelectionStrategy for MostSkilled {
+    fn select(&self, agents: &[AgentAvailability], context: &SelectionContext<'_>) -> Option<Uuid> {
+        let mut candidates: Vec<_> = agents
+            .iter()
+            .filter(|agent| agent.has_capacity())
+            .map(|agent| (MostSkilled::score(agent, context.required_skills), agent))
+            .filter(|((matched, _), _)| context.required_skills.is_empty() || *matched > 0)
+            .collect();
+
+        // Best score first, then least loaded, then id for a stable result
+        candidates.sort_by(|(score_a, a), (score_b, b)| {
+            score_b
+                .cmp(score_a)
+                .then(a.load().total_cmp(&b.load()))
+                .then(a.agent_id.cmp(&b.agent_id))
+        });
+        candidates.first().map(|(_, agent)| agent.agent_id)
+    }
+}
+
+/// First qualified agent in the order the queue lists its agents.
+#[derive(Debug, Default)]
+pub struct Priority;
+
+impl AgentSelectionStrategy for Priority {
+    fn select(&self, agents: &[AgentAvailability], context: &SelectionContext<'_>) -> Option<Uuid> {
+        let qualified = qualified(agents, context);
+        context
+            .queue
+            .agents
+            .iter()
+            .find(|id| qualified.iter().any(|agent| agent.agent_id == **id))
+            .copied()
+    }
+}
+
+/// Uniformly random qualified agent.
+#[derive(Debug)]
+pub struct Random {
+    state: Mutex<u64>,
+}
+
+impl Random {
+    /// Same seed, same sequence of picks.
+    pub fn with_seed(seed: u64) -> Self {
+        // xorshift never leaves zero
+        Self { state: Mutex::new(seed.max(1)) }
+    }
+
+    fn next(&self) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::with_seed(Uuid::new_v4().as_u128() as u64)
+    }
+}
+
+impl AgentSelectionStrategy for Random {
+    fn select(&self, agents: &[AgentAvailability], context: &SelectionContext<'_>) -> Option<Uuid> {
+        let qualified = qualified(agents, context);
+        if qualified.is_empty() {
+            return None;
+        }
+        let index = (self.next() % qualified.len() as u64) as usize;
+        Some(qualified[index].agent_id)
+    }
+}
+
+/// Resolves `RoutingQueue.strategy` to an implementation. `Custom` strategies
+/// are looked up by name and must be registered first.
+#[derive(Clone)]
+pub struct StrategyRegistry {
+    round_robin: Arc<RoundRobin>,
+    least_busy: Arc<LeastBusy>,
+    most_skilled: Arc<MostSkilled>,
+    priority: Arc<Priority>,
+    random: Arc<Random>,
+    custom: HashMap<String, Arc<dyn AgentSelectionStrategy>>,
+}
+
+impl StrategyRegistry {
+    pub fn new() -> Self {
+        Self::with_random(Random::default())
+    }
+
+    /// Registry whose `Random` strategy uses the given generator.
+    pub fn with_random(random: Random) -> Self {
+        Self {
+            round_robin: Arc::new(RoundRobin),
+            least_busy: Arc::new(LeastBusy),
+            most_skilled: Arc::new(MostSkilled),
+            priority: Arc::new(Priority),
+            random: Arc::new(random),
+            custom: HashMap::new(),
+        }
+    }
+
+    pub fn register_custom(&mut self, name: impl Into<String>, strategy: Arc<dyn AgentSelectionStrategy>) {
+        self.custom.insert(name.into(), strategy);
+    }
+
+    pub fn resolve(&self, strategy: &QueueStrategy) -> Result<Arc<dyn AgentSelectionStrategy>> {
+        Ok(match strategy {
+            QueueStrategy::RoundRobin => self.round_robin.clone(),
+            QueueStrategy::LeastBusy => self.least_busy.clone(),
+            QueueStrategy::MostSkilled => self.most_skilled.clone(),
+            QueueStrategy::Priority => self.priority.clone(),
+            QueueStrategy::Random => self.random.clone(),
+            QueueStrategy::Custom(name) => self
+                .custom
+                .get(name)
+                .cloned()
+                .ok_or_else(|| CallDockerError::CallRouting(format!("Unknown queue strategy: {}", name)))?,
+        })
+    }
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,95 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    Error,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Header carrying a request's id, both ways
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: Uuid;
+}
+
+/// Id of the request being handled, or a new one outside of `RequestId`.
+pub fn current_request_id() -> Uuid {
+    REQUEST_ID.try_with(|id| *id).unwrap_or_else(|_| Uuid::new_v4())
+}
+
+/// Gives every request an id: the caller's `x-request-id` when it is a UUID,
+/// a new one otherwise. The id is sent back in the same header, put on the
+/// request's tracing span and used by error responses, so a failure can be
+/// traced from the client to the logs. Errors from inner middleware are
+/// rendered here, while the id is still known.
+pub struct RequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdService { service: Rc::new(service) }))
+    }
+}
+
+pub struct RequestIdService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| Uuid::parse_str(value.trim()).ok())
+            .unwrap_or_else(Uuid::new_v4);
+        let span = tracing::info_span!("request", %request_id, method = %req.method(), path = %req.path());
+
+        let handled = async move {
+            match svc.call(req).await {
+                Ok(mut res) => {
+                    set_request_id(res.headers_mut(), request_id);
+                    Ok(res)
+                }
+                Err(e) => {
+                    let mut response = e.error_response();
+                    set_request_id(response.headers_mut(), request_id);
+                    Err(InternalError::from_response(e, response).into())
+                }
+            }
+        };
+        Box::pin(REQUEST_ID.scope(request_id, handled.instrument(span)))
+    }
+}
+
+fn set_request_id(headers: &mut HeaderMap, request_id: Uuid) {
+    if let Ok(value) = HeaderValue::from_str(&request_id.to_string()) {
+        headers.insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+}
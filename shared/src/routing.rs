@@ -24,6 +24,86 @@ pub struct RoutingCondition {
     pub value: serde_json::Value,
 }
 
+impl RoutingCondition {
+    /// Check the value suits the field and operator, so a rule is never saved
+    /// with a condition that could not match.
+    pub fn check(&self) -> std::result::Result<(), String> {
+        use RoutingOperator::*;
+
+        let kind = self.field.kind();
+        let value = &self.value;
+        let unsupported = || Err(format!("{:?} cannot be used with {:?}", self.operator, self.field));
+
+        match self.operator {
+            Equals | NotEquals => kind.check_value(value),
+            In | NotIn => match value.as_array() {
+                Some(values) if !values.is_empty() => values.iter().try_for_each(|value| kind.check_value(value)),
+                _ => Err(format!("{:?} needs a non-empty list of values", self.operator)),
+            },
+            Contains | NotContains => match kind {
+                FieldKind::Text | FieldKind::List | FieldKind::Any => expect_string(value),
+                _ => unsupported(),
+            },
+            StartsWith | EndsWith => match kind {
+                FieldKind::Text | FieldKind::Any => expect_string(value),
+                _ => unsupported(),
+            },
+            GreaterThan | LessThan | GreaterThanOrEqual | LessThanOrEqual => match kind {
+                FieldKind::Number | FieldKind::Time => kind.check_value(value),
+                FieldKind::Any if value.is_number() || value.is_string() => Ok(()),
+                FieldKind::Any => Err("Expected a number or string".to_string()),
+                _ => unsupported(),
+            },
+            IsNull | IsNotNull if value.is_null() => Ok(()),
+            IsNull | IsNotNull => Err(format!("{:?} takes no value", self.operator)),
+        }
+    }
+}
+
+/// Type of value a routing field holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Text,
+    Number,
+    /// Time of day, "HH:MM".
+    Time,
+    /// List of strings, compared element by element.
+    List,
+    Status,
+    /// Custom metadata, any scalar.
+    Any,
+}
+
+impl FieldKind {
+    /// Check a single value the field can be compared with.
+    fn check_value(self, value: &serde_json::Value) -> std::result::Result<(), String> {
+        match self {
+            FieldKind::Text | FieldKind::List => expect_string(value),
+            FieldKind::Number if value.is_number() => Ok(()),
+            FieldKind::Number => Err("Expected a number".to_string()),
+            FieldKind::Time => match value.as_str() {
+                Some(time) if time.len() == 5 && chrono::NaiveTime::parse_from_str(time, "%H:%M").is_ok() => Ok(()),
+                _ => Err("Expected a time of day as \"HH:MM\"".to_string()),
+            },
+            FieldKind::Status => value
+                .as_str()
+                .ok_or_else(|| "Expected an agent status".to_string())?
+                .parse::<AgentStatus>()
+                .map(|_| ()),
+            FieldKind::Any if value.is_string() || value.is_number() || value.is_boolean() => Ok(()),
+            FieldKind::Any => Err("Expected a string, number or boolean".to_string()),
+        }
+    }
+}
+
+fn expect_string(value: &serde_json::Value) -> std::result::Result<(), String> {
+    if value.is_string() {
+        Ok(())
+    } else {
+        Err("Expected a string".to_string())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RoutingField {
     CustomerPhone,
@@ -38,6 +118,19 @@ pub enum RoutingField {
     Custom(String),
 }
 
+impl RoutingField {
+    fn kind(&self) -> FieldKind {
+        match self {
+            RoutingField::CustomerPhone | RoutingField::CustomerEmail | RoutingField::CustomerName => FieldKind::Text,
+            RoutingField::CallDuration | RoutingField::QueueLength | RoutingField::CallPriority => FieldKind::Number,
+            RoutingField::CallTime => FieldKind::Time,
+            RoutingField::AgentSkills => FieldKind::List,
+            RoutingField::AgentStatus => FieldKind::Status,
+            RoutingField::Custom(_) => FieldKind::Any,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RoutingOperator {
     Equals,
@@ -86,12 +179,28 @@ pub struct RoutingQueue {
     pub max_wait_time: Option<u32>,
     pub max_queue_size: Option<u32>,
     pub overflow_action: OverflowAction,
+    /// IVR flow id for `RouteToIVR`, phone number for `RouteToExternal`
+    pub overflow_target: Option<String>,
     pub agents: Vec<Uuid>,
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl RoutingQueue {
+    /// Whether a call that started waiting at `wait_start` has waited longer
+    /// than the queue allows.
+    pub fn wait_exceeded(&self, wait_start: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.max_wait_time
+            .is_some_and(|max| now.signed_duration_since(wait_start).num_seconds() >= max as i64)
+    }
+
+    /// Whether `waiting` calls leave no room for another one.
+    pub fn is_full(&self, waiting: usize) -> bool {
+        self.max_queue_size.is_some_and(|max| waiting >= max as usize)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum QueueStrategy {
     RoundRobin,
@@ -111,6 +220,103 @@ pub enum OverflowAction {
     RouteToExternal,
 }
 
+impl std::fmt::Display for QueueStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueStrategy::RoundRobin => write!(f, "round_robin"),
+            QueueStrategy::LeastBusy => write!(f, "least_busy"),
+            QueueStrategy::MostSkilled => write!(f, "most_skilled"),
+            QueueStrategy::Priority => write!(f, "priority"),
+            QueueStrategy::Random => write!(f, "random"),
+            QueueStrategy::Custom(name) => write!(f, "custom:{}", name),
+        }
+    }
+}
+
+impl std::str::FromStr for QueueStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "round_robin" => Ok(QueueStrategy::RoundRobin),
+            "least_busy" => Ok(QueueStrategy::LeastBusy),
+            "most_skilled" => Ok(QueueStrategy::MostSkilled),
+            "priority" => Ok(QueueStrategy::Priority),
+            "random" => Ok(QueueStrategy::Random),
+            _ => match s.strip_prefix("custom:") {
+                Some(name) if !name.is_empty() => Ok(QueueStrategy::Custom(name.to_string())),
+                _ => Err(format!("Unknown queue strategy: {}", s)),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for OverflowAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverflowAction::RouteToFallback => write!(f, "fallback"),
+            OverflowAction::RouteToVoicemail => write!(f, "voicemail"),
+            OverflowAction::RouteToIVR => write!(f, "ivr"),
+            OverflowAction::Hangup => write!(f, "hangup"),
+            OverflowAction::RouteToExternal => write!(f, "external"),
+        }
+    }
+}
+
+impl OverflowAction {
+    /// Check that `target` says where the action sends calls, for the actions
+    /// that need one.
+    pub fn check_target(&self, target: Option<&str>) -> std::result::Result<(), String> {
+        match (self, target) {
+            (OverflowAction::RouteToIVR, Some(target)) if Uuid::parse_str(target).is_err() => {
+                Err("overflow_target of an ivr overflow must be an IVR flow id".to_string())
+            }
+            (OverflowAction::RouteToIVR, None) => Err("An ivr overflow needs an overflow_target".to_string()),
+            (OverflowAction::RouteToExternal, Some(target)) if target.trim().is_empty() => {
+                Err("overflow_target of an external overflow must be a phone number".to_string())
+            }
+            (OverflowAction::RouteToExternal, None) => {
+                Err("An external overflow needs an overflow_target".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Why a call left a routing queue through its overflow action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowReason {
+    /// The call waited longer than `max_wait_time`
+    WaitTimeExceeded,
+    /// The queue already held `max_queue_size` calls
+    QueueFull,
+}
+
+impl std::fmt::Display for OverflowReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverflowReason::WaitTimeExceeded => write!(f, "wait_time_exceeded"),
+            OverflowReason::QueueFull => write!(f, "queue_full"),
+        }
+    }
+}
+
+impl std::str::FromStr for OverflowAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "fallback" => Ok(OverflowAction::RouteToFallback),
+            "voicemail" => Ok(OverflowAction::RouteToVoicemail),
+            "ivr" => Ok(OverflowAction::RouteToIVR),
+            "hangup" => Ok(OverflowAction::Hangup),
+            "external" => Ok(OverflowAction::RouteToExternal),
+            _ => Err(format!("Unknown overflow action: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueueItem {
     pub id: Uuid,
@@ -153,7 +359,147 @@ pub struct AgentAvailability {
     pub is_available: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl AgentAvailability {
+    /// Whether the agent has every skill in `required`, ignoring case.
+    pub fn has_skills(&self, required: &[String]) -> bool {
+        required
+            .iter()
+            .all(|skill| self.skills.iter().any(|own| own.eq_ignore_ascii_case(skill)))
+    }
+
+    /// Whether the agent can take another call right now.
+    pub fn has_capacity(&self) -> bool {
+        self.is_available && self.status == AgentStatus::Online && self.current_calls < self.max_calls
+    }
+
+    /// Whether the agent is at work, so a call waiting for them can expect them
+    /// to free up: online, or busy with other calls.
+    pub fn is_working(&self) -> bool {
+        self.is_available && matches!(self.status, AgentStatus::Online | AgentStatus::Busy)
+    }
+
+    /// Share of the agent's concurrent call slots in use.
+    pub fn load(&self) -> f32 {
+        if self.max_calls == 0 {
+            1.0
+        } else {
+            self.current_calls as f32 / self.max_calls as f32
+        }
+    }
+}
+
+/// Seconds of waiting one point of call priority is worth when ordering a queue,
+/// unless the company's routing policy says otherwise.
+pub const PRIORITY_WEIGHT_SECS: f64 = 60.0;
+
+/// Sort key of a waiting call; lower is served first. Earlier arrival and higher
+/// priority both lower it, so a low priority call that has waited long enough
+/// still overtakes newer, more urgent ones instead of starving. Each point of
+/// priority counts as `seconds_per_point` of waiting.
+pub fn queue_score(priority: u32, wait_start: DateTime<Utc>, seconds_per_point: f64) -> f64 {
+    wait_start.timestamp() as f64 - priority as f64 * seconds_per_point
+}
+
+/// Handle time assumed for a company with no handled calls to learn from.
+pub const DEFAULT_HANDLE_TIME_SECS: f64 = 180.0;
+
+/// Seconds the caller at 1-based `position` can expect to wait: the calls ahead
+/// of them, and their own, share the staffed call slots, each slot freeing up
+/// once per average handle time. `None` when nobody is staffed to take calls.
+pub fn estimated_wait_time(position: u32, average_handle_time: f64, staffed_slots: u32) -> Option<u32> {
+    if staffed_slots == 0 {
+        return None;
+    }
+    Some((position as f64 * average_handle_time / staffed_slots as f64).ceil() as u32)
+}
+
+/// A call waiting for an agent, as callers and supervisors see it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedCall {
+    pub call_id: Uuid,
+    pub queue_id: Option<Uuid>,
+    pub priority: u32,
+    /// 1-based place in the company's queue
+    pub position: u32,
+    /// Seconds waited so far
+    pub waited: u64,
+    /// Seconds still to wait, when agents are staffed
+    pub estimated_wait_time: Option<u32>,
+}
+
+/// A call reserved for one agent. It rings them until they accept or reject it,
+/// or until `expires_at`, when it goes to the next agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallOffer {
+    pub call_id: Uuid,
+    pub company_id: Uuid,
+    pub agent_id: Uuid,
+    pub offered_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl CallOffer {
+    pub fn new(call_id: Uuid, company_id: Uuid, agent_id: Uuid, now: DateTime<Utc>, ring_timeout: u64) -> Self {
+        Self {
+            call_id,
+            company_id,
+            agent_id,
+            offered_at: now,
+            expires_at: now + chrono::Duration::seconds(ring_timeout as i64),
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Whole seconds between the offer and `now`
+    pub fn ring_time(&self, now: DateTime<Utc>) -> u64 {
+        now.signed_duration_since(self.offered_at).num_seconds().max(0) as u64
+    }
+}
+
+/// How an offer to an agent ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OfferOutcome {
+    Accepted,
+    Rejected,
+    /// Nobody answered before the ring timeout
+    TimedOut,
+    /// The call ended or was answered elsewhere first
+    Withdrawn,
+}
+
+impl std::fmt::Display for OfferOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OfferOutcome::Accepted => write!(f, "accepted"),
+            OfferOutcome::Rejected => write!(f, "rejected"),
+            OfferOutcome::TimedOut => write!(f, "timed_out"),
+            OfferOutcome::Withdrawn => write!(f, "withdrawn"),
+        }
+    }
+}
+
+/// Whether an agent who let `missed` offers in a row ring out should be set
+/// away. A `max_missed` of 0 never does.
+pub fn should_set_away(missed: u32, max_missed: u32) -> bool {
+    max_missed > 0 && missed >= max_missed
+}
+
+/// Agents able to take a call needing `required_skills`, least loaded first.
+/// Ties keep their input order.
+pub fn eligible_agents<'a>(agents: &'a [AgentAvailability], required_skills: &[String]) -> Vec<&'a AgentAvailability> {
+    let mut eligible: Vec<_> = agents
+        .iter()
+        .filter(|agent| agent.has_capacity() && agent.has_skills(required_skills))
+        .collect();
+    eligible.sort_by(|a, b| a.load().total_cmp(&b.load()));
+    eligible
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AgentStatus {
     Online,
     Offline,
@@ -163,6 +509,35 @@ pub enum AgentStatus {
     Training,
 }
 
+impl std::fmt::Display for AgentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AgentStatus::Online => write!(f, "online"),
+            AgentStatus::Offline => write!(f, "offline"),
+            AgentStatus::Busy => write!(f, "busy"),
+            AgentStatus::Away => write!(f, "away"),
+            AgentStatus::Break => write!(f, "break"),
+            AgentStatus::Training => write!(f, "training"),
+        }
+    }
+}
+
+impl std::str::FromStr for AgentStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "online" => Ok(AgentStatus::Online),
+            "offline" => Ok(AgentStatus::Offline),
+            "busy" => Ok(AgentStatus::Busy),
+            "away" => Ok(AgentStatus::Away),
+            "break" => Ok(AgentStatus::Break),
+            "training" => Ok(AgentStatus::Training),
+            _ => Err(format!("Unknown agent status: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingStats {
     pub company_id: Uuid,
@@ -192,31 +567,137 @@ pub struct QueueStats {
 pub struct AgentRoutingStats {
     pub agent_id: Uuid,
     pub agent_name: String,
+    /// Calls offered to the agent
     pub total_calls_routed: u64,
+    /// Offers the agent accepted
     pub answered_calls: u64,
+    /// Offers the agent turned down
+    pub rejected_calls: u64,
+    /// Offers that rang out
+    pub missed_calls: u64,
+    /// Seconds from offer to acceptance, averaged
     pub average_answer_time: u64,
+    /// Seconds, averaged over the agent's ended calls
     pub average_call_duration: u64,
+    /// Share of offers the agent responded to, accepting or rejecting them
     pub availability_percentage: f32,
 }
 
+/// Percentage of `offered` calls an agent responded to. Agents without offers
+/// have missed none.
+pub fn response_percentage(offered: u64, responded: u64) -> f32 {
+    if offered == 0 {
+        return 100.0;
+    }
+    (responded.min(offered) as f64 * 100.0 / offered as f64) as f32
+}
+
+/// Period and company agent routing statistics cover. The period defaults to
+/// the last 24 hours.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentStatsQuery {
+    pub company_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct CreateRoutingRuleRequest {
     #[validate(length(min = 2, max = 100))]
     pub name: String,
+    #[validate(length(max = 1000))]
     pub description: Option<String>,
     pub priority: u32,
+    #[validate(custom = "validate_conditions")]
     pub conditions: Vec<RoutingCondition>,
+    #[validate(length(min = 1, message = "A rule needs at least one action"))]
     pub actions: Vec<RoutingAction>,
 }
 
+/// Partial update of a routing rule; omitted fields are left alone.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateRoutingRuleRequest {
+    #[validate(length(min = 2, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+    pub priority: Option<u32>,
+    pub is_active: Option<bool>,
+    #[validate(custom = "validate_conditions")]
+    pub conditions: Option<Vec<RoutingCondition>>,
+    #[validate(length(min = 1, message = "A rule needs at least one action"))]
+    pub actions: Option<Vec<RoutingAction>>,
+}
+
+/// Every rule of the company, most important first. Priorities are reassigned
+/// to follow this order.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ReorderRoutingRulesRequest {
+    #[validate(length(min = 1), custom = "validate_unique_ids")]
+    pub rule_ids: Vec<Uuid>,
+}
+
+/// Company whose routing setup a request manages. Only super admins have to
+/// name it; everyone else is limited to their own company.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingScopeQuery {
+    pub company_id: Option<Uuid>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct CreateRoutingQueueRequest {
     #[validate(length(min = 2, max = 100))]
     pub name: String,
+    #[validate(length(max = 1000))]
     pub description: Option<String>,
     pub strategy: QueueStrategy,
+    #[validate(range(min = 1))]
     pub max_wait_time: Option<u32>,
+    #[validate(range(min = 1))]
     pub max_queue_size: Option<u32>,
     pub overflow_action: OverflowAction,
+    #[validate(length(max = 255))]
+    pub overflow_target: Option<String>,
+    #[validate(custom = "validate_unique_ids")]
     pub agents: Vec<Uuid>,
 }
+
+/// Partial update of a routing queue; omitted fields are left alone.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateRoutingQueueRequest {
+    #[validate(length(min = 2, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+    pub strategy: Option<QueueStrategy>,
+    #[validate(range(min = 1))]
+    pub max_wait_time: Option<u32>,
+    #[validate(range(min = 1))]
+    pub max_queue_size: Option<u32>,
+    pub overflow_action: Option<OverflowAction>,
+    #[validate(length(max = 255))]
+    pub overflow_target: Option<String>,
+    #[validate(custom = "validate_unique_ids")]
+    pub agents: Option<Vec<Uuid>>,
+    pub is_active: Option<bool>,
+}
+
+fn validate_conditions(conditions: &[RoutingCondition]) -> std::result::Result<(), validator::ValidationError> {
+    for condition in conditions {
+        if let Err(message) = condition.check() {
+            let mut error = validator::ValidationError::new("invalid_condition");
+            error.message = Some(message.into());
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+fn validate_unique_ids(ids: &[Uuid]) -> std::result::Result<(), validator::ValidationError> {
+    let mut seen = std::collections::HashSet::new();
+    if ids.iter().all(|id| seen.insert(id)) {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("duplicate_id"))
+    }
+}
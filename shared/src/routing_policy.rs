@@ -0,0 +1,306 @@
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use validator::Validate;
+use crate::call::{Call, CallDirection};
+use crate::routing::PRIORITY_WEIGHT_SECS;
+
+/// How a company prioritises its calls and works out the skills they need.
+/// Stored in the company's settings under `routing_policy`; companies without
+/// one get `RoutingPolicy::default()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct RoutingPolicy {
+    /// Priority every call starts from
+    pub base_priority: u32,
+    /// Added to inbound calls
+    pub inbound_bonus: u32,
+    /// Metadata key holding the customer's tier
+    #[validate(length(min = 1, max = 100))]
+    pub tier_key: String,
+    /// Added per tier, matched ignoring case
+    pub tier_bonuses: BTreeMap<String, u32>,
+    /// Added per tag on the call, matched ignoring case
+    pub tag_bonuses: BTreeMap<String, u32>,
+    /// Added when the call's metadata has a key, or a key with a given value
+    #[validate]
+    pub metadata_bonuses: Vec<MetadataBonus>,
+    /// Metadata key holding a list of skills the call needs
+    #[validate(length(min = 1, max = 100))]
+    pub skills_key: String,
+    /// Further skills inferred from the call
+    #[validate]
+    pub skill_rules: Vec<SkillRule>,
+    /// Seconds of waiting worth one point of priority. Lower values let waiting
+    /// calls catch up with higher priority ones sooner.
+    #[validate(range(min = 1.0, max = 3600.0))]
+    pub aging_seconds_per_point: f64,
+    /// Highest priority a call can get. Bounds how long a low priority call can
+    /// be overtaken: at most `(max_priority - its priority) * aging_seconds_per_point`.
+    pub max_priority: Option<u32>,
+    /// Routing returning customers back to the agent they last spoke with
+    #[validate]
+    pub sticky_routing: StickyRouting,
+    /// When the company takes calls, as IVR conditions see it
+    #[validate]
+    pub business_hours: BusinessHours,
+}
+
+impl Default for RoutingPolicy {
+    fn default() -> Self {
+        Self {
+            base_priority: 100,
+            inbound_bonus: 25,
+            tier_key: "customer_tier".to_string(),
+            tier_bonuses: BTreeMap::from([("premium".to_string(), 50), ("vip".to_string(), 50)]),
+            tag_bonuses: BTreeMap::from([
+                ("escalation".to_string(), 100),
+                ("high_priority".to_string(), 50),
+                ("urgent".to_string(), 75),
+            ]),
+            metadata_bonuses: Vec::new(),
+            skills_key: "required_skills".to_string(),
+            skill_rules: Vec::new(),
+            aging_seconds_per_point: PRIORITY_WEIGHT_SECS,
+            max_priority: None,
+            sticky_routing: StickyRouting::default(),
+            business_hours: BusinessHours::default(),
+        }
+    }
+}
+
+/// Preferred agent routing: a returning customer's call first goes to the agent
+/// who last handled one of their calls. Should that agent be busy the call waits
+/// for them up to `wait_window` seconds before the usual agent selection runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct StickyRouting {
+    pub enabled: bool,
+    /// Seconds a call may wait for its preferred agent; 0 only takes them when free
+    #[validate(range(max = 600))]
+    pub wait_window: u32,
+    /// How far back, in days, calls count as history
+    #[validate(range(min = 1, max = 365))]
+    pub lookback_days: u32,
+}
+
+impl Default for StickyRouting {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            wait_window: 30,
+            lookback_days: 30,
+        }
+    }
+}
+
+impl StickyRouting {
+    /// Oldest call, as of `now`, that still counts as history
+    pub fn history_since(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(self.lookback_days as i64)
+    }
+
+    /// Until when a call that came in at `wait_start` waits for its preferred
+    /// agent; `None` once that is over or there is no window at all.
+    pub fn wait_until(&self, wait_start: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let until = wait_start + Duration::seconds(self.wait_window as i64);
+        (until > now).then_some(until)
+    }
+}
+
+/// Opening hours, the same on every open day, in the company's local time.
+/// Hours closing before they open run past midnight; opening and closing at
+/// the same time means open all day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct BusinessHours {
+    /// Minutes local time is ahead of UTC
+    #[validate(range(min = -840, max = 840))]
+    pub utc_offset_minutes: i32,
+    /// Days the company opens on, e.g. "Mon"
+    pub days: Vec<Weekday>,
+    pub opens_at: NaiveTime,
+    pub closes_at: NaiveTime,
+}
+
+impl Default for BusinessHours {
+    fn default() -> Self {
+        Self {
+            utc_offset_minutes: 0,
+            days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            opens_at: NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+            closes_at: NaiveTime::from_hms_opt(17, 0, 0).unwrap_or_default(),
+        }
+    }
+}
+
+impl BusinessHours {
+    /// Whether the company is open at `now`. Past midnight, overnight hours
+    /// belong to the day they opened on.
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        let local = now.naive_utc() + Duration::minutes(self.utc_offset_minutes as i64);
+        let (day, time) = (local.weekday(), local.time());
+        let open_on = |day: Weekday| self.days.contains(&day);
+
+        if self.opens_at == self.closes_at {
+            open_on(day)
+        } else if self.opens_at < self.closes_at {
+            open_on(day) && time >= self.opens_at && time < self.closes_at
+        } else {
+            (open_on(day) && time >= self.opens_at) || (open_on(day.pred()) && time < self.closes_at)
+        }
+    }
+
+    /// The hours as callers are told them, e.g. "Monday to Friday, 09:00 to 17:00"
+    pub fn describe(&self) -> String {
+        let mut days = self.days.clone();
+        days.sort_by_key(|day| day.num_days_from_monday());
+        days.dedup();
+
+        let consecutive = days
+            .windows(2)
+            .all(|pair| pair[1].num_days_from_monday() == pair[0].num_days_from_monday() + 1);
+        let days = match days.as_slice() {
+            [] => return "closed".to_string(),
+            [day] => day_name(*day).to_string(),
+            all if all.len() == 7 => "every day".to_string(),
+            [first, .., last] if consecutive && days.len() > 2 => {
+                format!("{} to {}", day_name(*first), day_name(*last))
+            }
+            [rest @ .., last] => {
+                let rest: Vec<&str> = rest.iter().map(|day| day_name(*day)).collect();
+                format!("{} and {}", rest.join(", "), day_name(*last))
+            }
+        };
+
+        if self.opens_at == self.closes_at {
+            format!("{}, all day", days)
+        } else {
+            format!("{}, {} to {}", days, self.opens_at.format("%H:%M"), self.closes_at.format("%H:%M"))
+        }
+    }
+}
+
+fn day_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// Priority added for a metadata key, or only for one value of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct MetadataBonus {
+    #[validate(length(min = 1, max = 100))]
+    pub key: String,
+    /// Without a value any non-null value matches
+    #[serde(default)]
+    pub value: Option<Value>,
+    pub bonus: u32,
+}
+
+/// Skill a call needs when one of its details contains `pattern`, ignoring case.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct SkillRule {
+    pub source: SkillSource,
+    #[validate(length(min = 1, max = 255))]
+    pub pattern: String,
+    #[validate(length(min = 1, max = 100))]
+    pub skill: String,
+}
+
+/// Call detail a skill rule looks at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SkillSource {
+    CustomerEmail,
+    CustomerName,
+    CalledNumber,
+    /// Matches a tag equal to the pattern
+    Tag,
+    /// A string metadata value
+    Metadata(String),
+}
+
+impl RoutingPolicy {
+    /// Priority of a call before routing rules run, capped at `max_priority`.
+    pub fn priority(&self, call: &Call) -> u32 {
+        let mut priority = self.base_priority;
+
+        if matches!(call.direction, CallDirection::Inbound) {
+            priority += self.inbound_bonus;
+        }
+        if let Some(tier) = call.metadata.get(&self.tier_key).and_then(Value::as_str) {
+            priority += bonus_for(&self.tier_bonuses, tier);
+        }
+        for tag in &call.tags {
+            priority += bonus_for(&self.tag_bonuses, tag);
+        }
+        for bonus in &self.metadata_bonuses {
+            let matched = match (call.metadata.get(&bonus.key), &bonus.value) {
+                (None | Some(Value::Null), _) => false,
+                (Some(_), None) => true,
+                (Some(actual), Some(expected)) => actual == expected,
+            };
+            if matched {
+                priority += bonus.bonus;
+            }
+        }
+
+        self.cap(priority)
+    }
+
+    /// Keep a priority, e.g. one set by a routing rule, within `max_priority`.
+    pub fn cap(&self, priority: u32) -> u32 {
+        self.max_priority.map_or(priority, |max| priority.min(max))
+    }
+
+    /// Skills a call needs: those listed in its metadata plus those the skill
+    /// rules infer, without duplicates.
+    pub fn required_skills(&self, call: &Call) -> Vec<String> {
+        let mut skills: Vec<String> = Vec::new();
+        let mut add = |skill: &str| {
+            if !skills.iter().any(|known| known.eq_ignore_ascii_case(skill)) {
+                skills.push(skill.to_string());
+            }
+        };
+
+        if let Some(listed) = call.metadata.get(&self.skills_key).and_then(Value::as_array) {
+            listed.iter().filter_map(Value::as_str).for_each(&mut add);
+        }
+        for rule in &self.skill_rules {
+            if rule.matches(call) {
+                add(&rule.skill);
+            }
+        }
+
+        skills
+    }
+}
+
+impl SkillRule {
+    fn matches(&self, call: &Call) -> bool {
+        let pattern = self.pattern.to_lowercase();
+        let contains = |value: Option<&str>| value.is_some_and(|value| value.to_lowercase().contains(&pattern));
+
+        match &self.source {
+            SkillSource::CustomerEmail => contains(call.customer_email.as_deref()),
+            SkillSource::CustomerName => contains(call.customer_name.as_deref()),
+            SkillSource::CalledNumber => contains(call.called_number.as_deref()),
+            SkillSource::Tag => call.tags.iter().any(|tag| tag.eq_ignore_ascii_case(&self.pattern)),
+            SkillSource::Metadata(key) => contains(call.metadata.get(key).and_then(Value::as_str)),
+        }
+    }
+}
+
+fn bonus_for(bonuses: &BTreeMap<String, u32>, name: &str) -> u32 {
+    bonuses
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map_or(0, |(_, bonus)| *bonus)
+}
@@ -0,0 +1,389 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+use validator::Validate;
+use crate::call::{Call, CreateCallRequest};
+use crate::routing::{
+    AgentStatus, CreateRoutingRuleRequest, RoutingAction, RoutingActionType, RoutingCondition, RoutingField,
+    RoutingOperator, RoutingRule,
+};
+
+/// Facts about a call that routing rule conditions are tested against. Actions
+/// that change the call (priority, tags, metadata) update it as rules apply, so
+/// later rules see their effect.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingContext {
+    pub call_id: Uuid,
+    pub company_id: Uuid,
+    pub customer_phone: Option<String>,
+    pub customer_email: Option<String>,
+    pub customer_name: Option<String>,
+    pub call_time: DateTime<Utc>,
+    pub call_duration: Option<u64>,
+    /// Skills offered by the company's available agents.
+    pub agent_skills: Vec<String>,
+    /// Status of the agent already assigned to the call, if any.
+    pub agent_status: Option<AgentStatus>,
+    pub queue_length: u32,
+    pub priority: u32,
+    pub tags: Vec<String>,
+    pub metadata: Value,
+}
+
+impl RoutingContext {
+    /// Context for a call, before anything is known about agents or queues.
+    pub fn from_call(call: &Call, priority: u32) -> Self {
+        Self {
+            call_id: call.id,
+            company_id: call.company_id,
+            customer_phone: call.caller_number.clone(),
+            customer_email: call.customer_email.clone(),
+            customer_name: call.customer_name.clone(),
+            call_time: call.created_at,
+            call_duration: call.duration,
+            agent_skills: Vec::new(),
+            agent_status: None,
+            queue_length: 0,
+            priority,
+            tags: call.tags.clone(),
+            metadata: call.metadata.clone(),
+        }
+    }
+
+    /// Value a condition on `field` is compared with. `CallTime` is the UTC time
+    /// of day as "HH:MM"; a missing `Custom` metadata key is null.
+    pub fn field_value(&self, field: &RoutingField) -> Value {
+        match field {
+            RoutingField::CustomerPhone => json!(self.customer_phone),
+            RoutingField::CustomerEmail => json!(self.customer_email),
+            RoutingField::CustomerName => json!(self.customer_name),
+            RoutingField::CallTime => json!(self.call_time.format("%H:%M").to_string()),
+            RoutingField::CallDuration => json!(self.call_duration),
+            RoutingField::AgentSkills => json!(self.agent_skills),
+            RoutingField::AgentStatus => json!(self.agent_status.map(|status| status.to_string())),
+            RoutingField::QueueLength => json!(self.queue_length),
+            RoutingField::CallPriority => json!(self.priority),
+            RoutingField::Custom(key) => self.metadata.get(key).cloned().unwrap_or(Value::Null),
+        }
+    }
+}
+
+/// Where the first matching routing action sends the call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RoutingDecision {
+    Agent(Uuid),
+    /// Least busy agent with the department's skill.
+    Department(String),
+    Queue(Uuid),
+    IVR(Uuid),
+    /// Voicemail box, or the company's default one.
+    Voicemail(Option<Uuid>),
+    External(String),
+}
+
+/// Webhook a `SendWebhook` action asked for. Sending it is up to the caller.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookRequest {
+    pub rule_id: Uuid,
+    pub url: String,
+    pub payload: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionTrace {
+    pub field: RoutingField,
+    pub operator: RoutingOperator,
+    pub expected: Value,
+    pub actual: Value,
+    pub matched: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionTrace {
+    pub action_type: RoutingActionType,
+    pub applied: bool,
+    pub detail: String,
+}
+
+/// Why a rule did or did not apply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleTrace {
+    pub rule_id: Uuid,
+    pub name: String,
+    pub priority: u32,
+    pub matched: bool,
+    pub conditions: Vec<ConditionTrace>,
+    pub actions: Vec<ActionTrace>,
+}
+
+/// Outcome of running a company's rules against one call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleEvaluation {
+    /// The call after every applied action.
+    pub context: RoutingContext,
+    /// `None` when no matching rule routes the call; default routing applies.
+    pub decision: Option<RoutingDecision>,
+    pub webhooks: Vec<WebhookRequest>,
+    pub applied_rules: Vec<Uuid>,
+    /// Rules in the order they were evaluated.
+    pub trace: Vec<RuleTrace>,
+}
+
+/// Sample call to explain routing for. Without `rules` the company's saved
+/// rules are used; with them, unsaved rules can be tried out first.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RuleDryRunRequest {
+    #[validate]
+    pub call: CreateCallRequest,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When the call comes in; defaults to now.
+    pub call_time: Option<DateTime<Utc>>,
+    #[validate]
+    pub rules: Option<Vec<CreateRoutingRuleRequest>>,
+}
+
+/// Run active rules against a call, highest priority first. A rule matches when
+/// all its conditions hold (a rule without conditions always matches), and its
+/// actions then run in order. The first routing action decides where the call
+/// goes and ends evaluation once its rule is done.
+pub fn evaluate_rules(rules: &[RoutingRule], mut context: RoutingContext) -> RuleEvaluation {
+    let mut active: Vec<_> = rules.iter().filter(|rule| rule.is_active).collect();
+    active.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then(a.created_at.cmp(&b.created_at))
+            .then(a.id.cmp(&b.id))
+    });
+
+    let mut decision = None;
+    let mut webhooks = Vec::new();
+    let mut applied_rules = Vec::new();
+    let mut trace = Vec::new();
+
+    for rule in active {
+        let conditions: Vec<_> = rule
+            .conditions
+            .iter()
+            .map(|condition| trace_condition(condition, &context))
+            .collect();
+        let matched = conditions.iter().all(|condition| condition.matched);
+
+        let mut actions = Vec::new();
+        if matched {
+            applied_rules.push(rule.id);
+            for action in &rule.actions {
+                let outcome = apply_action(rule, action, &mut context, &mut decision, &mut webhooks);
+                actions.push(ActionTrace {
+                    action_type: action.action_type.clone(),
+                    applied: outcome.is_ok(),
+                    detail: outcome.unwrap_or_else(|e| e),
+                });
+            }
+        }
+
+        trace.push(RuleTrace {
+            rule_id: rule.id,
+            name: rule.name.clone(),
+            priority: rule.priority,
+            matched,
+            conditions,
+            actions,
+        });
+
+        if decision.is_some() {
+            break;
+        }
+    }
+
+    RuleEvaluation {
+        context,
+        decision,
+        webhooks,
+        applied_rules,
+        trace,
+    }
+}
+
+fn trace_condition(condition: &RoutingCondition, context: &RoutingContext) -> ConditionTrace {
+    let actual = context.field_value(&condition.field);
+    ConditionTrace {
+        field: condition.field.clone(),
+        operator: condition.operator.clone(),
+        matched: compare(&actual, &condition.operator, &condition.value),
+        expected: condition.value.clone(),
+        actual,
+    }
+}
+
+/// Whether `condition` holds for the call.
+pub fn condition_matches(condition: &RoutingCondition, context: &RoutingContext) -> bool {
+    trace_condition(condition, context).matched
+}
+
+/// Apply `operator` to a field value and the condition's value. Strings compare
+/// ignoring case, numbers numerically; comparing values of different types never
+/// matches, so the `Not*` operators match them.
+pub fn compare(actual: &Value, operator: &RoutingOperator, expected: &Value) -> bool {
+    match operator {
+        RoutingOperator::Equals => values_equal(actual, expected),
+        RoutingOperator::NotEquals => !values_equal(actual, expected),
+        RoutingOperator::Contains => contains(actual, expected),
+        RoutingOperator::NotContains => !contains(actual, expected),
+        RoutingOperator::StartsWith => {
+            string_pair(actual, expected).is_some_and(|(actual, expected)| actual.starts_with(&expected))
+        }
+        RoutingOperator::EndsWith => {
+            string_pair(actual, expected).is_some_and(|(actual, expected)| actual.ends_with(&expected))
+        }
+        RoutingOperator::GreaterThan => order(actual, expected).is_some_and(|o| o.is_gt()),
+        RoutingOperator::LessThan => order(actual, expected).is_some_and(|o| o.is_lt()),
+        RoutingOperator::GreaterThanOrEqual => order(actual, expected).is_some_and(|o| o.is_ge()),
+        RoutingOperator::LessThanOrEqual => order(actual, expected).is_some_and(|o| o.is_le()),
+        RoutingOperator::In => is_in(actual, expected),
+        RoutingOperator::NotIn => !is_in(actual, expected),
+        RoutingOperator::IsNull => is_null(actual),
+        RoutingOperator::IsNotNull => !is_null(actual),
+    }
+}
+
+fn values_equal(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::String(a), Value::String(b)) => a.eq_ignore_ascii_case(b),
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        _ => actual == expected,
+    }
+}
+
+/// Substring of a string field, or element of a list field such as `AgentSkills`.
+fn contains(actual: &Value, expected: &Value) -> bool {
+    match actual {
+        Value::Array(items) => items.iter().any(|item| values_equal(item, expected)),
+        _ => string_pair(actual, expected).is_some_and(|(actual, expected)| actual.contains(&expected)),
+    }
+}
+
+/// A scalar field in the listed values, or a list field sharing one with them.
+fn is_in(actual: &Value, expected: &Value) -> bool {
+    let Value::Array(options) = expected else {
+        return false;
+    };
+    match actual {
+        Value::Array(items) => items.iter().any(|item| options.iter().any(|o| values_equal(item, o))),
+        _ => options.iter().any(|option| values_equal(actual, option)),
+    }
+}
+
+fn is_null(actual: &Value) -> bool {
+    match actual {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        _ => false,
+    }
+}
+
+fn string_pair(actual: &Value, expected: &Value) -> Option<(String, String)> {
+    Some((actual.as_str()?.to_lowercase(), expected.as_str()?.to_lowercase()))
+}
+
+/// Numbers order numerically, strings lexically, which also orders "HH:MM" times.
+fn order(actual: &Value, expected: &Value) -> Option<std::cmp::Ordering> {
+    match (actual, expected) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.as_str().cmp(b.as_str())),
+        _ => None,
+    }
+}
+
+/// Run one action, describing what it did or why it could not.
+fn apply_action(
+    rule: &RoutingRule,
+    action: &RoutingAction,
+    context: &mut RoutingContext,
+    decision: &mut Option<RoutingDecision>,
+    webhooks: &mut Vec<WebhookRequest>,
+) -> std::result::Result<String, String> {
+    let params = &action.parameters;
+
+    let target = match action.action_type {
+        RoutingActionType::SetPriority => {
+            let priority = uint_param(params, "priority")?;
+            context.priority = priority;
+            return Ok(format!("Priority set to {}", priority));
+        }
+        RoutingActionType::AddTag => {
+            let tag = str_param(params, "tag")?;
+            if !context.tags.iter().any(|own| own == tag) {
+                context.tags.push(tag.to_string());
+            }
+            return Ok(format!("Tagged {}", tag));
+        }
+        RoutingActionType::SetMetadata => {
+            let key = str_param(params, "key")?;
+            let value = params.get("value").cloned().unwrap_or(Value::Null);
+            if !context.metadata.is_object() {
+                context.metadata = json!({});
+            }
+            context.metadata[key] = value;
+            return Ok(format!("Metadata {} set", key));
+        }
+        RoutingActionType::SendWebhook => {
+            let url = str_param(params, "url")?;
+            webhooks.push(WebhookRequest {
+                rule_id: rule.id,
+                url: url.to_string(),
+                payload: json!({
+                    "event": "routing_rule_matched",
+                    "rule_id": rule.id,
+                    "rule_name": rule.name,
+                    "call_id": context.call_id,
+                    "company_id": context.company_id,
+                    "priority": context.priority,
+                    "tags": context.tags,
+                }),
+            });
+            return Ok(format!("Webhook to {}", url));
+        }
+        RoutingActionType::RouteToAgent => RoutingDecision::Agent(uuid_param(params, "agent_id")?),
+        RoutingActionType::RouteToDepartment => RoutingDecision::Department(str_param(params, "department")?.to_string()),
+        RoutingActionType::RouteToQueue => RoutingDecision::Queue(uuid_param(params, "queue_id")?),
+        RoutingActionType::RouteToIVR => RoutingDecision::IVR(uuid_param(params, "ivr_flow_id")?),
+        RoutingActionType::RouteToVoicemail => RoutingDecision::Voicemail(match params.get("mailbox_id") {
+            None | Some(Value::Null) => None,
+            Some(_) => Some(uuid_param(params, "mailbox_id")?),
+        }),
+        RoutingActionType::RouteToExternal => RoutingDecision::External(str_param(params, "number")?.to_string()),
+    };
+
+    if let Some(existing) = decision {
+        return Err(format!("Skipped, call already routed to {:?}", existing));
+    }
+    let detail = format!("Routed to {:?}", target);
+    *decision = Some(target);
+    Ok(detail)
+}
+
+fn str_param<'a>(params: &'a Value, key: &str) -> std::result::Result<&'a str, String> {
+    params
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| format!("Missing string parameter {}", key))
+}
+
+fn uint_param(params: &Value, key: &str) -> std::result::Result<u32, String> {
+    params
+        .get(key)
+        .and_then(Value::as_u64)
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or_else(|| format!("Missing non-negative integer parameter {}", key))
+}
+
+fn uuid_param(params: &Value, key: &str) -> std::result::Result<Uuid, String> {
+    let value = str_param(params, key)?;
+    Uuid::parse_str(value).map_err(|_| format!("Parameter {} is not a UUID: {}", key, value))
+}
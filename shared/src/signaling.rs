@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use crate::auth::ParticipantRole;
+use crate::error::CallDockerError;
+use crate::ivr::IVROutcome;
+use crate::ivr_engine::IVRPrompt;
+use crate::routing::{AgentStatus, OfferOutcome};
+use crate::types::{SignalType, WebRTCSignal};
+
+/// Signaling protocol version spoken by this build.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Every protocol version this build can speak, oldest first.
+pub const SUPPORTED_VERSIONS: &[u16] = &[1];
+
+/// Pick the protocol version for a new connection. Clients that do not ask for
+/// one get the current version.
+pub fn negotiate_version(requested: Option<u16>) -> Result<u16, ProtocolError> {
+    match requested {
+        None => Ok(PROTOCOL_VERSION),
+        Some(version) if SUPPORTED_VERSIONS.contains(&version) => Ok(version),
+        Some(version) => Err(ProtocolError::new(
+            ErrorCode::UnsupportedVersion,
+            format!("Unsupported protocol version {}, supported: {:?}", version, SUPPORTED_VERSIONS),
+        )),
+    }
+}
+
+/// Media signaling exchanged between the participants of a call, over either the
+/// WebSocket or the REST endpoints.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase", deny_unknown_fields)]
+pub enum Signal {
+    Offer {
+        sdp: String,
+    },
+    Answer {
+        sdp: String,
+    },
+    #[serde(alias = "ice-candidate")]
+    IceCandidate {
+        candidate: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sdp_mid: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sdp_m_line_index: Option<u16>,
+    },
+    Hangup {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    Busy,
+    Hold {
+        held: bool,
+    },
+    Mute {
+        muted: bool,
+    },
+    Dtmf {
+        digits: String,
+    },
+}
+
+impl Signal {
+    pub fn signal_type(&self) -> SignalType {
+        match self {
+            Signal::Offer { .. } => SignalType::Offer,
+            Signal::Answer { .. } => SignalType::Answer,
+            Signal::IceCandidate { .. } => SignalType::IceCandidate,
+            Signal::Hangup { .. } => SignalType::Hangup,
+            Signal::Busy => SignalType::Busy,
+            Signal::Hold { .. } => SignalType::Hold,
+            Signal::Mute { .. } => SignalType::Mute,
+            Signal::Dtmf { .. } => SignalType::Dtmf,
+        }
+    }
+
+    /// Check the values serde cannot: non-empty SDP and candidates, and DTMF digits.
+    pub fn validate(&self) -> Result<(), ProtocolError> {
+        let invalid = |message: &str| Err(ProtocolError::new(ErrorCode::InvalidMessage, message.to_string()));
+
+        match self {
+            Signal::Offer { sdp } | Signal::Answer { sdp } if sdp.trim().is_empty() => invalid("sdp must not be empty"),
+            Signal::IceCandidate { candidate, .. } if candidate.trim().is_empty() => {
+                invalid("candidate must not be empty")
+            }
+            Signal::Dtmf { digits }
+                if digits.is_empty() || !digits.chars().all(|c| matches!(c, '0'..='9' | '*' | '#' | 'A'..='D')) =>
+            {
+                invalid("digits must be one or more of 0-9, *, # and A-D")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Messages a client sends over the call WebSocket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Ping,
+    #[serde(untagged)]
+    Signal(Signal),
+}
+
+impl ClientMessage {
+    /// Parse and validate a text frame, rejecting anything outside the schema.
+    pub fn parse(text: &str) -> Result<Self, ProtocolError> {
+        let message: ClientMessage = serde_json::from_str(text)
+            .map_err(|e| ProtocolError::new(ErrorCode::InvalidMessage, format!("Invalid message: {}", e)))?;
+
+        if let ClientMessage::Signal(signal) = &message {
+            signal.validate()?;
+        }
+
+        Ok(message)
+    }
+}
+
+/// A participant in a call room.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParticipantInfo {
+    pub session_id: String,
+    pub user_id: Uuid,
+    pub role: ParticipantRole,
+}
+
+/// A signal forwarded from another participant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayedSignal {
+    pub from: String,
+    #[serde(flatten)]
+    pub signal: Signal,
+}
+
+/// Messages the server sends over the call WebSocket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum ServerMessage {
+    Connected {
+        version: u16,
+        session_id: String,
+        call_id: Uuid,
+        user_id: Uuid,
+        role: ParticipantRole,
+    },
+    RoomJoined {
+        room_id: Uuid,
+        users: Vec<ParticipantInfo>,
+    },
+    UserJoined {
+        #[serde(flatten)]
+        participant: ParticipantInfo,
+        room_id: Uuid,
+        timestamp: i64,
+    },
+    UserLeft {
+        #[serde(flatten)]
+        participant: ParticipantInfo,
+        room_id: Uuid,
+        timestamp: i64,
+    },
+    Pong {
+        timestamp: i64,
+    },
+    /// The caller's place in the queue while the call waits for an agent
+    QueuePosition {
+        call_id: Uuid,
+        position: u32,
+        queue_length: u32,
+        estimated_wait_time: Option<u32>,
+    },
+    /// Welcome on an agent's own socket
+    AgentConnected {
+        version: u16,
+        session_id: String,
+        agent_id: Uuid,
+    },
+    /// A call reserved for the agent; accept or reject it before `expires_at`
+    CallOffered {
+        call_id: Uuid,
+        customer_name: Option<String>,
+        priority: u32,
+        expires_at: DateTime<Utc>,
+    },
+    /// An offer the agent can no longer accept
+    OfferWithdrawn {
+        call_id: Uuid,
+        reason: OfferOutcome,
+    },
+    /// The agent's status was changed for them
+    AgentStatusChanged {
+        agent_id: Uuid,
+        status: AgentStatus,
+        reason: String,
+    },
+    /// Something the IVR flow plays to the caller
+    IvrPrompt {
+        call_id: Uuid,
+        prompt: IVRPrompt,
+    },
+    /// The IVR flow is over and the call moves on
+    IvrEnded {
+        call_id: Uuid,
+        outcome: IVROutcome,
+    },
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+    #[serde(untagged)]
+    Signal(RelayedSignal),
+}
+
+impl ServerMessage {
+    pub fn to_text(&self) -> String {
+        serde_json::to_string(self).expect("server messages always serialize")
+    }
+}
+
+/// A message for the sockets of one agent, published so that whichever instance
+/// holds them can deliver it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentUpdate {
+    pub agent_id: Uuid,
+    pub message: ServerMessage,
+}
+
+/// A message for the sockets in one call's room, published so that whichever
+/// instance holds them can deliver it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallerUpdate {
+    pub call_id: Uuid,
+    pub message: ServerMessage,
+}
+
+/// Machine-readable reason carried by `ServerMessage::Error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidMessage,
+    UnsupportedVersion,
+    SignalTypeMismatch,
+    Internal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtocolError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ProtocolError {
+    pub fn new(code: ErrorCode, message: String) -> Self {
+        Self { code, message }
+    }
+}
+
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<ProtocolError> for ServerMessage {
+    fn from(err: ProtocolError) -> Self {
+        ServerMessage::Error {
+            code: err.code,
+            message: err.message,
+        }
+    }
+}
+
+impl From<ProtocolError> for CallDockerError {
+    fn from(err: ProtocolError) -> Self {
+        CallDockerError::Validation(err.message)
+    }
+}
+
+impl WebRTCSignal {
+    /// Parse `data` as the typed signal named by `signal_type`, so REST requests go
+    /// through the same schema as WebSocket frames.
+    pub fn signal(&self) -> Result<Signal, ProtocolError> {
+        let mut data = match &self.data {
+            serde_json::Value::Object(map) => map.clone(),
+            serde_json::Value::Null => serde_json::Map::new(),
+            _ => {
+                return Err(ProtocolError::new(
+                    ErrorCode::InvalidMessage,
+                    "data must be an object".to_string(),
+                ))
+            }
+        };
+
+        let expected = self.signal_type.to_string();
+        match data.get("type").and_then(|t| t.as_str()) {
+            Some(tag) if tag != expected => {
+                return Err(ProtocolError::new(
+                    ErrorCode::SignalTypeMismatch,
+                    format!("data type {} does not match signal_type {}", tag, expected),
+                ))
+            }
+            _ => {
+                data.insert("type".to_string(), serde_json::Value::String(expected));
+            }
+        }
+
+        let signal: Signal = serde_json::from_value(serde_json::Value::Object(data))
+            .map_err(|e| ProtocolError::new(ErrorCode::InvalidMessage, format!("Invalid {} signal: {}", self.signal_type, e)))?;
+        signal.validate()?;
+
+        Ok(signal)
+    }
+}
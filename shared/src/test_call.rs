@@ -0,0 +1,217 @@
+#[cfg(test)]
+mod tests {
+    use crate::call::{
+        Call, CallDirection, CallEventType, CallListQuery, CallStatus, EndCallRequest, UpdateCallRequest,
+    };
+    use crate::test_support::test_call;
+    use crate::CallDockerError;
+    use chrono::{Duration, Utc};
+    use validator::Validate;
+
+    const ALL_STATUSES: [CallStatus; 6] = [
+        CallStatus::Ringing,
+        CallStatus::Connected,
+        CallStatus::Ended,
+        CallStatus::Missed,
+        CallStatus::Busy,
+        CallStatus::Failed,
+    ];
+
+    fn call_with_status(status: CallStatus) -> Call {
+        Call { status, ..test_call() }
+    }
+
+    fn is_allowed(from: &CallStatus, to: &CallStatus) -> bool {
+        matches!(
+            (from, to),
+            (CallStatus::Ringing, _)
+                | (CallStatus::Connected, CallStatus::Ended)
+                | (CallStatus::Connected, CallStatus::Failed)
+        )
+    }
+
+    #[test]
+    fn test_every_transition_edge() {
+        for from in ALL_STATUSES.iter() {
+            for to in ALL_STATUSES.iter() {
+                let mut call = call_with_status(from.clone());
+                let result = call.transition_to(to.clone(), Utc::now());
+
+                if is_allowed(from, to) {
+                    assert!(result.is_ok(), "{} -> {} should be allowed", from, to);
+                    assert_eq!(&call.status, to);
+                } else {
+                    assert!(
+                        matches!(result, Err(CallDockerError::InvalidTransition(_))),
+                        "{} -> {} should be rejected",
+                        from,
+                        to
+                    );
+                    assert_eq!(&call.status, from, "rejected transition must not mutate the call");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_terminal_statuses_accept_nothing() {
+        for status in ALL_STATUSES.iter().filter(|s| s.is_terminal()) {
+            assert!(ALL_STATUSES.iter().all(|next| !status.can_transition_to(next)));
+        }
+        assert!(!CallStatus::Ringing.is_terminal());
+        assert!(!CallStatus::Connected.is_terminal());
+    }
+
+    #[test]
+    fn test_ended_call_cannot_ring_again() {
+        let mut call = call_with_status(CallStatus::Ended);
+        let result = call.transition_to(CallStatus::Ringing, Utc::now());
+        assert!(matches!(result, Err(CallDockerError::InvalidTransition(_))));
+    }
+
+    #[test]
+    fn test_answer_stamps_answered_at() {
+        let mut call = call_with_status(CallStatus::Ringing);
+        let at = Utc::now();
+
+        let event = call.transition_to(CallStatus::Connected, at).unwrap();
+
+        assert!(matches!(event, CallEventType::CallAnswered));
+        assert_eq!(call.answered_at, Some(at));
+        assert_eq!(call.ended_at, None);
+        assert_eq!(call.duration, None);
+    }
+
+    #[test]
+    fn test_hangup_computes_talk_time() {
+        let mut call = call_with_status(CallStatus::Ringing);
+        let answered_at = Utc::now();
+        call.transition_to(CallStatus::Connected, answered_at).unwrap();
+
+        let ended_at = answered_at + Duration::seconds(95);
+        let event = call.transition_to(CallStatus::Ended, ended_at).unwrap();
+
+        assert!(matches!(event, CallEventType::CallEnded));
+        assert_eq!(call.ended_at, Some(ended_at));
+        assert_eq!(call.duration, Some(95));
+        assert_eq!(call.updated_at, ended_at);
+    }
+
+    #[test]
+    fn test_unanswered_call_has_zero_duration() {
+        for status in [CallStatus::Missed, CallStatus::Busy, CallStatus::Failed, CallStatus::Ended] {
+            let mut call = call_with_status(CallStatus::Ringing);
+            let at = Utc::now();
+
+            let event = call.transition_to(status, at).unwrap();
+
+            assert!(matches!(event, CallEventType::CallEnded));
+            assert_eq!(call.answered_at, None);
+            assert_eq!(call.ended_at, Some(at));
+            assert_eq!(call.duration, Some(0));
+        }
+    }
+
+    #[test]
+    fn test_reoffer_keeps_call_ringing() {
+        let mut call = call_with_status(CallStatus::Ringing);
+
+        let event = call.transition_to(CallStatus::Ringing, Utc::now()).unwrap();
+
+        assert!(matches!(event, CallEventType::CallRinging));
+        assert_eq!(call.status, CallStatus::Ringing);
+        assert_eq!(call.answered_at, None);
+        assert_eq!(call.ended_at, None);
+    }
+
+    #[test]
+    fn test_status_round_trips_through_display() {
+        for status in ALL_STATUSES.iter() {
+            assert_eq!(&status.to_string().parse::<CallStatus>().unwrap(), status);
+        }
+        assert!("on_hold".parse::<CallStatus>().is_err());
+    }
+
+    #[test]
+    fn test_list_query_takes_stored_names() {
+        let query: CallListQuery =
+            serde_json::from_value(serde_json::json!({ "status": "ringing", "direction": "outbound" })).unwrap();
+        assert_eq!(query.status, Some(CallStatus::Ringing));
+        assert!(matches!(query.direction, Some(CallDirection::Outbound)));
+
+        // The variant names still work
+        let query: CallListQuery =
+            serde_json::from_value(serde_json::json!({ "status": "Missed", "direction": "Inbound" })).unwrap();
+        assert_eq!(query.status, Some(CallStatus::Missed));
+        assert!(matches!(query.direction, Some(CallDirection::Inbound)));
+
+        let query: CallListQuery = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(query.status.is_none() && query.direction.is_none());
+        assert!(serde_json::from_value::<CallListQuery>(serde_json::json!({ "status": "on_hold" })).is_err());
+    }
+
+    #[test]
+    fn test_wrap_up_tag_validation() {
+        let valid = EndCallRequest {
+            disposition_code: Some("resolved".to_string()),
+            notes: Some("Customer reset their password".to_string()),
+            tags: vec!["billing".to_string()],
+        };
+        assert!(valid.validate().is_ok());
+        assert!(EndCallRequest::default().validate().is_ok());
+
+        let blank_tag = EndCallRequest {
+            tags: vec!["  ".to_string()],
+            ..Default::default()
+        };
+        assert!(blank_tag.validate().is_err());
+
+        let too_many = EndCallRequest {
+            tags: (0..21).map(|i| format!("tag-{}", i)).collect(),
+            ..Default::default()
+        };
+        assert!(too_many.validate().is_err());
+    }
+
+    #[test]
+    fn test_update_metadata_must_be_object() {
+        let update = |metadata| UpdateCallRequest {
+            tags: None,
+            notes: None,
+            metadata: Some(metadata),
+        };
+
+        assert!(update(serde_json::json!({"priority": "high"})).validate().is_ok());
+        assert!(update(serde_json::json!(["not", "an", "object"])).validate().is_err());
+    }
+
+    #[test]
+    fn test_event_type_round_trips_through_display() {
+        let event_types = [
+            CallEventType::CallInitiated,
+            CallEventType::CallRinging,
+            CallEventType::CallAnswered,
+            CallEventType::CallEnded,
+            CallEventType::CallTransferred,
+            CallEventType::CallEscalated,
+            CallEventType::CallRecordingStarted,
+            CallEventType::CallRecordingStopped,
+            CallEventType::AgentJoined,
+            CallEventType::AgentLeft,
+            CallEventType::CustomerJoined,
+            CallEventType::CustomerLeft,
+            CallEventType::CallOffered,
+            CallEventType::OfferAccepted,
+            CallEventType::OfferRejected,
+            CallEventType::OfferTimedOut,
+            CallEventType::IvrStarted,
+            CallEventType::IvrCompleted,
+        ];
+
+        for event_type in event_types.iter() {
+            let parsed: CallEventType = event_type.to_string().parse().unwrap();
+            assert_eq!(parsed.to_string(), event_type.to_string());
+        }
+        assert!("call_parked".parse::<CallEventType>().is_err());
+    }
+}
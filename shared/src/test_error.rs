@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use crate::error::{CallDockerError, ErrorResponse};
+    use actix_web::{body::to_bytes, http::StatusCode, ResponseError};
+    use sqlx::error::{DatabaseError, ErrorKind};
+    use std::borrow::Cow;
+    use validator::Validate;
+
+    /// A driver error with just a SQLSTATE
+    #[derive(Debug)]
+    struct SqlState(&'static str);
+
+    impl std::fmt::Display for SqlState {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "duplicate key value violates unique constraint \"companies_uuid_key\"")
+        }
+    }
+
+    impl std::error::Error for SqlState {}
+
+    impl DatabaseError for SqlState {
+        fn message(&self) -> &str {
+            "duplicate key value violates unique constraint \"companies_uuid_key\""
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    async fn body_of(err: &CallDockerError) -> ErrorResponse {
+        let response = err.error_response();
+        let bytes = to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_status_per_variant() {
+        let cases = [
+            (CallDockerError::Authentication("expired".into()), StatusCode::UNAUTHORIZED),
+            (CallDockerError::Authorization("other company".into()), StatusCode::FORBIDDEN),
+            (CallDockerError::CompanyNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::AgentNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::CallNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::RoutingRuleNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::QueueNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::OfferNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::IVRFlowNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::IVRTemplateNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::IVRSessionNotFound("x".into()), StatusCode::NOT_FOUND),
+            (CallDockerError::InvalidTransition("ended -> ringing".into()), StatusCode::CONFLICT),
+            (CallDockerError::Conflict("User already exists".into()), StatusCode::CONFLICT),
+            (CallDockerError::Validation("bad".into()), StatusCode::UNPROCESSABLE_ENTITY),
+            (CallDockerError::InvalidUUID("bad".into()), StatusCode::UNPROCESSABLE_ENTITY),
+            (CallDockerError::Database("boom".into()), StatusCode::INTERNAL_SERVER_ERROR),
+            (CallDockerError::Internal("boom".into()), StatusCode::INTERNAL_SERVER_ERROR),
+        ];
+
+        for (err, status) in cases {
+            assert_eq!(err.status_code(), status, "{:?}", err);
+            assert_eq!(err.error_response().status(), status, "{:?}", err);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_client_errors_keep_their_message() {
+        let err = CallDockerError::CallNotFound("42".into());
+        let body = body_of(&err).await;
+
+        assert!(!body.success);
+        assert_eq!(body.code, "call_not_found");
+        assert_eq!(body.error, "Call not found: 42");
+    }
+
+    #[actix_web::test]
+    async fn test_server_errors_hide_internal_details() {
+        let err: CallDockerError = sqlx::Error::Protocol("relation \"calls\" does not exist".into()).into();
+        let response = err.error_response();
+        let header = response.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+        let body = body_of(&err).await;
+
+        assert_eq!(body.code, "database_error");
+        assert_eq!(body.error, "Internal server error");
+        assert!(!body.error.contains("relation"));
+
+        // Each response carries its own request id, also sent as a header
+        let body_id = serde_json::from_slice::<ErrorResponse>(&to_bytes(response.into_body()).await.unwrap())
+            .unwrap()
+            .request_id;
+        assert_eq!(header, body_id.to_string());
+        assert_ne!(body.request_id, body_id);
+    }
+
+    #[test]
+    fn test_validation_errors_convert() {
+        #[derive(Validate)]
+        struct Input {
+            #[validate(length(min = 1))]
+            name: String,
+        }
+
+        let err: CallDockerError = Input { name: String::new() }.validate().unwrap_err().into();
+        assert!(matches!(err, CallDockerError::Validation(_)));
+        assert_eq!(err.code(), "validation_error");
+    }
+
+    #[actix_web::test]
+    async fn test_unique_violations_are_conflicts() {
+        let err: CallDockerError = sqlx::Error::Database(Box::new(SqlState("23505"))).into();
+        assert!(matches!(err, CallDockerError::Conflict(_)));
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+        let body = body_of(&err).await;
+        assert_eq!(body.code, "conflict");
+        assert!(!body.error.contains("companies_uuid_key"));
+
+        // Other constraints are still server errors
+        let err: CallDockerError = sqlx::Error::Database(Box::new(SqlState("23503"))).into();
+        assert!(matches!(err, CallDockerError::Database(_)));
+    }
+}
@@ -0,0 +1,208 @@
+#[cfg(test)]
+mod tests {
+    use crate::call::Call;
+    use crate::ivr_condition::{parse_condition, IVRContext, MAX_CONDITION_DEPTH, MAX_CONDITION_LENGTH};
+    use crate::routing_policy::RoutingPolicy;
+    use crate::test_support::test_call;
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+
+    fn context(queue_length: u32, open: bool, caller: Value) -> IVRContext {
+        IVRContext {
+            caller: caller.as_object().cloned().unwrap_or_default(),
+            queue_length,
+            business_hours_open: open,
+        }
+    }
+
+    fn holds(source: &str, session_data: &Value, context: &IVRContext) -> bool {
+        parse_condition(source).unwrap().holds(session_data, context)
+    }
+
+    /// Column and message of a condition that must not parse
+    fn rejected(source: &str) -> (usize, String) {
+        let e = parse_condition(source).unwrap_err();
+        (e.column, e.message)
+    }
+
+    #[test]
+    fn test_queue_and_tier_example() {
+        let source = r#"queue.length > 5 && caller.tier == "gold""#;
+        let session = json!({});
+
+        assert!(holds(source, &session, &context(6, true, json!({ "tier": "gold" }))));
+        // Strings compare ignoring case
+        assert!(holds(source, &session, &context(6, true, json!({ "tier": "GOLD" }))));
+        assert!(!holds(source, &session, &context(5, true, json!({ "tier": "gold" }))));
+        assert!(!holds(source, &session, &context(6, true, json!({ "tier": "silver" }))));
+        assert!(!holds(source, &session, &context(6, true, json!({}))));
+    }
+
+    #[test]
+    fn test_precedence_and_grouping() {
+        let session = json!({});
+        let context = context(3, false, json!({}));
+
+        // && binds tighter than ||
+        assert!(holds("true || false && false", &session, &context));
+        assert!(!holds("(true || false) && false", &session, &context));
+        assert!(holds("!false && true", &session, &context));
+        assert!(!holds("!(queue.length >= 3)", &session, &context));
+        assert!(holds("!business_hours.open || queue.length == 0", &session, &context));
+        assert!(holds("!!true", &session, &context));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let session = json!({});
+        let context = context(10, true, json!({ "name": "Jane", "plan": "pro", "seats": 25 }));
+        let cases = [
+            ("queue.length == 10", true),
+            ("queue.length != 10", false),
+            ("queue.length < 10.5", true),
+            ("queue.length <= 9", false),
+            ("-1 < queue.length", true),
+            ("caller.name == 'jane'", true),
+            ("caller.name < \"Zed\"", true),
+            ("caller.plan == \"pro\" && caller.seats >= 20", true),
+            ("caller.email == null", true),
+            ("caller.name != null", true),
+            ("business_hours.open == true", true),
+            // Values of different types are never equal, so never ordered either
+            ("caller.seats == \"many\"", false),
+            ("caller.seats != \"many\"", true),
+            ("caller.plan > 3", false),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(holds(source, &session, &context), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_session_data() {
+        let context = context(0, true, json!({}));
+        let session = json!({ "account_number": "1042", "menu_choice": "2", "vip": true, "note": "call me" });
+
+        // Entered digits compare as numbers with numbers, and as strings with strings
+        assert!(holds("session.account_number >= 1000", &session, &context));
+        assert!(holds("session.menu_choice == 2", &session, &context));
+        assert!(holds("session.menu_choice == \"2\"", &session, &context));
+        assert!(!holds("session.note > 1", &session, &context));
+        assert!(holds("session.vip", &session, &context));
+
+        // Missing keys are null, and only `true` holds
+        assert!(holds("session.missing == null", &session, &context));
+        assert!(!holds("session.missing", &session, &context));
+        assert!(holds("!session.missing", &session, &context));
+        assert!(!holds("session.note", &session, &context));
+        assert!(!holds("session.account_number > 1", &json!(null), &context));
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let cases = [
+            ("queue.length", 1, "Expected a true or false condition but found a number"),
+            ("queue.length > \"5\"", 1, "Cannot compare a number with a string"),
+            ("business_hours.open == 1", 1, "Cannot compare a boolean with a number"),
+            ("business_hours.open > true", 1, "Only numbers and strings can be ordered, not a boolean"),
+            ("null < 1", 1, "Cannot compare null with a number"),
+            ("true && caller.name", 9, "Expected a true or false condition but found a string"),
+            ("!5", 2, "found a number"),
+            ("(queue.length) || true", 1, "found a number"),
+            ("queue.size > 1", 1, "Unknown variable `queue.size`"),
+            ("caller.address.city == 'x'", 1, "Unknown variable"),
+            ("session. == 1", 1, "Unknown variable"),
+            ("tier == 'gold'", 1, "Unknown variable `tier`"),
+            ("1 < 2 < 3", 7, "Comparisons cannot be chained"),
+        ];
+
+        for (source, column, message) in cases {
+            let (at, error) = rejected(source);
+            assert_eq!(at, column, "{}", source);
+            assert!(error.contains(message), "{}: {}", source, error);
+        }
+
+        // Types only known at run time fit anything
+        assert!(parse_condition("session.tries > 2 && caller.plan == true").is_ok());
+        assert!(parse_condition("session.tries == caller.seats").is_ok());
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let cases = [
+            ("", 1, "Condition is empty"),
+            ("   ", 1, "Condition is empty"),
+            ("queue.length >", 15, "Condition ends too early"),
+            ("(true", 6, "Missing `)`"),
+            ("(true false)", 7, "Expected `)` but found `false`"),
+            ("true)", 5, "Unexpected `)`"),
+            ("true false", 6, "Unexpected `false`"),
+            ("&& true", 1, "Unexpected `&&`"),
+            ("caller.tier = 'gold'", 13, "did you mean `==`?"),
+            ("true & false", 6, "did you mean `&&`?"),
+            ("caller.tier == \"gold", 16, "Unterminated string"),
+            ("queue.length > 1.2.3", 16, "Invalid number 1.2.3"),
+            ("queue.length # 1", 14, "Unexpected character `#`"),
+        ];
+
+        for (source, column, message) in cases {
+            let (at, error) = rejected(source);
+            assert_eq!(at, column, "{}", source);
+            assert!(error.contains(message), "{}: {}", source, error);
+        }
+
+        let e = parse_condition("queue.length >").unwrap_err();
+        assert_eq!(e.to_string(), "Condition ends too early at column 15");
+    }
+
+    #[test]
+    fn test_strings_and_escapes() {
+        let context = context(0, true, json!({ "name": "O'Brien \"Bob\"" }));
+        let session = json!({});
+
+        assert!(holds(r#"caller.name == 'O\'Brien "Bob"'"#, &session, &context));
+        assert!(holds(r#"caller.name == "O'Brien \"Bob\"""#, &session, &context));
+        assert!(holds("caller.name != ''", &session, &context));
+    }
+
+    #[test]
+    fn test_limits() {
+        let long = format!("caller.name == \"{}\"", "x".repeat(MAX_CONDITION_LENGTH));
+        assert!(rejected(&long).1.contains("longer than 500 characters"));
+
+        let nested = |depth: usize| format!("{}true{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse_condition(&nested(MAX_CONDITION_DEPTH)).is_ok());
+        assert!(rejected(&nested(MAX_CONDITION_DEPTH + 1)).1.contains("nests deeper than 32 levels"));
+        assert!(parse_condition(&format!("{}true", "!".repeat(MAX_CONDITION_DEPTH))).is_ok());
+        assert!(parse_condition(&format!("{}true", "!".repeat(MAX_CONDITION_DEPTH + 1))).is_err());
+    }
+
+    #[test]
+    fn test_context_from_call() {
+        // A Wednesday, 10:00 UTC
+        let now = Utc.with_ymd_and_hms(2026, 10, 14, 10, 0, 0).unwrap();
+        let call = Call {
+            caller_number: Some("+15550001111".to_string()),
+            customer_name: Some("Jane Doe".to_string()),
+            metadata: json!({ "customer_tier": "gold", "plan": "pro", "name": "overridden" }),
+            created_at: now,
+            updated_at: now,
+            ..test_call()
+        };
+        let policy = RoutingPolicy::default();
+
+        let context = IVRContext::from_call(&call, &policy, 4, now);
+        assert_eq!(context.queue_length, 4);
+        assert!(context.business_hours_open);
+        assert_eq!(context.caller["tier"], "gold");
+        assert_eq!(context.caller["plan"], "pro");
+        assert_eq!(context.caller["name"], "Jane Doe");
+        assert_eq!(context.caller["email"], Value::Null);
+        assert!(holds(r#"caller.tier == "gold" && caller.phone != null"#, &json!({}), &context));
+
+        // Saturday
+        let weekend = Utc.with_ymd_and_hms(2026, 10, 17, 10, 0, 0).unwrap();
+        assert!(!IVRContext::from_call(&call, &policy, 4, weekend).business_hours_open);
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use crate::ivr::{IVRFlow, IVRFlowVersion, IVRNodeType};
+    use crate::ivr_diff::diff_flows;
+    use crate::test_support::{id, node};
+    use chrono::Utc;
+
+    fn flow() -> IVRFlow {
+        IVRFlow {
+            id: id(100),
+            company_id: id(200),
+            name: "Main line".to_string(),
+            description: None,
+            is_active: true,
+            welcome_message: Some("Welcome".to_string()),
+            welcome_audio_url: None,
+            nodes: vec![
+                node(1, IVRNodeType::Playback, Some(2)),
+                node(2, IVRNodeType::Input, Some(3)),
+                node(3, IVRNodeType::Hangup, None),
+            ],
+            published_version: Some(1),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_identical_flows_have_no_diff() {
+        let flow = flow();
+        let version = IVRFlowVersion::snapshot(&flow, 1, None, Utc::now());
+
+        assert!(diff_flows(&flow, &version.apply_to(&flow)).is_empty());
+    }
+
+    #[test]
+    fn test_diff_matches_nodes_by_id() {
+        let from = flow();
+        let mut to = flow();
+        to.welcome_message = Some("Hello".to_string());
+        to.nodes[1].timeout_seconds = Some(20);
+        to.nodes[1].variable = Some("account".to_string());
+        to.nodes[1].position.x = 10.0;
+        to.nodes.remove(2);
+        to.nodes.push(node(4, IVRNodeType::Voicemail, None));
+
+        let diff = diff_flows(&from, &to);
+        assert_eq!(diff.changed_fields, vec!["welcome_message"]);
+        assert_eq!(diff.added_nodes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![id(4)]);
+        assert_eq!(diff.removed_nodes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![id(3)]);
+        assert_eq!(diff.changed_nodes.len(), 1);
+        assert_eq!(diff.changed_nodes[0].node_id, id(2));
+        assert_eq!(diff.changed_nodes[0].fields, vec!["position", "timeout_seconds", "variable"]);
+        assert_eq!(diff.changed_nodes[0].before.timeout_seconds, None);
+    }
+
+    #[test]
+    fn test_reordering_only_counts_for_the_first_node() {
+        let from = flow();
+        let mut to = flow();
+        to.nodes.swap(1, 2);
+        assert!(diff_flows(&from, &to).is_empty());
+
+        to.nodes.swap(0, 1);
+        assert_eq!(diff_flows(&from, &to).changed_fields, vec!["first_node"]);
+    }
+
+    #[test]
+    fn test_versions_replace_the_draft_content() {
+        let mut flow = flow();
+        let version = IVRFlowVersion::snapshot(&flow, 2, Some(id(9)), Utc::now());
+        flow.name = "Edited".to_string();
+        flow.nodes.clear();
+
+        let published = version.apply_to(&flow);
+        assert_eq!(version.flow_id, flow.id);
+        assert_eq!(published.name, "Main line");
+        assert_eq!(published.nodes.len(), 3);
+        assert_eq!(published.company_id, flow.company_id);
+    }
+}
@@ -0,0 +1,250 @@
+#[cfg(test)]
+mod tests {
+    use crate::ivr::{IVRAction, IVRFlow, IVRNodeType, IVROutcome, IVRTransfer};
+    use crate::ivr_condition::IVRContext;
+    use crate::ivr_engine::{IVREngine, IVRInput, DEFAULT_TIMEOUT_SECS};
+    use crate::rule_engine::RoutingDecision;
+    use crate::test_support::{id, node, option};
+    use chrono::{Duration, Utc};
+
+    /// 1 plays a greeting, 2 is the main menu: 1 asks for an account number
+    /// (3) and transfers to billing (4), 2 leaves a voicemail (5), 9 hangs up.
+    /// After three failed tries the menu falls back to the front desk queue (6).
+    fn flow() -> IVRFlow {
+        let mut menu = node(2, IVRNodeType::Menu, Some(6));
+        menu.options = vec![
+            option("1", 3, IVRAction::GoToNode),
+            option("2", 5, IVRAction::RecordVoicemail),
+            option("9", 2, IVRAction::Hangup),
+        ];
+        let mut account = node(3, IVRNodeType::Input, Some(4));
+        account.variable = Some("account_number".to_string());
+        account.max_attempts = Some(2);
+        let mut billing = node(4, IVRNodeType::Transfer, None);
+        billing.transfer = Some(IVRTransfer::Department("billing".to_string()));
+        let mut front_desk = node(6, IVRNodeType::Transfer, None);
+        front_desk.transfer = Some(IVRTransfer::Queue(id(60)));
+        front_desk.text_to_speech = None;
+
+        IVRFlow {
+            id: id(100),
+            company_id: id(200),
+            name: "Main line".to_string(),
+            description: None,
+            is_active: true,
+            welcome_message: Some("Welcome".to_string()),
+            welcome_audio_url: None,
+            nodes: vec![
+                node(1, IVRNodeType::Playback, Some(2)),
+                menu,
+                account,
+                billing,
+                node(5, IVRNodeType::Voicemail, None),
+                front_desk,
+            ],
+            published_version: Some(3),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn digits(digits: &str) -> IVRInput {
+        IVRInput::Digits(digits.to_string())
+    }
+
+    #[test]
+    fn test_start_plays_up_to_the_first_menu() {
+        let flow = flow();
+        let now = Utc::now();
+        let (session, step) = IVREngine::new(&flow, &IVRContext::default()).start(id(7), now).unwrap();
+
+        assert!(session.is_active);
+        assert_eq!(session.call_id, id(7));
+        assert_eq!(session.current_node_id, id(2));
+        assert_eq!(session.flow_version, Some(3));
+        assert_eq!(session.timeout_at, Some(now + Duration::seconds(DEFAULT_TIMEOUT_SECS as i64)));
+        assert!(step.outcome.is_none());
+
+        let texts: Vec<_> = step.prompts.iter().map(|prompt| prompt.text.as_deref()).collect();
+        assert_eq!(texts, vec![Some("Welcome"), Some("Prompt 1"), Some("Prompt 2")]);
+        let keys: Vec<_> = step.prompts[2].options.iter().map(|option| option.key.as_str()).collect();
+        assert_eq!(keys, vec!["1", "2", "9"]);
+    }
+
+    #[test]
+    fn test_menu_and_input_lead_to_transfer() {
+        let flow = flow();
+        let context = IVRContext::default();
+        let engine = IVREngine::new(&flow, &context);
+        let now = Utc::now();
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+
+        let step = engine.advance(&mut session, digits("1"), now).unwrap();
+        assert_eq!(session.current_node_id, id(3));
+        assert_eq!(step.interaction.unwrap().selected_option.as_deref(), Some("1"));
+
+        let step = engine.advance(&mut session, digits("12345#"), now).unwrap();
+        assert_eq!(session.session_data["account_number"], "12345");
+        assert_eq!(step.interaction.unwrap().input.as_deref(), Some("12345"));
+
+        let outcome = IVROutcome::Transfer {
+            transfer: IVRTransfer::Department("billing".to_string()),
+        };
+        assert_eq!(step.outcome, Some(outcome.clone()));
+        assert!(!session.is_active);
+        assert_eq!(session.outcome, Some(outcome.clone()));
+        assert_eq!(session.timeout_at, None);
+        assert_eq!(outcome.routing_decision(), Some(RoutingDecision::Department("billing".to_string())));
+
+        assert!(engine.advance(&mut session, digits("1"), now).is_err());
+    }
+
+    #[test]
+    fn test_option_actions_end_the_session() {
+        let flow = flow();
+        let context = IVRContext::default();
+        let engine = IVREngine::new(&flow, &context);
+        let now = Utc::now();
+
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+        let step = engine.advance(&mut session, digits("2"), now).unwrap();
+        assert_eq!(step.outcome, Some(IVROutcome::Voicemail));
+        assert_eq!(IVROutcome::Voicemail.routing_decision(), Some(RoutingDecision::Voicemail(None)));
+
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+        let step = engine.advance(&mut session, digits("9"), now).unwrap();
+        assert_eq!(step.outcome, Some(IVROutcome::Hangup));
+        assert_eq!(IVROutcome::Hangup.routing_decision(), None);
+    }
+
+    #[test]
+    fn test_menu_falls_back_after_max_attempts() {
+        let flow = flow();
+        let context = IVRContext::default();
+        let engine = IVREngine::new(&flow, &context);
+        let now = Utc::now();
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+
+        let step = engine.advance(&mut session, digits("5"), now).unwrap();
+        assert_eq!(session.attempts, 1);
+        assert_eq!(session.current_node_id, id(2));
+        assert_eq!(step.prompts.len(), 1);
+        assert_eq!(step.interaction.unwrap().selected_option, None);
+
+        // A timeout that has not come yet is ignored
+        let step = engine.advance(&mut session, IVRInput::Timeout, now).unwrap();
+        assert!(step.prompts.is_empty() && step.interaction.is_none());
+        assert_eq!(session.attempts, 1);
+
+        let later = now + Duration::seconds(DEFAULT_TIMEOUT_SECS as i64);
+        let step = engine.advance(&mut session, IVRInput::Timeout, later).unwrap();
+        assert_eq!(session.attempts, 2);
+        assert_eq!(step.interaction.unwrap().input, None);
+
+        let step = engine.advance(&mut session, digits(""), later).unwrap();
+        assert_eq!(
+            step.outcome,
+            Some(IVROutcome::Transfer {
+                transfer: IVRTransfer::Queue(id(60))
+            })
+        );
+        // The transfer node has nothing to say
+        assert!(step.prompts.is_empty());
+    }
+
+    #[test]
+    fn test_input_hangs_up_after_max_attempts() {
+        let flow = flow();
+        let context = IVRContext::default();
+        let engine = IVREngine::new(&flow, &context);
+        let now = Utc::now();
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+        engine.advance(&mut session, digits("1"), now).unwrap();
+
+        engine.advance(&mut session, digits("#"), now).unwrap();
+        let step = engine.advance(&mut session, digits(""), now).unwrap();
+        assert_eq!(step.outcome, Some(IVROutcome::Hangup));
+    }
+
+    #[test]
+    fn test_broken_flows_fail() {
+        let mut flow = flow();
+        flow.nodes[3].transfer = None;
+        let context = IVRContext::default();
+        let engine = IVREngine::new(&flow, &context);
+        let now = Utc::now();
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+        engine.advance(&mut session, digits("1"), now).unwrap();
+        assert!(engine.advance(&mut session, digits("42"), now).is_err());
+
+        // Playback nodes looping forever
+        let mut flow = self::flow();
+        flow.nodes[0].next_node_id = Some(id(1));
+        assert!(IVREngine::new(&flow, &IVRContext::default()).start(id(7), now).is_err());
+
+        flow.nodes[0].next_node_id = Some(id(99));
+        assert!(IVREngine::new(&flow, &IVRContext::default()).start(id(7), now).is_err());
+
+        flow.nodes.clear();
+        assert!(IVREngine::new(&flow, &IVRContext::default()).start(id(7), now).is_err());
+    }
+
+    #[test]
+    fn test_condition_nodes_branch() {
+        // Open with a short queue, or for gold callers: billing (4); otherwise voicemail (5)
+        let mut check = node(1, IVRNodeType::Condition, Some(4));
+        check.condition = Some(r#"business_hours.open && (queue.length < 5 || caller.tier == "gold")"#.to_string());
+        check.else_node_id = Some(id(5));
+        let mut flow = flow();
+        flow.nodes[0] = check;
+        let now = Utc::now();
+
+        let outcome = |context: &IVRContext| {
+            let (session, step) = IVREngine::new(&flow, context).start(id(7), now).unwrap();
+            assert!(!session.is_active);
+            step.outcome
+        };
+        let billing = Some(IVROutcome::Transfer { transfer: IVRTransfer::Department("billing".to_string()) });
+
+        let mut context = IVRContext { business_hours_open: true, queue_length: 2, ..IVRContext::default() };
+        assert_eq!(outcome(&context), billing);
+        context.queue_length = 8;
+        assert_eq!(outcome(&context), Some(IVROutcome::Voicemail));
+        context.caller.insert("tier".to_string(), serde_json::json!("Gold"));
+        assert_eq!(outcome(&context), billing);
+        context.business_hours_open = false;
+        assert_eq!(outcome(&context), Some(IVROutcome::Voicemail));
+    }
+
+    #[test]
+    fn test_conditions_read_what_the_caller_entered() {
+        // Account numbers from 1000 go to billing (4), lower ones to the front desk (6)
+        let mut check = node(8, IVRNodeType::Condition, Some(4));
+        check.condition = Some("session.account_number >= 1000".to_string());
+        check.else_node_id = Some(id(6));
+        let mut flow = flow();
+        flow.nodes[2].next_node_id = Some(id(8));
+        flow.nodes.push(check);
+        let context = IVRContext::default();
+        let engine = IVREngine::new(&flow, &context);
+        let now = Utc::now();
+
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+        engine.advance(&mut session, digits("1"), now).unwrap();
+        let step = engine.advance(&mut session, digits("999"), now).unwrap();
+        assert_eq!(step.outcome, Some(IVROutcome::Transfer { transfer: IVRTransfer::Queue(id(60)) }));
+
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+        engine.advance(&mut session, digits("1"), now).unwrap();
+        let step = engine.advance(&mut session, digits("1000"), now).unwrap();
+        assert_eq!(step.outcome.unwrap().routing_decision(), Some(RoutingDecision::Department("billing".to_string())));
+
+        // A condition that no longer parses fails the flow rather than guessing a branch
+        let mut flow = flow.clone();
+        flow.nodes[6].condition = Some("session.account_number >".to_string());
+        let engine = IVREngine::new(&flow, &context);
+        let (mut session, _) = engine.start(id(7), now).unwrap();
+        engine.advance(&mut session, digits("1"), now).unwrap();
+        assert!(engine.advance(&mut session, digits("1000"), now).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashSet};
+    use crate::company::CompanyContact;
+    use crate::error::{CallDockerError, Result};
+    use crate::ivr::{IVRCategory, IVRFlow, IVRTemplate};
+    use crate::ivr_template::{
+        builtin_template, builtin_templates, company_placeholders, instantiate_template, COMPANY_PLACEHOLDERS,
+        SALES_TEMPLATE_ID,
+    };
+    use crate::ivr_validation::validate_flow;
+    use crate::routing_policy::BusinessHours;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn contact() -> CompanyContact {
+        CompanyContact {
+            name: "Acme".to_string(),
+            phone: Some("+15550001111".to_string()),
+            website: Some("acme.example".to_string()),
+            address: Some("1 Main Street, Springfield".to_string()),
+        }
+    }
+
+    fn instantiate(template: &IVRTemplate, placeholders: &BTreeMap<String, String>) -> Result<IVRFlow> {
+        instantiate_template(template, Uuid::from_u128(7), None, placeholders, Utc::now())
+    }
+
+    #[test]
+    fn test_builtin_templates() {
+        let templates = builtin_templates();
+        let categories: Vec<IVRCategory> = templates.iter().map(|t| t.category.clone()).collect();
+        assert_eq!(
+            categories,
+            vec![IVRCategory::CustomerService, IVRCategory::Sales, IVRCategory::Appointment, IVRCategory::AfterHours]
+        );
+
+        let ids: HashSet<Uuid> = templates.iter().map(|t| t.id).collect();
+        assert_eq!(ids.len(), templates.len());
+
+        for template in &templates {
+            assert!(template.is_public && template.company_id.is_none() && template.created_by.is_none());
+            let report = validate_flow(&template.template_data.nodes);
+            assert!(report.diagnostics.is_empty(), "{}: {:?}", template.name, report.diagnostics);
+            assert_eq!(builtin_template(template.id).map(|t| t.name), Some(template.name.clone()));
+        }
+        assert!(builtin_template(Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn test_instantiate_renumbers_nodes() {
+        let placeholders = company_placeholders(&contact(), &BusinessHours::default());
+
+        for template in builtin_templates() {
+            let flow = instantiate(&template, &placeholders).unwrap();
+            assert_eq!(flow.company_id, Uuid::from_u128(7));
+            assert_eq!(flow.name, template.name);
+            assert!(flow.is_active);
+            assert_eq!(flow.published_version, None);
+
+            // Same graph, new ids
+            let nodes = &template.template_data.nodes;
+            assert_eq!(flow.nodes.len(), nodes.len());
+            let new_id = |old: Uuid| {
+                let index = nodes.iter().position(|node| node.id == old).unwrap();
+                flow.nodes[index].id
+            };
+            for (before, after) in nodes.iter().zip(&flow.nodes) {
+                assert!(nodes.iter().all(|node| node.id != after.id));
+                assert_eq!(after.next_node_id, before.next_node_id.map(new_id));
+                assert_eq!(after.else_node_id, before.else_node_id.map(new_id));
+                for (old, new) in before.options.iter().zip(&after.options) {
+                    assert_eq!(new.next_node_id, new_id(old.next_node_id));
+                }
+            }
+            assert!(validate_flow(&flow.nodes).diagnostics.is_empty(), "{}", template.name);
+
+            // Every flow gets its own ids
+            let again = instantiate(&template, &placeholders).unwrap();
+            assert_ne!(again.id, flow.id);
+            assert!(again.nodes.iter().all(|node| flow.nodes.iter().all(|other| other.id != node.id)));
+        }
+    }
+
+    #[test]
+    fn test_instantiate_fills_placeholders() {
+        let placeholders = company_placeholders(&contact(), &BusinessHours::default());
+        let sales = builtin_template(SALES_TEMPLATE_ID).unwrap();
+
+        let name = Some("Acme sales".to_string());
+        let flow = instantiate_template(&sales, Uuid::new_v4(), name, &placeholders, Utc::now()).unwrap();
+        assert_eq!(flow.name, "Acme sales");
+        assert_eq!(flow.welcome_message.as_deref(), Some("Thank you for your interest in Acme."));
+        let closed = flow.nodes.iter().find(|node| node.name == "Closed").unwrap();
+        assert_eq!(
+            closed.text_to_speech.as_deref(),
+            Some("Our sales team is available Monday to Friday, 09:00 to 17:00. You can also find us at acme.example.")
+        );
+        // Nothing is left to fill
+        let json = serde_json::to_string(&flow).unwrap();
+        assert!(!json.contains("{{"), "{}", json);
+    }
+
+    #[test]
+    fn test_missing_placeholders() {
+        let company = CompanyContact {
+            name: "Acme".to_string(),
+            website: Some("   ".to_string()),
+            ..CompanyContact::default()
+        };
+        let mut placeholders = company_placeholders(&company, &BusinessHours::default());
+        let sales = builtin_template(SALES_TEMPLATE_ID).unwrap();
+
+        match instantiate(&sales, &placeholders) {
+            Err(CallDockerError::Validation(message)) => assert!(message.ends_with("placeholders: company_website")),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+
+        // Values given with the request fill the gaps
+        placeholders.insert("company_website".to_string(), "acme.example".to_string());
+        assert!(instantiate(&sales, &placeholders).is_ok());
+
+        let mut template = sales.clone();
+        template.name = "{{ region }} sales for {{company_name}}".to_string();
+        template.description = Some("{{company_name}} {{ unclosed".to_string());
+        template.template_data.welcome_message = Some("Call {{company_phone}} or {{region}}".to_string());
+        match instantiate(&template, &placeholders) {
+            Err(CallDockerError::Validation(message)) => {
+                assert!(message.ends_with("placeholders: company_phone, region"), "{}", message)
+            }
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+
+        // Values are not searched for placeholders again
+        placeholders.insert("region".to_string(), "{{company_phone}}".to_string());
+        placeholders.insert("company_phone".to_string(), "+15550001111".to_string());
+        let flow = instantiate(&template, &placeholders).unwrap();
+        assert_eq!(flow.name, "{{company_phone}} sales for Acme");
+        assert_eq!(flow.description.as_deref(), Some("Acme {{ unclosed"));
+        assert_eq!(flow.welcome_message.as_deref(), Some("Call +15550001111 or {{company_phone}}"));
+    }
+
+    #[test]
+    fn test_company_placeholders() {
+        let placeholders = company_placeholders(&contact(), &BusinessHours::default());
+        let keys: Vec<&str> = placeholders.keys().map(|key| key.as_str()).collect();
+        let mut expected = COMPANY_PLACEHOLDERS.to_vec();
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert_eq!(placeholders["company_address"], "1 Main Street, Springfield");
+
+        let company = CompanyContact {
+            name: " Acme ".to_string(),
+            phone: Some(String::new()),
+            ..CompanyContact::default()
+        };
+        let placeholders = company_placeholders(&company, &BusinessHours::default());
+        assert_eq!(placeholders.len(), 2);
+        assert_eq!(placeholders["company_name"], "Acme");
+        assert_eq!(placeholders["business_hours"], "Monday to Friday, 09:00 to 17:00");
+    }
+
+    #[test]
+    fn test_category_names() {
+        for category in [IVRCategory::CustomerService, IVRCategory::AfterHours, IVRCategory::Custom] {
+            assert_eq!(category.to_string().parse::<IVRCategory>(), Ok(category));
+        }
+        assert_eq!(IVRCategory::AfterHours.to_string(), "after_hours");
+        assert!("closed".parse::<IVRCategory>().is_err());
+    }
+}
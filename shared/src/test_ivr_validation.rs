@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod tests {
+    use crate::ivr::{IVRAction, IVRNode, IVRNodeType, IVRTransfer};
+    use crate::ivr_validation::{validate_flow, DiagnosticCode, DiagnosticSeverity};
+    use crate::test_support::{id, node, option};
+    use uuid::Uuid;
+
+    fn transfer(n: u128) -> IVRNode {
+        let mut node = node(n, IVRNodeType::Transfer, None);
+        node.transfer = Some(IVRTransfer::Department("sales".to_string()));
+        node
+    }
+
+    fn codes(nodes: &[IVRNode]) -> Vec<(Option<Uuid>, DiagnosticCode)> {
+        validate_flow(nodes)
+            .diagnostics
+            .into_iter()
+            .map(|d| (d.node_id, d.code))
+            .collect()
+    }
+
+    #[test]
+    fn test_sound_flow_has_no_diagnostics() {
+        let mut menu = node(2, IVRNodeType::Menu, Some(3));
+        menu.options = vec![
+            option("1", 3, IVRAction::GoToNode),
+            option("2", 4, IVRAction::RecordVoicemail),
+            // Where a hangup option points does not matter
+            option("9", 99, IVRAction::Hangup),
+        ];
+        let nodes = vec![
+            node(1, IVRNodeType::Playback, Some(2)),
+            menu,
+            transfer(3),
+            node(4, IVRNodeType::Voicemail, None),
+        ];
+
+        let report = validate_flow(&nodes);
+        assert!(report.is_valid());
+        assert!(report.diagnostics.is_empty());
+        assert!(report.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_broken_nodes_are_reported_per_node() {
+        let mut menu = node(2, IVRNodeType::Menu, None);
+        menu.options = vec![
+            option("1", 3, IVRAction::GoToNode),
+            option("1", 4, IVRAction::GoToNode),
+            option("2", 42, IVRAction::GoToNode),
+        ];
+        let nodes = vec![
+            node(1, IVRNodeType::Playback, Some(2)),
+            menu,
+            node(3, IVRNodeType::Transfer, None),
+            node(4, IVRNodeType::Menu, Some(3)),
+            node(5, IVRNodeType::Input, Some(43)),
+        ];
+
+        assert_eq!(
+            codes(&nodes),
+            vec![
+                (Some(id(2)), DiagnosticCode::DanglingReference),
+                (Some(id(2)), DiagnosticCode::DuplicateOptionKey),
+                (Some(id(3)), DiagnosticCode::TransferWithoutTarget),
+                (Some(id(4)), DiagnosticCode::MenuWithoutOptions),
+                (Some(id(5)), DiagnosticCode::DanglingReference),
+                (Some(id(5)), DiagnosticCode::UnreachableNode),
+            ]
+        );
+
+        let report = validate_flow(&nodes);
+        assert!(!report.is_valid());
+        assert_eq!(report.for_node(id(2)).count(), 2);
+        let message = report.into_result().unwrap_err().to_string();
+        assert!(message.contains(&id(42).to_string()));
+        assert!(message.contains("Transfer has no target"));
+    }
+
+    #[test]
+    fn test_unreachable_nodes_are_only_warnings() {
+        let nodes = vec![
+            node(1, IVRNodeType::Playback, Some(2)),
+            node(2, IVRNodeType::Hangup, None),
+            node(3, IVRNodeType::Playback, Some(2)),
+        ];
+
+        let report = validate_flow(&nodes);
+        assert!(report.is_valid());
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].node_id, Some(id(3)));
+        assert_eq!(report.diagnostics[0].severity, DiagnosticSeverity::Warning);
+    }
+
+    #[test]
+    fn test_loops_need_a_way_out() {
+        // Playback nodes going round in circles
+        let nodes = vec![
+            node(1, IVRNodeType::Playback, Some(2)),
+            node(2, IVRNodeType::Playback, Some(1)),
+        ];
+        assert_eq!(
+            codes(&nodes),
+            vec![(Some(id(1)), DiagnosticCode::NoExit), (Some(id(2)), DiagnosticCode::NoExit)]
+        );
+
+        // A menu whose every option leads back is only left by failing it
+        let mut menu = node(2, IVRNodeType::Menu, Some(3));
+        menu.options = vec![option("1", 1, IVRAction::GoToNode), option("2", 2, IVRAction::PlayMessage)];
+        let mut nodes = vec![node(1, IVRNodeType::Playback, Some(2)), menu, transfer(3)];
+        assert_eq!(
+            codes(&nodes),
+            vec![(Some(id(1)), DiagnosticCode::NoExit), (Some(id(2)), DiagnosticCode::NoExit)]
+        );
+
+        // One option out is enough
+        nodes[1].options.push(option("0", 3, IVRAction::TransferToDepartment));
+        assert!(codes(&nodes).is_empty());
+    }
+
+    #[test]
+    fn test_flow_level_problems() {
+        assert_eq!(codes(&[]), vec![(None, DiagnosticCode::EmptyFlow)]);
+
+        let nodes = vec![node(1, IVRNodeType::Hangup, None), node(1, IVRNodeType::Voicemail, None)];
+        assert_eq!(codes(&nodes), vec![(Some(id(1)), DiagnosticCode::DuplicateNodeId)]);
+    }
+
+    #[test]
+    fn test_diagnostics_serialize_for_clients() {
+        let report = validate_flow(&[node(1, IVRNodeType::Transfer, None)]);
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["diagnostics"][0]["node_id"], id(1).to_string());
+        assert_eq!(json["diagnostics"][0]["code"], "transfer_without_target");
+        assert_eq!(json["diagnostics"][0]["severity"], "error");
+    }
+
+    #[test]
+    fn test_conditions_are_type_checked() {
+        let mut check = node(1, IVRNodeType::Condition, Some(2));
+        check.condition = Some("queue.length > 5 && caller.tier == \"gold\"".to_string());
+        check.else_node_id = Some(id(3));
+        let mut nodes = vec![check, transfer(2), node(3, IVRNodeType::Voicemail, None)];
+        assert!(codes(&nodes).is_empty());
+
+        nodes[0].condition = Some("queue.length > \"five\"".to_string());
+        let report = validate_flow(&nodes);
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, DiagnosticCode::InvalidCondition);
+        assert!(report.diagnostics[0].message.contains("Cannot compare a number with a string at column 1"));
+
+        // The else branch is followed like any other
+        nodes[0].condition = Some("business_hours.open".to_string());
+        nodes[0].else_node_id = Some(id(42));
+        assert_eq!(
+            codes(&nodes),
+            vec![
+                (Some(id(1)), DiagnosticCode::DanglingReference),
+                (Some(id(3)), DiagnosticCode::UnreachableNode),
+            ]
+        );
+        nodes[0].else_node_id = Some(id(1));
+        nodes[0].next_node_id = Some(id(1));
+        assert_eq!(
+            codes(&nodes),
+            vec![
+                (Some(id(1)), DiagnosticCode::NoExit),
+                (Some(id(2)), DiagnosticCode::UnreachableNode),
+                (Some(id(3)), DiagnosticCode::UnreachableNode),
+            ]
+        );
+    }
+}
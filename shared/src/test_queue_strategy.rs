@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod tests {
+    use crate::queue_strategy::{
+        AgentSelectionStrategy, LeastBusy, MostSkilled, Priority, Random, RoundRobin, SelectionContext,
+        StrategyRegistry,
+    };
+    use crate::routing::{AgentAvailability, AgentStatus, OverflowAction, QueueStrategy, RoutingQueue};
+    use crate::CallDockerError;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    fn agent(n: u128, current_calls: u32, max_calls: u32, idle_minutes: i64, skills: &[&str]) -> AgentAvailability {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        AgentAvailability {
+            agent_id: Uuid::from_u128(n),
+            status: AgentStatus::Online,
+            current_calls,
+            max_calls,
+            skills: skills.iter().map(|s| s.to_string()).collect(),
+            last_activity: now - Duration::minutes(idle_minutes),
+            is_available: true,
+        }
+    }
+
+    fn queue(strategy: QueueStrategy, agents: &[u128]) -> RoutingQueue {
+        RoutingQueue {
+            id: Uuid::from_u128(100),
+            company_id: Uuid::from_u128(200),
+            name: "Support".to_string(),
+            description: None,
+            strategy,
+            max_wait_time: None,
+            max_queue_size: None,
+            overflow_action: OverflowAction::RouteToVoicemail,
+            overflow_target: None,
+            agents: agents.iter().map(|n| Uuid::from_u128(*n)).collect(),
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn context<'a>(queue: &'a RoutingQueue, skills: &'a [String], cursor: Option<u128>) -> SelectionContext<'a> {
+        SelectionContext {
+            queue,
+            required_skills: skills,
+            cursor: cursor.map(Uuid::from_u128),
+        }
+    }
+
+    fn skills(skills: &[&str]) -> Vec<String> {
+        skills.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_round_robin_cycles_after_cursor() {
+        let queue = queue(QueueStrategy::RoundRobin, &[]);
+        let agents = vec![agent(3, 0, 1, 0, &[]), agent(1, 0, 1, 0, &[]), agent(2, 1, 1, 0, &[])];
+        let none = skills(&[]);
+
+        assert_eq!(RoundRobin.select(&agents, &context(&queue, &none, None)), Some(Uuid::from_u128(1)));
+        // Agent 2 is full, so 1 is followed by 3
+        assert_eq!(RoundRobin.select(&agents, &context(&queue, &none, Some(1))), Some(Uuid::from_u128(3)));
+        assert_eq!(RoundRobin.select(&agents, &context(&queue, &none, Some(3))), Some(Uuid::from_u128(1)));
+        // A cursor pointing at an agent who went offline still advances
+        assert_eq!(RoundRobin.select(&agents, &context(&queue, &none, Some(2))), Some(Uuid::from_u128(3)));
+    }
+
+    #[test]
+    fn test_least_busy_prefers_fewest_calls_then_longest_idle() {
+        let queue = queue(QueueStrategy::LeastBusy, &[]);
+        let none = skills(&[]);
+        let agents = vec![
+            agent(1, 1, 3, 60, &[]),
+            agent(2, 0, 3, 5, &[]),
+            agent(3, 0, 3, 30, &[]),
+        ];
+
+        assert_eq!(LeastBusy.select(&agents, &context(&queue, &none, None)), Some(Uuid::from_u128(3)));
+
+        let billing = skills(&["billing"]);
+        assert_eq!(LeastBusy.select(&agents, &context(&queue, &billing, None)), None);
+    }
+
+    #[test]
+    fn test_most_skilled_ranks_by_match_score() {
+        let queue = queue(QueueStrategy::MostSkilled, &[]);
+        let agents = vec![
+            agent(1, 0, 1, 0, &["billing"]),
+            agent(2, 0, 2, 0, &["billing", "spanish", "refunds"]),
+            agent(3, 1, 2, 0, &["billing", "spanish"]),
+            agent(4, 0, 1, 0, &["technical"]),
+        ];
+
+        let required = skills(&["billing", "spanish"]);
+        assert_eq!(MostSkilled::score(&agents[1], &required), (2, 3));
+        assert_eq!(MostSkilled.select(&agents, &context(&queue, &required, None)), Some(Uuid::from_u128(2)));
+
+        // Partial matches are accepted when nobody has every skill
+        let required = skills(&["spanish", "french"]);
+        assert_eq!(MostSkilled.select(&agents, &context(&queue, &required, None)), Some(Uuid::from_u128(2)));
+
+        let required = skills(&["french"]);
+        assert_eq!(MostSkilled.select(&agents, &context(&queue, &required, None)), None);
+    }
+
+    #[test]
+    fn test_priority_follows_queue_agent_order() {
+        let queue = queue(QueueStrategy::Priority, &[3, 1, 2]);
+        let none = skills(&[]);
+        let agents = vec![agent(1, 0, 1, 0, &[]), agent(2, 0, 1, 0, &[]), agent(3, 1, 1, 0, &[])];
+
+        assert_eq!(Priority.select(&agents, &context(&queue, &none, None)), Some(Uuid::from_u128(1)));
+    }
+
+    #[test]
+    fn test_random_is_reproducible_with_seed() {
+        let queue = queue(QueueStrategy::Random, &[]);
+        let none = skills(&[]);
+        let agents: Vec<_> = (1..=5).map(|n| agent(n, 0, 1, 0, &[])).collect();
+
+        let picks = |seed| {
+            let random = Random::with_seed(seed);
+            (0..20)
+                .map(|_| random.select(&agents, &context(&queue, &none, None)).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(picks(42), picks(42));
+        assert!(picks(42).iter().all(|id| agents.iter().any(|a| a.agent_id == *id)));
+        // Twenty picks over five agents hit more than one of them
+        let mut distinct = picks(42);
+        distinct.sort();
+        distinct.dedup();
+        assert!(distinct.len() > 1);
+
+        assert_eq!(Random::with_seed(7).select(&[], &context(&queue, &none, None)), None);
+    }
+
+    #[test]
+    fn test_registry_resolves_each_variant() {
+        struct Fixed(Uuid);
+        impl AgentSelectionStrategy for Fixed {
+            fn select(&self, _: &[AgentAvailability], _: &SelectionContext<'_>) -> Option<Uuid> {
+                Some(self.0)
+            }
+        }
+
+        let mut registry = StrategyRegistry::with_random(Random::with_seed(1));
+        registry.register_custom("vip_desk", Arc::new(Fixed(Uuid::from_u128(9))));
+
+        let agents = vec![agent(1, 1, 2, 0, &["billing"]), agent(2, 0, 2, 0, &["billing"])];
+        let none = skills(&[]);
+        let expected = [
+            (QueueStrategy::RoundRobin, Some(1)),
+            (QueueStrategy::LeastBusy, Some(2)),
+            (QueueStrategy::MostSkilled, Some(2)),
+            (QueueStrategy::Priority, Some(2)),
+            (QueueStrategy::Custom("vip_desk".to_string()), Some(9)),
+        ];
+
+        for (strategy, expected) in expected {
+            let queue = queue(strategy.clone(), &[2, 1]);
+            let selected = registry.resolve(&strategy).unwrap().select(&agents, &context(&queue, &none, None));
+            assert_eq!(selected, expected.map(Uuid::from_u128), "{:?}", strategy);
+        }
+
+        assert!(registry.resolve(&QueueStrategy::Random).is_ok());
+        assert!(matches!(
+            registry.resolve(&QueueStrategy::Custom("missing".to_string())),
+            Err(CallDockerError::CallRouting(_))
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use crate::error::{CallDockerError, ErrorResponse};
+    use crate::request_id::{RequestId, REQUEST_ID_HEADER};
+    use actix_web::{test, web, App, HttpResponse};
+    use uuid::Uuid;
+
+    async fn failing() -> Result<HttpResponse, CallDockerError> {
+        Err(CallDockerError::Internal("connection refused".to_string()))
+    }
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    fn header_id<B>(res: &actix_web::dev::ServiceResponse<B>) -> Uuid {
+        let value = res.headers().get(REQUEST_ID_HEADER).expect("request id header");
+        Uuid::parse_str(value.to_str().unwrap()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_incoming_id_is_echoed_and_used_by_errors() {
+        let app = test::init_service(
+            App::new().wrap(RequestId).route("/fail", web::get().to(failing)),
+        )
+        .await;
+        let incoming = Uuid::new_v4();
+
+        let req = test::TestRequest::get()
+            .uri("/fail")
+            .insert_header((REQUEST_ID_HEADER, incoming.to_string()))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(header_id(&res), incoming);
+        let body: ErrorResponse = test::read_body_json(res).await;
+        assert_eq!(body.request_id, incoming);
+    }
+
+    #[actix_web::test]
+    async fn test_missing_id_is_generated() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .route("/ok", web::get().to(ok))
+                .route("/fail", web::get().to(failing)),
+        )
+        .await;
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/ok").to_request()).await;
+        assert!(res.headers().contains_key(REQUEST_ID_HEADER));
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/fail").to_request()).await;
+        let id = header_id(&res);
+        let body: ErrorResponse = test::read_body_json(res).await;
+        assert_eq!(body.request_id, id);
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_id_is_replaced() {
+        let app = test::init_service(App::new().wrap(RequestId).route("/ok", web::get().to(ok))).await;
+
+        let req = test::TestRequest::get()
+            .uri("/ok")
+            .insert_header((REQUEST_ID_HEADER, "not-a-uuid"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        let value = res.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap();
+        assert!(Uuid::parse_str(value).is_ok());
+    }
+}
@@ -0,0 +1,279 @@
+#[cfg(test)]
+mod tests {
+    use crate::routing::{
+        eligible_agents, estimated_wait_time, queue_score, response_percentage, should_set_away, AgentAvailability,
+        AgentStatus, CallOffer, CreateRoutingRuleRequest, OverflowAction, QueueStrategy, RoutingAction,
+        RoutingActionType, RoutingCondition, RoutingField, RoutingOperator, RoutingQueue, PRIORITY_WEIGHT_SECS,
+    };
+    use serde_json::{json, Value};
+    use validator::Validate;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    fn agent(status: AgentStatus, current_calls: u32, max_calls: u32, skills: &[&str]) -> AgentAvailability {
+        AgentAvailability {
+            agent_id: Uuid::new_v4(),
+            status,
+            current_calls,
+            max_calls,
+            skills: skills.iter().map(|s| s.to_string()).collect(),
+            last_activity: Utc::now(),
+            is_available: true,
+        }
+    }
+
+    fn skills(skills: &[&str]) -> Vec<String> {
+        skills.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_skill_matching_requires_every_skill() {
+        let agent = agent(AgentStatus::Online, 0, 1, &["billing_support", "Spanish"]);
+
+        assert!(agent.has_skills(&[]));
+        assert!(agent.has_skills(&skills(&["billing_support"])));
+        assert!(agent.has_skills(&skills(&["spanish", "billing_support"])));
+        assert!(!agent.has_skills(&skills(&["billing_support", "technical_support"])));
+    }
+
+    #[test]
+    fn test_capacity_respects_status_and_max_calls() {
+        assert!(agent(AgentStatus::Online, 1, 2, &[]).has_capacity());
+        assert!(!agent(AgentStatus::Online, 2, 2, &[]).has_capacity());
+        assert!(!agent(AgentStatus::Online, 0, 0, &[]).has_capacity());
+        assert!(!agent(AgentStatus::Away, 0, 1, &[]).has_capacity());
+
+        let mut inactive = agent(AgentStatus::Online, 0, 1, &[]);
+        inactive.is_available = false;
+        assert!(!inactive.has_capacity());
+    }
+
+    #[test]
+    fn test_working_agents_include_busy_ones() {
+        assert!(agent(AgentStatus::Online, 2, 2, &[]).is_working());
+        assert!(agent(AgentStatus::Busy, 1, 1, &[]).is_working());
+        assert!(!agent(AgentStatus::Away, 0, 1, &[]).is_working());
+        assert!(!agent(AgentStatus::Offline, 0, 1, &[]).is_working());
+
+        let mut inactive = agent(AgentStatus::Online, 0, 1, &[]);
+        inactive.is_available = false;
+        assert!(!inactive.is_working());
+    }
+
+    #[test]
+    fn test_eligible_agents_are_ordered_by_load() {
+        let agents = vec![
+            agent(AgentStatus::Online, 1, 2, &["technical_support"]),
+            agent(AgentStatus::Online, 0, 1, &["billing_support"]),
+            agent(AgentStatus::Online, 0, 3, &["technical_support"]),
+            agent(AgentStatus::Busy, 0, 3, &["technical_support"]),
+            agent(AgentStatus::Online, 3, 3, &["technical_support"]),
+        ];
+
+        let eligible: Vec<Uuid> = eligible_agents(&agents, &skills(&["technical_support"]))
+            .into_iter()
+            .map(|agent| agent.agent_id)
+            .collect();
+        assert_eq!(eligible, vec![agents[2].agent_id, agents[0].agent_id]);
+
+        assert_eq!(eligible_agents(&agents, &[]).len(), 3);
+        assert!(eligible_agents(&agents, &skills(&["french"])).is_empty());
+    }
+
+    #[test]
+    fn test_agent_status_round_trips_through_display() {
+        for status in [
+            AgentStatus::Online,
+            AgentStatus::Offline,
+            AgentStatus::Busy,
+            AgentStatus::Away,
+            AgentStatus::Break,
+            AgentStatus::Training,
+        ] {
+            assert_eq!(status.to_string().parse::<AgentStatus>(), Ok(status));
+        }
+        assert!("lunch".parse::<AgentStatus>().is_err());
+    }
+
+    #[test]
+    fn test_queue_settings_round_trip_through_display() {
+        for strategy in [
+            QueueStrategy::RoundRobin,
+            QueueStrategy::LeastBusy,
+            QueueStrategy::MostSkilled,
+            QueueStrategy::Priority,
+            QueueStrategy::Random,
+            QueueStrategy::Custom("vip_desk".to_string()),
+        ] {
+            let parsed: QueueStrategy = strategy.to_string().parse().unwrap();
+            assert_eq!(parsed.to_string(), strategy.to_string());
+        }
+        assert!("custom:".parse::<QueueStrategy>().is_err());
+
+        for action in [
+            OverflowAction::RouteToFallback,
+            OverflowAction::RouteToVoicemail,
+            OverflowAction::RouteToIVR,
+            OverflowAction::Hangup,
+            OverflowAction::RouteToExternal,
+        ] {
+            let parsed: OverflowAction = action.to_string().parse().unwrap();
+            assert_eq!(parsed.to_string(), action.to_string());
+        }
+        assert!("queue".parse::<OverflowAction>().is_err());
+    }
+
+    #[test]
+    fn test_condition_values_must_suit_field_and_operator() {
+        use RoutingOperator::*;
+        let check = |field: RoutingField, operator: RoutingOperator, value: Value| {
+            RoutingCondition { field, operator, value }.check()
+        };
+
+        assert!(check(RoutingField::CustomerPhone, StartsWith, json!("+1")).is_ok());
+        assert!(check(RoutingField::CustomerPhone, GreaterThan, json!("+1")).is_err());
+        assert!(check(RoutingField::QueueLength, GreaterThan, json!(5)).is_ok());
+        assert!(check(RoutingField::QueueLength, GreaterThan, json!("5")).is_err());
+        assert!(check(RoutingField::QueueLength, Contains, json!(5)).is_err());
+        assert!(check(RoutingField::CallTime, LessThan, json!("09:00")).is_ok());
+        assert!(check(RoutingField::CallTime, LessThan, json!("9:00")).is_err());
+        assert!(check(RoutingField::CallTime, LessThan, json!("25:00")).is_err());
+        assert!(check(RoutingField::AgentSkills, Contains, json!("billing")).is_ok());
+        assert!(check(RoutingField::AgentStatus, In, json!(["online", "busy"])).is_ok());
+        assert!(check(RoutingField::AgentStatus, Equals, json!("lunch")).is_err());
+        assert!(check(RoutingField::CustomerEmail, In, json!([])).is_err());
+        assert!(check(RoutingField::CustomerEmail, In, json!("vip")).is_err());
+        assert!(check(RoutingField::CustomerName, IsNull, Value::Null).is_ok());
+        assert!(check(RoutingField::CustomerName, IsNull, json!("x")).is_err());
+        assert!(check(RoutingField::Custom("tier".to_string()), Equals, json!(true)).is_ok());
+        assert!(check(RoutingField::Custom("tier".to_string()), Equals, json!({ "a": 1 })).is_err());
+    }
+
+    #[test]
+    fn test_rule_requests_need_actions_and_valid_conditions() {
+        let mut request = CreateRoutingRuleRequest {
+            name: "VIP".to_string(),
+            description: None,
+            priority: 10,
+            conditions: vec![RoutingCondition {
+                field: RoutingField::CustomerEmail,
+                operator: RoutingOperator::Contains,
+                value: json!("vip"),
+            }],
+            actions: vec![RoutingAction {
+                action_type: RoutingActionType::AddTag,
+                parameters: json!({ "tag": "vip" }),
+            }],
+        };
+        assert!(request.validate().is_ok());
+
+        request.conditions[0].value = json!(1);
+        let errors = request.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("conditions"));
+
+        request.conditions.clear();
+        request.actions.clear();
+        let errors = request.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("actions"));
+    }
+
+    #[test]
+    fn test_queue_score_orders_by_priority_and_wait() {
+        let now = Utc::now();
+
+        let score = |priority, wait_start| queue_score(priority, wait_start, PRIORITY_WEIGHT_SECS);
+
+        // Same arrival: higher priority first
+        assert!(score(150, now) < score(100, now));
+        // Same priority: earlier arrival first
+        assert!(score(100, now - Duration::seconds(1)) < score(100, now));
+        // 50 points of priority are worth 50 minutes of waiting
+        assert!(score(100, now - Duration::minutes(51)) < score(150, now));
+        assert!(score(150, now) < score(100, now - Duration::minutes(49)));
+        // Faster aging: 50 points are only worth 5 minutes
+        assert!(queue_score(100, now - Duration::minutes(6), 6.0) < queue_score(150, now, 6.0));
+    }
+
+    #[test]
+    fn test_estimated_wait_time_shares_handle_time_across_slots() {
+        // First in line with one slot waits a full handle time
+        assert_eq!(estimated_wait_time(1, 120.0, 1), Some(120));
+        // Four slots clear four calls per handle time
+        assert_eq!(estimated_wait_time(4, 120.0, 4), Some(120));
+        assert_eq!(estimated_wait_time(5, 120.0, 4), Some(150));
+        // Rounded up to whole seconds
+        assert_eq!(estimated_wait_time(1, 100.0, 3), Some(34));
+        // Nobody staffed: no estimate
+        assert_eq!(estimated_wait_time(1, 120.0, 0), None);
+    }
+
+    #[test]
+    fn test_queue_limits() {
+        let now = Utc::now();
+        let mut queue = RoutingQueue {
+            id: Uuid::new_v4(),
+            company_id: Uuid::new_v4(),
+            name: "Support".to_string(),
+            description: None,
+            strategy: QueueStrategy::LeastBusy,
+            max_wait_time: Some(120),
+            max_queue_size: Some(2),
+            overflow_action: OverflowAction::RouteToVoicemail,
+            overflow_target: None,
+            agents: vec![],
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        };
+
+        assert!(!queue.wait_exceeded(now - Duration::seconds(119), now));
+        assert!(queue.wait_exceeded(now - Duration::seconds(120), now));
+        assert!(!queue.is_full(1));
+        assert!(queue.is_full(2));
+
+        queue.max_wait_time = None;
+        queue.max_queue_size = None;
+        assert!(!queue.wait_exceeded(now - Duration::days(1), now));
+        assert!(!queue.is_full(10_000));
+    }
+
+    #[test]
+    fn test_overflow_targets() {
+        let flow_id = Uuid::new_v4().to_string();
+
+        assert!(OverflowAction::RouteToIVR.check_target(Some(&flow_id)).is_ok());
+        assert!(OverflowAction::RouteToIVR.check_target(Some("main menu")).is_err());
+        assert!(OverflowAction::RouteToIVR.check_target(None).is_err());
+        assert!(OverflowAction::RouteToExternal.check_target(Some("+15550000000")).is_ok());
+        assert!(OverflowAction::RouteToExternal.check_target(Some(" ")).is_err());
+        assert!(OverflowAction::RouteToExternal.check_target(None).is_err());
+        assert!(OverflowAction::RouteToVoicemail.check_target(None).is_ok());
+        assert!(OverflowAction::Hangup.check_target(Some("ignored")).is_ok());
+        assert!(OverflowAction::RouteToFallback.check_target(None).is_ok());
+    }
+
+    #[test]
+    fn test_call_offer_rings_until_timeout() {
+        let now = Utc::now();
+        let offer = CallOffer::new(Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), now, 20);
+
+        assert_eq!(offer.expires_at, now + Duration::seconds(20));
+        assert!(!offer.is_expired(now + Duration::seconds(19)));
+        assert!(offer.is_expired(now + Duration::seconds(20)));
+        assert_eq!(offer.ring_time(now + Duration::milliseconds(7500)), 7);
+        assert_eq!(offer.ring_time(now - Duration::seconds(1)), 0);
+    }
+
+    #[test]
+    fn test_missed_offers_and_response_percentage() {
+        assert!(!should_set_away(2, 3));
+        assert!(should_set_away(3, 3));
+        assert!(should_set_away(4, 3));
+        // 0 turns automatic away off
+        assert!(!should_set_away(10, 0));
+
+        assert_eq!(response_percentage(0, 0), 100.0);
+        assert_eq!(response_percentage(4, 3), 75.0);
+        assert_eq!(response_percentage(2, 5), 100.0);
+    }
+}
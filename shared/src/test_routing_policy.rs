@@ -0,0 +1,218 @@
+#[cfg(test)]
+mod tests {
+    use crate::call::{Call, CallDirection};
+    use crate::routing_policy::{BusinessHours, MetadataBonus, RoutingPolicy, SkillRule, SkillSource, StickyRouting};
+    use crate::test_support::test_call;
+    use chrono::{Duration, NaiveTime, TimeZone, Utc, Weekday};
+    use serde_json::json;
+    use validator::Validate;
+
+    fn call(direction: CallDirection, tags: &[&str], metadata: serde_json::Value) -> Call {
+        Call {
+            direction,
+            called_number: Some("+15550001111".to_string()),
+            customer_name: Some("Jane Doe".to_string()),
+            customer_email: Some("jane@Billing.example.com".to_string()),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            metadata,
+            ..test_call()
+        }
+    }
+
+    #[test]
+    fn test_default_policy_scores_direction_tier_and_tags() {
+        let policy = RoutingPolicy::default();
+
+        assert_eq!(policy.priority(&call(CallDirection::Outbound, &[], json!({}))), 100);
+        assert_eq!(policy.priority(&call(CallDirection::Inbound, &[], json!({}))), 125);
+        assert_eq!(policy.priority(&call(CallDirection::Inbound, &[], json!({ "customer_tier": "VIP" }))), 175);
+        assert_eq!(policy.priority(&call(CallDirection::Outbound, &["urgent", "escalation"], json!({}))), 275);
+        // The email address no longer affects priority
+        let mut vip_email = call(CallDirection::Outbound, &[], json!({}));
+        vip_email.customer_email = Some("vip@example.com".to_string());
+        assert_eq!(policy.priority(&vip_email), 100);
+    }
+
+    #[test]
+    fn test_metadata_bonuses_and_cap() {
+        let mut policy = RoutingPolicy {
+            metadata_bonuses: vec![
+                MetadataBonus { key: "contract".to_string(), value: None, bonus: 10 },
+                MetadataBonus { key: "plan".to_string(), value: Some(json!("enterprise")), bonus: 40 },
+            ],
+            ..RoutingPolicy::default()
+        };
+
+        let enterprise = call(CallDirection::Outbound, &[], json!({ "contract": "c-1", "plan": "enterprise" }));
+        assert_eq!(policy.priority(&enterprise), 150);
+        let starter = call(CallDirection::Outbound, &[], json!({ "contract": null, "plan": "starter" }));
+        assert_eq!(policy.priority(&starter), 100);
+
+        policy.max_priority = Some(120);
+        assert_eq!(policy.priority(&enterprise), 120);
+        assert_eq!(policy.cap(500), 120);
+        assert_eq!(policy.cap(90), 90);
+    }
+
+    #[test]
+    fn test_required_skills_come_from_metadata_and_rules() {
+        let policy = RoutingPolicy {
+            skill_rules: vec![
+                SkillRule {
+                    source: SkillSource::CustomerEmail,
+                    pattern: "billing".to_string(),
+                    skill: "billing_support".to_string(),
+                },
+                SkillRule { source: SkillSource::Tag, pattern: "Spanish".to_string(), skill: "spanish".to_string() },
+                SkillRule {
+                    source: SkillSource::Metadata("product".to_string()),
+                    pattern: "router".to_string(),
+                    skill: "networking".to_string(),
+                },
+                SkillRule {
+                    source: SkillSource::CalledNumber,
+                    pattern: "+1555999".to_string(),
+                    skill: "sales".to_string(),
+                },
+            ],
+            ..RoutingPolicy::default()
+        };
+        let call = call(
+            CallDirection::Inbound,
+            &["spanish"],
+            json!({ "required_skills": ["Billing_Support", "escalations"], "product": "Home Router X" }),
+        );
+
+        assert_eq!(
+            policy.required_skills(&call),
+            vec!["Billing_Support", "escalations", "spanish", "networking"]
+        );
+        // Without rules nothing is inferred from the email
+        assert_eq!(RoutingPolicy::default().required_skills(&call), vec!["Billing_Support", "escalations"]);
+    }
+
+    #[test]
+    fn test_policy_parses_partially_and_validates() {
+        let policy: RoutingPolicy =
+            serde_json::from_value(json!({ "base_priority": 50, "tag_bonuses": { "gold": 5 } })).unwrap();
+        assert_eq!(policy.base_priority, 50);
+        assert_eq!(policy.tag_bonuses.get("gold"), Some(&5));
+        assert_eq!(policy.tier_key, "customer_tier");
+        assert!(policy.validate().is_ok());
+
+        let invalid = RoutingPolicy {
+            aging_seconds_per_point: 0.0,
+            skill_rules: vec![SkillRule { source: SkillSource::Tag, pattern: String::new(), skill: "x".to_string() }],
+            ..RoutingPolicy::default()
+        };
+        let errors = invalid.validate().unwrap_err();
+        assert!(errors.errors().contains_key("aging_seconds_per_point"));
+        assert!(errors.errors().contains_key("skill_rules"));
+    }
+
+    #[test]
+    fn test_sticky_routing_window_and_history() {
+        let sticky = StickyRouting { enabled: true, wait_window: 30, lookback_days: 7 };
+        let now = Utc::now();
+
+        assert_eq!(sticky.history_since(now), now - Duration::days(7));
+        assert_eq!(sticky.wait_until(now - Duration::seconds(10), now), Some(now + Duration::seconds(20)));
+        assert_eq!(sticky.wait_until(now - Duration::seconds(30), now), None);
+        let no_window = StickyRouting { wait_window: 0, ..sticky.clone() };
+        assert_eq!(no_window.wait_until(now, now), None);
+
+        // Off unless a company turns it on
+        let policy: RoutingPolicy = serde_json::from_value(json!({ "sticky_routing": { "enabled": true } })).unwrap();
+        assert!(!RoutingPolicy::default().sticky_routing.enabled);
+        assert_eq!(policy.sticky_routing, StickyRouting { enabled: true, ..StickyRouting::default() });
+
+        let invalid = RoutingPolicy {
+            sticky_routing: StickyRouting { enabled: true, wait_window: 3600, lookback_days: 0 },
+            ..RoutingPolicy::default()
+        };
+        assert!(invalid.validate().unwrap_err().errors().contains_key("sticky_routing"));
+    }
+
+    #[test]
+    fn test_business_hours() {
+        let at = |day: u32, hour: u32, minute: u32| Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap();
+        let time = |hour: u32| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+
+        // Weekdays 09:00 to 17:00 UTC; the 12th is a Monday
+        let hours = BusinessHours::default();
+        assert!(!hours.is_open(at(12, 8, 59)));
+        assert!(hours.is_open(at(12, 9, 0)));
+        assert!(hours.is_open(at(16, 16, 59)));
+        assert!(!hours.is_open(at(16, 17, 0)));
+        assert!(!hours.is_open(at(17, 12, 0)));
+
+        // Local time two hours ahead of UTC
+        let ahead = BusinessHours { utc_offset_minutes: 120, ..hours.clone() };
+        assert!(ahead.is_open(at(12, 7, 0)));
+        assert!(!ahead.is_open(at(12, 15, 0)));
+
+        // Friday night shift, running into Saturday
+        let overnight =
+            BusinessHours { days: vec![Weekday::Fri], opens_at: time(22), closes_at: time(6), ..hours.clone() };
+        assert!(overnight.is_open(at(16, 23, 0)));
+        assert!(overnight.is_open(at(17, 5, 59)));
+        assert!(!overnight.is_open(at(17, 22, 0)));
+        assert!(!overnight.is_open(at(16, 5, 0)));
+
+        let all_day = BusinessHours { opens_at: time(0), closes_at: time(0), ..hours.clone() };
+        assert!(all_day.is_open(at(12, 3, 0)));
+        assert!(!all_day.is_open(at(18, 3, 0)));
+
+        let policy: RoutingPolicy = serde_json::from_value(json!({
+            "business_hours": { "days": ["Sat", "Sun"], "opens_at": "10:00", "closes_at": "14:30" }
+        }))
+        .unwrap();
+        assert_eq!(policy.business_hours.days, vec![Weekday::Sat, Weekday::Sun]);
+        assert_eq!(policy.business_hours.closes_at, NaiveTime::from_hms_opt(14, 30, 0).unwrap());
+        assert_eq!(policy.business_hours.utc_offset_minutes, 0);
+        assert!(policy.business_hours.is_open(at(17, 12, 0)));
+
+        let invalid = RoutingPolicy {
+            business_hours: BusinessHours { utc_offset_minutes: 1000, ..hours },
+            ..RoutingPolicy::default()
+        };
+        assert!(invalid.validate().unwrap_err().errors().contains_key("business_hours"));
+    }
+
+    #[test]
+    fn test_business_hours_description() {
+        let time = |hour: u32, minute: u32| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+        let hours = |days: Vec<Weekday>, opens_at: NaiveTime, closes_at: NaiveTime| BusinessHours {
+            days,
+            opens_at,
+            closes_at,
+            ..BusinessHours::default()
+        };
+        let every_day = vec![
+            Weekday::Sun,
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+        ];
+
+        let cases = [
+            (BusinessHours::default(), "Monday to Friday, 09:00 to 17:00"),
+            (hours(vec![Weekday::Sat], time(10, 0), time(14, 30)), "Saturday, 10:00 to 14:30"),
+            (hours(vec![Weekday::Sun, Weekday::Sat], time(10, 0), time(14, 0)), "Saturday and Sunday, 10:00 to 14:00"),
+            (
+                hours(vec![Weekday::Mon, Weekday::Wed, Weekday::Fri, Weekday::Mon], time(8, 0), time(12, 0)),
+                "Monday, Wednesday and Friday, 08:00 to 12:00",
+            ),
+            (hours(every_day, time(0, 0), time(0, 0)), "every day, all day"),
+            (hours(vec![Weekday::Fri], time(22, 0), time(6, 0)), "Friday, 22:00 to 06:00"),
+            (hours(Vec::new(), time(9, 0), time(17, 0)), "closed"),
+        ];
+
+        for (hours, expected) in cases {
+            assert_eq!(hours.describe(), expected);
+        }
+    }
+}
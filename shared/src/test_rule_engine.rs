@@ -0,0 +1,191 @@
+#[cfg(test)]
+mod tests {
+    use crate::routing::{
+        AgentStatus, RoutingAction, RoutingActionType, RoutingCondition, RoutingField, RoutingOperator, RoutingRule,
+    };
+    use crate::rule_engine::{compare, condition_matches, evaluate_rules, RoutingContext, RoutingDecision};
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    fn context() -> RoutingContext {
+        RoutingContext {
+            call_id: Uuid::from_u128(1),
+            company_id: Uuid::from_u128(2),
+            customer_phone: Some("+15551234567".to_string()),
+            customer_email: Some("Jane@VIP.example.com".to_string()),
+            customer_name: None,
+            call_time: Utc.with_ymd_and_hms(2024, 1, 1, 18, 30, 0).unwrap(),
+            call_duration: None,
+            agent_skills: vec!["billing".to_string(), "Spanish".to_string()],
+            agent_status: Some(AgentStatus::Online),
+            queue_length: 4,
+            priority: 100,
+            tags: vec![],
+            metadata: json!({ "plan": "enterprise", "seats": 250 }),
+        }
+    }
+
+    fn condition(field: RoutingField, operator: RoutingOperator, value: Value) -> RoutingCondition {
+        RoutingCondition { field, operator, value }
+    }
+
+    fn action(action_type: RoutingActionType, parameters: Value) -> RoutingAction {
+        RoutingAction { action_type, parameters }
+    }
+
+    fn rule(n: u128, priority: u32, conditions: Vec<RoutingCondition>, actions: Vec<RoutingAction>) -> RoutingRule {
+        RoutingRule {
+            id: Uuid::from_u128(n),
+            company_id: Uuid::from_u128(2),
+            name: format!("rule {}", n),
+            description: None,
+            priority,
+            is_active: true,
+            conditions,
+            actions,
+            created_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            updated_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_every_operator() {
+        use RoutingOperator::*;
+        let cases = [
+            (json!("abc"), Equals, json!("ABC"), true),
+            (json!(4), Equals, json!(4.0), true),
+            (json!(4), Equals, json!("4"), false),
+            (json!("abc"), NotEquals, json!("abd"), true),
+            (json!("jane@vip.example.com"), Contains, json!("VIP"), true),
+            (json!(["billing", "spanish"]), Contains, json!("Spanish"), true),
+            (json!(["billing"]), NotContains, json!("french"), true),
+            (json!("+15551234567"), StartsWith, json!("+1555"), true),
+            (json!("+15551234567"), EndsWith, json!("4567"), true),
+            (json!(5), StartsWith, json!("5"), false),
+            (json!(4), GreaterThan, json!(3), true),
+            (json!(4), LessThan, json!(3), false),
+            (json!(4), GreaterThanOrEqual, json!(4), true),
+            (json!("18:30"), LessThanOrEqual, json!("09:00"), false),
+            (json!("18:30"), GreaterThan, json!("17:00"), true),
+            (json!("4"), GreaterThan, json!(3), false),
+            (json!("gold"), In, json!(["Gold", "platinum"]), true),
+            (json!(["french", "billing"]), In, json!(["billing"]), true),
+            (json!("gold"), In, json!("gold"), false),
+            (json!("bronze"), NotIn, json!(["gold", "platinum"]), true),
+            (Value::Null, IsNull, Value::Null, true),
+            (json!(""), IsNull, Value::Null, true),
+            (json!("x"), IsNotNull, Value::Null, true),
+        ];
+
+        for (actual, operator, expected, result) in cases {
+            assert_eq!(compare(&actual, &operator, &expected), result, "{} {:?} {}", actual, operator, expected);
+        }
+    }
+
+    #[test]
+    fn test_fields_resolve_from_context() {
+        let context = context();
+        let cases = [
+            (RoutingField::CustomerPhone, RoutingOperator::StartsWith, json!("+1555")),
+            (RoutingField::CustomerEmail, RoutingOperator::EndsWith, json!("example.com")),
+            (RoutingField::CustomerName, RoutingOperator::IsNull, Value::Null),
+            (RoutingField::CallTime, RoutingOperator::GreaterThanOrEqual, json!("18:00")),
+            (RoutingField::CallDuration, RoutingOperator::IsNull, Value::Null),
+            (RoutingField::AgentSkills, RoutingOperator::Contains, json!("spanish")),
+            (RoutingField::AgentStatus, RoutingOperator::Equals, json!("online")),
+            (RoutingField::QueueLength, RoutingOperator::GreaterThan, json!(3)),
+            (RoutingField::CallPriority, RoutingOperator::Equals, json!(100)),
+            (RoutingField::Custom("plan".to_string()), RoutingOperator::In, json!(["enterprise"])),
+            (RoutingField::Custom("seats".to_string()), RoutingOperator::GreaterThan, json!(100)),
+            (RoutingField::Custom("region".to_string()), RoutingOperator::IsNull, Value::Null),
+        ];
+
+        for (field, operator, value) in cases {
+            let condition = condition(field, operator, value);
+            assert!(condition_matches(&condition, &context), "{:?}", condition);
+        }
+    }
+
+    #[test]
+    fn test_rules_run_by_priority_and_stop_at_first_route() {
+        let queue_id = Uuid::new_v4();
+        let rules = vec![
+            rule(1, 10, vec![], vec![action(RoutingActionType::RouteToVoicemail, json!({}))]),
+            rule(
+                2,
+                50,
+                vec![condition(RoutingField::CustomerEmail, RoutingOperator::Contains, json!("vip"))],
+                vec![
+                    action(RoutingActionType::SetPriority, json!({ "priority": 200 })),
+                    action(RoutingActionType::AddTag, json!({ "tag": "vip" })),
+                ],
+            ),
+            rule(
+                3,
+                40,
+                vec![condition(RoutingField::CallPriority, RoutingOperator::GreaterThanOrEqual, json!(200))],
+                vec![
+                    action(RoutingActionType::RouteToQueue, json!({ "queue_id": queue_id.to_string() })),
+                    action(RoutingActionType::SetMetadata, json!({ "key": "segment", "value": "vip" })),
+                    action(RoutingActionType::RouteToVoicemail, json!({})),
+                ],
+            ),
+            rule(
+                4,
+                45,
+                vec![condition(RoutingField::QueueLength, RoutingOperator::GreaterThan, json!(100))],
+                vec![action(RoutingActionType::RouteToExternal, json!({ "number": "+15550000000" }))],
+            ),
+        ];
+
+        let evaluation = evaluate_rules(&rules, context());
+
+        // Rule 3 only matches because rule 2 raised the priority first
+        assert_eq!(evaluation.decision, Some(RoutingDecision::Queue(queue_id)));
+        assert_eq!(evaluation.applied_rules, vec![Uuid::from_u128(2), Uuid::from_u128(3)]);
+        assert_eq!(evaluation.context.priority, 200);
+        assert_eq!(evaluation.context.tags, vec!["vip".to_string()]);
+        assert_eq!(evaluation.context.metadata["segment"], json!("vip"));
+
+        // Rule 4 does not match and rule 1 is never reached
+        let evaluated: Vec<_> = evaluation.trace.iter().map(|t| (t.rule_id.as_u128(), t.matched)).collect();
+        assert_eq!(evaluated, vec![(2, true), (4, false), (3, true)]);
+        assert!(!evaluation.trace[1].conditions[0].matched);
+        assert_eq!(evaluation.trace[1].conditions[0].actual, json!(4));
+        let second_route = &evaluation.trace[2].actions[2];
+        assert!(!second_route.applied);
+        assert!(second_route.detail.starts_with("Skipped"));
+    }
+
+    #[test]
+    fn test_inactive_rules_and_bad_parameters() {
+        let mut inactive = rule(1, 90, vec![], vec![action(RoutingActionType::RouteToVoicemail, json!({}))]);
+        inactive.is_active = false;
+        let rules = vec![
+            inactive,
+            rule(
+                2,
+                50,
+                vec![],
+                vec![
+                    action(RoutingActionType::RouteToAgent, json!({ "agent_id": "not-a-uuid" })),
+                    action(RoutingActionType::SetPriority, json!({ "priority": -5 })),
+                    action(RoutingActionType::SendWebhook, json!({ "url": "https://example.com/hook" })),
+                ],
+            ),
+        ];
+
+        let evaluation = evaluate_rules(&rules, context());
+
+        assert_eq!(evaluation.decision, None);
+        assert_eq!(evaluation.applied_rules, vec![Uuid::from_u128(2)]);
+        assert_eq!(evaluation.context.priority, 100);
+        let applied: Vec<_> = evaluation.trace[0].actions.iter().map(|a| a.applied).collect();
+        assert_eq!(applied, vec![false, false, true]);
+
+        assert_eq!(evaluation.webhooks.len(), 1);
+        assert_eq!(evaluation.webhooks[0].url, "https://example.com/hook");
+        assert_eq!(evaluation.webhooks[0].payload["call_id"], json!(Uuid::from_u128(1)));
+    }
+}
@@ -0,0 +1,218 @@
+#[cfg(test)]
+mod tests {
+    use crate::auth::ParticipantRole;
+    use crate::ivr::{IVROutcome, IVRTransfer};
+    use crate::ivr_engine::{IVRPrompt, IVRPromptOption};
+    use crate::routing::{AgentStatus, OfferOutcome};
+    use crate::signaling::{
+        negotiate_version, AgentUpdate, ClientMessage, ErrorCode, ParticipantInfo, RelayedSignal, ServerMessage,
+        Signal, PROTOCOL_VERSION,
+    };
+    use crate::types::{SignalType, WebRTCSignal};
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn parse(value: serde_json::Value) -> Result<ClientMessage, ErrorCode> {
+        ClientMessage::parse(&value.to_string()).map_err(|e| e.code)
+    }
+
+    #[test]
+    fn test_parses_every_client_message() {
+        let cases = vec![
+            (json!({"type": "ping"}), ClientMessage::Ping),
+            (json!({"type": "offer", "sdp": "v=0"}), ClientMessage::Signal(Signal::Offer { sdp: "v=0".to_string() })),
+            (json!({"type": "answer", "sdp": "v=0"}), ClientMessage::Signal(Signal::Answer { sdp: "v=0".to_string() })),
+            (
+                json!({"type": "ice_candidate", "candidate": "candidate:1", "sdpMid": "0", "sdpMLineIndex": 0}),
+                ClientMessage::Signal(Signal::IceCandidate {
+                    candidate: "candidate:1".to_string(),
+                    sdp_mid: Some("0".to_string()),
+                    sdp_m_line_index: Some(0),
+                }),
+            ),
+            (json!({"type": "hangup"}), ClientMessage::Signal(Signal::Hangup { reason: None })),
+            (json!({"type": "busy"}), ClientMessage::Signal(Signal::Busy)),
+            (json!({"type": "hold", "held": true}), ClientMessage::Signal(Signal::Hold { held: true })),
+            (json!({"type": "mute", "muted": false}), ClientMessage::Signal(Signal::Mute { muted: false })),
+            (json!({"type": "dtmf", "digits": "12#"}), ClientMessage::Signal(Signal::Dtmf { digits: "12#".to_string() })),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse(input.clone()), Ok(expected), "failed to parse {}", input);
+        }
+    }
+
+    #[test]
+    fn test_accepts_legacy_ice_candidate_tag() {
+        let message = parse(json!({"type": "ice-candidate", "candidate": "candidate:1"})).unwrap();
+        assert!(matches!(message, ClientMessage::Signal(Signal::IceCandidate { .. })));
+    }
+
+    #[test]
+    fn test_rejects_messages_outside_the_schema() {
+        let invalid = vec![
+            json!({"type": "teleport"}),
+            json!({"sdp": "v=0"}),
+            json!({"type": "offer"}),
+            json!({"type": "offer", "sdp": 42}),
+            json!({"type": "offer", "sdp": "v=0", "extra": true}),
+            json!({"type": "offer", "sdp": "  "}),
+            json!({"type": "hold"}),
+            json!({"type": "dtmf", "digits": ""}),
+            json!({"type": "dtmf", "digits": "12x"}),
+        ];
+
+        for input in invalid {
+            assert_eq!(parse(input.clone()), Err(ErrorCode::InvalidMessage), "accepted {}", input);
+        }
+        assert!(ClientMessage::parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_server_messages_serialize_with_camel_case_fields() {
+        let call_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        let participant = ParticipantInfo {
+            session_id: "s1".to_string(),
+            user_id,
+            role: ParticipantRole::Agent,
+        };
+
+        let joined = ServerMessage::UserJoined { participant, room_id: call_id, timestamp: 7 };
+        assert_eq!(
+            serde_json::to_value(&joined).unwrap(),
+            json!({"type": "user_joined", "sessionId": "s1", "userId": user_id, "role": "agent", "roomId": call_id, "timestamp": 7})
+        );
+
+        let position = ServerMessage::QueuePosition {
+            call_id,
+            position: 2,
+            queue_length: 5,
+            estimated_wait_time: Some(90),
+        };
+        assert_eq!(
+            serde_json::to_value(&position).unwrap(),
+            json!({"type": "queue_position", "callId": call_id, "position": 2, "queueLength": 5, "estimatedWaitTime": 90})
+        );
+
+        let offered = ServerMessage::CallOffered {
+            call_id,
+            customer_name: Some("Jane".to_string()),
+            priority: 125,
+            expires_at: "2026-01-01T12:00:20Z".parse().unwrap(),
+        };
+        assert_eq!(
+            serde_json::to_value(&offered).unwrap(),
+            json!({
+                "type": "call_offered", "callId": call_id, "customerName": "Jane", "priority": 125,
+                "expiresAt": "2026-01-01T12:00:20Z"
+            })
+        );
+
+        let withdrawn = ServerMessage::OfferWithdrawn { call_id, reason: OfferOutcome::TimedOut };
+        assert_eq!(
+            serde_json::to_value(&withdrawn).unwrap(),
+            json!({"type": "offer_withdrawn", "callId": call_id, "reason": "timed_out"})
+        );
+
+        let prompt = ServerMessage::IvrPrompt {
+            call_id,
+            prompt: IVRPrompt {
+                node_id: None,
+                text: Some("Press 1 for sales".to_string()),
+                audio_url: None,
+                options: vec![IVRPromptOption { key: "1".to_string(), label: "Sales".to_string() }],
+                timeout_seconds: Some(10),
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&prompt).unwrap(),
+            json!({
+                "type": "ivr_prompt", "callId": call_id,
+                "prompt": {
+                    "nodeId": null, "text": "Press 1 for sales", "audioUrl": null,
+                    "options": [{"key": "1", "label": "Sales"}], "timeoutSeconds": 10
+                }
+            })
+        );
+
+        let ended = ServerMessage::IvrEnded {
+            call_id,
+            outcome: IVROutcome::Transfer { transfer: IVRTransfer::Department("sales".to_string()) },
+        };
+        assert_eq!(
+            serde_json::to_value(&ended).unwrap(),
+            json!({
+                "type": "ivr_ended", "callId": call_id,
+                "outcome": {"type": "transfer", "transfer": {"type": "department", "target": "sales"}}
+            })
+        );
+
+        let error = ServerMessage::Error { code: ErrorCode::InvalidMessage, message: "bad".to_string() };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({"type": "error", "code": "invalid_message", "message": "bad"})
+        );
+    }
+
+    #[test]
+    fn test_relayed_signal_round_trips() {
+        let relayed = ServerMessage::Signal(RelayedSignal {
+            from: "s1".to_string(),
+            signal: Signal::Answer { sdp: "v=0".to_string() },
+        });
+
+        let value = serde_json::to_value(&relayed).unwrap();
+        assert_eq!(value, json!({"type": "answer", "from": "s1", "sdp": "v=0"}));
+        assert_eq!(serde_json::from_value::<ServerMessage>(value).unwrap(), relayed);
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        assert_eq!(negotiate_version(None), Ok(PROTOCOL_VERSION));
+        assert_eq!(negotiate_version(Some(1)), Ok(1));
+        assert_eq!(negotiate_version(Some(99)).unwrap_err().code, ErrorCode::UnsupportedVersion);
+    }
+
+    #[test]
+    fn test_rest_signal_uses_the_same_schema() {
+        let signal = |signal_type, data| WebRTCSignal {
+            call_id: Uuid::new_v4(),
+            signal_type,
+            data,
+            timestamp: Utc::now(),
+        };
+
+        let offer = signal(SignalType::Offer, json!({"sdp": "v=0"})).signal().unwrap();
+        assert_eq!(offer, Signal::Offer { sdp: "v=0".to_string() });
+        assert_eq!(offer.signal_type(), SignalType::Offer);
+
+        let ice = signal(SignalType::IceCandidate, json!({"candidate": "candidate:1"})).signal().unwrap();
+        assert_eq!(ice.signal_type(), SignalType::IceCandidate);
+
+        assert_eq!(signal(SignalType::Busy, serde_json::Value::Null).signal().unwrap(), Signal::Busy);
+
+        let missing_sdp = signal(SignalType::Answer, json!({})).signal().unwrap_err();
+        assert_eq!(missing_sdp.code, ErrorCode::InvalidMessage);
+
+        let mismatch = signal(SignalType::Offer, json!({"type": "answer", "sdp": "v=0"})).signal().unwrap_err();
+        assert_eq!(mismatch.code, ErrorCode::SignalTypeMismatch);
+    }
+
+    #[test]
+    fn test_agent_update_round_trips() {
+        let agent_id = Uuid::new_v4();
+        let update = AgentUpdate {
+            agent_id,
+            message: ServerMessage::AgentStatusChanged {
+                agent_id,
+                status: AgentStatus::Away,
+                reason: "3 offers in a row rang out".to_string(),
+            },
+        };
+
+        let text = serde_json::to_string(&update).unwrap();
+        assert_eq!(serde_json::from_str::<AgentUpdate>(&text).unwrap(), update);
+    }
+}
@@ -0,0 +1,70 @@
+//! Fixtures for the tests of this crate and, through the `test-util` feature,
+//! of the services.
+
+use crate::call::{Call, CallDirection, CallStatus};
+use crate::ivr::{IVRAction, IVRNode, IVRNodeType, IVROption, IVRPosition};
+use chrono::Utc;
+use uuid::Uuid;
+
+/// A ringing inbound call of a new company, with nothing else set. Tests fill
+/// in what they look at: `Call { status, ..test_call() }`.
+pub fn test_call() -> Call {
+    let now = Utc::now();
+    Call {
+        id: Uuid::new_v4(),
+        company_id: Uuid::new_v4(),
+        agent_id: None,
+        customer_id: None,
+        status: CallStatus::Ringing,
+        direction: CallDirection::Inbound,
+        caller_number: None,
+        called_number: None,
+        customer_name: None,
+        customer_email: None,
+        duration: None,
+        recording_url: None,
+        notes: None,
+        tags: Vec::new(),
+        metadata: serde_json::json!({}),
+        created_at: now,
+        updated_at: now,
+        answered_at: None,
+        ended_at: None,
+    }
+}
+
+/// Uuid `n`, so IVR nodes can be numbered
+pub fn id(n: u128) -> Uuid {
+    Uuid::from_u128(n)
+}
+
+/// IVR node `n` with a prompt, leading to node `next`
+pub fn node(n: u128, node_type: IVRNodeType, next: Option<u128>) -> IVRNode {
+    IVRNode {
+        id: id(n),
+        node_type,
+        name: format!("Node {}", n),
+        description: None,
+        audio_url: None,
+        text_to_speech: Some(format!("Prompt {}", n)),
+        options: Vec::new(),
+        timeout_seconds: None,
+        max_attempts: None,
+        next_node_id: next.map(id),
+        position: IVRPosition { x: 0.0, y: 0.0 },
+        transfer: None,
+        variable: None,
+        condition: None,
+        else_node_id: None,
+    }
+}
+
+/// Menu option `key`, leading to node `next`
+pub fn option(key: &str, next: u128, action: IVRAction) -> IVROption {
+    IVROption {
+        key: key.to_string(),
+        label: format!("Option {}", key),
+        next_node_id: id(next),
+        action,
+    }
+}
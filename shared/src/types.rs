@@ -45,6 +45,8 @@ pub struct Pagination {
     pub per_page: u32,
     pub total: u64,
     pub total_pages: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,13 +79,31 @@ pub struct WebRTCSignal {
     pub timestamp: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SignalType {
     Offer,
     Answer,
     IceCandidate,
     Hangup,
     Busy,
+    Hold,
+    Mute,
+    Dtmf,
+}
+
+impl std::fmt::Display for SignalType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignalType::Offer => write!(f, "offer"),
+            SignalType::Answer => write!(f, "answer"),
+            SignalType::IceCandidate => write!(f, "ice_candidate"),
+            SignalType::Hangup => write!(f, "hangup"),
+            SignalType::Busy => write!(f, "busy"),
+            SignalType::Hold => write!(f, "hold"),
+            SignalType::Mute => write!(f, "mute"),
+            SignalType::Dtmf => write!(f, "dtmf"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]